@@ -30,6 +30,12 @@ pub struct LoadAssetCompletionEvent {
     /// The ID of the asset that was loaded.
     pub id: AssetId,
 
+    /// The generation of the load, see
+    /// [`AssetDatabaseEntry::load_generation`].
+    ///
+    /// [`AssetDatabaseEntry::load_generation`]: crate::database::AssetDatabaseEntry::load_generation
+    pub generation: u64,
+
     /// The path of the asset that was requested to be loaded.
     pub path: AssetPath,
 
@@ -14,6 +14,9 @@ pub struct AssetDatabaseEntry {
 
     /// The dependencies of the asset (by path, as they may not be loaded yet).
     dependencies: HashSet<AssetPath>,
+
+    /// The generation of the latest load of the asset.
+    load_generation: u64,
 }
 
 impl AssetDatabaseEntry {
@@ -36,6 +39,13 @@ impl AssetDatabaseEntry {
     pub fn dependencies(&self) -> &HashSet<AssetPath> {
         &self.dependencies
     }
+
+    /// Returns the generation of the latest load of the asset.
+    ///
+    /// Completions of loads with another generation are stale.
+    pub fn load_generation(&self) -> u64 {
+        self.load_generation
+    }
 }
 
 #[derive(Default)]
@@ -53,6 +63,9 @@ pub struct AssetDatabase {
 
     /// Path to AssetId mapping for quick lookups.
     path_to_id: HashMap<AssetPath, AssetId>,
+
+    /// The generation of the next load.
+    next_load_generation: u64,
 }
 
 impl AssetDatabase {
@@ -78,7 +91,9 @@ impl AssetDatabase {
             path: path.clone(),
             state: AssetState::Loading,
             dependencies: HashSet::new(),
+            load_generation: self.next_load_generation,
         };
+        self.next_load_generation += 1;
 
         // Insert into slotmap and get ID
         let id = self.assets.insert(entry);
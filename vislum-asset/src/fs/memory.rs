@@ -16,6 +16,11 @@ impl MemoryFs {
             files: HashMap::new(),
         }
     }
+
+    /// Adds a file, replacing the file at the same path if any.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, bytes: Bytes) {
+        self.files.insert(path.into(), bytes);
+    }
 }
 
 impl Fs for MemoryFs {
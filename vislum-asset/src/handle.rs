@@ -0,0 +1,141 @@
+use std::{
+    any::Any,
+    sync::{Arc, OnceLock},
+};
+
+use crossbeam::channel::Sender;
+
+use crate::{
+    asset::{Asset, AssetId},
+    path::AssetPath,
+};
+
+/// A typed handle to an asset.
+///
+/// Cloning a handle is cheap, and shares the asset. Handles created by the
+/// [`AssetManager`] are reference counted: once the last handle to an asset
+/// is dropped, the manager is notified to unload it. They may also be
+/// returned before the asset finishes loading, see [`Handle::is_ready`].
+///
+/// [`AssetManager`]: crate::manager::AssetManager
+pub struct Handle<T: Asset> {
+    inner: Arc<HandleInner<T>>,
+}
+
+pub(crate) struct HandleInner<T> {
+    path: AssetPath,
+    asset: OnceLock<Arc<T>>,
+    _tracker: Option<HandleTracker>,
+}
+
+/// The shared state of a handle, with its asset type erased.
+pub(crate) trait ErasedHandleInner: Any + Send + Sync {
+    /// Sets the loaded asset, making the handles ready.
+    fn set_asset(&self, asset: Arc<dyn Asset>);
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: Asset> ErasedHandleInner for HandleInner<T> {
+    fn set_asset(&self, asset: Arc<dyn Asset>) {
+        match asset.downcast_arc::<T>() {
+            Ok(asset) => {
+                let _ = self.asset.set(asset);
+            }
+            Err(_) => log::error!("Asset {} has an incompatible type", self.path),
+        }
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// Notifies the manager once the last handle to an asset is dropped.
+pub(crate) struct HandleTracker {
+    id: AssetId,
+    drop_tx: Sender<AssetId>,
+}
+
+impl HandleTracker {
+    pub(crate) fn new(id: AssetId, drop_tx: Sender<AssetId>) -> Self {
+        Self { id, drop_tx }
+    }
+}
+
+impl Drop for HandleTracker {
+    fn drop(&mut self) {
+        // The manager may be gone already, in which case there is nothing
+        // left to unload.
+        let _ = self.drop_tx.send(self.id);
+    }
+}
+
+impl<T: Asset> Handle<T> {
+    /// Creates an untracked handle to an asset loaded from `path`.
+    pub fn new(path: AssetPath, asset: Arc<T>) -> Self {
+        Self {
+            inner: Arc::new(HandleInner {
+                path,
+                asset: OnceLock::from(asset),
+                _tracker: None,
+            }),
+        }
+    }
+
+    /// Creates a handle notifying `tracker` once its last clone is dropped.
+    ///
+    /// The handle is loading until the asset is set, unless it's given.
+    pub(crate) fn tracked(path: AssetPath, asset: Option<Arc<T>>, tracker: HandleTracker) -> Self {
+        let slot = OnceLock::new();
+        if let Some(asset) = asset {
+            let _ = slot.set(asset);
+        }
+
+        Self {
+            inner: Arc::new(HandleInner {
+                path,
+                asset: slot,
+                _tracker: Some(tracker),
+            }),
+        }
+    }
+
+    /// Creates a handle sharing the reference count of `inner`.
+    pub(crate) fn from_inner(inner: Arc<HandleInner<T>>) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the shared state of the handle, to create more handles from.
+    pub(crate) fn inner(&self) -> &Arc<HandleInner<T>> {
+        &self.inner
+    }
+
+    /// Returns the path the asset was loaded from.
+    pub fn path(&self) -> &AssetPath {
+        &self.inner.path
+    }
+
+    /// Returns true once the asset finished loading.
+    pub fn is_ready(&self) -> bool {
+        self.inner.asset.get().is_some()
+    }
+
+    /// Returns the asset, or `None` while it's loading.
+    pub fn get(&self) -> Option<&T> {
+        self.inner.asset.get().map(|asset| &**asset)
+    }
+
+    /// Returns the asset, or `placeholder` while it's loading.
+    pub fn get_or<'a>(&'a self, placeholder: &'a T) -> &'a T {
+        self.get().unwrap_or(placeholder)
+    }
+}
+
+impl<T: Asset> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
@@ -1,7 +1,9 @@
 pub mod asset;
 pub mod database;
 pub mod fs;
+pub mod handle;
 pub mod loader;
 pub mod manager;
 pub mod path;
 pub mod vfs;
+pub mod worker;
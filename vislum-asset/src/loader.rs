@@ -1,10 +1,12 @@
-use std::collections::HashSet;
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use thiserror::Error;
 
 use crate::asset::Asset;
 use crate::fs::{Bytes, ReadError};
+use crate::handle::Handle;
 use crate::path::AssetPath;
 use crate::vfs::VirtualFileSystem;
 
@@ -50,6 +52,89 @@ impl AssetLoaders {
 
 static_assertions::assert_impl_all!(AssetLoaders: Send, Sync);
 
+/// Loads assets with the loaders registered for their extension.
+///
+/// Several loaders may handle the same extension as long as they produce
+/// different asset types, e.g. a `png` loader producing textures and another
+/// one producing images for the CPU. The loader is picked by the requested
+/// asset type.
+pub struct AssetLoaderRegistry {
+    /// The virtual filesystem the assets are read from.
+    virtual_fs: VirtualFileSystem,
+
+    /// The registered loaders, in registration order.
+    loaders: Vec<Arc<dyn ErasedAssetLoader>>,
+
+    /// The registered loaders, by extension.
+    by_extension: HashMap<&'static str, Vec<Arc<dyn ErasedAssetLoader>>>,
+}
+
+impl AssetLoaderRegistry {
+    pub fn new(virtual_fs: VirtualFileSystem) -> Self {
+        Self {
+            virtual_fs,
+            loaders: Vec::new(),
+            by_extension: HashMap::new(),
+        }
+    }
+
+    /// Registers a loader for all of its extensions.
+    ///
+    /// A loader registered later for the same extension and asset type
+    /// replaces the previous one.
+    pub fn register(&mut self, loader: Box<dyn ErasedAssetLoader>) -> &mut Self {
+        let loader: Arc<dyn ErasedAssetLoader> = Arc::from(loader);
+
+        for extension in loader.extensions() {
+            let loaders = self.by_extension.entry(extension).or_default();
+            loaders.retain(|other| other.asset_type_id() != loader.asset_type_id());
+            loaders.push(loader.clone());
+        }
+
+        self.loaders.push(loader);
+        self
+    }
+
+    /// Finds the loader producing `T` assets from files with the extension.
+    pub fn find<T: Asset>(&self, extension: &str) -> Option<&Arc<dyn ErasedAssetLoader>> {
+        self.by_extension
+            .get(extension)?
+            .iter()
+            .find(|loader| loader.asset_type_id() == TypeId::of::<T>())
+    }
+
+    /// Returns the registered loaders, e.g. to load dependencies.
+    pub fn loaders(&self) -> AssetLoaders {
+        AssetLoaders {
+            loaders: Arc::from_iter(self.loaders.iter().cloned()),
+        }
+    }
+
+    /// Loads a `T` asset, blocking until it's read and decoded.
+    pub fn load<T: Asset>(&self, path: AssetPath) -> Result<Handle<T>, LoadError> {
+        let extension = path
+            .path()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .ok_or(LoadError::NoLoaderFound)?;
+        let loader = self.find::<T>(extension).ok_or(LoadError::NoLoaderFound)?;
+
+        let mut context = LoadContext {
+            path: path.clone(),
+            virtual_fs: self.virtual_fs.clone(),
+            loaders: self.loaders(),
+            dependencies: Default::default(),
+        };
+
+        let asset = loader
+            .load(&mut context)?
+            .downcast_arc::<T>()
+            .unwrap_or_else(|_| unreachable!("the loader produces the requested type"));
+
+        Ok(Handle::new(path, asset))
+    }
+}
+
 /// The context for loading assets.
 pub struct LoadContext {
     /// The path of the asset to load.
@@ -114,6 +199,8 @@ pub enum LoadError {
     ProjectNotLoaded,
     #[error("No loader found for the given path")]
     NoLoaderFound,
+    #[error("{0}")]
+    Custom(String),
 }
 
 pub trait AssetLoader: Send + Sync {
@@ -129,6 +216,9 @@ pub trait AssetLoader: Send + Sync {
 pub trait ErasedAssetLoader: Send + Sync {
     fn extensions(&self) -> &'static [&'static str];
 
+    /// Returns the type id of the assets produced by the loader.
+    fn asset_type_id(&self) -> TypeId;
+
     fn load(&self, context: &mut LoadContext) -> Result<Arc<dyn Asset>, LoadError>;
 }
 
@@ -140,6 +230,10 @@ where
         L::extensions(self)
     }
 
+    fn asset_type_id(&self) -> TypeId {
+        TypeId::of::<L::Asset>()
+    }
+
     fn load(&self, context: &mut LoadContext) -> Result<Arc<dyn Asset>, LoadError> {
         match L::load(self, context) {
             Ok(asset) => Ok(Arc::new(asset)),
@@ -147,3 +241,130 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{fs::memory::MemoryFs, vfs::VirtualFileSystemEntry};
+
+    use super::*;
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    /// A texture decoded from the header of a PNG file.
+    struct Texture {
+        width: u32,
+        height: u32,
+    }
+
+    impl Asset for Texture {}
+
+    /// The raw bytes of a PNG file.
+    struct RawImage {
+        bytes: Bytes,
+    }
+
+    impl Asset for RawImage {}
+
+    struct PngLoader;
+
+    impl AssetLoader for PngLoader {
+        type Asset = Texture;
+
+        fn extensions(&self) -> &'static [&'static str] {
+            &["png"]
+        }
+
+        fn load(&self, context: &mut LoadContext) -> Result<Texture, LoadError> {
+            let path = context.path.clone();
+            let bytes = context.read(&path)?;
+            if !bytes.starts_with(PNG_SIGNATURE) || bytes.len() < 24 {
+                return Err(LoadError::Custom("Not a PNG file".to_string()));
+            }
+
+            let read_u32 =
+                |offset: usize| u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+            Ok(Texture {
+                width: read_u32(16),
+                height: read_u32(20),
+            })
+        }
+    }
+
+    struct RawImageLoader;
+
+    impl AssetLoader for RawImageLoader {
+        type Asset = RawImage;
+
+        fn extensions(&self) -> &'static [&'static str] {
+            &["png", "jpg"]
+        }
+
+        fn load(&self, context: &mut LoadContext) -> Result<RawImage, LoadError> {
+            let path = context.path.clone();
+            Ok(RawImage {
+                bytes: context.read(&path)?,
+            })
+        }
+    }
+
+    /// Returns the signature and `IHDR` chunk of a PNG file.
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        bytes.extend(13u32.to_be_bytes());
+        bytes.extend(b"IHDR");
+        bytes.extend(width.to_be_bytes());
+        bytes.extend(height.to_be_bytes());
+        bytes
+    }
+
+    fn create_registry() -> AssetLoaderRegistry {
+        let mut fs = MemoryFs::new();
+        fs.insert("textures/checker.png", Bytes::new_owned(png_header(64, 32)));
+
+        let mut virtual_fs = VirtualFileSystem::default();
+        virtual_fs.add(VirtualFileSystemEntry::new(
+            AssetPath::new_owned("memory"),
+            true,
+            Arc::new(fs),
+        ));
+
+        AssetLoaderRegistry::new(virtual_fs)
+    }
+
+    #[test]
+    fn test_load_png_texture() {
+        let mut registry = create_registry();
+        registry.register(Box::new(PngLoader));
+
+        let path = AssetPath::new_owned("memory/textures/checker.png");
+        let texture = registry.load::<Texture>(path.clone()).unwrap();
+        assert_eq!(texture.path(), &path);
+        let texture = texture.get().unwrap();
+        assert_eq!((texture.width, texture.height), (64, 32));
+
+        assert!(matches!(
+            registry.load::<Texture>(AssetPath::new_owned("memory/missing.png")),
+            Err(LoadError::ReadError(ReadError::NotFound))
+        ));
+        assert!(matches!(
+            registry.load::<Texture>(AssetPath::new_owned("memory/shader.hlsl")),
+            Err(LoadError::NoLoaderFound)
+        ));
+    }
+
+    #[test]
+    fn test_loader_is_picked_by_asset_type() {
+        let mut registry = create_registry();
+        registry
+            .register(Box::new(PngLoader))
+            .register(Box::new(RawImageLoader));
+
+        let path = AssetPath::new_owned("memory/textures/checker.png");
+        let raw = registry.load::<RawImage>(path.clone()).unwrap();
+        assert_eq!(&*raw.get().unwrap().bytes, png_header(64, 32));
+
+        let texture = registry.load::<Texture>(path).unwrap();
+        assert_eq!(texture.get().unwrap().width, 64);
+    }
+}
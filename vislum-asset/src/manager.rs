@@ -1,6 +1,6 @@
 use std::{
-    collections::HashSet,
-    sync::{Arc, Mutex},
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, Weak},
 };
 
 use crossbeam::channel::{Receiver, Sender};
@@ -9,9 +9,11 @@ use thiserror::Error;
 use crate::{
     asset::{Asset, AssetId, InternalAssetEvent, LoadAssetCompletionEvent},
     database::{AssetDatabase, AssetState},
+    handle::{ErasedHandleInner, Handle, HandleInner, HandleTracker},
     loader::{AssetLoaders, LoadContext},
     path::AssetPath,
     vfs::{VirtualFileSystem, VirtualFileSystemEntry},
+    worker::LoadWorkers,
 };
 
 /// The shared, mutable state of the asset manager.
@@ -22,6 +24,20 @@ pub struct AssetManagerShared {
 
     /// The virtual filesystem for the assets.
     virtual_fs: VirtualFileSystem,
+
+    /// The shared state of the live handles, by asset.
+    handles: HashMap<AssetId, Weak<dyn ErasedHandleInner>>,
+
+    /// The assets whose last handle was dropped, with the frame they can be
+    /// unloaded at, counted from their latest drop.
+    pending_unloads: HashMap<AssetId, u64>,
+
+    /// The number of calls to [`AssetManager::process_dropped`].
+    frame: u64,
+
+    /// The number of frames to wait before unloading an asset whose last
+    /// handle was dropped.
+    unload_delay: u64,
 }
 
 impl AssetManagerShared {
@@ -51,6 +67,15 @@ pub struct AssetManager {
     /// The loaders for the assets.
     loaders: AssetLoaders,
 
+    /// The threads loading the assets.
+    workers: Arc<LoadWorkers>,
+
+    /// The receiver for the assets whose last handle was dropped.
+    handle_drop_rx: Receiver<AssetId>,
+
+    /// The sender for the assets whose last handle was dropped.
+    handle_drop_tx: Sender<AssetId>,
+
     /// The shared, mutable state of the asset manager.
     shared: Arc<Mutex<AssetManagerShared>>,
 }
@@ -73,11 +98,15 @@ impl AssetManager {
     pub fn new(loaders: AssetLoaders) -> Self {
         let (internal_events_tx, internal_events_rx) =
             crossbeam::channel::unbounded::<InternalAssetEvent>();
+        let (handle_drop_tx, handle_drop_rx) = crossbeam::channel::unbounded();
 
         Self {
             internal_events_rx,
             internal_events_tx,
             loaders,
+            workers: Arc::new(LoadWorkers::with_available_parallelism()),
+            handle_drop_rx,
+            handle_drop_tx,
             shared: Arc::new(Mutex::new(AssetManagerShared::default())),
         }
     }
@@ -87,6 +116,112 @@ impl AssetManager {
         self.get_untyped(id)?.clone().downcast_arc::<T>().ok()
     }
 
+    /// Returns a reference counted handle to a loaded asset.
+    ///
+    /// All the handles to an asset share the same count. Once the last one is
+    /// dropped, the asset is unloaded by [`AssetManager::process_dropped`].
+    pub fn handle<T: Asset>(&self, id: AssetId) -> Option<Handle<T>> {
+        let asset = self.get::<T>(id)?;
+        self.track_handle(id, Some(asset))
+    }
+
+    /// Loads an asset in the background, returning a handle to it right away.
+    ///
+    /// The handle is ready once [`AssetManager::process_events`] receives the
+    /// loaded asset, so the render thread can upload it. Until then,
+    /// consumers can draw a placeholder with [`Handle::get_or`]. The handle
+    /// never becomes ready if the asset fails to load.
+    pub fn load_async<T: Asset>(&mut self, path: AssetPath) -> Handle<T> {
+        let id = self.load(path);
+        self.track_handle(id, self.get::<T>(id))
+            .expect("the asset was just registered")
+    }
+
+    /// Returns a handle sharing the count of the live handles to an asset,
+    /// or tracks a new one.
+    fn track_handle<T: Asset>(&self, id: AssetId, asset: Option<Arc<T>>) -> Option<Handle<T>> {
+        let mut shared = self.shared.lock().unwrap();
+
+        let live = shared
+            .handles
+            .get(&id)
+            .and_then(Weak::upgrade)
+            .and_then(|inner| inner.into_any().downcast::<HandleInner<T>>().ok());
+        if let Some(inner) = live {
+            return Some(Handle::from_inner(inner));
+        }
+
+        let path = shared.database.get(id)?.path().clone();
+        let tracker = HandleTracker::new(id, self.handle_drop_tx.clone());
+        let handle = Handle::tracked(path, asset, tracker);
+
+        let inner: Arc<dyn ErasedHandleInner> = handle.inner().clone();
+        shared.handles.insert(id, Arc::downgrade(&inner));
+
+        Some(handle)
+    }
+
+    /// Sets the number of frames to wait before unloading an asset whose
+    /// last handle was dropped, usually the number of frames in flight, so
+    /// the frames still using its GPU resources finish first.
+    pub fn set_unload_delay(&mut self, frames: u64) {
+        self.shared.lock().unwrap().unload_delay = frames;
+    }
+
+    /// Unloads the assets whose last handle was dropped at least the unload
+    /// delay frames ago. Must be called once per frame.
+    ///
+    /// Assets which got a new handle in the meantime are kept. Assets still
+    /// loading are retried on the next call, so their completion doesn't
+    /// outlive their database entry. Returns the unloaded assets.
+    pub fn process_dropped(&mut self) -> Vec<AssetId> {
+        let mut shared = self.shared.lock().unwrap();
+        shared.frame += 1;
+
+        let unload_frame = shared.frame + shared.unload_delay;
+        for id in self.handle_drop_rx.try_iter() {
+            // An asset dropped again after getting a new handle waits for
+            // the frames using the new handle instead.
+            shared.pending_unloads.insert(id, unload_frame);
+        }
+
+        let frame = shared.frame;
+        let mut due = Vec::new();
+        shared.pending_unloads.retain(|&id, &mut unload_frame| {
+            let is_due = unload_frame <= frame;
+            if is_due {
+                due.push(id);
+            }
+            !is_due
+        });
+
+        let mut unloaded = Vec::new();
+        for id in due {
+            let has_handles = shared
+                .handles
+                .get(&id)
+                .is_some_and(|inner| inner.strong_count() > 0);
+            if has_handles {
+                continue;
+            }
+
+            let loading = shared
+                .database
+                .get(id)
+                .is_some_and(|entry| matches!(entry.state(), AssetState::Loading));
+            if loading {
+                shared.pending_unloads.insert(id, frame);
+                continue;
+            }
+
+            shared.handles.remove(&id);
+            shared.database.remove_asset(id);
+            unloaded.push(id);
+        }
+
+        unloaded
+    }
+
     /// Gets an untyped asset by its ID.
     pub fn get_untyped(&self, id: AssetId) -> Option<Arc<dyn Asset>> {
         let shared = self.shared.lock().unwrap();
@@ -115,6 +250,11 @@ impl AssetManager {
 
         // Register the asset and get its ID
         let id = shared.database.add(path.clone());
+        let generation = shared
+            .database
+            .get(id)
+            .expect("the asset was just registered")
+            .load_generation();
 
         let mut load_context = LoadContext {
             path: path.clone(),
@@ -125,13 +265,14 @@ impl AssetManager {
 
         let internal_events_tx = self.internal_events_tx.clone();
 
-        // Spawn a thread to load the asset.
-        std::thread::spawn(move || {
+        // Load the asset on a background thread.
+        self.workers.spawn(move || {
             let result = load_context.load(&path);
 
             // Report the completion of the asset loading.
             let _ = internal_events_tx.send(InternalAssetEvent::Loaded(LoadAssetCompletionEvent {
                 id,
+                generation,
                 path,
                 result,
                 dependencies: load_context.dependencies,
@@ -171,23 +312,37 @@ impl AssetManager {
                 InternalAssetEvent::Loaded(loaded_asset_event) => {
                     let mut shared = self.shared.lock().unwrap();
 
-                    // Get the AssetId for this path
-                    if let Some(asset_id) = shared.database.get_id_by_path(&loaded_asset_event.path)
-                    {
-                        // Set the asset in the database.
-                        match loaded_asset_event.result {
-                            Ok(asset) => {
-                                shared.database.set_asset_loaded(
-                                    asset_id,
-                                    asset,
-                                    loaded_asset_event.dependencies,
-                                );
-                            }
-                            Err(_) => {
-                                shared
-                                    .database
-                                    .set_asset_failed(asset_id, "Failed to load asset".to_string());
+                    // Ignore the completions of loads superseded by a newer
+                    // one, or of assets removed in the meantime.
+                    let asset_id = loaded_asset_event.id;
+                    let current = shared.database.get(asset_id).is_some_and(|entry| {
+                        entry.load_generation() == loaded_asset_event.generation
+                    });
+                    if !current {
+                        log::debug!("Ignoring stale load of asset {}", loaded_asset_event.path);
+                        continue;
+                    }
+
+                    // Set the asset in the database.
+                    match loaded_asset_event.result {
+                        Ok(asset) => {
+                            // Make the handles returned while loading ready.
+                            if let Some(inner) =
+                                shared.handles.get(&asset_id).and_then(Weak::upgrade)
+                            {
+                                inner.set_asset(asset.clone());
                             }
+
+                            shared.database.set_asset_loaded(
+                                asset_id,
+                                asset,
+                                loaded_asset_event.dependencies,
+                            );
+                        }
+                        Err(_) => {
+                            shared
+                                .database
+                                .set_asset_failed(asset_id, "Failed to load asset".to_string());
                         }
                     }
                 }
@@ -195,3 +350,240 @@ impl AssetManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        fs::{Bytes, memory::MemoryFs},
+        loader::{AssetLoader, AssetLoadersBuilder, LoadError},
+    };
+
+    /// The number of [`Counted`] assets alive.
+    static ALIVE: AtomicUsize = AtomicUsize::new(0);
+
+    /// An asset standing for a GPU resource, counting its instances.
+    struct Counted;
+
+    impl Asset for Counted {}
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            ALIVE.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    struct CountedLoader;
+
+    impl AssetLoader for CountedLoader {
+        type Asset = Counted;
+
+        fn extensions(&self) -> &'static [&'static str] {
+            &["counted"]
+        }
+
+        fn load(&self, context: &mut LoadContext) -> Result<Counted, LoadError> {
+            let path = context.path.clone();
+            context.read(&path)?;
+            ALIVE.fetch_add(1, Ordering::SeqCst);
+            Ok(Counted)
+        }
+    }
+
+    /// Loads an asset, blocking until it's loaded.
+    fn load_blocking(manager: &mut AssetManager, path: &str) -> AssetId {
+        let id = manager.load(AssetPath::new_owned(path));
+        while !manager.ready() {
+            std::thread::yield_now();
+            manager.process_events();
+        }
+
+        id
+    }
+
+    #[test]
+    fn test_dropped_asset_is_unloaded_after_delay() {
+        let mut fs = MemoryFs::new();
+        fs.insert("mesh.counted", Bytes::new_static(&[]));
+
+        let mut loaders = AssetLoadersBuilder::default();
+        loaders.add(CountedLoader);
+        let mut manager = AssetManager::new(loaders.build());
+        manager.add_virtual_fs(VirtualFileSystemEntry::new(
+            AssetPath::new_owned("memory"),
+            true,
+            Arc::new(fs),
+        ));
+        manager.set_unload_delay(2);
+
+        let id = load_blocking(&mut manager, "memory/mesh.counted");
+        let handle = manager.handle::<Counted>(id).unwrap();
+        let other = handle.clone();
+        assert_eq!(ALIVE.load(Ordering::SeqCst), 1);
+
+        // Dropping a clone keeps the asset alive.
+        drop(other);
+        assert_eq!(manager.process_dropped(), []);
+
+        drop(handle);
+        assert_eq!(manager.process_dropped(), []);
+        assert_eq!(manager.process_dropped(), []);
+        assert_eq!(ALIVE.load(Ordering::SeqCst), 1);
+
+        // The frames which may still use the asset finished.
+        assert_eq!(manager.process_dropped(), [id]);
+        assert_eq!(ALIVE.load(Ordering::SeqCst), 0);
+        assert!(manager.get::<Counted>(id).is_none());
+    }
+
+    #[test]
+    fn test_reacquired_asset_is_unloaded_after_its_last_drop() {
+        let (gate_tx, gate) = crossbeam::channel::unbounded();
+        gate_tx.send(0).unwrap();
+
+        let mut loaders = AssetLoadersBuilder::default();
+        loaders.add(GatedLoader { gate });
+        let mut manager = AssetManager::new(loaders.build());
+        manager.set_unload_delay(2);
+
+        let id = load_blocking(&mut manager, "texture.gated");
+        drop(manager.handle::<Gated>(id).unwrap());
+        assert_eq!(manager.process_dropped(), []);
+
+        // A new handle dropped a frame later postpones the unload.
+        drop(manager.handle::<Gated>(id).unwrap());
+        assert_eq!(manager.process_dropped(), []);
+        assert_eq!(manager.process_dropped(), []);
+
+        // The asset is unloaded once.
+        assert_eq!(manager.process_dropped(), [id]);
+        assert_eq!(manager.process_dropped(), []);
+        assert!(manager.get::<Gated>(id).is_none());
+    }
+
+    /// An asset whose loading waits for the test to open a gate.
+    struct Gated(u32);
+
+    impl Asset for Gated {}
+
+    struct GatedLoader {
+        gate: Receiver<u32>,
+    }
+
+    impl AssetLoader for GatedLoader {
+        type Asset = Gated;
+
+        fn extensions(&self) -> &'static [&'static str] {
+            &["gated"]
+        }
+
+        fn load(&self, _context: &mut LoadContext) -> Result<Gated, LoadError> {
+            Ok(Gated(self.gate.recv().unwrap()))
+        }
+    }
+
+    #[test]
+    fn test_asset_dropped_while_loading_is_unloaded_once_loaded() {
+        let (gate_tx, gate) = crossbeam::channel::bounded(0);
+
+        let mut loaders = AssetLoadersBuilder::default();
+        loaders.add(GatedLoader { gate });
+        let mut manager = AssetManager::new(loaders.build());
+
+        let handle = manager.load_async::<Gated>(AssetPath::new_owned("texture.gated"));
+        let id = manager.load(AssetPath::new_owned("texture.gated"));
+        drop(handle);
+
+        // The load is blocked on the gate, so the asset can't be unloaded yet.
+        assert_eq!(manager.process_dropped(), []);
+        assert_eq!(manager.process_dropped(), []);
+        assert!(manager.shared.lock().unwrap().database.get(id).is_some());
+
+        gate_tx.send(7).unwrap();
+        while !manager.ready() {
+            std::thread::yield_now();
+            manager.process_events();
+        }
+        assert_eq!(manager.get::<Gated>(id).map(|asset| asset.0), Some(7));
+
+        assert_eq!(manager.process_dropped(), [id]);
+        assert!(manager.get::<Gated>(id).is_none());
+    }
+
+    #[test]
+    fn test_stale_load_completions_are_ignored() {
+        let (gate_tx, gate) = crossbeam::channel::bounded(0);
+
+        let mut loaders = AssetLoadersBuilder::default();
+        loaders.add(GatedLoader { gate });
+        let mut manager = AssetManager::new(loaders.build());
+
+        let path = AssetPath::new_owned("texture.gated");
+        let handle = manager.load_async::<Gated>(path.clone());
+        let id = manager.load(path.clone());
+        let generation = manager
+            .shared
+            .lock()
+            .unwrap()
+            .database
+            .get(id)
+            .unwrap()
+            .load_generation();
+
+        // A completion from an earlier load of the asset is dropped.
+        manager
+            .internal_events_tx
+            .send(InternalAssetEvent::Loaded(LoadAssetCompletionEvent {
+                id,
+                generation: generation.wrapping_sub(1),
+                path,
+                filesystem_path: None,
+                result: Ok(Arc::new(Gated(1))),
+                dependencies: HashSet::new(),
+            }))
+            .unwrap();
+        manager.process_events();
+        assert!(!handle.is_ready());
+        assert!(manager.get::<Gated>(id).is_none());
+
+        // The completion of the current load is applied.
+        gate_tx.send(7).unwrap();
+        while !handle.is_ready() {
+            std::thread::yield_now();
+            manager.process_events();
+        }
+        assert_eq!(manager.get::<Gated>(id).map(|asset| asset.0), Some(7));
+    }
+
+    #[test]
+    fn test_async_handle_becomes_ready_after_polling() {
+        let (gate_tx, gate) = crossbeam::channel::bounded(0);
+
+        let mut loaders = AssetLoadersBuilder::default();
+        loaders.add(GatedLoader { gate });
+        let mut manager = AssetManager::new(loaders.build());
+
+        let handle = manager.load_async::<Gated>(AssetPath::new_owned("texture.gated"));
+        assert!(!handle.is_ready());
+        assert_eq!(handle.get_or(&Gated(0)).0, 0);
+
+        // The load is blocked on the gate, so polling completes nothing.
+        manager.process_events();
+        assert!(!handle.is_ready());
+
+        gate_tx.send(7).unwrap();
+        while !handle.is_ready() {
+            std::thread::yield_now();
+            manager.process_events();
+        }
+
+        assert_eq!(handle.get().map(|asset| asset.0), Some(7));
+
+        // Handles requested later share the loaded asset.
+        let id = manager.load(AssetPath::new_owned("texture.gated"));
+        let other = manager.handle::<Gated>(id).unwrap();
+        assert!(std::ptr::eq(other.get().unwrap(), handle.get().unwrap()));
+    }
+}
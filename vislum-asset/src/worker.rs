@@ -0,0 +1,51 @@
+use std::num::NonZeroUsize;
+
+use crossbeam::channel::Sender;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A pool of background threads reading and decoding assets.
+///
+/// The threads exit once the pool is dropped and the queued jobs are done.
+pub struct LoadWorkers {
+    jobs_tx: Sender<Job>,
+}
+
+static_assertions::assert_impl_all!(LoadWorkers: Send, Sync);
+
+impl LoadWorkers {
+    /// Creates a pool with `count` threads.
+    pub fn new(count: NonZeroUsize) -> Self {
+        let (jobs_tx, jobs_rx) = crossbeam::channel::unbounded::<Job>();
+
+        for index in 0..count.get() {
+            let jobs_rx = jobs_rx.clone();
+            std::thread::Builder::new()
+                .name(format!("asset-loader-{index}"))
+                .spawn(move || {
+                    for job in jobs_rx {
+                        job();
+                    }
+                })
+                .expect("failed to spawn an asset loader thread");
+        }
+
+        Self { jobs_tx }
+    }
+
+    /// Creates a pool with a thread per available core, up to 4.
+    pub fn with_available_parallelism() -> Self {
+        let count = std::thread::available_parallelism().map_or(NonZeroUsize::MIN, |count| {
+            count.min(NonZeroUsize::new(4).unwrap())
+        });
+
+        Self::new(count)
+    }
+
+    /// Queues a job to run on the first free thread.
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        self.jobs_tx
+            .send(Box::new(job))
+            .expect("the asset loader threads exited");
+    }
+}
@@ -14,7 +14,7 @@ extern "C" {
   void dxc_compiler_release(DxcShimCompiler *compiler);
   
   // Compiles a shader.
-  DxcShimCompilationResult* dxc_compile(DxcShimCompiler *compiler, const char *data, DxcShimUserCallback userCallback, void* userData);
+  DxcShimCompilationResult* dxc_compile(DxcShimCompiler *compiler, const char *data, const DxcShimCompileOptions *options, DxcShimUserCallback userCallback, void* userData);
   
   // Returns whether a compilation was successful.
   bool dxc_compilation_result_is_successful(DxcShimCompilationResult *result);
@@ -59,8 +59,8 @@ void dxc_compiler_release(DxcShimCompiler *compiler) {
   delete compiler;
 }
 
-DxcShimCompilationResult* dxc_compile(DxcShimCompiler *compiler, const char *data, DxcShimUserCallback userCallback, void* userData) {
-  return compiler->compile(data, userCallback, userData);
+DxcShimCompilationResult* dxc_compile(DxcShimCompiler *compiler, const char *data, const DxcShimCompileOptions *options, DxcShimUserCallback userCallback, void* userData) {
+  return compiler->compile(data, *options, userCallback, userData);
 }
 
 bool dxc_compilation_result_is_successful(DxcShimCompilationResult *result) {
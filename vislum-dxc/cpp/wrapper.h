@@ -96,6 +96,15 @@ private:
   std::vector<uint8_t> m_bytecode;
 };
 
+struct DxcShimCompileOptions {
+  const char* targetProfile;
+  const char* entryPoint;
+  uint8_t optimizationLevel;
+  // The "NAME=VALUE" preprocessor defines.
+  const char* const* defines;
+  size_t defineCount;
+};
+
 typedef char* (*DxcShimUserCallback)(const char* filename, void* userData);
 
 class DxcShimIncludeHandler : public IDxcIncludeHandler {
@@ -169,7 +178,7 @@ public:
     }
   }
 
-  inline DxcShimCompilationResult* compile(const char* data, DxcShimUserCallback userCallback, void* userData) {
+  inline DxcShimCompilationResult* compile(const char* data, DxcShimCompileOptions const& options, DxcShimUserCallback userCallback, void* userData) {
     CComPtr<IDxcResult> dxcResult;
 
     DxcBuffer buffer = {
@@ -178,21 +187,36 @@ public:
       .Encoding = CP_UTF8,
     };
 
-    LPCWSTR args[] = {
+    std::wstring entryPoint = utf8_to_utf16(options.entryPoint);
+    std::wstring targetProfile = utf8_to_utf16(options.targetProfile);
+    std::wstring optimizationLevel = L"-O" + std::to_wstring(options.optimizationLevel);
+
+    std::vector<LPCWSTR> args = {
       L"-spirv",
       L"-fspv-target-env=vulkan1.3",
-      L"-E", L"main",
-      L"-T", L"vs_6_5"
+      L"-E", entryPoint.c_str(),
+      L"-T", targetProfile.c_str(),
+      optimizationLevel.c_str(),
     };
 
-    UINT32 argCount = sizeof(args) / sizeof(args[0]);
+    // Converted up front, so the pointers stay valid while the vector is no longer modified.
+    std::vector<std::wstring> defines;
+    defines.reserve(options.defineCount);
+    for (size_t i = 0; i < options.defineCount; i++) {
+      defines.push_back(utf8_to_utf16(options.defines[i]));
+    }
+
+    for (auto const& define : defines) {
+      args.push_back(L"-D");
+      args.push_back(define.c_str());
+    }
 
     CComPtr<IDxcIncludeHandler> includeHandler; 
     if (userCallback != nullptr) {
       includeHandler = new DxcShimIncludeHandler(m_utils, userCallback, userData);
     }
 
-    m_compiler->Compile(&buffer, args, argCount, includeHandler, IID_PPV_ARGS(&dxcResult));
+    m_compiler->Compile(&buffer, args.data(), static_cast<UINT32>(args.size()), includeHandler, IID_PPV_ARGS(&dxcResult));
     
     HRESULT hr;
     dxcResult->GetStatus(&hr);
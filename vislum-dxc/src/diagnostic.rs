@@ -0,0 +1,120 @@
+/// The severity of a [`DxcDiagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DxcDiagnosticSeverity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A diagnostic emitted by DXC, parsed from its `file:line:col: severity: message`
+/// output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DxcDiagnostic {
+    pub severity: DxcDiagnosticSeverity,
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    /// The message, followed by any lines DXC printed after it, such as the
+    /// offending source line and the caret pointing at it.
+    pub message: String,
+}
+
+/// Parses the diagnostics out of DXC's error output.
+///
+/// Lines which don't start a new diagnostic are appended to the message of the
+/// previous one. Lines before the first diagnostic are ignored.
+pub(crate) fn parse_diagnostics(output: &str) -> Vec<DxcDiagnostic> {
+    let mut diagnostics = Vec::<DxcDiagnostic>::new();
+
+    for line in output.lines() {
+        if let Some(diagnostic) = parse_diagnostic_header(line) {
+            diagnostics.push(diagnostic);
+        } else if let Some(diagnostic) = diagnostics.last_mut()
+            && !line.trim().is_empty()
+        {
+            diagnostic.message.push('\n');
+            diagnostic.message.push_str(line.trim_end());
+        }
+    }
+
+    diagnostics
+}
+
+fn parse_diagnostic_header(line: &str) -> Option<DxcDiagnostic> {
+    const SEVERITIES: [(&str, DxcDiagnosticSeverity); 4] = [
+        (": fatal error: ", DxcDiagnosticSeverity::Error),
+        (": error: ", DxcDiagnosticSeverity::Error),
+        (": warning: ", DxcDiagnosticSeverity::Warning),
+        (": note: ", DxcDiagnosticSeverity::Note),
+    ];
+
+    let (location, severity, message) = SEVERITIES.iter().find_map(|(separator, severity)| {
+        let (location, message) = line.split_once(separator)?;
+        Some((location, *severity, message))
+    })?;
+
+    // Split from the end, as the file may contain colons itself.
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next()?;
+
+    Some(DxcDiagnostic {
+        severity,
+        file: file.to_string(),
+        line,
+        column,
+        message: message.trim_end().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors_and_warnings() {
+        let output = "\
+hlsl.hlsl:3:12: warning: implicit truncation of vector type
+    return position;
+           ^
+hlsl.hlsl:5:5: error: use of undeclared identifier 'foo'
+    foo();
+    ^
+";
+
+        let diagnostics = parse_diagnostics(output);
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].severity, DxcDiagnosticSeverity::Warning);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 12));
+
+        assert_eq!(diagnostics[1].severity, DxcDiagnosticSeverity::Error);
+        assert_eq!(diagnostics[1].file, "hlsl.hlsl");
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (5, 5));
+        assert_eq!(
+            diagnostics[1].message,
+            "use of undeclared identifier 'foo'\n    foo();\n    ^"
+        );
+    }
+
+    #[test]
+    fn test_parse_notes_and_paths_with_colons() {
+        let output = "\
+C:\\shaders\\main.hlsl:10:1: fatal error: 'common.hlsl' file not found
+C:\\shaders\\main.hlsl:2:1: note: included from here
+";
+
+        let diagnostics = parse_diagnostics(output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, DxcDiagnosticSeverity::Error);
+        assert_eq!(diagnostics[0].file, "C:\\shaders\\main.hlsl");
+        assert_eq!(diagnostics[1].severity, DxcDiagnosticSeverity::Note);
+        assert_eq!(diagnostics[1].message, "included from here");
+    }
+
+    #[test]
+    fn test_unstructured_output() {
+        assert!(parse_diagnostics("error: unknown argument").is_empty());
+    }
+}
@@ -1,11 +1,14 @@
 use std::{
     ffi::{CStr, CString},
     mem::MaybeUninit,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
+mod diagnostic;
 pub mod sys;
 
+pub use diagnostic::{DxcDiagnostic, DxcDiagnosticSeverity};
+
 #[derive(thiserror::Error, Debug)]
 pub enum DxcLoaderError {
     #[error("failed to open library")]
@@ -18,6 +21,11 @@ pub struct DxcLoader {
     inner: *mut sys::DxcShimLoader,
 }
 
+// SAFETY: The loader is immutable once opened, and only hands out the
+// `DxcCreateInstance2` entry point, which is safe to call from any thread.
+unsafe impl Send for DxcLoader {}
+unsafe impl Sync for DxcLoader {}
+
 impl Drop for DxcLoader {
     fn drop(&mut self) {
         unsafe { sys::dxc_loader_close(self.inner) };
@@ -74,16 +82,114 @@ struct DxcIncludeHandlerUserData<'a> {
 }
 
 #[derive(thiserror::Error, Debug)]
-#[error("compilation failed: {0}")]
-pub struct DxcCompilationError(String);
+#[error("compilation failed: {message}")]
+pub struct DxcCompilationError {
+    message: String,
+    diagnostics: Vec<DxcDiagnostic>,
+}
+
+impl DxcCompilationError {
+    fn new(message: String) -> Self {
+        let diagnostics = diagnostic::parse_diagnostics(&message);
+        Self {
+            message,
+            diagnostics,
+        }
+    }
+
+    /// Returns the raw error output of DXC.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the diagnostics parsed from the error output.
+    ///
+    /// May be empty if DXC failed without pointing at the source, in which case
+    /// only [`DxcCompilationError::message`] is available.
+    #[inline]
+    pub fn diagnostics(&self) -> &[DxcDiagnostic] {
+        &self.diagnostics
+    }
+}
+
+/// The optimization level of a compilation, mapping to DXC's `-O` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DxcOptimizationLevel {
+    /// Disables optimizations.
+    O0,
+    O1,
+    O2,
+    /// The default optimization level of DXC.
+    #[default]
+    O3,
+}
+
+/// The options of a compilation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DxcCompileOptions {
+    /// The target profile, such as `vs_6_6`, `ps_6_6` or `cs_6_0`.
+    pub target_profile: String,
+    /// The name of the entry point function.
+    pub entry_point: String,
+    pub optimization_level: DxcOptimizationLevel,
+    /// The preprocessor defines, as `(name, value)` pairs.
+    pub defines: Vec<(String, String)>,
+}
+
+impl DxcCompileOptions {
+    pub fn new(target_profile: impl Into<String>, entry_point: impl Into<String>) -> Self {
+        Self {
+            target_profile: target_profile.into(),
+            entry_point: entry_point.into(),
+            optimization_level: Default::default(),
+            defines: Vec::new(),
+        }
+    }
 
+    /// Adds a preprocessor define, equivalent to `-D name=value`.
+    pub fn define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.push((name.into(), value.into()));
+        self
+    }
+
+    /// Adds several preprocessor defines.
+    pub fn defines(mut self, defines: &[(&str, &str)]) -> Self {
+        self.defines.extend(
+            defines
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        self
+    }
+
+    pub fn optimization_level(mut self, optimization_level: DxcOptimizationLevel) -> Self {
+        self.optimization_level = optimization_level;
+        self
+    }
+}
+
+impl Default for DxcCompileOptions {
+    /// The `main` entry point of a vertex shader.
+    fn default() -> Self {
+        Self::new("vs_6_5", "main")
+    }
+}
+
+/// An in-process DXC compiler.
+///
+/// The compiler can be shared between threads, but the underlying `IDxcCompiler3`
+/// instance isn't safe to use concurrently, so compilations on the same compiler
+/// are serialized. Create a compiler per thread to compile in parallel.
 pub struct DxcCompiler {
     _loader: Arc<DxcLoader>,
     inner: *mut sys::DxcShimCompiler,
+    /// Serializes the calls into the compiler instance.
+    compile_lock: Mutex<()>,
 }
 
-// SAFETY: DxcCompiler is thread-safe.
-// This is also a lie. Gotta fix this...
+// SAFETY: The compiler instance is only ever used while holding `compile_lock`,
+// and it is released on drop, which requires exclusive access.
 unsafe impl Send for DxcCompiler {}
 unsafe impl Sync for DxcCompiler {}
 
@@ -98,6 +204,7 @@ impl DxcCompiler {
                 Ok(Arc::new(Self {
                     _loader: loader,
                     inner,
+                    compile_lock: Mutex::new(()),
                 }))
             }
             sys::DxcShimStatus::GetDxcCompilerInstanceError => {
@@ -110,22 +217,61 @@ impl DxcCompiler {
         }
     }
 
+    /// Compiles the `main` entry point of a vertex shader.
+    pub fn compile_simple(
+        &self,
+        data: &str,
+        include_handler: &dyn DxcIncludeHandler,
+    ) -> Result<Vec<u8>, DxcCompilationError> {
+        self.compile(data, &DxcCompileOptions::default(), include_handler)
+    }
+
     pub fn compile<'a>(
         &self,
         data: &str,
+        options: &DxcCompileOptions,
         include_handler: &'a dyn DxcIncludeHandler,
     ) -> Result<Vec<u8>, DxcCompilationError> {
         let data_cstr = CString::new(data).unwrap();
+        let target_profile_cstr = CString::new(options.target_profile.as_str()).unwrap();
+        let entry_point_cstr = CString::new(options.entry_point.as_str()).unwrap();
+
+        // Interned for the duration of the call, as the shim only borrows them.
+        let define_cstrs = options
+            .defines
+            .iter()
+            .map(|(name, value)| CString::new(format!("{name}={value}")).unwrap())
+            .collect::<Vec<_>>();
+        let define_ptrs = define_cstrs
+            .iter()
+            .map(|define| define.as_ptr())
+            .collect::<Vec<_>>();
+
+        let raw_options = sys::DxcShimCompileOptions {
+            target_profile: target_profile_cstr.as_ptr(),
+            entry_point: entry_point_cstr.as_ptr(),
+            optimization_level: options.optimization_level as u8,
+            defines: define_ptrs.as_ptr(),
+            define_count: define_ptrs.len(),
+        };
 
         let user_data = DxcIncludeHandlerUserData {
             include_handler,
             strings: Vec::new(),
         };
 
+        // A poisoned lock only means another compilation panicked, which leaves the
+        // compiler instance untouched.
+        let guard = self
+            .compile_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
         let raw_result = unsafe {
             sys::dxc_compile(
                 self.inner,
                 data_cstr.as_ptr() as *const _,
+                &raw_options,
                 Some(
                     dxc_include_handler_trampoline
                         as unsafe extern "C" fn(
@@ -137,6 +283,8 @@ impl DxcCompiler {
             )
         };
 
+        drop(guard);
+
         let result = if unsafe { sys::dxc_compilation_result_is_successful(raw_result) } {
             let mut bytecode = MaybeUninit::<*mut std::ffi::c_void>::uninit();
             let mut size = MaybeUninit::<usize>::uninit();
@@ -159,7 +307,7 @@ impl DxcCompiler {
             let error_message = unsafe { CStr::from_ptr(error_message_c) }
                 .to_string_lossy()
                 .into_owned();
-            Err(DxcCompilationError(error_message))
+            Err(DxcCompilationError::new(error_message))
         };
 
         unsafe { sys::dxc_compilation_result_free(raw_result) };
@@ -191,3 +339,108 @@ unsafe extern "C" fn dxc_include_handler_trampoline(
         None => std::ptr::null(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoIncludes;
+
+    impl DxcIncludeHandler for NoIncludes {
+        fn load_source(&self, _filename: &str) -> Option<String> {
+            None
+        }
+    }
+
+    /// Creates a compiler. The DXC library is loaded at runtime, so the tests
+    /// using it are ignored by default.
+    fn create_compiler() -> Arc<DxcCompiler> {
+        let loader = DxcLoader::new().expect("the test requires the DXC library");
+        DxcCompiler::new(loader).unwrap()
+    }
+
+    #[test]
+    #[ignore = "requires the DXC library"]
+    fn test_compile_compute_shader() {
+        let compiler = create_compiler();
+
+        let source = r#"
+            [[vk::binding(0, 0)]] RWStructuredBuffer<float> values;
+
+            [numthreads(64, 1, 1)]
+            void cs_main(uint3 id : SV_DispatchThreadID) {
+                values[id.x] *= 2.0;
+            }
+        "#;
+
+        let options = DxcCompileOptions::new("cs_6_0", "cs_main")
+            .optimization_level(DxcOptimizationLevel::O0);
+        let bytecode = compiler.compile(source, &options, &NoIncludes).unwrap();
+        assert_eq!(bytecode[..4], 0x07230203u32.to_le_bytes());
+
+        // The default options target a vertex shader, which has no `main` here.
+        assert!(compiler.compile_simple(source, &NoIncludes).is_err());
+    }
+
+    #[test]
+    #[ignore = "requires the DXC library"]
+    fn test_defines_change_output() {
+        let compiler = create_compiler();
+
+        let source = r#"
+            float4 main(float3 position : POSITION) : SV_Position {
+            #ifdef FEATURE_X
+                return float4(position * 2.0, 1.0);
+            #else
+                return float4(position, 1.0);
+            #endif
+            }
+        "#;
+
+        let options = DxcCompileOptions::default();
+        let without = compiler.compile(source, &options, &NoIncludes).unwrap();
+        let with = compiler
+            .compile(source, &options.define("FEATURE_X", "1"), &NoIncludes)
+            .unwrap();
+
+        assert_ne!(without, with);
+    }
+
+    #[test]
+    #[ignore = "requires the DXC library"]
+    fn test_concurrent_compilation() {
+        let compiler = create_compiler();
+
+        let source = r#"
+            float4 main(float3 position : POSITION) : SV_Position {
+                return float4(position * SCALE, 1.0);
+            }
+        "#;
+
+        let expected = compiler
+            .compile(
+                source,
+                &DxcCompileOptions::default().define("SCALE", "2.0"),
+                &NoIncludes,
+            )
+            .unwrap();
+
+        let threads = (0..8)
+            .map(|_| {
+                let compiler = compiler.clone();
+                std::thread::spawn(move || {
+                    let options = DxcCompileOptions::default().define("SCALE", "2.0");
+                    (0..16)
+                        .map(|_| compiler.compile(source, &options, &NoIncludes).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            for bytecode in thread.join().unwrap() {
+                assert_eq!(bytecode, expected);
+            }
+        }
+    }
+}
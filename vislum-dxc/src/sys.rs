@@ -32,6 +32,17 @@ pub struct DxcShimCompilationResult {
     _marker: PhantomData<(*mut u8, PhantomPinned)>,
 }
 
+/// The options of a compilation, borrowed for the duration of [`dxc_compile`].
+#[repr(C)]
+pub struct DxcShimCompileOptions {
+    pub target_profile: *const std::ffi::c_char,
+    pub entry_point: *const std::ffi::c_char,
+    pub optimization_level: u8,
+    /// The `NAME=VALUE` preprocessor defines.
+    pub defines: *const *const std::ffi::c_char,
+    pub define_count: usize,
+}
+
 pub type DxcShimUserCallback = Option<
     unsafe extern "C" fn(
         filename: *const std::ffi::c_char,
@@ -50,6 +61,7 @@ unsafe extern "C" {
     pub unsafe fn dxc_compile(
         compiler: *mut DxcShimCompiler,
         data: *const std::ffi::c_char,
+        options: *const DxcShimCompileOptions,
         user_callback: DxcShimUserCallback,
         user_data: *mut std::ffi::c_void,
     ) -> *mut DxcShimCompilationResult;
@@ -0,0 +1,77 @@
+//! Glob pattern matching over namespace-relative paths.
+
+use std::path::Path;
+
+/// Returns true if the path matches the glob pattern.
+///
+/// Patterns are split into `/` separated segments, which are matched against
+/// the components of the path:
+///
+/// - `**` matches any number of components, including none.
+/// - `*` matches any sequence of characters within a component.
+/// - `?` matches a single character within a component.
+pub fn matches(pattern: &str, path: &Path) -> bool {
+    let pattern = pattern
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    let components = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    let components = components.iter().map(|c| &**c).collect::<Vec<_>>();
+
+    matches_segments(&pattern, &components)
+}
+
+fn matches_segments(pattern: &[&str], components: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => components.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=components.len()).any(|skip| matches_segments(rest, &components[skip..]))
+        }
+        Some((segment, rest)) => match components.split_first() {
+            Some((component, components)) => {
+                matches_component(segment.as_bytes(), component.as_bytes())
+                    && matches_segments(rest, components)
+            }
+            None => false,
+        },
+    }
+}
+
+fn matches_component(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| matches_component(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && matches_component(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && matches_component(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_star_stays_within_component() {
+        assert!(matches("*.png", Path::new("a.png")));
+        assert!(!matches("*.png", Path::new("dir/a.png")));
+        assert!(matches("materials/*.png", Path::new("materials/a.png")));
+    }
+
+    #[test]
+    fn test_double_star_matches_any_depth() {
+        assert!(matches("**/*.png", Path::new("a.png")));
+        assert!(matches("**/*.png", Path::new("a/b/c.png")));
+        assert!(!matches("**/*.png", Path::new("a/b/c.jpg")));
+        assert!(matches("a/**", Path::new("a/b/c")));
+    }
+
+    #[test]
+    fn test_question_mark_matches_single_character() {
+        assert!(matches("tex?.png", Path::new("tex1.png")));
+        assert!(!matches("tex?.png", Path::new("tex10.png")));
+    }
+}
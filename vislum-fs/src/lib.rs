@@ -1,13 +1,23 @@
 use std::{
-    path::{Path, PathBuf}, sync::Arc
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use atomicow::CowArc;
 use crossbeam::channel::{Receiver, Sender, TryIter};
 
+mod glob;
+pub mod memory;
+pub mod overlay;
 pub mod physical;
 pub mod watcher;
 
+#[cfg(test)]
+mod test_util;
+
+pub use memory::MemoryFs;
+pub use overlay::OverlayFs;
 pub use physical::PhysicalFsWatcher;
 pub use watcher::PhysicalFs;
 
@@ -17,8 +27,10 @@ pub enum FsError {
     NotFound(PathBuf),
     #[error("a physical I/O error occurred at {0}: {1}")]
     PhysicalIo(PathBuf, std::io::Error),
-    #[error("unknown namespace: {0:?}")]
+    #[error("unknown namespace: {0}")]
     UnknownNamespace(VirtualNamespace),
+    #[error("the operation is not supported by the filesystem: {0}")]
+    Unsupported(&'static str),
 }
 
 /// An iterator over the files in a directory.
@@ -31,17 +43,80 @@ pub trait Fs {
     /// Returns the contents of the file as a byte array or a file error.
     fn read(&self, path: &Path) -> Result<Arc<[u8]>, FsError>;
 
+    /// Opens a file for streaming reads.
+    ///
+    /// The default implementation reads the whole file into memory, so
+    /// filesystems which can stream from their backing storage should
+    /// override it.
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>, FsError> {
+        let data = self.read(path)?;
+
+        Ok(Box::new(Cursor::new(data)))
+    }
+
     /// Writes a file to the filesystem.
+    ///
+    /// Missing parent directories are created.
     fn write(&self, path: &Path, data: &[u8]) -> Result<(), FsError>;
 
+    /// Creates a directory and all of its missing parents.
+    ///
+    /// Read-only filesystems return [`FsError::Unsupported`].
+    fn create_dir(&self, _path: &Path) -> Result<(), FsError> {
+        Err(FsError::Unsupported("create_dir"))
+    }
+
     /// Lists the files in a directory.
     ///
     /// If no path is provided, the root directory is listed.
     fn list(&self, path: Option<&Path>) -> FsIterator;
+
+    /// Lists the files in a directory and all of its subdirectories.
+    ///
+    /// Only files are returned. The default implementation walks the tree
+    /// through [`Fs::list`] up front, treating the entries with children as
+    /// directories, so filesystems which can tell directories apart should
+    /// override it with a lazy walk.
+    fn list_recursive(&self, path: Option<&Path>) -> FsIterator {
+        let mut files = Vec::new();
+        let mut pending = self.list(path).collect::<Vec<_>>();
+        while let Some(path) = pending.pop() {
+            let children = self.list(Some(&path)).collect::<Vec<_>>();
+            if children.is_empty() {
+                files.push(path);
+            } else {
+                pending.extend(children);
+            }
+        }
+
+        Box::new(files.into_iter())
+    }
+
+    /// Lists the files matching the glob pattern, at any depth.
+    ///
+    /// Patterns are matched against the root-relative path and support `*`,
+    /// `**` and `?`. For example, `materials/**/*.png` matches every PNG under
+    /// the `materials` directory at any depth.
+    fn glob(&self, pattern: &str) -> FsIterator {
+        let pattern = pattern.to_string();
+
+        Box::new(
+            self.list_recursive(None)
+                .filter(move |path| glob::matches(&pattern, path)),
+        )
+    }
 }
 
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum VirtualPathError {
+    #[error("the path has no namespace: {0:?}")]
+    MissingNamespace(String),
+    #[error("invalid namespace name: {0:?}")]
+    InvalidNamespace(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum VirtualNamespace {
     /// The library namespace.
     ///
@@ -57,6 +132,49 @@ pub enum VirtualNamespace {
     ///
     /// Stores compiled shader modules.
     ShaderCache,
+
+    /// A namespace mounted by the user, such as `assets`.
+    Custom(Arc<str>),
+}
+
+impl VirtualNamespace {
+    /// Creates a namespace from its name.
+    ///
+    /// The engine namespaces are recognized by name, and any other valid name
+    /// results in a [`VirtualNamespace::Custom`] namespace. Names may only contain
+    /// lowercase ASCII letters, digits, `-` and `_`.
+    pub fn from_name(name: &str) -> Result<Self, VirtualPathError> {
+        let is_valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !is_valid {
+            return Err(VirtualPathError::InvalidNamespace(name.to_string()));
+        }
+
+        Ok(match name {
+            "library" => VirtualNamespace::Library,
+            "project" => VirtualNamespace::Project,
+            "shader-cache" => VirtualNamespace::ShaderCache,
+            _ => VirtualNamespace::Custom(Arc::from(name)),
+        })
+    }
+
+    /// Returns the name of the namespace, as used in virtual paths.
+    pub fn name(&self) -> &str {
+        match self {
+            VirtualNamespace::Library => "library",
+            VirtualNamespace::Project => "project",
+            VirtualNamespace::ShaderCache => "shader-cache",
+            VirtualNamespace::Custom(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for VirtualNamespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -66,24 +184,22 @@ pub struct VirtualPath {
 }
 
 impl VirtualPath {
-    pub fn parse(path: &str) -> Self {
-        let (namespace, path) = path.split_once("://").unwrap();
-        let namespace = match namespace {
-            "library" => VirtualNamespace::Library,
-            "project" => VirtualNamespace::Project,
-            "shader-cache" => VirtualNamespace::ShaderCache,
-            _ => unreachable!(),
-        };
+    /// Parses a `namespace://path` string.
+    pub fn parse(path: &str) -> Result<Self, VirtualPathError> {
+        let (namespace, path) = path
+            .split_once("://")
+            .ok_or_else(|| VirtualPathError::MissingNamespace(path.to_string()))?;
+        let namespace = VirtualNamespace::from_name(namespace)?;
 
-        Self {
+        Ok(Self {
             namespace,
             path: CowArc::new_owned_from_arc(Path::new(path)),
-        }
+        })
     }
 
     #[inline(always)]
-    pub fn namespace(&self) -> VirtualNamespace {
-        self.namespace
+    pub fn namespace(&self) -> &VirtualNamespace {
+        &self.namespace
     }
 
     #[inline(always)]
@@ -94,6 +210,43 @@ impl VirtualPath {
     pub fn new(namespace: VirtualNamespace, path: &Path) -> Self {
         Self { namespace, path: CowArc::new_owned_from_arc(path) }
     }
+
+    /// Returns the path with `segment` appended, in the same namespace.
+    pub fn join(&self, segment: &str) -> VirtualPath {
+        self.with_path(self.path.join(segment))
+    }
+
+    /// Returns the parent path, in the same namespace.
+    ///
+    /// Returns `None` if the path is the root of the namespace.
+    pub fn parent(&self) -> Option<VirtualPath> {
+        let parent = self.path.parent()?;
+
+        Some(self.with_path(parent.to_path_buf()))
+    }
+
+    /// Returns the path with its extension replaced, or added if it has none.
+    pub fn with_extension(&self, extension: &str) -> VirtualPath {
+        self.with_path(self.path.with_extension(extension))
+    }
+
+    /// Returns the final component of the path, if any.
+    pub fn file_name(&self) -> Option<&str> {
+        self.path.file_name()?.to_str()
+    }
+
+    fn with_path(&self, path: PathBuf) -> VirtualPath {
+        Self {
+            namespace: self.namespace.clone(),
+            path: CowArc::Owned(Arc::from(path)),
+        }
+    }
+}
+
+impl std::fmt::Display for VirtualPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://{}", self.namespace, self.path.display())
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -122,6 +275,9 @@ pub trait Watcher {
 
 pub struct VirtualFs {
     entries: Vec<(VirtualNamespace, Box<dyn Fs>)>,
+    watchers: Vec<Box<dyn Watcher>>,
+    events_tx: Sender<FileEvent>,
+    events: Receiver<FileEvent>,
 }
 
 impl VirtualFs {
@@ -129,8 +285,40 @@ impl VirtualFs {
         entries: impl IntoIterator<Item = (VirtualNamespace, Box<dyn Fs>)>,
     ) -> Self {
         let entries = entries.into_iter().collect();
+        let (events_tx, events) = crossbeam::channel::unbounded();
+
+        Self {
+            entries,
+            watchers: Vec::new(),
+            events_tx,
+            events,
+        }
+    }
 
-        Self { entries }
+    /// Creates a virtual filesystem which watches the given physical roots for
+    /// changes, one per namespace.
+    pub fn with_watchers(
+        entries: impl IntoIterator<Item = (VirtualNamespace, Box<dyn Fs>)>,
+        watch_roots: impl IntoIterator<Item = (VirtualNamespace, PathBuf)>,
+    ) -> Self {
+        let mut fs = Self::new(entries);
+        for (namespace, root) in watch_roots {
+            fs.watch::<PhysicalFsWatcher>(namespace, root);
+        }
+
+        fs
+    }
+
+    /// Starts watching `root` for changes, reporting them as events of the
+    /// namespace.
+    ///
+    /// The watcher lives as long as the filesystem.
+    pub fn watch<W>(&mut self, namespace: VirtualNamespace, root: PathBuf)
+    where
+        W: Watcher,
+    {
+        let watcher = W::new(namespace, root, self.events_tx.clone());
+        self.watchers.push(watcher);
     }
 
     pub fn read(&self, path: VirtualPath) -> Result<Arc<[u8]>, FsError> {
@@ -139,29 +327,172 @@ impl VirtualFs {
         fs.read(&path.path)
     }
 
+    pub fn open_read(&self, path: VirtualPath) -> Result<Box<dyn Read + Send>, FsError> {
+        let fs = self.resolve(path.namespace())?;
+
+        fs.open_read(&path.path)
+    }
+
     pub fn write(&self, path: VirtualPath, data: &[u8]) -> Result<(), FsError> {
         let fs = self.resolve(path.namespace())?;
 
         fs.write(&path.path, data)
     }
 
+    pub fn create_dir(&self, path: VirtualPath) -> Result<(), FsError> {
+        let fs = self.resolve(path.namespace())?;
+
+        fs.create_dir(&path.path)
+    }
+
     pub fn list(&self, path: VirtualPath) -> Result<FsIterator, FsError> {
         let fs = self.resolve(path.namespace())?;
         Ok(fs.list(None))
     }
 
-    fn resolve(&self, namespace: VirtualNamespace) -> Result<&dyn Fs, FsError> {
+    /// Returns the paths of the files in the namespace matching the glob pattern.
+    ///
+    /// See [`Fs::glob`] for the supported patterns.
+    pub fn glob(
+        &self,
+        namespace: VirtualNamespace,
+        pattern: &str,
+    ) -> Result<impl Iterator<Item = VirtualPath>, FsError> {
+        let fs = self.resolve(&namespace)?;
+
+        Ok(fs
+            .glob(pattern)
+            .map(move |path| VirtualPath::new(namespace.clone(), &path)))
+    }
+
+    fn resolve(&self, namespace: &VirtualNamespace) -> Result<&dyn Fs, FsError> {
         let entry = self
             .entries
             .iter()
-            .find(|e| e.0 == namespace)
-            .ok_or(FsError::UnknownNamespace(namespace))?;
+            .find(|e| e.0 == *namespace)
+            .ok_or_else(|| FsError::UnknownNamespace(namespace.clone()))?;
 
         Ok(&*entry.1)
     }
 
-    pub fn events(&self) -> impl Iterator<Item = FileEvent> {
-        todo!()
-        // self.events.try_iter()
+    /// Returns the file events received since the last call, without blocking.
+    pub fn events(&self) -> TryIter<'_, FileEvent> {
+        self.events.try_iter()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A watcher which reports its root as modified as soon as it is created.
+    struct TouchWatcher;
+
+    impl Watcher for TouchWatcher {
+        fn new(
+            namespace: VirtualNamespace,
+            root: PathBuf,
+            notify: Sender<FileEvent>,
+        ) -> Box<dyn Watcher> {
+            notify
+                .send(FileEvent {
+                    path: VirtualPath::new(namespace, &root),
+                    event_type: FileEventType::Modified,
+                })
+                .unwrap();
+
+            Box::new(Self)
+        }
+    }
+
+    #[test]
+    fn test_events_are_forwarded_from_watchers() {
+        let mut vfs = VirtualFs::new([]);
+        assert_eq!(vfs.events().count(), 0);
+
+        vfs.watch::<TouchWatcher>(VirtualNamespace::ShaderCache, PathBuf::from("main.hlsl"));
+
+        let events = vfs.events().collect::<Vec<_>>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].path,
+            VirtualPath::parse("shader-cache://main.hlsl").unwrap()
+        );
+        assert_eq!(events[0].event_type, FileEventType::Modified);
+        assert_eq!(vfs.events().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_namespaces() {
+        let path = VirtualPath::parse("library://textures/a.png").unwrap();
+        assert_eq!(*path.namespace(), VirtualNamespace::Library);
+        assert_eq!(path.path(), Path::new("textures/a.png"));
+
+        let path = VirtualPath::parse("assets://a.png").unwrap();
+        assert_eq!(*path.namespace(), VirtualNamespace::Custom("assets".into()));
+        assert_eq!(path.namespace().name(), "assets");
+
+        assert_eq!(
+            VirtualPath::parse("a.png"),
+            Err(VirtualPathError::MissingNamespace("a.png".into()))
+        );
+        assert_eq!(
+            VirtualPath::parse("Bad Name://a.png"),
+            Err(VirtualPathError::InvalidNamespace("Bad Name".into()))
+        );
+    }
+
+    #[test]
+    fn test_custom_namespace_resolution() {
+        struct EmptyFs;
+
+        impl Fs for EmptyFs {
+            fn read(&self, path: &Path) -> Result<Arc<[u8]>, FsError> {
+                Err(FsError::NotFound(path.to_path_buf()))
+            }
+
+            fn write(&self, _path: &Path, _data: &[u8]) -> Result<(), FsError> {
+                Err(FsError::Unsupported("write"))
+            }
+
+            fn list(&self, _path: Option<&Path>) -> FsIterator {
+                Box::new(std::iter::empty())
+            }
+        }
+
+        let vfs = VirtualFs::new([(
+            VirtualNamespace::from_name("assets").unwrap(),
+            Box::new(EmptyFs) as Box<dyn Fs>,
+        )]);
+
+        assert!(matches!(
+            vfs.read(VirtualPath::parse("assets://a.png").unwrap()),
+            Err(FsError::NotFound(_))
+        ));
+        assert!(matches!(
+            vfs.read(VirtualPath::parse("cache://a.png").unwrap()),
+            Err(FsError::UnknownNamespace(_))
+        ));
+    }
+
+    #[test]
+    fn test_path_helpers_keep_namespace() {
+        let path = VirtualPath::parse("library://shaders/main.hlsl").unwrap();
+
+        assert_eq!(path.file_name(), Some("main.hlsl"));
+        assert_eq!(
+            path.parent().unwrap().join("common.hlsl"),
+            VirtualPath::parse("library://shaders/common.hlsl").unwrap()
+        );
+        assert_eq!(
+            path.with_extension("spv"),
+            VirtualPath::parse("library://shaders/main.spv").unwrap()
+        );
+
+        assert_eq!(path.to_string(), "library://shaders/main.hlsl");
+
+        let root = path.parent().unwrap().parent().unwrap();
+        assert_eq!(root.path(), Path::new(""));
+        assert!(root.parent().is_none());
+        assert_eq!(root.file_name(), None);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use crate::{Fs, FsError, FsIterator};
+
+/// A filesystem holding its files in memory.
+///
+/// Mostly useful for tests, which can mount it in place of a physical
+/// filesystem. Directories are implicit: they exist as long as a file is
+/// stored under them.
+#[derive(Default)]
+pub struct MemoryFs {
+    files: RwLock<HashMap<PathBuf, Arc<[u8]>>>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Inserts a file, replacing the previous one at the same path.
+    pub fn insert(&self, path: impl Into<PathBuf>, data: impl Into<Arc<[u8]>>) {
+        self.files.write().unwrap().insert(path.into(), data.into());
+    }
+}
+
+impl FromIterator<(PathBuf, Vec<u8>)> for MemoryFs {
+    fn from_iter<T: IntoIterator<Item = (PathBuf, Vec<u8>)>>(iter: T) -> Self {
+        let files = iter
+            .into_iter()
+            .map(|(path, data)| (path, Arc::from(data)))
+            .collect();
+
+        Self {
+            files: RwLock::new(files),
+        }
+    }
+}
+
+impl Fs for MemoryFs {
+    fn read(&self, path: &Path) -> Result<Arc<[u8]>, FsError> {
+        self.files
+            .read()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| FsError::NotFound(path.to_path_buf()))
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<(), FsError> {
+        self.insert(path, data);
+        Ok(())
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<(), FsError> {
+        // Directories are implicit.
+        Ok(())
+    }
+
+    fn list(&self, path: Option<&Path>) -> FsIterator {
+        let dir = path.unwrap_or(Path::new(""));
+
+        // Files directly in the directory, and the subdirectories holding the
+        // rest. Sorted, for a deterministic order.
+        let entries = self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter_map(|file| {
+                let child = file.strip_prefix(dir).ok()?.components().next()?;
+                Some(dir.join(child))
+            })
+            .collect::<BTreeSet<_>>();
+
+        Box::new(entries.into_iter())
+    }
+
+    fn list_recursive(&self, path: Option<&Path>) -> FsIterator {
+        let dir = path.unwrap_or(Path::new(""));
+
+        let mut files = self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|file| file.starts_with(dir) && *file != dir)
+            .cloned()
+            .collect::<Vec<_>>();
+        files.sort();
+
+        Box::new(files.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_back_written_data() {
+        let fs = MemoryFs::new();
+        fs.write(Path::new("shaders/main.spv"), b"spirv").unwrap();
+
+        assert_eq!(&*fs.read(Path::new("shaders/main.spv")).unwrap(), b"spirv");
+        assert!(matches!(
+            fs.read(Path::new("shaders/missing.spv")),
+            Err(FsError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_list_keys() {
+        let fs = [
+            (PathBuf::from("a.hlsl"), b"a".to_vec()),
+            (PathBuf::from("shaders/b.hlsl"), b"b".to_vec()),
+            (PathBuf::from("shaders/deep/c.hlsl"), b"c".to_vec()),
+        ]
+        .into_iter()
+        .collect::<MemoryFs>();
+        fs.insert("shaders/d.hlsl", b"d".to_vec());
+
+        assert_eq!(
+            fs.list(None).collect::<Vec<_>>(),
+            [PathBuf::from("a.hlsl"), PathBuf::from("shaders")]
+        );
+        assert_eq!(
+            fs.list(Some(Path::new("shaders"))).collect::<Vec<_>>(),
+            [
+                PathBuf::from("shaders/b.hlsl"),
+                PathBuf::from("shaders/d.hlsl"),
+                PathBuf::from("shaders/deep"),
+            ]
+        );
+        assert_eq!(
+            fs.glob("shaders/**/*.hlsl").collect::<Vec<_>>(),
+            [
+                PathBuf::from("shaders/b.hlsl"),
+                PathBuf::from("shaders/d.hlsl"),
+                PathBuf::from("shaders/deep/c.hlsl"),
+            ]
+        );
+    }
+}
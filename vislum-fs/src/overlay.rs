@@ -0,0 +1,185 @@
+use std::{
+    collections::HashSet,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{Fs, FsError, FsIterator};
+
+/// A filesystem layering several filesystems on top of each other.
+///
+/// Reads fall through the layers from the top until one has the file, so the
+/// upper layers shadow the lower ones. Writes go to the topmost layer which
+/// supports them, skipping read-only layers returning [`FsError::Unsupported`].
+///
+/// For example, a writable project directory overlaid on the read-only
+/// library lets local edits shadow the shipped defaults.
+pub struct OverlayFs {
+    /// The layers, from top to bottom.
+    layers: Vec<Box<dyn Fs>>,
+}
+
+impl OverlayFs {
+    /// Creates an overlay from its layers, ordered from top to bottom.
+    pub fn new(layers: impl IntoIterator<Item = Box<dyn Fs>>) -> Self {
+        Self {
+            layers: layers.into_iter().collect(),
+        }
+    }
+
+    /// Calls `f` on each layer from the top, until one doesn't return the
+    /// error accepted by `fall_through`.
+    fn first_layer<T>(
+        &self,
+        path: &Path,
+        fall_through: fn(&FsError) -> bool,
+        f: impl Fn(&dyn Fs) -> Result<T, FsError>,
+    ) -> Result<T, FsError> {
+        let mut last_error = None;
+        for layer in &self.layers {
+            match f(&**layer) {
+                Err(error) if fall_through(&error) => last_error = Some(error),
+                result => return result,
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| FsError::NotFound(path.to_path_buf())))
+    }
+
+    /// Unions the entries listed by each layer, keeping the first occurrence
+    /// of each path.
+    fn union(&self, list: impl Fn(&dyn Fs) -> FsIterator) -> FsIterator {
+        let entries = self
+            .layers
+            .iter()
+            .map(|layer| list(&**layer))
+            .collect::<Vec<_>>();
+
+        let mut seen = HashSet::<PathBuf>::new();
+        Box::new(
+            entries
+                .into_iter()
+                .flatten()
+                .filter(move |path| seen.insert(path.clone())),
+        )
+    }
+}
+
+fn is_not_found(error: &FsError) -> bool {
+    matches!(error, FsError::NotFound(_))
+}
+
+fn is_unsupported(error: &FsError) -> bool {
+    matches!(error, FsError::Unsupported(_))
+}
+
+impl Fs for OverlayFs {
+    fn read(&self, path: &Path) -> Result<Arc<[u8]>, FsError> {
+        self.first_layer(path, is_not_found, |layer| layer.read(path))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>, FsError> {
+        self.first_layer(path, is_not_found, |layer| layer.open_read(path))
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<(), FsError> {
+        self.first_layer(path, is_unsupported, |layer| layer.write(path, data))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), FsError> {
+        self.first_layer(path, is_unsupported, |layer| layer.create_dir(path))
+    }
+
+    fn list(&self, path: Option<&Path>) -> FsIterator {
+        self.union(|layer| layer.list(path))
+    }
+
+    fn list_recursive(&self, path: Option<&Path>) -> FsIterator {
+        self.union(|layer| layer.list_recursive(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PhysicalFs, test_util::temp_root};
+
+    use super::*;
+
+    /// Exposes a filesystem without its write operations.
+    struct ReadOnlyFs(PhysicalFs);
+
+    impl Fs for ReadOnlyFs {
+        fn read(&self, path: &Path) -> Result<Arc<[u8]>, FsError> {
+            self.0.read(path)
+        }
+
+        fn write(&self, _path: &Path, _data: &[u8]) -> Result<(), FsError> {
+            Err(FsError::Unsupported("write"))
+        }
+
+        fn list(&self, path: Option<&Path>) -> FsIterator {
+            self.0.list(path)
+        }
+    }
+
+    #[test]
+    fn test_writes_skip_read_only_layers() {
+        let project_root = temp_root("project");
+        let library_root = temp_root("library");
+
+        let project = PhysicalFs::new(project_root.clone());
+        let library = PhysicalFs::new(library_root.clone());
+        library.write(Path::new("default.mat"), b"library").unwrap();
+        library.write(Path::new("shared.mat"), b"library").unwrap();
+
+        let overlay = OverlayFs::new([
+            Box::new(ReadOnlyFs(library)) as Box<dyn Fs>,
+            Box::new(project) as Box<dyn Fs>,
+        ]);
+
+        overlay.write(Path::new("local.mat"), b"project").unwrap();
+        assert_eq!(
+            std::fs::read(project_root.join("local.mat")).unwrap(),
+            b"project"
+        );
+
+        assert_eq!(
+            &*overlay.read(Path::new("default.mat")).unwrap(),
+            b"library"
+        );
+        assert_eq!(&*overlay.read(Path::new("local.mat")).unwrap(), b"project");
+        assert!(matches!(
+            overlay.read(Path::new("missing.mat")),
+            Err(FsError::NotFound(_))
+        ));
+
+        std::fs::remove_dir_all(project_root).unwrap();
+        std::fs::remove_dir_all(library_root).unwrap();
+    }
+
+    #[test]
+    fn test_list_unions_layers() {
+        let top_root = temp_root("list-top");
+        let bottom_root = temp_root("list-bottom");
+
+        let top = PhysicalFs::new(top_root.clone());
+        let bottom = PhysicalFs::new(bottom_root.clone());
+        top.write(Path::new("a.mat"), b"top").unwrap();
+        bottom.write(Path::new("a.mat"), b"bottom").unwrap();
+        bottom.write(Path::new("b.mat"), b"bottom").unwrap();
+
+        let overlay = OverlayFs::new([
+            Box::new(top) as Box<dyn Fs>,
+            Box::new(bottom) as Box<dyn Fs>,
+        ]);
+
+        let mut entries = overlay.list(None).collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, [PathBuf::from("a.mat"), PathBuf::from("b.mat")]);
+        assert_eq!(&*overlay.read(Path::new("a.mat")).unwrap(), b"top");
+
+        std::fs::remove_dir_all(top_root).unwrap();
+        std::fs::remove_dir_all(bottom_root).unwrap();
+    }
+}
@@ -24,6 +24,7 @@ impl Watcher for PhysicalFsWatcher {
         root: PathBuf,
         event_tx: Sender<FileEvent>,
     ) -> Box<dyn Watcher> {
+        let watched_root = root.clone();
         let mut debouncer = notify_debouncer_full::new_debouncer(
             Duration::from_secs(1),
             None,
@@ -38,12 +39,16 @@ impl Watcher for PhysicalFsWatcher {
                         EventKind::Create(_) => FileEventType::Created,
                         EventKind::Modify(_) => FileEventType::Modified,
                         EventKind::Remove(_) => FileEventType::Removed,
-                        _ => return,
+                        _ => continue,
                     };
 
                     for path in event.event.paths {
-                        // Convert the filesystem path to a virtual path.
-                        let virtual_path = VirtualPath::new(namespace, &*path);
+                        // Convert the filesystem path to a virtual path, relative to the root.
+                        let Ok(path) = path.strip_prefix(&root) else {
+                            continue;
+                        };
+
+                        let virtual_path = VirtualPath::new(namespace.clone(), path);
                         let event = FileEvent {
                             path: virtual_path,
                             event_type: kind,
@@ -57,7 +62,9 @@ impl Watcher for PhysicalFsWatcher {
         )
         .unwrap();
 
-        debouncer.watch(&root, RecursiveMode::Recursive).unwrap();
+        debouncer
+            .watch(&watched_root, RecursiveMode::Recursive)
+            .unwrap();
 
         Box::new(Self { debouncer })
     }
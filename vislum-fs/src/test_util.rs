@@ -0,0 +1,8 @@
+use std::path::PathBuf;
+
+/// Creates a scratch directory unique to this process and test name.
+pub(crate) fn temp_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("vislum-fs-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&root).unwrap();
+    root
+}
@@ -1,5 +1,7 @@
 use std::{
     borrow::Cow,
+    fs::{File, ReadDir},
+    io::{BufReader, Read},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -29,7 +31,23 @@ impl Fs for PhysicalFs {
         }
     }
 
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>, FsError> {
+        let path = self.root.join(path);
+
+        match File::open(&path) {
+            Ok(file) => Ok(Box::new(BufReader::new(file))),
+            Err(error) => match error.kind() {
+                std::io::ErrorKind::NotFound => Err(FsError::NotFound(path)),
+                _ => Err(FsError::PhysicalIo(path, error)),
+            },
+        }
+    }
+
     fn write(&self, path: &Path, data: &[u8]) -> Result<(), FsError> {
+        if let Some(parent) = path.parent() {
+            self.create_dir(parent)?;
+        }
+
         let path = self.root.join(path);
         match std::fs::write(&path, data) {
             Ok(_) => Ok(()),
@@ -40,6 +58,12 @@ impl Fs for PhysicalFs {
         }
     }
 
+    fn create_dir(&self, path: &Path) -> Result<(), FsError> {
+        let path = self.root.join(path);
+
+        std::fs::create_dir_all(&path).map_err(|error| FsError::PhysicalIo(path, error))
+    }
+
     fn list(&self, path: Option<&Path>) -> FsIterator {
         let path = match path {
             Some(path) => Cow::Owned(self.root.join(path)),
@@ -63,6 +87,195 @@ impl Fs for PhysicalFs {
             Err(_) => Box::new(std::iter::empty()),
         }
     }
+
+    fn list_recursive(&self, path: Option<&Path>) -> FsIterator {
+        let path = match path {
+            Some(path) => self.root.join(path),
+            None => self.root.clone(),
+        };
+
+        Box::new(PhysicalWalk {
+            root: self.root.clone(),
+            pending: std::fs::read_dir(path).into_iter().collect(),
+        })
+    }
+}
+
+/// A lazy depth-first walk over the files of a physical directory tree.
+///
+/// Directories are only read once the walk reaches them.
+struct PhysicalWalk {
+    root: PathBuf,
+    pending: Vec<ReadDir>,
 }
 
+impl Iterator for PhysicalWalk {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entries = self.pending.last_mut()?;
+            let Some(entry) = entries.next() else {
+                self.pending.pop();
+                continue;
+            };
+
+            let Ok(entry) = entry else {
+                continue;
+            };
+
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                if let Ok(entries) = std::fs::read_dir(&path) {
+                    self.pending.push(entries);
+                }
+                continue;
+            }
+
+            // Strip the root prefix from the entry path.
+            if let Ok(path) = path.strip_prefix(&self.root) {
+                return Some(path.to_path_buf());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::temp_root;
+
+    use super::*;
+
+    #[test]
+    fn test_open_read_streams_file_in_chunks() {
+        let root = temp_root("open-read");
+        let fs = PhysicalFs::new(root.clone());
 
+        let data = (0..10_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        fs.write(Path::new("large.bin"), &data).unwrap();
+
+        let mut reader = fs.open_read(Path::new("large.bin")).unwrap();
+        let mut streamed = Vec::new();
+        let mut chunk = [0u8; 1024];
+        loop {
+            let read = reader.read(&mut chunk).unwrap();
+            if read == 0 {
+                break;
+            }
+            streamed.extend_from_slice(&chunk[..read]);
+        }
+
+        assert_eq!(streamed, &*fs.read(Path::new("large.bin")).unwrap());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_write_creates_missing_directories() {
+        use crate::{VirtualFs, VirtualNamespace, VirtualPath};
+
+        let root = temp_root("nested-write");
+        let vfs = VirtualFs::new([(
+            VirtualNamespace::Project,
+            Box::new(PhysicalFs::new(root.clone())) as Box<dyn Fs>,
+        )]);
+
+        vfs.write(
+            VirtualPath::parse("project://new/sub/file.txt").unwrap(),
+            b"hello",
+        )
+        .unwrap();
+
+        assert!(root.join("new/sub").is_dir());
+        assert_eq!(std::fs::read(root.join("new/sub/file.txt")).unwrap(), b"hello");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_create_dir() {
+        let root = temp_root("create-dir");
+        let fs = PhysicalFs::new(root.clone());
+
+        fs.create_dir(Path::new("a/b/c")).unwrap();
+        assert!(root.join("a/b/c").is_dir());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_open_read_missing_file() {
+        let root = temp_root("open-read-missing");
+        let fs = PhysicalFs::new(root.clone());
+
+        assert!(matches!(
+            fs.open_read(Path::new("missing.bin")),
+            Err(FsError::NotFound(_))
+        ));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_glob_matches_files_at_any_depth() {
+        use crate::{VirtualFs, VirtualNamespace};
+
+        let root = temp_root("glob");
+        let fs = PhysicalFs::new(root.clone());
+        for path in ["a.png", "b.jpg", "materials/c.png", "materials/deep/d.png", "materials/e.txt"] {
+            fs.write(Path::new(path), b"").unwrap();
+        }
+
+        let vfs = VirtualFs::new([(VirtualNamespace::Project, Box::new(fs) as Box<dyn Fs>)]);
+        let mut matches = vfs
+            .glob(VirtualNamespace::Project, "**/*.png")
+            .unwrap()
+            .map(|path| path.path().to_path_buf())
+            .collect::<Vec<_>>();
+        matches.sort();
+
+        assert_eq!(
+            matches,
+            [
+                PathBuf::from("a.png"),
+                PathBuf::from("materials/c.png"),
+                PathBuf::from("materials/deep/d.png"),
+            ]
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_list_recursive_descends_into_subdirectories() {
+        let root = temp_root("list-recursive");
+        let fs = PhysicalFs::new(root.clone());
+        for path in [
+            "a.vslm",
+            "projects/b.vslm",
+            "projects/deep/c.vslm",
+            "projects/d.txt",
+        ] {
+            fs.write(Path::new(path), b"").unwrap();
+        }
+        fs.create_dir(Path::new("empty")).unwrap();
+
+        let mut files = fs
+            .list_recursive(Some(Path::new("projects")))
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("projects/b.vslm"),
+                PathBuf::from("projects/d.txt"),
+                PathBuf::from("projects/deep/c.vslm"),
+            ]
+        );
+
+        // The flat listing is unchanged.
+        assert_eq!(fs.list(None).count(), 3);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::{Add, AddAssign, Mul, Sub};
 
-use cgmath::SquareMatrix;
+use cgmath::{Array, ElementWise, EuclideanSpace, InnerSpace, Matrix, SquareMatrix, Transform};
 
 macro_rules! impl_vector {
     ($ident:ident; $components:expr; $ty:ty; $constructor:ident; $inner:path; $($field:ident),*) => {
@@ -87,12 +87,80 @@ macro_rules! impl_vector {
     (@zero_field $field:ident) => { Default::default() };
 }
 
+macro_rules! impl_float_vector {
+    ($ident:ident) => {
+        impl $ident {
+            /// Returns the dot product of the two vectors.
+            pub fn dot(self, rhs: Self) -> f32 {
+                self.inner.dot(rhs.inner)
+            }
+
+            /// Returns the squared length of the vector.
+            ///
+            /// Cheaper than [`Self::length`], and enough to compare lengths.
+            pub fn length_squared(self) -> f32 {
+                self.inner.magnitude2()
+            }
+
+            /// Returns the length of the vector.
+            pub fn length(self) -> f32 {
+                self.inner.magnitude()
+            }
+
+            /// Returns the vector scaled to a length of one.
+            ///
+            /// The zero vector has no direction, so it is returned unchanged
+            /// instead of producing NaNs.
+            pub fn normalize(self) -> Self {
+                if self.length_squared() == 0.0 {
+                    return self;
+                }
+
+                Self {
+                    inner: self.inner.normalize(),
+                }
+            }
+        }
+    };
+}
+
+macro_rules! impl_int_vector {
+    ($ident:ident) => {
+        impl $ident {
+            /// Returns the dot product of the two vectors.
+            pub fn dot(self, rhs: Self) -> i32 {
+                self.inner.mul_element_wise(rhs.inner).sum()
+            }
+
+            /// Returns the squared length of the vector.
+            pub fn length_squared(self) -> i32 {
+                self.dot(self)
+            }
+        }
+    };
+}
+
 impl_vector!(Vector4; 4; f32; vec4; cgmath::Vector4<f32>; x, y, z, w);
 impl_vector!(Vector3; 3; f32; vec3; cgmath::Vector3<f32>; x, y, z);
 impl_vector!(Vector2; 2; f32; vec2; cgmath::Vector2<f32>; x, y);
 
 impl_vector!(Vector2I; 2; i32; vec2i; cgmath::Vector2<i32>; x, y);
 
+impl_float_vector!(Vector4);
+impl_float_vector!(Vector3);
+impl_float_vector!(Vector2);
+
+impl_int_vector!(Vector2I);
+
+impl Vector3 {
+    /// Returns the cross product of the two vectors.
+    pub fn cross(self, rhs: Self) -> Self {
+        Self {
+            inner: self.inner.cross(rhs.inner),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Matrix4 {
     inner: cgmath::Matrix4<f32>,
@@ -104,6 +172,114 @@ impl Matrix4 {
             inner: cgmath::Matrix4::from_cols(col1.into(), col2.into(), col3.into(), col4.into()),
         }
     }
+
+    /// Creates a translation matrix.
+    pub fn from_translation(translation: Vector3) -> Self {
+        Self {
+            inner: cgmath::Matrix4::from_translation(translation.inner),
+        }
+    }
+
+    /// Creates a non-uniform scale matrix.
+    pub fn from_scale(scale: Vector3) -> Self {
+        Self {
+            inner: cgmath::Matrix4::from_nonuniform_scale(scale.x(), scale.y(), scale.z()),
+        }
+    }
+
+    /// Creates a rotation of `angle` radians around `axis`.
+    ///
+    /// The axis doesn't need to be normalized.
+    pub fn from_rotation_axis(axis: Vector3, angle: f32) -> Self {
+        Self {
+            inner: cgmath::Matrix4::from_axis_angle(axis.normalize().inner, cgmath::Rad(angle)),
+        }
+    }
+
+    /// Creates a right-handed view matrix looking from `eye` at `target`.
+    ///
+    /// The view looks down the negative z axis, with y pointing up.
+    pub fn look_at(eye: Vector3, target: Vector3, up: Vector3) -> Self {
+        Self {
+            inner: cgmath::Matrix4::look_at_rh(
+                cgmath::Point3::from_vec(eye.inner),
+                cgmath::Point3::from_vec(target.inner),
+                up.inner,
+            ),
+        }
+    }
+
+    /// Creates a right-handed perspective projection with a vertical field of
+    /// view of `fov_y` radians.
+    ///
+    /// Depth is mapped to `0.0..=1.0` between the near and far planes, as in
+    /// Vulkan. The y axis is not flipped.
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1.0 / (fov_y / 2.0).tan();
+        let depth = near - far;
+
+        Self::new(
+            vec4(f / aspect, 0.0, 0.0, 0.0),
+            vec4(0.0, f, 0.0, 0.0),
+            vec4(0.0, 0.0, far / depth, -1.0),
+            vec4(0.0, 0.0, near * far / depth, 0.0),
+        )
+    }
+
+    /// Creates a right-handed orthographic projection of the given view box.
+    ///
+    /// Depth is mapped to `0.0..=1.0` between the near and far planes, as in
+    /// Vulkan. The y axis is not flipped.
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let width = right - left;
+        let height = top - bottom;
+        let depth = near - far;
+
+        Self::new(
+            vec4(2.0 / width, 0.0, 0.0, 0.0),
+            vec4(0.0, 2.0 / height, 0.0, 0.0),
+            vec4(0.0, 0.0, 1.0 / depth, 0.0),
+            vec4(
+                -(right + left) / width,
+                -(top + bottom) / height,
+                near / depth,
+                1.0,
+            ),
+        )
+    }
+
+    /// Returns the inverse of the matrix, or `None` if the matrix is singular.
+    pub fn inverse(self) -> Option<Self> {
+        self.inner.invert().map(|inner| Self { inner })
+    }
+
+    /// Returns the transpose of the matrix.
+    pub fn transpose(self) -> Self {
+        Self {
+            inner: self.inner.transpose(),
+        }
+    }
+
+    /// Transforms a point, which is affected by translation (w = 1).
+    pub fn transform_point(self, point: Vector3) -> Vector3 {
+        let point = cgmath::Point3::from_vec(point.inner);
+
+        Vector3 {
+            inner: self.inner.transform_point(point).to_vec(),
+        }
+    }
+
+    /// Transforms a direction, which is not affected by translation (w = 0).
+    pub fn transform_vector(self, vector: Vector3) -> Vector3 {
+        Vector3 {
+            inner: self.inner.transform_vector(vector.inner),
+        }
+    }
+
+    /// Returns the columns of the matrix, laid out as shaders expect them.
+    pub fn to_cols_array(self) -> [[f32; 4]; 4] {
+        self.inner.into()
+    }
 }
 impl std::ops::Add for Matrix4 {
     type Output = Self;
@@ -180,3 +356,80 @@ impl Default for Matrix4 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_length() {
+        assert_eq!(vec3(3.0, 0.0, 0.0).length(), 3.0);
+        assert_eq!(vec2(3.0, 4.0).length_squared(), 25.0);
+        assert_eq!(vec2i(3, -4).length_squared(), 25);
+    }
+
+    #[test]
+    fn test_vector_dot_and_cross() {
+        let x = vec3(1.0, 0.0, 0.0);
+        let y = vec3(0.0, 1.0, 0.0);
+
+        assert_eq!(x.dot(y), 0.0);
+        assert_eq!(x.cross(y), vec3(0.0, 0.0, 1.0));
+        assert_eq!(vec2i(1, 2).dot(vec2i(3, 4)), 11);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(vec3(0.0, 2.0, 0.0).normalize(), vec3(0.0, 1.0, 0.0));
+        assert_eq!(Vector3::default().normalize(), Vector3::default());
+    }
+
+    #[test]
+    fn test_transform_point_and_vector() {
+        let matrix = Matrix4::from_translation(vec3(1.0, 2.0, 3.0))
+            * Matrix4::from_scale(vec3(2.0, 2.0, 2.0));
+
+        assert_eq!(
+            matrix.transform_point(vec3(1.0, 1.0, 1.0)),
+            vec3(3.0, 4.0, 5.0)
+        );
+        assert_eq!(
+            matrix.transform_vector(vec3(1.0, 1.0, 1.0)),
+            vec3(2.0, 2.0, 2.0)
+        );
+        assert_eq!(matrix.to_cols_array()[3], [1.0, 2.0, 3.0, 1.0]);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let matrix = Matrix4::from_translation(vec3(1.0, 2.0, 3.0));
+        let inverse = matrix.inverse().unwrap();
+
+        assert_eq!(matrix * inverse, Matrix4::default());
+        assert_eq!(Matrix4::from_scale(vec3(0.0, 1.0, 1.0)).inverse(), None);
+    }
+
+    #[test]
+    fn test_projections_map_depth_to_unit_range() {
+        let perspective = Matrix4::perspective(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 10.0);
+        let orthographic = Matrix4::orthographic(-1.0, 1.0, -1.0, 1.0, 1.0, 10.0);
+
+        for projection in [perspective, orthographic] {
+            let near = projection * vec4(0.0, 0.0, -1.0, 1.0);
+            let far = projection * vec4(0.0, 0.0, -10.0, 1.0);
+
+            assert!((near.z() / near.w()).abs() < 1e-6);
+            assert!((far.z() / far.w() - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_rotation_and_transpose() {
+        let rotation =
+            Matrix4::from_rotation_axis(vec3(0.0, 0.0, 2.0), std::f32::consts::FRAC_PI_2);
+        let rotated = rotation.transform_vector(vec3(1.0, 0.0, 0.0));
+
+        assert!((rotated - vec3(0.0, 1.0, 0.0)).length() < 1e-6);
+        assert_eq!(rotation.transpose().transpose(), rotation);
+    }
+}
@@ -128,9 +128,8 @@ impl ApplicationHandler<PlayerEvent> for Player {
             let runtime = Runtime::new(wgpu.device.clone(), wgpu.queue.clone());
 
             // TESTING DATA
-            let mut texture_manager = runtime.get_resource_mut::<TextureManager>();
-            let mut mesh_manager = runtime.get_resource_mut::<MeshManager>();
-            let mut scene_manager = runtime.get_resource_mut::<SceneManager>();
+            let (mut texture_manager, mut mesh_manager, mut scene_manager) = runtime
+                .get_many_resources_mut::<(TextureManager, MeshManager, SceneManager)>();
 
             let color_texture = texture_manager.create(TextureDescriptor {
                 format: TextureFormat::Rgba8Unorm,
@@ -157,9 +156,8 @@ impl ApplicationHandler<PlayerEvent> for Player {
             let scene = scene_manager
                 .create_with_commands(vec![SceneCommand::AddObject(Object { mesh })]);
 
-            drop(texture_manager);
-            drop(mesh_manager);
-            drop(scene_manager);
+            // Release the managers before moving the runtime.
+            drop((texture_manager, mesh_manager, scene_manager));
 
             self.state = PlayerState::Ready {
                 window: window.clone(),
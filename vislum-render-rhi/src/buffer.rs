@@ -1,4 +1,10 @@
-use std::sync::Arc;
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 use ash::vk;
 
@@ -10,19 +16,41 @@ vk_enum_flags! {
         TRANSFER_DST => TRANSFER_DST,
         VERTEX_BUFFER => VERTEX_BUFFER,
         INDEX_BUFFER => INDEX_BUFFER,
+        UNIFORM_BUFFER => UNIFORM_BUFFER,
+        STORAGE_BUFFER => STORAGE_BUFFER,
+        INDIRECT_BUFFER => INDIRECT_BUFFER,
     }
 }
 
+#[derive(Default)]
 pub struct BufferCreateInfo {
     pub size: u64,
     pub usage: BufferUsage,
 }
 
+impl BufferCreateInfo {
+    /// Creates the info for a buffer of the given size in bytes.
+    pub fn new(size: u64) -> Self {
+        Self {
+            size,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the usage of the buffer.
+    pub fn usage(mut self, usage: BufferUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+}
+
 pub struct Buffer {
     device: Arc<Device>,
     buffer: DebugWrapper<vk::Buffer>,
     memory: MemoryAllocation,
+    location: MemoryLocation,
     size: u64,
+    mapped: AtomicBool,
 }
 
 impl Buffer {
@@ -65,7 +93,9 @@ impl Buffer {
             device,
             buffer: DebugWrapper(buffer),
             memory,
+            location,
             size: create_info.size,
+            mapped: AtomicBool::new(false),
         })
     }
 
@@ -92,10 +122,101 @@ impl Buffer {
     /// # Safety
     /// The buffer must be allocated with host-visible memory (CpuToGpu or GpuToCpu).
     pub unsafe fn write(&self, data: &[u8]) {
+        self.map()[..data.len()].copy_from_slice(data);
+    }
+
+    /// Maps the buffer for host access.
+    ///
+    /// Host-visible buffers are persistently mapped, so this is cheap enough to
+    /// call every frame. Non-coherent memory is flushed when the guard is dropped.
+    ///
+    /// Panics if the buffer is allocated in [`MemoryLocation::GpuOnly`] memory,
+    /// or if it is already mapped.
+    pub fn map(&self) -> MappedBuffer<'_> {
+        assert!(
+            self.location != MemoryLocation::GpuOnly,
+            "cannot map a buffer allocated in GpuOnly memory"
+        );
+        assert!(
+            !self.mapped.swap(true, Ordering::Acquire),
+            "the buffer is already mapped"
+        );
+
+        // SAFETY: The mapped flag guarantees this is the only live slice.
+        let data = unsafe { self.mapped_bytes() };
+
+        MappedBuffer { buffer: self, data }
+    }
+
+    /// Returns the mapped memory of a host-visible buffer as a typed slice.
+    ///
+    /// Trailing bytes which don't fit a whole `T` are excluded. Unlike
+    /// [`Buffer::map`], non-coherent memory is never flushed.
+    ///
+    /// # Safety
+    /// The buffer must not be mapped, nor accessed through another slice
+    /// returned by this function, while the returned slice is alive. The GPU
+    /// must not be accessing the buffer while it is written.
+    ///
+    /// Panics if the buffer is allocated in [`MemoryLocation::GpuOnly`] memory,
+    /// or if the mapped memory isn't aligned for `T`.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn mapped_slice_mut<T: bytemuck::Pod>(&self) -> &mut [T] {
+        assert!(
+            self.location != MemoryLocation::GpuOnly,
+            "cannot map a buffer allocated in GpuOnly memory"
+        );
+
+        let bytes = unsafe { self.mapped_bytes() };
+        let len = bytes.len() - bytes.len() % std::mem::size_of::<T>();
+        bytemuck::cast_slice_mut(&mut bytes[..len])
+    }
+
+    /// Returns the persistently mapped memory of the buffer.
+    ///
+    /// # Safety
+    /// The caller must guarantee the returned slice isn't aliased.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn mapped_bytes(&self) -> &mut [u8] {
+        let allocation = self.memory.allocation.as_ref().unwrap();
+        let mapped_ptr = allocation
+            .mapped_ptr()
+            .expect("host-visible memory is persistently mapped");
+
+        unsafe {
+            std::slice::from_raw_parts_mut(mapped_ptr.as_ptr() as *mut u8, self.size as usize)
+        }
+    }
+
+    /// Flushes the mapped memory of the buffer, if it isn't host-coherent.
+    fn flush(&self) {
         let allocation = self.memory.allocation.as_ref().unwrap();
-        let mapped_ptr = allocation.mapped_ptr().unwrap().as_ptr();
+        if allocation
+            .memory_properties()
+            .contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+        {
+            return;
+        }
+
+        // Flushed ranges must be aligned to the atom size, so flush from the
+        // aligned offset until the end of the memory object.
+        let atom_size = self
+            .device
+            .physical_device()
+            .properties()
+            .non_coherent_atom_size;
+        let offset = allocation.offset() - allocation.offset() % atom_size;
+
+        let range = vk::MappedMemoryRange::default()
+            .memory(unsafe { allocation.memory() })
+            .offset(offset)
+            .size(vk::WHOLE_SIZE);
+
         unsafe {
-            std::ptr::copy_nonoverlapping(data.as_ptr(), mapped_ptr as *mut u8, data.len());
+            self.device
+                .ash_handle()
+                .flush_mapped_memory_ranges(&[range])
+                .unwrap();
         }
     }
 
@@ -113,6 +234,40 @@ impl Buffer {
     pub fn memory(&self) -> &MemoryAllocation {
         &self.memory
     }
+
+    /// Returns the memory location the buffer is allocated in.
+    pub fn location(&self) -> MemoryLocation {
+        self.location
+    }
+}
+
+/// A guard over the mapped memory of a host-visible [`Buffer`].
+///
+/// Flushes the memory on drop if it isn't host-coherent.
+pub struct MappedBuffer<'a> {
+    buffer: &'a Buffer,
+    data: &'a mut [u8],
+}
+
+impl Deref for MappedBuffer<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl DerefMut for MappedBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
+}
+
+impl Drop for MappedBuffer<'_> {
+    fn drop(&mut self) {
+        self.buffer.flush();
+        self.buffer.mapped.store(false, Ordering::Release);
+    }
 }
 
 impl VkHandle for Buffer {
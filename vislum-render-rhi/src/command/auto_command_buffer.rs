@@ -1,16 +1,18 @@
-use std::sync::Arc;
+use std::{ops::Range, sync::Arc};
 use ash::vk;
 use smallvec::SmallVec;
 
 use crate::{
     buffer::Buffer, 
-    command::{BufferMemoryBarrier2, ImageMemoryBarrier2, MemoryBarrier2, RawCommandBuffer, types::{BufferImageCopy, CommandBufferUsageFlags, ImageLayout, IndexType, PipelineBindPoint, Rect2D, Viewport}}, 
+    command::{BufferMemoryBarrier2, CommandBufferInheritanceInfo, ImageMemoryBarrier2, MemoryBarrier2, RawCommandBuffer, ResourceStateTracker, types::{AccessFlags2, BufferImageCopy, CommandBufferUsageFlags, ImageBlit, ImageLayout, IndexType, PipelineBindPoint, PipelineStageFlags2, Rect2D, Viewport}}, 
     image::Image,
+    query::QueryPool,
 };
 
 /// A command encoder that performs automatic resource transitions.
 pub struct CommandEncoder {
     command_buffer: RawCommandBuffer,
+    tracker: ResourceStateTracker,
 }
 
 impl CommandEncoder {
@@ -18,14 +20,37 @@ impl CommandEncoder {
     pub fn new(command_buffer: RawCommandBuffer) -> Self {
         Self {
             command_buffer,
+            tracker: ResourceStateTracker::new(),
         }
     }
 
     /// Begins recording commands.
     pub fn begin(&mut self, flags: CommandBufferUsageFlags) {
+        self.tracker.clear();
         self.command_buffer.begin(flags);
     }
 
+    /// Begins recording commands into a secondary command buffer.
+    ///
+    /// See [`RawCommandBuffer::begin_secondary`].
+    pub fn begin_secondary(
+        &mut self,
+        flags: CommandBufferUsageFlags,
+        inheritance: &CommandBufferInheritanceInfo,
+    ) {
+        self.command_buffer.begin_secondary(flags, inheritance);
+    }
+
+    /// Executes secondary command buffers, in order.
+    ///
+    /// See [`RawCommandBuffer::execute_commands`].
+    pub fn execute_commands<'a>(
+        &mut self,
+        secondaries: impl IntoIterator<Item = &'a RawCommandBuffer>,
+    ) {
+        self.command_buffer.execute_commands(secondaries);
+    }
+
     /// Ends recording commands.
     pub fn end(&mut self) {
         self.command_buffer.end();
@@ -50,6 +75,17 @@ impl CommandEncoder {
         );
     }
 
+    /// Writes data stored in the command buffer into a buffer, without a
+    /// staging buffer.
+    ///
+    /// The offset and the size of the data must be multiples of 4, and the
+    /// data at most 65536 bytes.
+    pub fn update_buffer(&mut self, dst_buffer: Arc<Buffer>, dst_offset: u64, data: &[u8]) {
+        use crate::VkHandle;
+        self.command_buffer
+            .update_buffer(dst_buffer.vk_handle(), dst_offset, data);
+    }
+
     /// Copies data from a buffer to an image.
     /// Caller must ensure the image is in the correct layout before calling this.
     pub fn copy_buffer_to_image(
@@ -69,6 +105,50 @@ impl CommandEncoder {
         );
     }
 
+    /// Copies data from an image to a buffer, e.g. to read it back.
+    /// Caller must ensure the image is in the correct layout before calling this.
+    pub fn copy_image_to_buffer(
+        &mut self,
+        src_image: Arc<Image>,
+        src_layout: ImageLayout,
+        dst_buffer: Arc<Buffer>,
+        regions: impl IntoIterator<Item = BufferImageCopy>,
+    ) {
+        use crate::VkHandle;
+        let regions_vk: SmallVec<[vk::BufferImageCopy; 4]> =
+            regions.into_iter().map(BufferImageCopy::to_vk).collect();
+        self.command_buffer.copy_image_to_buffer(
+            src_image.vk_handle(),
+            src_layout,
+            dst_buffer.vk_handle(),
+            &regions_vk,
+        );
+    }
+
+    /// Blits regions between images, with the given filter when scaling.
+    /// Caller must ensure the images are in the given layouts.
+    pub fn blit_image(
+        &mut self,
+        src_image: Arc<Image>,
+        src_layout: ImageLayout,
+        dst_image: Arc<Image>,
+        dst_layout: ImageLayout,
+        regions: impl IntoIterator<Item = ImageBlit>,
+        filter: vk::Filter,
+    ) {
+        use crate::VkHandle;
+        let regions_vk: SmallVec<[vk::ImageBlit; 4]> =
+            regions.into_iter().map(ImageBlit::to_vk).collect();
+        self.command_buffer.blit_image(
+            src_image.vk_handle(),
+            src_layout,
+            dst_image.vk_handle(),
+            dst_layout,
+            &regions_vk,
+            filter,
+        );
+    }
+
     /// Inserts a pipeline barrier.
     pub fn pipeline_barrier(
         &mut self,
@@ -79,6 +159,53 @@ impl CommandEncoder {
         self.command_buffer.pipeline_barrier(memory_barriers, buffer_memory_barriers, image_memory_barriers);
     }
 
+    /// Transitions the whole image to a new layout, making it available to
+    /// the given stages and accesses.
+    ///
+    /// The old layout and the stages to wait for are inferred from the
+    /// previous transitions of the image in this recording. On its first use
+    /// the image is transitioned from [`ImageLayout::Undefined`], discarding
+    /// its contents. Nothing is recorded if the image is already in the
+    /// layout and was last accessed the same way.
+    ///
+    /// Barriers recorded with [`CommandEncoder::pipeline_barrier`] are not
+    /// tracked, so the two shouldn't be mixed for the same image.
+    pub fn transition_image(
+        &mut self,
+        image: Arc<Image>,
+        new_layout: ImageLayout,
+        dst_stage: PipelineStageFlags2,
+        dst_access: AccessFlags2,
+    ) {
+        if let Some(barrier) = self
+            .tracker
+            .transition_image(image, new_layout, dst_stage, dst_access)
+        {
+            self.command_buffer.pipeline_barrier([], [], [barrier]);
+        }
+    }
+
+    /// Declares the state of an image this recording hasn't transitioned yet,
+    /// so its first [`CommandEncoder::transition_image`] preserves its
+    /// contents.
+    ///
+    /// See [`ResourceStateTracker::assume_image_state`].
+    pub fn assume_image_state(
+        &mut self,
+        image: &Image,
+        layout: ImageLayout,
+        stage: PipelineStageFlags2,
+        access: AccessFlags2,
+    ) {
+        self.tracker.assume_image_state(image, layout, stage, access);
+    }
+
+    /// Returns the layout of the image as of the last transition recorded by
+    /// [`CommandEncoder::transition_image`].
+    pub fn image_layout(&self, image: &Image) -> ImageLayout {
+        self.tracker.image_layout(image)
+    }
+
     /// Returns a reference to the underlying command buffer.
     pub fn command_buffer(&self) -> &RawCommandBuffer {
         &self.command_buffer
@@ -146,6 +273,8 @@ impl CommandEncoder {
     }
 
     /// Binds an index buffer.
+    ///
+    /// The `offset` is in bytes and must be a multiple of the index size.
     pub fn bind_index_buffer(
         &mut self,
         buffer: Arc<Buffer>,
@@ -155,7 +284,22 @@ impl CommandEncoder {
         self.command_buffer.bind_index_buffer_buffer(&buffer, offset, index_type);
     }
 
+    /// Draws non-indexed primitives.
+    pub fn draw(
+        &mut self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        self.command_buffer
+            .draw(vertex_count, instance_count, first_vertex, first_instance);
+    }
+
     /// Draws indexed primitives.
+    ///
+    /// See [`RawCommandBuffer::draw_indexed`] for the semantics of `first_index`
+    /// and `vertex_offset`.
     pub fn draw_indexed(
         &mut self,
         index_count: u32,
@@ -173,6 +317,33 @@ impl CommandEncoder {
         );
     }
 
+    /// Dispatches compute work groups.
+    pub fn dispatch(&mut self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        self.command_buffer.dispatch(group_count_x, group_count_y, group_count_z);
+    }
+
+    /// Dispatches compute work groups, reading a `VkDispatchIndirectCommand`
+    /// from the buffer at the given offset.
+    pub fn dispatch_indirect(&mut self, buffer: Arc<Buffer>, offset: u64) {
+        use crate::VkHandle;
+        self.command_buffer.dispatch_indirect(buffer.vk_handle(), offset);
+    }
+
+    /// Resets a range of queries, which must be done before writing them.
+    pub fn reset_query_pool(&mut self, pool: &QueryPool, queries: Range<u32>) {
+        use crate::VkHandle;
+        self.command_buffer
+            .reset_query_pool(pool.vk_handle(), queries.start, queries.len() as u32);
+    }
+
+    /// Writes a timestamp to a query once all previous commands reach the
+    /// given stage.
+    pub fn write_timestamp(&mut self, stage: PipelineStageFlags2, pool: &QueryPool, query: u32) {
+        use crate::VkHandle;
+        self.command_buffer
+            .write_timestamp(stage, pool.vk_handle(), query);
+    }
+
     /// Ends dynamic rendering.
     pub fn end_rendering(&mut self) {
         self.command_buffer.end_rendering();
@@ -0,0 +1,117 @@
+use crate::command::{
+    BufferMemoryBarrier2, CommandEncoder, ImageMemoryBarrier2, MemoryBarrier2, RawCommandBuffer,
+};
+
+/// Accumulates barriers to record them in a single pipeline barrier.
+///
+/// Recording a batch with one `vkCmdPipelineBarrier2` lets the driver resolve
+/// all the transitions at once, instead of serializing one call per barrier.
+#[derive(Default)]
+pub struct BarrierBuilder {
+    memory_barriers: Vec<MemoryBarrier2>,
+    buffer_memory_barriers: Vec<BufferMemoryBarrier2>,
+    image_memory_barriers: Vec<ImageMemoryBarrier2>,
+}
+
+impl BarrierBuilder {
+    /// Creates an empty barrier batch.
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a global memory barrier.
+    pub fn memory(mut self, barrier: MemoryBarrier2) -> Self {
+        self.push_memory(barrier);
+        self
+    }
+
+    /// Adds a buffer memory barrier.
+    pub fn buffer(mut self, barrier: BufferMemoryBarrier2) -> Self {
+        self.push_buffer(barrier);
+        self
+    }
+
+    /// Adds an image memory barrier.
+    pub fn image(mut self, barrier: ImageMemoryBarrier2) -> Self {
+        self.push_image(barrier);
+        self
+    }
+
+    /// Adds a global memory barrier.
+    pub fn push_memory(&mut self, barrier: MemoryBarrier2) {
+        self.memory_barriers.push(barrier);
+    }
+
+    /// Adds a buffer memory barrier.
+    pub fn push_buffer(&mut self, barrier: BufferMemoryBarrier2) {
+        self.buffer_memory_barriers.push(barrier);
+    }
+
+    /// Adds an image memory barrier.
+    pub fn push_image(&mut self, barrier: ImageMemoryBarrier2) {
+        self.image_memory_barriers.push(barrier);
+    }
+
+    /// Returns the number of barriers in the batch.
+    pub fn len(&self) -> usize {
+        self.memory_barriers.len()
+            + self.buffer_memory_barriers.len()
+            + self.image_memory_barriers.len()
+    }
+
+    /// Returns true if the batch has no barriers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Records all the barriers in a single pipeline barrier.
+    ///
+    /// Nothing is recorded if the batch is empty.
+    pub fn record(self, command_buffer: &RawCommandBuffer) {
+        if self.is_empty() {
+            return;
+        }
+
+        command_buffer.pipeline_barrier(
+            self.memory_barriers,
+            self.buffer_memory_barriers,
+            self.image_memory_barriers,
+        );
+    }
+}
+
+impl CommandEncoder {
+    /// Records a batch of barriers in a single pipeline barrier.
+    pub fn barriers(&mut self, barriers: BarrierBuilder) {
+        barriers.record(self.command_buffer());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{AccessFlags2, PipelineStageFlags2};
+
+    fn transfer_to_fragment() -> MemoryBarrier2 {
+        MemoryBarrier2 {
+            src_stage_mask: PipelineStageFlags2::TRANSFER,
+            src_access_mask: AccessFlags2::TRANSFER_WRITE,
+            dst_stage_mask: PipelineStageFlags2::FRAGMENT_SHADER,
+            dst_access_mask: AccessFlags2::SHADER_READ,
+        }
+    }
+
+    #[test]
+    fn test_barriers_are_batched() {
+        let mut builder = BarrierBuilder::new()
+            .memory(transfer_to_fragment())
+            .memory(transfer_to_fragment());
+        builder.push_memory(transfer_to_fragment());
+
+        assert_eq!(builder.len(), 3);
+        assert_eq!(builder.memory_barriers.len(), 3);
+        assert!(!builder.is_empty());
+        assert!(BarrierBuilder::new().is_empty());
+    }
+}
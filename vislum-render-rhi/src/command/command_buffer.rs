@@ -7,8 +7,10 @@ use crate::command::types::{
     CommandBufferUsageFlags, ImageLayout, IndexType, PipelineBindPoint,
     Rect2D, Viewport,
 };
-use crate::command::{BufferMemoryBarrier2, ImageMemoryBarrier2, MemoryBarrier2};
-use crate::{AshHandle, DebugWrapper, VkHandle, device::Device, vk_enum};
+use crate::command::{
+    BufferMemoryBarrier2, ImageMemoryBarrier2, MemoryBarrier2, PipelineStageFlags2,
+};
+use crate::{AshHandle, DebugWrapper, VkHandle, device::Device, image::ImageFormat, vk_enum};
 
 vk_enum! {
     pub enum CommandBufferLevel: ash::vk::CommandBufferLevel {
@@ -17,6 +19,32 @@ vk_enum! {
     }
 }
 
+/// The dynamic rendering instance a secondary command buffer is executed in.
+///
+/// The primary must begin rendering with
+/// `RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS` and the same
+/// attachment formats before executing the secondary.
+#[derive(Debug, Clone, Default)]
+pub struct CommandBufferInheritanceRendering {
+    pub color_attachment_formats: SmallVec<[ImageFormat; 4]>,
+    pub depth_attachment_format: Option<ImageFormat>,
+    pub stencil_attachment_format: Option<ImageFormat>,
+}
+
+/// The state a secondary command buffer inherits from the primary executing
+/// it.
+#[derive(Debug, Clone, Default)]
+pub struct CommandBufferInheritanceInfo {
+    /// The rendering instance the secondary is executed in, or `None` if it
+    /// is executed outside of rendering.
+    pub rendering: Option<CommandBufferInheritanceRendering>,
+}
+
+/// A pool command buffers are allocated from.
+///
+/// Pools are not synchronized: a pool, and the command buffers allocated from
+/// it, must only be used by one thread at a time. Recording on several
+/// threads requires one pool per thread.
 pub struct CommandPool {
     device: Arc<Device>,
     pool: DebugWrapper<vk::CommandPool>,
@@ -63,6 +91,25 @@ impl CommandPool {
             recording: false,
         }
     }
+
+    /// Allocates a secondary command buffer from this pool.
+    ///
+    /// Secondaries are recorded with [`RawCommandBuffer::begin_secondary`] and
+    /// executed by a primary with [`RawCommandBuffer::execute_commands`].
+    pub fn allocate_secondary(&self) -> RawCommandBuffer {
+        self.allocate(CommandBufferLevel::SECONDARY)
+    }
+
+    /// Resets all the command buffers allocated from this pool, which must
+    /// not be pending execution.
+    pub fn reset(&self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .reset_command_pool(self.pool.0, vk::CommandPoolResetFlags::empty())
+                .unwrap();
+        }
+    }
 }
 
 impl Drop for CommandPool {
@@ -102,6 +149,74 @@ impl RawCommandBuffer {
         self.recording = true;
     }
 
+    /// Begins recording commands into a secondary command buffer, inheriting
+    /// the given state from the primary executing it.
+    ///
+    /// Secondaries executed inside of rendering are begun with
+    /// [`CommandBufferUsageFlags::RENDER_PASS_CONTINUE`].
+    pub fn begin_secondary(
+        &mut self,
+        flags: CommandBufferUsageFlags,
+        inheritance: &CommandBufferInheritanceInfo,
+    ) {
+        let mut flags = flags.to_vk();
+        let mut inheritance_info = vk::CommandBufferInheritanceInfo::default();
+
+        let color_attachment_formats: SmallVec<[vk::Format; 4]> = inheritance
+            .rendering
+            .iter()
+            .flat_map(|rendering| &rendering.color_attachment_formats)
+            .map(|format| format.to_vk())
+            .collect();
+        let mut rendering_info = vk::CommandBufferInheritanceRenderingInfo::default();
+
+        if let Some(rendering) = &inheritance.rendering {
+            let format_or_undefined =
+                |format: Option<ImageFormat>| format.map_or(vk::Format::UNDEFINED, |f| f.to_vk());
+
+            rendering_info = rendering_info
+                .color_attachment_formats(&color_attachment_formats)
+                .depth_attachment_format(format_or_undefined(rendering.depth_attachment_format))
+                .stencil_attachment_format(format_or_undefined(rendering.stencil_attachment_format))
+                .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+            inheritance_info = inheritance_info.push_next(&mut rendering_info);
+            flags |= vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE;
+        }
+
+        let begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(flags)
+            .inheritance_info(&inheritance_info);
+
+        unsafe {
+            self.device
+                .ash_handle()
+                .begin_command_buffer(self.command_buffer.0, &begin_info)
+                .unwrap();
+        }
+
+        self.recording = true;
+    }
+
+    /// Executes secondary command buffers, in order.
+    ///
+    /// The secondaries must have finished recording, and must be kept alive
+    /// until the device is done executing this command buffer.
+    pub fn execute_commands<'a>(
+        &self,
+        secondaries: impl IntoIterator<Item = &'a RawCommandBuffer>,
+    ) {
+        let secondaries: SmallVec<[vk::CommandBuffer; 8]> = secondaries
+            .into_iter()
+            .map(|secondary| secondary.command_buffer.0)
+            .collect();
+
+        unsafe {
+            self.device
+                .ash_handle()
+                .cmd_execute_commands(self.command_buffer.0, &secondaries);
+        }
+    }
+
     /// Ends recording commands into the command buffer.
     pub fn end(&mut self) {
         unsafe {
@@ -248,6 +363,8 @@ impl RawCommandBuffer {
     }
 
     /// Binds an index buffer from an RHI Buffer type.
+    ///
+    /// The `offset` is in bytes and must be a multiple of the index size.
     pub fn bind_index_buffer_buffer(
         &self,
         buffer: &Arc<crate::buffer::Buffer>,
@@ -255,10 +372,46 @@ impl RawCommandBuffer {
         index_type: IndexType,
     ) {
         use crate::VkHandle;
+
+        debug_assert!(
+            offset.is_multiple_of(index_type.size()),
+            "index buffer offset {offset} is not aligned to the index size {}",
+            index_type.size(),
+        );
+        debug_assert!(
+            offset < buffer.size(),
+            "index buffer offset {offset} is out of bounds (buffer size is {})",
+            buffer.size(),
+        );
+
         self.bind_index_buffer(buffer.vk_handle(), offset, index_type);
     }
 
+    /// Draws non-indexed primitives, e.g. a full screen triangle generated
+    /// from the vertex index.
+    pub fn draw(
+        &self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.device.ash_handle().cmd_draw(
+                self.command_buffer.0,
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            );
+        }
+    }
+
     /// Draws indexed primitives.
+    ///
+    /// `first_index` is counted in indices from the bound index buffer offset,
+    /// and `vertex_offset` is added to every index before fetching vertices,
+    /// which allows drawing a mesh stored anywhere in a shared buffer.
     pub fn draw_indexed(
         &self,
         index_count: u32,
@@ -279,6 +432,52 @@ impl RawCommandBuffer {
         }
     }
 
+    /// Dispatches compute work groups.
+    pub fn dispatch(&self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        unsafe {
+            self.device.ash_handle().cmd_dispatch(
+                self.command_buffer.0,
+                group_count_x,
+                group_count_y,
+                group_count_z,
+            );
+        }
+    }
+
+    /// Dispatches compute work groups, reading the group counts from a buffer.
+    pub fn dispatch_indirect(&self, buffer: vk::Buffer, offset: u64) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .cmd_dispatch_indirect(self.command_buffer.0, buffer, offset);
+        }
+    }
+
+    /// Resets a range of queries, which must be done before writing them.
+    pub fn reset_query_pool(&self, pool: vk::QueryPool, first_query: u32, query_count: u32) {
+        unsafe {
+            self.device.ash_handle().cmd_reset_query_pool(
+                self.command_buffer.0,
+                pool,
+                first_query,
+                query_count,
+            );
+        }
+    }
+
+    /// Writes a timestamp to a query once all previous commands reach the
+    /// given stage.
+    pub fn write_timestamp(&self, stage: PipelineStageFlags2, pool: vk::QueryPool, query: u32) {
+        unsafe {
+            self.device.ash_handle().cmd_write_timestamp2(
+                self.command_buffer.0,
+                stage.to_vk(),
+                pool,
+                query,
+            );
+        }
+    }
+
     /// Ends dynamic rendering.
     pub fn end_rendering(&self) {
         unsafe {
@@ -347,6 +546,24 @@ impl RawCommandBuffer {
         }
     }
 
+    /// Writes data stored in the command buffer into a buffer.
+    ///
+    /// The offset and the size of the data must be multiples of 4, and the
+    /// data at most 65536 bytes.
+    pub fn update_buffer(&self, dst_buffer: vk::Buffer, dst_offset: u64, data: &[u8]) {
+        debug_assert!(dst_offset.is_multiple_of(4) && data.len().is_multiple_of(4));
+        debug_assert!(data.len() <= 65536);
+
+        unsafe {
+            self.device.ash_handle().cmd_update_buffer(
+                self.command_buffer.0,
+                dst_buffer,
+                dst_offset,
+                data,
+            );
+        }
+    }
+
     /// Copies data from a buffer to an image.
     pub fn copy_buffer_to_image(
         &self,
@@ -366,6 +583,48 @@ impl RawCommandBuffer {
         }
     }
 
+    /// Copies data from an image to a buffer.
+    pub fn copy_image_to_buffer(
+        &self,
+        src_image: vk::Image,
+        src_layout: ImageLayout,
+        dst_buffer: vk::Buffer,
+        regions: &[vk::BufferImageCopy],
+    ) {
+        unsafe {
+            self.device.ash_handle().cmd_copy_image_to_buffer(
+                self.command_buffer.0,
+                src_image,
+                src_layout.to_vk(),
+                dst_buffer,
+                regions,
+            );
+        }
+    }
+
+    /// Blits regions between images, with the given filter when scaling.
+    pub fn blit_image(
+        &self,
+        src_image: vk::Image,
+        src_layout: ImageLayout,
+        dst_image: vk::Image,
+        dst_layout: ImageLayout,
+        regions: &[vk::ImageBlit],
+        filter: vk::Filter,
+    ) {
+        unsafe {
+            self.device.ash_handle().cmd_blit_image(
+                self.command_buffer.0,
+                src_image,
+                src_layout.to_vk(),
+                dst_image,
+                dst_layout.to_vk(),
+                regions,
+                filter,
+            );
+        }
+    }
+
     /// Returns a reference to the device.
     pub fn device(&self) -> &Arc<Device> {
         &self.device
@@ -379,3 +638,201 @@ impl VkHandle for RawCommandBuffer {
         self.command_buffer.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::{Buffer, BufferCreateInfo, BufferUsage},
+        command::{AccessFlags2, BufferMemoryBarrier2, CommandEncoder},
+        image::{Extent2D, ImageFormat},
+        memory::MemoryLocation,
+        pipeline::{
+            GraphicsPipeline, PipelineLayout, PipelineLayoutCreateInfo, VertexAttribute,
+            VertexBufferLayout, VertexFormat, VertexInputRate,
+        },
+        sync::Fence,
+        test_util::TestDevice,
+    };
+
+    const VERTEX_SHADER: &str = r#"
+        struct Output {
+            float4 position : SV_Position;
+            [[vk::location(0)]] float4 color : COLOR;
+        };
+
+        Output main(
+            [[vk::location(0)]] float2 position : POSITION,
+            [[vk::location(1)]] float4 color : COLOR
+        ) {
+            Output output;
+            output.position = float4(position, 0.0, 1.0);
+            output.color = color;
+            return output;
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &str = r#"
+        float4 main([[vk::location(0)]] float4 color : COLOR) : SV_Target {
+            return color;
+        }
+    "#;
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_execute_secondaries_in_order() {
+        let TestDevice {
+            device,
+            queue,
+            allocator,
+        } = TestDevice::new();
+
+        let output = Buffer::new(
+            device.clone(),
+            allocator.clone(),
+            BufferCreateInfo::new(8).usage(BufferUsage::TRANSFER_DST),
+            MemoryLocation::GpuToCpu,
+        );
+
+        // Each secondary copies its word into its half of the output.
+        let pool = CommandPool::new(device.clone(), 0);
+        let secondaries = [[1u8; 4], [2u8; 4]]
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let staging =
+                    Buffer::new_staging_with_data(device.clone(), allocator.clone(), data);
+
+                let mut secondary = pool.allocate_secondary();
+                secondary.begin_secondary(
+                    CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+                    &CommandBufferInheritanceInfo::default(),
+                );
+                secondary.copy_buffer(
+                    staging.vk_handle(),
+                    output.vk_handle(),
+                    0,
+                    index as u64 * 4,
+                    4,
+                );
+                secondary.end();
+
+                (staging, secondary)
+            })
+            .collect::<Vec<_>>();
+
+        let mut encoder = CommandEncoder::new(pool.allocate(CommandBufferLevel::PRIMARY));
+        encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        encoder.execute_commands(secondaries.iter().map(|(_, secondary)| secondary));
+        encoder.pipeline_barrier(
+            [],
+            [BufferMemoryBarrier2 {
+                buffer: output.clone(),
+                src_stage_mask: PipelineStageFlags2::TRANSFER,
+                src_access_mask: AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: PipelineStageFlags2::HOST,
+                dst_access_mask: AccessFlags2::HOST_READ,
+                offset: 0,
+                size: vk::WHOLE_SIZE,
+            }],
+            [],
+        );
+        encoder.end();
+
+        let fence = Fence::unsignaled(device.clone());
+        queue.submit(encoder, vec![], vec![], Some(fence.clone()));
+        assert!(fence.wait(u64::MAX));
+
+        let values = unsafe { output.mapped_slice_mut::<u8>() };
+        assert_eq!(values, [1, 1, 1, 1, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver and the DXC library"]
+    fn test_draw_mesh_from_shared_buffer() {
+        let test_device = TestDevice::new();
+        let device = test_device.device.clone();
+
+        // A red triangle covering the whole target, followed by a green one
+        // covering its right half. Both are indexed from their first vertex.
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let green = [0.0, 1.0, 0.0, 1.0];
+        // Each vertex is a float2 position followed by a float4 color.
+        let vertices = [
+            ([-1.0, -1.0], red),
+            ([-1.0, 3.0], red),
+            ([3.0, -1.0], red),
+            ([0.0, -1.0], green),
+            ([0.0, 3.0], green),
+            ([2.0, -1.0], green),
+        ]
+        .map(|([x, y], [r, g, b, a])| [x, y, r, g, b, a]);
+        let indices = [0u16, 1, 2, 0, 1, 2];
+
+        // The vertices and indices share a single buffer.
+        let vertex_bytes = bytemuck::cast_slice::<_, u8>(&vertices);
+        let index_offset = vertex_bytes.len() as u64;
+        let buffer = Buffer::new(
+            device.clone(),
+            test_device.allocator.clone(),
+            BufferCreateInfo::new(index_offset + size_of_val(&indices) as u64)
+                .usage(BufferUsage::VERTEX_BUFFER | BufferUsage::INDEX_BUFFER),
+            MemoryLocation::CpuToGpu,
+        );
+        unsafe {
+            buffer.write(&[vertex_bytes, bytemuck::cast_slice(&indices)].concat());
+        }
+
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineLayoutCreateInfo {
+                set_layouts: vec![],
+            },
+        );
+        let pipeline =
+            GraphicsPipeline::builder(layout, test_device.compile_shader(VERTEX_SHADER, "vs_6_0"))
+                .fragment_shader(test_device.compile_shader(FRAGMENT_SHADER, "ps_6_0"))
+                .vertex_buffer(VertexBufferLayout {
+                    binding: 0,
+                    stride: size_of::<[f32; 6]>() as u32,
+                    input_rate: VertexInputRate::Vertex,
+                    attributes: vec![
+                        VertexAttribute {
+                            location: 0,
+                            format: VertexFormat::Float2,
+                            offset: 0,
+                        },
+                        VertexAttribute {
+                            location: 1,
+                            format: VertexFormat::Float4,
+                            offset: 8,
+                        },
+                    ],
+                })
+                .color_attachment(ImageFormat::Rgba8Unorm, None)
+                .build(device.clone())
+                .unwrap();
+
+        let target = test_device.create_render_target(ImageFormat::Rgba8Unorm, Extent2D::new(4, 1));
+        test_device.submit_and_wait(|encoder| {
+            target.begin_rendering(encoder);
+            encoder.bind_pipeline(PipelineBindPoint::Graphics, pipeline.vk_handle());
+            encoder.bind_vertex_buffers(0, [buffer.clone()], [0]);
+            encoder.bind_index_buffer(buffer.clone(), index_offset, IndexType::Uint16);
+            // Only the second triangle is drawn.
+            encoder.draw_indexed(3, 1, 3, 3, 0);
+            encoder.end_rendering();
+        });
+
+        let pixels = test_device.read_image(&target.image, ImageLayout::ColorAttachmentOptimal);
+        assert_eq!(
+            pixels,
+            [
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [0, 255, 0, 255],
+                [0, 255, 0, 255]
+            ]
+        );
+    }
+}
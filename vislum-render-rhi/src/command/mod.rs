@@ -1,9 +1,11 @@
 pub mod auto_command_buffer;
+pub mod barrier;
 pub mod command_buffer;
 pub mod types;
 pub mod tracker;
 pub use types::*;
 
 pub use auto_command_buffer::*;
+pub use barrier::*;
 pub use command_buffer::*;
 pub use tracker::*;
\ No newline at end of file
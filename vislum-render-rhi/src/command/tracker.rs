@@ -1,129 +1,208 @@
-// use std::{collections::HashMap, sync::Arc};
-
-// use crate::{command::{AccessFlags2, ImageLayout, ImageMemoryBarrier2, PipelineStageFlags2}, image::{Image, image::ImageId}};
-
-// #[derive(Copy, Clone)]
-// pub(crate) struct ImageState {
-//     pub id: ImageId,
-//     /// The layout of the image.
-//     pub current_layout: ImageLayout,
-//     /// The access flags of the image last seen.
-//     pub last_seen_access: AccessFlags2,
-//     /// The stage of the image last seen.
-//     pub last_seen_stage: PipelineStageFlags2,
-// }
-
-// #[derive(Default)]
-// pub struct ResourceStateTracker {
-//     images: HashMap<ImageId, ImageState>,
-// }
-
-// impl ResourceStateTracker {
-//     /// Marks an image as seen.
-//     pub fn transition_image(
-//         &mut self, 
-//         image: Arc<Image>, 
-//         layout: ImageLayout,
-//         access: AccessFlags2, 
-//         stage: PipelineStageFlags2
-//     ) -> Option<ImageMemoryBarrier2> {
-//         let state = self.resolve_image_state(&image);
-//         let (new_state, barrier) = transition_image(image, *state, ImageState {
-//             current_layout: layout,
-//             last_seen_access: access,
-//             last_seen_stage: stage,
-//         });
-
-//         *state = new_state;
-//         barrier
-//     }
-
-//     fn resolve_image_state(&mut self, image: &Arc<Image>) -> &mut ImageState {
-//         let id = image.id();
-//         self.images.entry(id)
-//             .or_insert_with(|| {
-//                 ImageState {
-//                     current_layout: ImageLayout::Undefined,
-//                     last_seen_access: AccessFlags2::NONE,
-//                     last_seen_stage: PipelineStageFlags2::NONE,
-//                 }
-//             })
-//     }
-// }
-
-// // The natural order of the pipeline stages used for drawing.
-// const DRAW_PIPELINE_STAGES: [PipelineStageFlags2; 5] = [
-//     PipelineStageFlags2::TOP_OF_PIPE,
-//     PipelineStageFlags2::FRAGMENT_SHADER,
-//     PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
-//     PipelineStageFlags2::EARLY_FRAGMENT_TESTS,
-//     PipelineStageFlags2::LATE_FRAGMENT_TESTS,
-// ];
-
-// fn transition_image(
-//     image: Arc<Image>,
-//     current_state: ImageState,
-//     desired_state: ImageState,
-// ) -> (ImageState, Option<ImageMemoryBarrier2>) {
-//     // No transition needed. Image is in the correct layout.
-//     //
-//     // Just update when it was last seen.
-//     if current_state.current_layout == desired_state.current_layout {
-//         return (desired_state, None);
-//     }
-
-//     // Find the index of the desired stage in the draw pipeline stages.
-//     //
-//     // If the desired stage is not found, use the top of the pipeline to signal
-//     // that the barrier is split between the top of the pipeline and the desired stage.
-//     let (last_seen_stage, last_seen_access) = DRAW_PIPELINE_STAGES
-//         .iter()
-//         .copied()
-//         .rev()
-//         .find(|stage| (desired_state.last_seen_stage.contains(*stage)))
-//         .map(|stage| (stage, desired_state.last_seen_access))
-//         .unwrap_or((PipelineStageFlags2::TOP_OF_PIPE, AccessFlags2::NONE));
-
-//     // Transition needed. Generate the barrier.
-//     let barrier = ImageMemoryBarrier2 {
-//         image,
-//         src_stage_mask: current_state.last_seen_stage,
-//         src_access_mask: current_state.last_seen_access,
-//         dst_stage_mask: last_seen_stage,
-//         dst_access_mask: last_seen_access,
-//         old_layout: current_state.current_layout,
-//         new_layout: desired_state.current_layout,
-//     };
-
-//     (desired_state, Some(barrier))
-// }
-
-// fn next_image_stage(last_seen_stage: PipelineStageFlags2) -> PipelineStageFlags2 {
-//     macro_rules! c {
-//         ($expr:expr) => {
-//             last_seen_stage.contains($expr)
-//         };
-//     };
-
-//     if c!(PipelineStageFlags2::VERTEX_SHADER) {
-//         return PipelineStageFlags2::FRAGMENT_SHADER
-//     }
-    
-//     if c!(PipelineStageFlags2::FRAGMENT_SHADER) {
-//         PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
-//     }
-    
-//     if c!(PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT) {
-//         PipelineStageFlags2::EARLY_FRAGMENT_TESTS
-//     }
-    
-//     if c!(PipelineStageFlags2::EARLY_FRAGMENT_TESTS) {
-//         PipelineStageFlags2::LATE_FRAGMENT_TESTS
-//     }
-    
-//     if c!(PipelineStageFlags2::LATE_FRAGMENT_TESTS) {
-//         PipelineStageFlags2::ALL_COMMANDS
-//     }
-    
-//     PipelineStageFlags2::TOP_OF_PIPE
-// }
\ No newline at end of file
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    command::{AccessFlags2, ImageLayout, ImageMemoryBarrier2, PipelineStageFlags2},
+    image::{Image, image::ImageId},
+};
+
+/// The last known state of an image within a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ImageState {
+    layout: ImageLayout,
+    /// The stages which last accessed the image.
+    stage: PipelineStageFlags2,
+    /// How the stages accessed the image.
+    access: AccessFlags2,
+}
+
+impl ImageState {
+    /// The state of an image the recording hasn't used yet.
+    ///
+    /// Its contents are discarded by the first transition.
+    const UNUSED: Self = Self {
+        layout: ImageLayout::Undefined,
+        stage: PipelineStageFlags2::TOP_OF_PIPE,
+        access: AccessFlags2::NONE,
+    };
+}
+
+/// Tracks the layout of the images used by a command buffer recording, to
+/// infer the barriers transitioning them.
+///
+/// Only the transitions recorded through the tracker are known. Images start
+/// in [`ImageLayout::Undefined`] on their first use, so their contents are
+/// not preserved across recordings.
+#[derive(Default)]
+pub struct ResourceStateTracker {
+    images: HashMap<ImageId, ImageState>,
+}
+
+impl ResourceStateTracker {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the current layout of an image, or [`ImageLayout::Undefined`]
+    /// if it hasn't been used yet.
+    pub fn image_layout(&self, image: &Image) -> ImageLayout {
+        self.images
+            .get(&image.id())
+            .map_or(ImageLayout::Undefined, |state| state.layout)
+    }
+
+    /// Returns the barrier making the whole image available to the given
+    /// stages and accesses in the new layout, and records its new state.
+    ///
+    /// Returns `None` if the image is already in the layout and was last
+    /// accessed the same way.
+    pub fn transition_image(
+        &mut self,
+        image: Arc<Image>,
+        new_layout: ImageLayout,
+        dst_stage: PipelineStageFlags2,
+        dst_access: AccessFlags2,
+    ) -> Option<ImageMemoryBarrier2> {
+        let new_state = ImageState {
+            layout: new_layout,
+            stage: dst_stage,
+            access: dst_access,
+        };
+
+        let state = self.images.entry(image.id()).or_insert(ImageState::UNUSED);
+        let old_state = std::mem::replace(state, new_state);
+        if old_state == new_state {
+            return None;
+        }
+
+        Some(ImageMemoryBarrier2 {
+            image,
+            src_stage_mask: old_state.stage,
+            src_access_mask: old_state.access,
+            dst_stage_mask: dst_stage,
+            dst_access_mask: dst_access,
+            old_layout: old_state.layout,
+            new_layout,
+            ownership_transfer: None,
+            subresource_range: None,
+        })
+    }
+
+    /// Declares the state of an image the recording hasn't transitioned yet,
+    /// e.g. a texture left in a layout by a previous submission.
+    ///
+    /// The next transition of the image waits for the given stages and
+    /// accesses, instead of discarding its contents. Does nothing if the
+    /// image was already transitioned.
+    pub fn assume_image_state(
+        &mut self,
+        image: &Image,
+        layout: ImageLayout,
+        stage: PipelineStageFlags2,
+        access: AccessFlags2,
+    ) {
+        self.images.entry(image.id()).or_insert(ImageState {
+            layout,
+            stage,
+            access,
+        });
+    }
+
+    /// Forgets the state of all the images, e.g. when the recording starts
+    /// over.
+    pub fn clear(&mut self) {
+        self.images.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        image::{Extent3D, ImageCreateInfo, ImageFormat, ImageUsage},
+        memory::MemoryLocation,
+        test_util::TestDevice,
+    };
+
+    fn create_image() -> Arc<Image> {
+        let TestDevice {
+            device, allocator, ..
+        } = TestDevice::new();
+
+        Image::new(
+            device,
+            allocator,
+            ImageCreateInfo::new(
+                ImageFormat::Rgba8Unorm,
+                Extent3D {
+                    width: 4,
+                    height: 4,
+                    depth: 1,
+                },
+            )
+            .usage(ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED),
+            MemoryLocation::GpuOnly,
+        )
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_sequential_transitions_are_chained() {
+        let image = create_image();
+        let mut tracker = ResourceStateTracker::new();
+        assert_eq!(tracker.image_layout(&image), ImageLayout::Undefined);
+
+        let first = tracker
+            .transition_image(
+                image.clone(),
+                ImageLayout::TransferDstOptimal,
+                PipelineStageFlags2::TRANSFER,
+                AccessFlags2::TRANSFER_WRITE,
+            )
+            .unwrap();
+        assert_eq!(first.old_layout, ImageLayout::Undefined);
+        assert_eq!(first.new_layout, ImageLayout::TransferDstOptimal);
+        assert_eq!(first.src_stage_mask, PipelineStageFlags2::TOP_OF_PIPE);
+        assert_eq!(first.src_access_mask, AccessFlags2::NONE);
+
+        // The second transition waits for the accesses of the first one.
+        let second = tracker
+            .transition_image(
+                image.clone(),
+                ImageLayout::ShaderReadOnlyOptimal,
+                PipelineStageFlags2::FRAGMENT_SHADER,
+                AccessFlags2::SHADER_READ,
+            )
+            .unwrap();
+        assert_eq!(second.old_layout, ImageLayout::TransferDstOptimal);
+        assert_eq!(second.new_layout, ImageLayout::ShaderReadOnlyOptimal);
+        assert_eq!(second.src_stage_mask, PipelineStageFlags2::TRANSFER);
+        assert_eq!(second.src_access_mask, AccessFlags2::TRANSFER_WRITE);
+        assert_eq!(second.dst_stage_mask, PipelineStageFlags2::FRAGMENT_SHADER);
+        assert_eq!(second.dst_access_mask, AccessFlags2::SHADER_READ);
+        assert_eq!(
+            tracker.image_layout(&image),
+            ImageLayout::ShaderReadOnlyOptimal
+        );
+
+        // Assumed states don't override the tracked ones.
+        tracker.assume_image_state(
+            &image,
+            ImageLayout::General,
+            PipelineStageFlags2::ALL_COMMANDS,
+            AccessFlags2::NONE,
+        );
+
+        // Repeating the last transition is a no-op.
+        assert!(
+            tracker
+                .transition_image(
+                    image,
+                    ImageLayout::ShaderReadOnlyOptimal,
+                    PipelineStageFlags2::FRAGMENT_SHADER,
+                    AccessFlags2::SHADER_READ,
+                )
+                .is_none()
+        );
+    }
+}
@@ -86,6 +86,17 @@ vk_enum! {
     }
 }
 
+impl IndexType {
+    /// Returns the size of a single index in bytes.
+    pub const fn size(self) -> u64 {
+        match self {
+            IndexType::Uint16 => 2,
+            IndexType::Uint32 => 4,
+            IndexType::Uint8Ext => 1,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Viewport {
     pub x: f32,
@@ -236,6 +247,28 @@ impl BufferMemoryBarrier2 {
     }
 }
 
+/// Transfers the ownership of a resource between two queue families.
+///
+/// The barrier must be recorded twice: a release on the source queue and an
+/// acquire on the destination queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueFamilyOwnershipTransfer {
+    pub src_queue_family_index: u32,
+    pub dst_queue_family_index: u32,
+}
+
+impl QueueFamilyOwnershipTransfer {
+    /// Creates an ownership transfer between two queue families.
+    ///
+    /// Returns `None` if both families are the same, as no transfer is needed.
+    pub fn new(src_queue_family_index: u32, dst_queue_family_index: u32) -> Option<Self> {
+        (src_queue_family_index != dst_queue_family_index).then_some(Self {
+            src_queue_family_index,
+            dst_queue_family_index,
+        })
+    }
+}
+
 pub struct ImageMemoryBarrier2 {
     pub image: Arc<Image>,
     pub src_stage_mask: PipelineStageFlags2,
@@ -244,6 +277,10 @@ pub struct ImageMemoryBarrier2 {
     pub dst_access_mask: AccessFlags2,
     pub old_layout: ImageLayout,
     pub new_layout: ImageLayout,
+    /// The queue family ownership transfer, if any.
+    pub ownership_transfer: Option<QueueFamilyOwnershipTransfer>,
+    /// The mip levels and layers to transition, or `None` for the whole image.
+    pub subresource_range: Option<ImageSubresourceRange>,
 }
 
 impl ImageMemoryBarrier2 {
@@ -256,14 +293,25 @@ impl ImageMemoryBarrier2 {
             .dst_access_mask(self.dst_access_mask.to_vk())
             .old_layout(self.old_layout.to_vk())
             .new_layout(self.new_layout.to_vk())
-            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
-            .subresource_range(vk::ImageSubresourceRange::default()
-                .aspect_mask(vk::ImageAspectFlags::COLOR)
-                .base_mip_level(0)
-                .level_count(1)
-                .base_array_layer(0)
-                .layer_count(1))
+            .src_queue_family_index(
+                self.ownership_transfer
+                    .map_or(vk::QUEUE_FAMILY_IGNORED, |t| t.src_queue_family_index),
+            )
+            .dst_queue_family_index(
+                self.ownership_transfer
+                    .map_or(vk::QUEUE_FAMILY_IGNORED, |t| t.dst_queue_family_index),
+            )
+            .subresource_range(self.subresource_range.map_or_else(
+                || {
+                    vk::ImageSubresourceRange::default()
+                        .aspect_mask(self.image.format().aspect_mask())
+                        .base_mip_level(0)
+                        .level_count(self.image.mip_levels())
+                        .base_array_layer(0)
+                        .layer_count(self.image.array_layers())
+                },
+                ImageSubresourceRange::to_vk,
+            ))
     }
 }
 
@@ -296,7 +344,6 @@ pub struct BufferImageCopy {
     pub image_extent: Extent3D,
 }
 
-
 impl BufferImageCopy {
     pub fn to_vk(self) -> vk::BufferImageCopy {
         vk::BufferImageCopy {
@@ -314,3 +361,48 @@ impl BufferImageCopy {
     }
 }
 
+/// A region blitted between two images, scaling and converting formats as
+/// needed.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageBlit {
+    pub src_subresource: ImageSubresourceLayers,
+    /// The opposite corners of the source region.
+    pub src_offsets: [[i32; 3]; 2],
+    pub dst_subresource: ImageSubresourceLayers,
+    /// The opposite corners of the destination region.
+    pub dst_offsets: [[i32; 3]; 2],
+}
+
+impl ImageBlit {
+    pub fn to_vk(self) -> vk::ImageBlit {
+        let offsets = |offsets: [[i32; 3]; 2]| offsets.map(|[x, y, z]| vk::Offset3D { x, y, z });
+
+        vk::ImageBlit {
+            src_subresource: self.src_subresource.to_vk(),
+            src_offsets: offsets(self.src_offsets),
+            dst_subresource: self.dst_subresource.to_vk(),
+            dst_offsets: offsets(self.dst_offsets),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_family_ownership_transfer_is_noop() {
+        assert_eq!(QueueFamilyOwnershipTransfer::new(0, 0), None);
+    }
+
+    #[test]
+    fn test_ownership_transfer_between_families() {
+        assert_eq!(
+            QueueFamilyOwnershipTransfer::new(0, 2),
+            Some(QueueFamilyOwnershipTransfer {
+                src_queue_family_index: 0,
+                dst_queue_family_index: 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use ash::vk;
+use smallvec::SmallVec;
+
+use crate::{
+    AshHandle, DebugWrapper, VkHandle, descriptor::DescriptorType, device::Device,
+    shader::ShaderStageFlags,
+};
+
+/// A binding of a descriptor set layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescriptorSetLayoutBinding {
+    pub binding: u32,
+    pub descriptor_type: DescriptorType,
+    /// The number of descriptors in the binding, for arrays.
+    pub count: u32,
+    pub stages: ShaderStageFlags,
+}
+
+pub struct DescriptorSetLayoutCreateInfo {
+    pub bindings: Vec<DescriptorSetLayoutBinding>,
+}
+
+pub struct DescriptorSetLayout {
+    device: Arc<Device>,
+    bindings: Vec<DescriptorSetLayoutBinding>,
+    layout: DebugWrapper<vk::DescriptorSetLayout>,
+}
+
+impl DescriptorSetLayout {
+    pub fn new(device: Arc<Device>, create_info: DescriptorSetLayoutCreateInfo) -> Arc<Self> {
+        let vk_bindings = create_info
+            .bindings
+            .iter()
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.descriptor_type.to_vk())
+                    .descriptor_count(binding.count)
+                    .stage_flags(binding.stages.to_vk())
+            })
+            .collect::<SmallVec<[vk::DescriptorSetLayoutBinding; 8]>>();
+
+        let vk_create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&vk_bindings);
+
+        let layout = unsafe {
+            device
+                .ash_handle()
+                .create_descriptor_set_layout(&vk_create_info, None)
+                .unwrap()
+        };
+
+        Arc::new(Self {
+            device,
+            bindings: create_info.bindings,
+            layout: DebugWrapper(layout),
+        })
+    }
+
+    /// Returns the bindings of the layout.
+    #[inline]
+    pub fn bindings(&self) -> &[DescriptorSetLayoutBinding] {
+        &self.bindings
+    }
+}
+
+impl VkHandle for DescriptorSetLayout {
+    type Handle = vk::DescriptorSetLayout;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.layout.0
+    }
+}
+
+impl Drop for DescriptorSetLayout {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .destroy_descriptor_set_layout(self.layout.0, None);
+        }
+    }
+}
@@ -1,9 +1,15 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, Weak};
 
 use ash::vk;
 use smallvec::SmallVec;
 
-use crate::{AshHandle, DebugWrapper, device::Device, vk_enum, vk_enum_flags};
+use crate::{AshHandle, DebugWrapper, Error, VkHandle, device::Device, vk_enum, vk_enum_flags};
+
+mod layout;
+mod write;
+
+pub use layout::{DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo};
+pub use write::{CombinedImageSampler, DescriptorInfo, DescriptorResourceInfo, DescriptorWrites};
 
 vk_enum! {
     pub enum DescriptorType: vk::DescriptorType {
@@ -48,7 +54,7 @@ vk_enum_flags! {
 }
 
 /// Describes the number of descriptors that can be allocated from a pool.
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub struct DescriptorPoolSizes {
     pub sampler: u32,
     pub combined_image_sampler: u32,
@@ -63,6 +69,7 @@ pub struct DescriptorPoolSizes {
     pub input_attachment: u32,
 }
 
+#[derive(Clone, Copy)]
 pub struct DescriptorPoolCreateInfo {
     /// The sizes of the descriptor pools.
     ///
@@ -136,12 +143,98 @@ impl RawDescriptorPool {
             pool: DebugWrapper(pool),
         })
     }
+
+    /// Allocates a descriptor set with the given layout.
+    pub fn allocate(
+        self: &Arc<Self>,
+        layout: &Arc<DescriptorSetLayout>,
+    ) -> Result<Arc<DescriptorSet>, vk::Result> {
+        let layouts = [layout.vk_handle()];
+        let allocate_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(self.pool.0)
+            .set_layouts(&layouts);
+
+        let sets = unsafe {
+            self.device
+                .ash_handle()
+                .allocate_descriptor_sets(&allocate_info)?
+        };
+
+        Ok(Arc::new(DescriptorSet {
+            _pool: self.clone(),
+            layout: layout.clone(),
+            set: DebugWrapper(sets[0]),
+        }))
+    }
+
+    /// Resets the pool, freeing all the sets allocated from it.
+    ///
+    /// # Safety
+    ///
+    /// None of the sets allocated from the pool may be used afterwards.
+    pub unsafe fn reset(&self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .reset_descriptor_pool(self.pool.0, vk::DescriptorPoolResetFlags::empty())
+                .unwrap();
+        }
+    }
+}
+
+impl VkHandle for RawDescriptorPool {
+    type Handle = vk::DescriptorPool;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.pool.0
+    }
 }
 
+impl Drop for RawDescriptorPool {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .destroy_descriptor_pool(self.pool.0, None);
+        }
+    }
+}
+
+/// A descriptor set allocated from a [`DescriptorPool`].
+///
+/// The set is freed when its pool is reset or destroyed.
+pub struct DescriptorSet {
+    _pool: Arc<RawDescriptorPool>,
+    layout: Arc<DescriptorSetLayout>,
+    set: DebugWrapper<vk::DescriptorSet>,
+}
+
+impl DescriptorSet {
+    /// Returns the layout the set was allocated with.
+    #[inline]
+    pub fn layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.layout
+    }
+}
+
+impl VkHandle for DescriptorSet {
+    type Handle = vk::DescriptorSet;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.set.0
+    }
+}
+
+/// A growable descriptor pool.
+///
+/// Sets are allocated from the most recent [`RawDescriptorPool`], and a new
+/// one is created with the same sizes whenever it runs out of space.
 pub struct DescriptorPool {
     device: Arc<Device>,
     create_info: DescriptorPoolCreateInfo,
-    pools: Vec<RawDescriptorPool>,
+    pools: Mutex<Vec<Arc<RawDescriptorPool>>>,
+    /// The sets allocated since the last reset.
+    allocated: Mutex<Vec<Weak<DescriptorSet>>>,
 }
 
 impl DescriptorPool {
@@ -150,6 +243,60 @@ impl DescriptorPool {
             device,
             create_info,
             pools: Default::default(),
+            allocated: Default::default(),
         })
     }
-}
\ No newline at end of file
+
+    /// Allocates a descriptor set with the given layout, growing the pool if
+    /// the current one is exhausted.
+    pub fn allocate(&self, layout: &Arc<DescriptorSetLayout>) -> Result<Arc<DescriptorSet>, Error> {
+        let mut pools = self.pools.lock().unwrap();
+
+        let set = match pools.last().map(|pool| pool.allocate(layout)) {
+            Some(Ok(set)) => set,
+            Some(Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL))
+            | None => {
+                let pool = RawDescriptorPool::new(self.device.clone(), self.create_info);
+                let set = pool.allocate(layout)?;
+                pools.push(pool);
+                set
+            }
+            Some(Err(error)) => return Err(Error::Vulkan(error)),
+        };
+
+        let mut allocated = self.allocated.lock().unwrap();
+        allocated.retain(|set| set.strong_count() > 0);
+        allocated.push(Arc::downgrade(&set));
+
+        Ok(set)
+    }
+
+    /// Returns the number of allocated sets which are still alive.
+    pub fn live_sets(&self) -> usize {
+        self.allocated
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|set| set.strong_count() > 0)
+            .count()
+    }
+
+    /// Resets the pool, freeing all the allocated sets at once.
+    ///
+    /// Panics if any allocated set is still alive, as it would be left
+    /// dangling.
+    pub fn reset(&self) {
+        assert_eq!(
+            self.live_sets(),
+            0,
+            "descriptor sets are still alive while resetting their pool"
+        );
+
+        for pool in self.pools.lock().unwrap().iter() {
+            // SAFETY: No set allocated from the pool is alive.
+            unsafe { pool.reset() };
+        }
+
+        self.allocated.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use ash::vk;
+
+use crate::{
+    AshHandle, VkHandle, buffer::Buffer, command::ImageLayout, descriptor::DescriptorType,
+    device::Device, image::ImageView, sampler::Sampler,
+};
+
+/// The information written into a descriptor.
+#[derive(Debug, Clone, Copy)]
+pub enum DescriptorResourceInfo {
+    Image(vk::DescriptorImageInfo),
+    Buffer(vk::DescriptorBufferInfo),
+}
+
+/// A resource that can be written into a descriptor.
+pub trait DescriptorInfo {
+    /// The descriptor type the resource is written as.
+    fn descriptor_type(&self) -> DescriptorType;
+
+    /// Returns the information written into the descriptor.
+    fn descriptor_info(&self) -> DescriptorResourceInfo;
+}
+
+/// Image views are written as sampled images in the shader read-only layout.
+impl DescriptorInfo for ImageView {
+    fn descriptor_type(&self) -> DescriptorType {
+        DescriptorType::SampledImage
+    }
+
+    fn descriptor_info(&self) -> DescriptorResourceInfo {
+        DescriptorResourceInfo::Image(
+            vk::DescriptorImageInfo::default()
+                .image_layout(ImageLayout::ShaderReadOnlyOptimal.to_vk())
+                .image_view(self.vk_handle()),
+        )
+    }
+}
+
+impl DescriptorInfo for Sampler {
+    fn descriptor_type(&self) -> DescriptorType {
+        DescriptorType::Sampler
+    }
+
+    fn descriptor_info(&self) -> DescriptorResourceInfo {
+        DescriptorResourceInfo::Image(vk::DescriptorImageInfo::default().sampler(self.vk_handle()))
+    }
+}
+
+/// Buffers are written whole, as uniform buffers.
+impl DescriptorInfo for Buffer {
+    fn descriptor_type(&self) -> DescriptorType {
+        DescriptorType::UniformBuffer
+    }
+
+    fn descriptor_info(&self) -> DescriptorResourceInfo {
+        DescriptorResourceInfo::Buffer(
+            vk::DescriptorBufferInfo::default()
+                .buffer(self.vk_handle())
+                .offset(0)
+                .range(vk::WHOLE_SIZE),
+        )
+    }
+}
+
+/// An image view and the sampler it is sampled with, bound to a single binding.
+pub struct CombinedImageSampler {
+    pub view: Arc<ImageView>,
+    pub sampler: Arc<Sampler>,
+}
+
+impl DescriptorInfo for CombinedImageSampler {
+    fn descriptor_type(&self) -> DescriptorType {
+        DescriptorType::CombinedImageSampler
+    }
+
+    fn descriptor_info(&self) -> DescriptorResourceInfo {
+        DescriptorResourceInfo::Image(
+            vk::DescriptorImageInfo::default()
+                .image_layout(ImageLayout::ShaderReadOnlyOptimal.to_vk())
+                .image_view(self.view.vk_handle())
+                .sampler(self.sampler.vk_handle()),
+        )
+    }
+}
+
+struct DescriptorWrite {
+    set: vk::DescriptorSet,
+    binding: u32,
+    array_element: u32,
+    descriptor_type: DescriptorType,
+    info: DescriptorResourceInfo,
+}
+
+/// A batch of descriptor writes, applied with a single descriptor set update.
+#[derive(Default)]
+pub struct DescriptorWrites {
+    writes: Vec<DescriptorWrite>,
+}
+
+impl DescriptorWrites {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Writes a resource into a binding of a descriptor set.
+    pub fn write(
+        &mut self,
+        set: vk::DescriptorSet,
+        binding: u32,
+        resource: &dyn DescriptorInfo,
+    ) -> &mut Self {
+        self.write_info(
+            set,
+            binding,
+            resource.descriptor_type(),
+            resource.descriptor_info(),
+        )
+    }
+
+    /// Writes raw descriptor information into a binding of a descriptor set.
+    pub fn write_info(
+        &mut self,
+        set: vk::DescriptorSet,
+        binding: u32,
+        descriptor_type: DescriptorType,
+        info: DescriptorResourceInfo,
+    ) -> &mut Self {
+        self.writes.push(DescriptorWrite {
+            set,
+            binding,
+            array_element: 0,
+            descriptor_type,
+            info,
+        });
+        self
+    }
+
+    /// Returns the Vulkan descriptor writes, borrowing the stored information.
+    pub fn to_vk(&self) -> Vec<vk::WriteDescriptorSet<'_>> {
+        self.writes
+            .iter()
+            .map(|write| {
+                let vk_write = vk::WriteDescriptorSet::default()
+                    .dst_set(write.set)
+                    .dst_binding(write.binding)
+                    .dst_array_element(write.array_element)
+                    .descriptor_type(write.descriptor_type.to_vk());
+
+                match &write.info {
+                    DescriptorResourceInfo::Image(info) => {
+                        vk_write.image_info(std::slice::from_ref(info))
+                    }
+                    DescriptorResourceInfo::Buffer(info) => {
+                        vk_write.buffer_info(std::slice::from_ref(info))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Applies all the writes.
+    pub fn update(&self, device: &Device) {
+        let writes = self.to_vk();
+
+        unsafe {
+            device.ash_handle().update_descriptor_sets(&writes, &[]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::vk::Handle;
+
+    use super::*;
+
+    #[test]
+    fn test_writes_match_hand_built_version() {
+        let set = vk::DescriptorSet::from_raw(1);
+        let image_info = vk::DescriptorImageInfo::default()
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(vk::ImageView::from_raw(2));
+        let sampler_info = vk::DescriptorImageInfo::default().sampler(vk::Sampler::from_raw(3));
+
+        let mut writes = DescriptorWrites::new();
+        writes
+            .write_info(
+                set,
+                0,
+                DescriptorType::SampledImage,
+                DescriptorResourceInfo::Image(image_info),
+            )
+            .write_info(
+                set,
+                1,
+                DescriptorType::Sampler,
+                DescriptorResourceInfo::Image(sampler_info),
+            );
+
+        let image_infos = [image_info];
+        let sampler_infos = [sampler_info];
+        let expected = [
+            vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(0)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                .image_info(&image_infos),
+            vk::WriteDescriptorSet::default()
+                .dst_set(set)
+                .dst_binding(1)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::SAMPLER)
+                .image_info(&sampler_infos),
+        ];
+
+        for (actual, expected) in writes.to_vk().iter().zip(&expected) {
+            assert_eq!(actual.dst_set, expected.dst_set);
+            assert_eq!(actual.dst_binding, expected.dst_binding);
+            assert_eq!(actual.descriptor_type, expected.descriptor_type);
+            assert_eq!(actual.descriptor_count, expected.descriptor_count);
+
+            let actual_info = unsafe { *actual.p_image_info };
+            let expected_info = unsafe { *expected.p_image_info };
+            assert_eq!(actual_info.image_view, expected_info.image_view);
+            assert_eq!(actual_info.sampler, expected_info.sampler);
+            assert_eq!(actual_info.image_layout, expected_info.image_layout);
+        }
+    }
+}
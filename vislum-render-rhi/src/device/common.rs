@@ -6,6 +6,7 @@ impl_extensions! {
         khr_synchronization2 => ash::khr::synchronization2::NAME,
         khr_dynamic_rendering => ash::khr::dynamic_rendering::NAME,
         khr_ext_descriptor_indexing => ash::ext::descriptor_indexing::NAME,
+        ext_memory_budget => ash::ext::memory_budget::NAME,
     }
 }
 
@@ -35,10 +36,19 @@ pub struct PhysicalDeviceProperties {
     pub device_id: u32,
     pub device_type: PhysicalDeviceType,
     pub device_name: String,
+    /// The alignment required for dynamic uniform buffer offsets.
+    pub min_uniform_buffer_offset_alignment: u64,
+    /// The alignment required for flushing non-coherent mapped memory.
+    pub non_coherent_atom_size: u64,
+    /// The number of nanoseconds per timestamp query tick.
+    pub timestamp_period: f32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, Default)]
 pub struct QueueFamilyProperties {
     pub queue_flags: QueueFlags,
     pub queue_count: u32,
+    /// The number of meaningful bits in timestamps written by the queues, or
+    /// zero if timestamps aren't supported.
+    pub timestamp_valid_bits: u32,
 }
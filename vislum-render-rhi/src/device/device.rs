@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{ffi::CString, sync::Arc};
 
 use ash::vk;
 
@@ -11,7 +11,11 @@ use crate::{
 pub struct Device {
     instance: Arc<Instance>,
     physical_device: Arc<PhysicalDevice>,
+    enabled_extensions: DeviceExtensions,
+    enabled_features: DeviceFeatures,
     device: AshDebugWrapper<ash::Device>,
+    /// Loaded when the instance has debug utils enabled.
+    debug_utils: Option<ash::ext::debug_utils::Device>,
 }
 
 impl AshHandle for Device {
@@ -27,6 +31,9 @@ pub struct DeviceCreateInfo {
     pub physical_device: Arc<PhysicalDevice>,
     pub extensions: DeviceExtensions,
     pub features: DeviceFeatures,
+    /// The queue families to create a queue in besides family 0, e.g. a
+    /// present family without graphics support.
+    pub additional_queue_families: Vec<u32>,
 }
 
 impl Device {
@@ -34,9 +41,21 @@ impl Device {
     pub fn new(instance: Arc<Instance>, create_info: DeviceCreateInfo) -> Arc<Self> {
         let queue_priorities = [1.0];
 
-        let queue_create_infos = [vk::DeviceQueueCreateInfo::default()
-            .queue_priorities(&queue_priorities)
-            .queue_family_index(0)];
+        let mut queue_families = vec![0];
+        for &family in &create_info.additional_queue_families {
+            if !queue_families.contains(&family) {
+                queue_families.push(family);
+            }
+        }
+
+        let queue_create_infos = queue_families
+            .iter()
+            .map(|&family| {
+                vk::DeviceQueueCreateInfo::default()
+                    .queue_priorities(&queue_priorities)
+                    .queue_family_index(family)
+            })
+            .collect::<Vec<_>>();
 
         let enabled_extension_names = create_info.extensions.iter_c_ptrs().collect::<Vec<_>>();
 
@@ -60,10 +79,18 @@ impl Device {
         }
         .unwrap();
 
+        let debug_utils = instance
+            .enabled_extensions()
+            .ext_debug_utils
+            .then(|| ash::ext::debug_utils::Device::new(instance.ash_handle(), &device));
+
         Arc::new(Self {
             instance,
             physical_device: create_info.physical_device,
+            enabled_extensions: create_info.extensions,
+            enabled_features: create_info.features,
             device: AshDebugWrapper(device),
+            debug_utils,
         })
     }
 
@@ -76,4 +103,43 @@ impl Device {
     pub fn physical_device(&self) -> &Arc<PhysicalDevice> {
         &self.physical_device
     }
+
+    /// Returns the extensions the device was created with.
+    pub fn enabled_extensions(&self) -> &DeviceExtensions {
+        &self.enabled_extensions
+    }
+
+    /// Returns the features the device was created with.
+    pub fn enabled_features(&self) -> &DeviceFeatures {
+        &self.enabled_features
+    }
+
+    /// Blocks until the device is idle, e.g. before destroying resources
+    /// which may still be in use.
+    pub fn wait_idle(&self) {
+        unsafe { self.device.device_wait_idle() }.unwrap();
+    }
+
+    /// Attaches a debug name to an object, shown by validation messages and
+    /// graphics debuggers.
+    ///
+    /// Does nothing if the instance was created without debug utils.
+    pub fn set_object_name<T: VkHandle>(&self, object: &T, name: &str) {
+        let Some(debug_utils) = &self.debug_utils else {
+            return;
+        };
+
+        // Names are purely informational, so interior nul bytes truncate them.
+        let name = name.split('\0').next().unwrap_or_default();
+        let name = CString::new(name).unwrap();
+
+        let name_info = vk::DebugUtilsObjectNameInfoEXT::default()
+            .object_handle(object.vk_handle())
+            .object_name(&name);
+
+        unsafe {
+            // Naming is best effort, a failure shouldn't be fatal.
+            let _ = debug_utils.set_debug_utils_object_name(&name_info);
+        }
+    }
 }
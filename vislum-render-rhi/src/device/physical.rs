@@ -9,6 +9,7 @@ use crate::{
         DeviceExtensions, DeviceFeatures, PhysicalDeviceFeaturesFfi,
         PhysicalDeviceProperties, PhysicalDeviceType, QueueFamilyProperties, QueueFlags,
     },
+    image::{FormatFeatures, ImageFormat},
     instance::Instance,
 };
 
@@ -63,6 +64,11 @@ impl PhysicalDevice {
                 device_type: PhysicalDeviceType::from_vk(properties.device_type)
                     .unwrap_or(PhysicalDeviceType::OTHER),
                 device_name,
+                min_uniform_buffer_offset_alignment: properties
+                    .limits
+                    .min_uniform_buffer_offset_alignment,
+                non_coherent_atom_size: properties.limits.non_coherent_atom_size,
+                timestamp_period: properties.limits.timestamp_period,
             }
         })
     }
@@ -81,6 +87,7 @@ impl PhysicalDevice {
                 .map(|properties| QueueFamilyProperties {
                     queue_flags: QueueFlags::from_vk(properties.queue_flags),
                     queue_count: properties.queue_count,
+                    timestamp_valid_bits: properties.timestamp_valid_bits,
                 })
                 .collect()
         });
@@ -126,4 +133,15 @@ impl PhysicalDevice {
 
         features_ffi.into_device_features()
     }
+
+    /// Returns the features supported by the format with optimal tiling.
+    pub fn format_features(&self, format: ImageFormat) -> FormatFeatures {
+        let properties = unsafe {
+            self.instance
+                .ash_handle()
+                .get_physical_device_format_properties(self.physical_device, format.to_vk())
+        };
+
+        FormatFeatures::from_vk(properties.optimal_tiling_features)
+    }
 }
@@ -0,0 +1,14 @@
+use ash::vk;
+
+/// An error returned by the RHI.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("a Vulkan call failed: {0}")]
+    Vulkan(#[from] vk::Result),
+    #[error("the device feature `{0}` is not enabled")]
+    MissingFeature(&'static str),
+    #[error("the swapchain is out of date with its surface and must be recreated")]
+    SwapchainOutOfDate,
+    #[error("the queue family {0} does not support timestamp queries")]
+    TimestampsNotSupported(u32),
+}
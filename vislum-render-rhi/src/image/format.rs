@@ -1,4 +1,4 @@
-use crate::vk_enum;
+use crate::{vk_enum, vk_enum_flags};
 
 vk_enum! {
     #[derive(Default)]
@@ -9,6 +9,95 @@ vk_enum! {
         Rgb8Unorm => R8G8B8_UNORM,
         Rgb8Srgb => R8G8B8_SRGB,
         Bgra8Unorm => B8G8R8A8_UNORM,
+        Bgra8Srgb => B8G8R8A8_SRGB,
+        D16Unorm => D16_UNORM,
+        D32Sfloat => D32_SFLOAT,
+        D24UnormS8Uint => D24_UNORM_S8_UINT,
     }
 }
 
+impl ImageFormat {
+    /// Returns the sRGB counterpart of a UNORM format, if any.
+    pub const fn to_srgb(self) -> Option<Self> {
+        match self {
+            ImageFormat::Rgba8Unorm => Some(ImageFormat::Rgba8Srgb),
+            ImageFormat::Rgb8Unorm => Some(ImageFormat::Rgb8Srgb),
+            ImageFormat::Bgra8Unorm => Some(ImageFormat::Bgra8Srgb),
+            _ => None,
+        }
+    }
+
+    /// Returns the UNORM counterpart of an sRGB format, if any.
+    pub const fn to_unorm(self) -> Option<Self> {
+        match self {
+            ImageFormat::Rgba8Srgb => Some(ImageFormat::Rgba8Unorm),
+            ImageFormat::Rgb8Srgb => Some(ImageFormat::Rgb8Unorm),
+            ImageFormat::Bgra8Srgb => Some(ImageFormat::Bgra8Unorm),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the format holds depth values.
+    pub const fn is_depth(self) -> bool {
+        matches!(
+            self,
+            ImageFormat::D16Unorm | ImageFormat::D32Sfloat | ImageFormat::D24UnormS8Uint
+        )
+    }
+
+    /// Returns true if the format holds stencil values.
+    pub const fn has_stencil(self) -> bool {
+        matches!(self, ImageFormat::D24UnormS8Uint)
+    }
+
+    /// Returns the aspects of the format.
+    pub const fn aspect_mask(self) -> ash::vk::ImageAspectFlags {
+        if self.has_stencil() {
+            ash::vk::ImageAspectFlags::from_raw(
+                ash::vk::ImageAspectFlags::DEPTH.as_raw()
+                    | ash::vk::ImageAspectFlags::STENCIL.as_raw(),
+            )
+        } else if self.is_depth() {
+            ash::vk::ImageAspectFlags::DEPTH
+        } else {
+            ash::vk::ImageAspectFlags::COLOR
+        }
+    }
+
+    /// Returns true if an image of this format can be viewed with the other format.
+    ///
+    /// Only the UNORM/sRGB reinterpretation of the same layout is supported.
+    pub fn is_view_compatible(self, other: Self) -> bool {
+        self == other || self.to_srgb() == Some(other) || self.to_unorm() == Some(other)
+    }
+}
+
+
+vk_enum_flags! {
+    /// The features supported by an image format with optimal tiling.
+    pub struct FormatFeatures: ash::vk::FormatFeatureFlags {
+        SAMPLED_IMAGE => SAMPLED_IMAGE,
+        STORAGE_IMAGE => STORAGE_IMAGE,
+        COLOR_ATTACHMENT => COLOR_ATTACHMENT,
+        DEPTH_STENCIL_ATTACHMENT => DEPTH_STENCIL_ATTACHMENT,
+        BLIT_SRC => BLIT_SRC,
+        BLIT_DST => BLIT_DST,
+        SAMPLED_IMAGE_FILTER_LINEAR => SAMPLED_IMAGE_FILTER_LINEAR,
+        TRANSFER_SRC => TRANSFER_SRC,
+        TRANSFER_DST => TRANSFER_DST,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srgb_unorm_view_compatibility() {
+        assert!(ImageFormat::Rgba8Unorm.is_view_compatible(ImageFormat::Rgba8Srgb));
+        assert!(ImageFormat::Bgra8Srgb.is_view_compatible(ImageFormat::Bgra8Unorm));
+        assert!(ImageFormat::Rgba8Unorm.is_view_compatible(ImageFormat::Rgba8Unorm));
+        assert!(!ImageFormat::Rgba8Unorm.is_view_compatible(ImageFormat::Bgra8Srgb));
+        assert!(!ImageFormat::Rgb8Srgb.is_view_compatible(ImageFormat::Rgba8Unorm));
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use ash::vk;
 
@@ -28,9 +28,19 @@ vk_enum! {
 vk_enum_flags! {
     pub struct ImageUsage: ash::vk::ImageUsageFlags {
         COLOR_ATTACHMENT => COLOR_ATTACHMENT,
+        DEPTH_STENCIL_ATTACHMENT => DEPTH_STENCIL_ATTACHMENT,
         TRANSFER_DST => TRANSFER_DST,
         TRANSFER_SRC => TRANSFER_SRC,
         SAMPLED => SAMPLED,
+        STORAGE => STORAGE,
+    }
+}
+
+vk_enum_flags! {
+    pub struct ImageCreateFlags: ash::vk::ImageCreateFlags {
+        /// Views of the image may use a different, compatible format.
+        MUTABLE_FORMAT => MUTABLE_FORMAT,
+        CUBE_COMPATIBLE => CUBE_COMPATIBLE,
     }
 }
 
@@ -57,6 +67,11 @@ pub struct ImageCreateInfo {
     // pub samples: vk::SampleCountFlags,
     // pub tiling: vk::ImageTiling,
     pub usage: ImageUsage,
+    pub flags: ImageCreateFlags,
+    /// The formats views of this image may be created with, besides its own.
+    ///
+    /// Requires [`ImageCreateFlags::MUTABLE_FORMAT`].
+    pub view_formats: Vec<ImageFormat>,
 }
 
 impl Default for ImageCreateInfo {
@@ -68,8 +83,59 @@ impl Default for ImageCreateInfo {
             mip_levels: 1,
             array_layers: 1,
             usage: ImageUsage::empty(),
+            flags: ImageCreateFlags::empty(),
+            view_formats: Vec::new(),
+        }
+    }
+}
+
+impl ImageCreateInfo {
+    /// Creates the info for a 2D image with a single mip level and layer.
+    pub fn new(format: ImageFormat, extent: Extent3D) -> Self {
+        Self {
+            format,
+            extent,
+            ..Default::default()
         }
     }
+
+    /// Sets the dimensions of the image.
+    pub fn dimensions(mut self, dimensions: ImageType) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Sets the usage of the image.
+    pub fn usage(mut self, usage: ImageUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Sets the number of mip levels.
+    pub fn mips(mut self, mip_levels: u32) -> Self {
+        self.mip_levels = mip_levels;
+        self
+    }
+
+    /// Sets the number of array layers.
+    pub fn array_layers(mut self, array_layers: u32) -> Self {
+        self.array_layers = array_layers;
+        self
+    }
+
+    /// Sets the creation flags.
+    pub fn flags(mut self, flags: ImageCreateFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the additional formats views may be created with, enabling
+    /// [`ImageCreateFlags::MUTABLE_FORMAT`].
+    pub fn view_formats(mut self, view_formats: impl IntoIterator<Item = ImageFormat>) -> Self {
+        self.view_formats = view_formats.into_iter().collect();
+        self.flags |= ImageCreateFlags::MUTABLE_FORMAT;
+        self
+    }
 }
 
 impl_atomic_id!(pub struct ImageId);
@@ -78,6 +144,14 @@ pub struct Image {
     id: ImageId,
     device: Arc<Device>,
     image: DebugWrapper<vk::Image>,
+    format: ImageFormat,
+    view_formats: Vec<ImageFormat>,
+    extent: Extent3D,
+    mip_levels: u32,
+    array_layers: u32,
+    usage: ImageUsage,
+    /// The debug name set with [`Image::set_name`].
+    name: Mutex<Option<String>>,
     storage: ImageStorage,
 }
 
@@ -89,7 +163,24 @@ impl Image {
         create_info: ImageCreateInfo,
         memory_location: MemoryLocation,
     ) -> Arc<Self> {
-        let vk_create_info = vk::ImageCreateInfo::default()
+        assert!(
+            create_info.view_formats.is_empty()
+                || create_info.flags.contains(ImageCreateFlags::MUTABLE_FORMAT),
+            "view formats require the MUTABLE_FORMAT flag",
+        );
+
+        let vk_view_formats = create_info
+            .view_formats
+            .iter()
+            .chain(std::iter::once(&create_info.format))
+            .map(|format| format.to_vk())
+            .collect::<Vec<_>>();
+
+        let mut format_list =
+            vk::ImageFormatListCreateInfo::default().view_formats(&vk_view_formats);
+
+        let mut vk_create_info = vk::ImageCreateInfo::default()
+            .flags(create_info.flags.to_vk())
             .image_type(create_info.dimensions.to_vk())
             .format(create_info.format.to_vk())
             .extent(create_info.extent.to_vk())
@@ -99,6 +190,10 @@ impl Image {
             .tiling(vk::ImageTiling::OPTIMAL)
             .usage(create_info.usage.to_vk());
 
+        if !create_info.view_formats.is_empty() {
+            vk_create_info = vk_create_info.push_next(&mut format_list);
+        }
+
         let image = unsafe {
             device
                 .ash_handle()
@@ -125,6 +220,13 @@ impl Image {
             id: ImageId::new(),
             device,
             image: DebugWrapper(image),
+            format: create_info.format,
+            view_formats: create_info.view_formats,
+            extent: create_info.extent,
+            mip_levels: create_info.mip_levels,
+            array_layers: create_info.array_layers,
+            usage: create_info.usage,
+            name: Mutex::new(None),
             storage: ImageStorage::User { memory },
         })
     }
@@ -144,21 +246,98 @@ impl Image {
     pub(crate) fn from_swapchain_image(
         swapchain: Arc<Swapchain>,
         swapchain_image: vk::Image,
+        usage: ImageUsage,
     ) -> Arc<Self> {
+        let extent = swapchain.image_extent();
+
         Arc::new(Self {
             id: ImageId::new(),
             device: swapchain.device().clone(),
             image: DebugWrapper(swapchain_image),
+            format: swapchain.image_format(),
+            view_formats: Vec::new(),
+            extent: Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            usage,
+            name: Mutex::new(None),
             storage: ImageStorage::Swapchain { swapchain },
         })
     }
 
+    /// Returns the format of the image.
+    #[inline]
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// Returns the extent of the image.
+    #[inline]
+    pub fn extent(&self) -> Extent3D {
+        self.extent
+    }
+
+    /// Returns the number of mip levels of the image.
+    #[inline]
+    pub fn mip_levels(&self) -> u32 {
+        self.mip_levels
+    }
+
+    /// Returns the number of array layers of the image.
+    #[inline]
+    pub fn array_layers(&self) -> u32 {
+        self.array_layers
+    }
+
+    /// Returns the usage of the image.
+    #[inline]
+    pub fn usage(&self) -> ImageUsage {
+        self.usage
+    }
+
+    /// Attaches a debug name to the image, shown by validation messages,
+    /// graphics debuggers and its [`Debug`](std::fmt::Debug) output.
+    pub fn set_name(&self, name: &str) {
+        self.device.set_object_name(self, name);
+        *self.name.lock().unwrap() = Some(name.to_owned());
+    }
+
+    /// Returns the debug name of the image, if set.
+    pub fn name(&self) -> Option<String> {
+        self.name.lock().unwrap().clone()
+    }
+
+    /// Returns true if a view of the image can be created with the format.
+    pub fn supports_view_format(&self, format: ImageFormat) -> bool {
+        format == self.format
+            || (self.view_formats.contains(&format) && self.format.is_view_compatible(format))
+    }
+
     /// Returns the device associated with the image.
     pub fn device(&self) -> &Arc<Device> {
         &self.device
     }
 }
 
+impl std::fmt::Debug for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Image")
+            .field("handle", &self.image)
+            .field("name", &*self.name.lock().unwrap())
+            .field("format", &self.format)
+            .field("extent", &self.extent)
+            .field("mip_levels", &self.mip_levels)
+            .field("array_layers", &self.array_layers)
+            .field("usage", &self.usage)
+            .field("swapchain", &self.is_swapchain_image())
+            .finish()
+    }
+}
+
 impl VkHandle for Image {
     type Handle = vk::Image;
 
@@ -180,3 +359,40 @@ impl Drop for Image {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_info_builder_defaults() {
+        let extent = Extent3D {
+            width: 64,
+            height: 32,
+            depth: 1,
+        };
+        let info = ImageCreateInfo::new(ImageFormat::Rgba8Srgb, extent)
+            .usage(ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST)
+            .mips(4);
+
+        assert_eq!(info.format, ImageFormat::Rgba8Srgb);
+        assert_eq!(info.extent, extent);
+        assert_eq!(info.mip_levels, 4);
+        assert_eq!(info.usage, ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST);
+
+        // Unset fields keep their defaults.
+        assert_eq!(info.dimensions, ImageType::D2);
+        assert_eq!(info.array_layers, 1);
+        assert_eq!(info.flags, ImageCreateFlags::empty());
+        assert!(info.view_formats.is_empty());
+    }
+
+    #[test]
+    fn test_view_formats_enable_mutable_format() {
+        let info = ImageCreateInfo::new(ImageFormat::Rgba8Unorm, Extent3D::default())
+            .view_formats([ImageFormat::Rgba8Srgb]);
+
+        assert!(info.flags.contains(ImageCreateFlags::MUTABLE_FORMAT));
+        assert_eq!(info.view_formats, [ImageFormat::Rgba8Srgb]);
+    }
+}
@@ -42,7 +42,18 @@ pub struct ImageView {
 }
 
 impl ImageView {
+    /// Creates a new image view.
+    ///
+    /// The view format may differ from the image format only if the image was
+    /// created with it in its view formats, e.g. an sRGB view of a UNORM image.
     pub fn new(device: Arc<Device>, create_info: ImageViewCreateInfo) -> Arc<Self> {
+        assert!(
+            create_info.image.supports_view_format(create_info.format),
+            "image of format {:?} cannot be viewed as {:?}",
+            create_info.image.format(),
+            create_info.format,
+        );
+
         let vk_create_info = vk::ImageViewCreateInfo::default()
             .image(create_info.image.vk_handle())
             .view_type(create_info.view_type.to_vk())
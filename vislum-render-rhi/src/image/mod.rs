@@ -3,8 +3,8 @@ pub mod extent;
 pub mod image_view;
 pub mod image;
 
-pub use format::ImageFormat;
+pub use format::{FormatFeatures, ImageFormat};
 pub use extent::{Extent2D, Extent3D};
-pub use image::{Image, ImageCreateInfo, ImageUsage, ImageType};
+pub use image::{Image, ImageCreateFlags, ImageCreateInfo, ImageUsage, ImageType};
 pub use image_view::{ImageView, ImageViewCreateInfo, ImageViewType};
 
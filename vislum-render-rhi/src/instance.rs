@@ -12,6 +12,7 @@ impl_extensions! {
         khr_xlib_surface => ash::khr::xlib_surface::NAME,
         khr_xcb_surface => ash::khr::xcb_surface::NAME,
         khr_win32_surface => ash::khr::win32_surface::NAME,
+        ext_debug_utils => ash::ext::debug_utils::NAME,
     }
 }
 
@@ -24,11 +25,24 @@ impl Library {
         let entry = unsafe { ash::Entry::load() }.expect("failed to load vulkan library");
         Arc::new(Library { entry })
     }
+
+    /// Returns the instance extensions supported by the library.
+    pub fn supported_extensions(&self) -> InstanceExtensions {
+        let properties =
+            unsafe { self.entry.enumerate_instance_extension_properties(None) }.unwrap_or_default();
+
+        InstanceExtensions::from_iter(
+            properties
+                .iter()
+                .filter_map(|property| property.extension_name_as_c_str().ok()),
+        )
+    }
 }
 
 pub struct Instance {
     entry: Arc<Library>,
     instance: AshDebugWrapper<ash::Instance>,
+    enabled_extensions: InstanceExtensions,
     physical_devices: OnceCell<SmallVec<[Arc<PhysicalDevice>; 2]>>,
 }
 
@@ -51,6 +65,7 @@ impl Instance {
         Arc::new(Self {
             entry,
             instance: AshDebugWrapper(instance),
+            enabled_extensions: extensions,
             physical_devices: Default::default(),
         })
     }
@@ -74,6 +89,11 @@ impl Instance {
     pub fn library(&self) -> &Arc<Library> {
         &self.entry
     }
+
+    /// Returns the extensions the instance was created with.
+    pub fn enabled_extensions(&self) -> &InstanceExtensions {
+        &self.enabled_extensions
+    }
 }
 
 impl AshHandle for Instance {
@@ -5,16 +5,24 @@ pub mod command;
 pub mod device;
 pub mod image;
 pub mod sampler;
+pub mod shader;
 pub mod instance;
 pub mod memory;
+pub mod pipeline;
+pub mod query;
 pub mod queue;
 pub mod surface;
 pub mod swapchain;
 pub mod descriptor;
 pub mod sync;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
+mod error;
 mod macros;
 
+pub use error::Error;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct Version {
     pub major: u8,
@@ -15,8 +15,140 @@ pub enum MemoryLocation {
     GpuToCpu,
 }
 
+/// The memory allocated in a single [`MemoryLocation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LocationStats {
+    pub allocated_bytes: u64,
+    pub allocation_count: usize,
+}
+
+/// The memory allocated by a [`MemoryAllocator`].
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct AllocatorStats {
+    pub gpu_only: LocationStats,
+    pub cpu_to_gpu: LocationStats,
+    pub gpu_to_cpu: LocationStats,
+}
+
+impl AllocatorStats {
+    /// Returns the memory allocated in a location.
+    pub fn location(&self, location: MemoryLocation) -> &LocationStats {
+        match location {
+            MemoryLocation::GpuOnly => &self.gpu_only,
+            MemoryLocation::CpuToGpu => &self.cpu_to_gpu,
+            MemoryLocation::GpuToCpu => &self.gpu_to_cpu,
+        }
+    }
+
+    /// Returns the total number of bytes allocated.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.gpu_only.allocated_bytes
+            + self.cpu_to_gpu.allocated_bytes
+            + self.gpu_to_cpu.allocated_bytes
+    }
+
+    /// Returns the total number of active allocations.
+    pub fn allocation_count(&self) -> usize {
+        self.gpu_only.allocation_count
+            + self.cpu_to_gpu.allocation_count
+            + self.gpu_to_cpu.allocation_count
+    }
+
+    fn location_mut(&mut self, location: MemoryLocation) -> &mut LocationStats {
+        match location {
+            MemoryLocation::GpuOnly => &mut self.gpu_only,
+            MemoryLocation::CpuToGpu => &mut self.cpu_to_gpu,
+            MemoryLocation::GpuToCpu => &mut self.gpu_to_cpu,
+        }
+    }
+
+    fn record_allocation(&mut self, location: MemoryLocation, size: u64) {
+        let stats = self.location_mut(location);
+        stats.allocated_bytes += size;
+        stats.allocation_count += 1;
+    }
+
+    fn record_free(&mut self, location: MemoryLocation, size: u64) {
+        let stats = self.location_mut(location);
+        stats.allocated_bytes -= size;
+        stats.allocation_count -= 1;
+    }
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.50 MiB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.2} {}", UNITS[unit])
+}
+
+impl std::fmt::Debug for AllocatorStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {} allocations (",
+            format_bytes(self.allocated_bytes()),
+            self.allocation_count()
+        )?;
+
+        for (index, location) in [
+            MemoryLocation::GpuOnly,
+            MemoryLocation::CpuToGpu,
+            MemoryLocation::GpuToCpu,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let stats = self.location(location);
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{location:?}: {} in {}",
+                format_bytes(stats.allocated_bytes),
+                stats.allocation_count
+            )?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// The budget and usage of a memory heap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapBudget {
+    pub heap_index: u32,
+    /// The size of the heap, in bytes.
+    pub size: u64,
+    /// How much of the heap the process can use before allocations start
+    /// failing or degrading performance, in bytes.
+    ///
+    /// Without `VK_EXT_memory_budget` this is the size of the heap.
+    pub budget: u64,
+    /// How much of the heap the process currently uses, in bytes.
+    ///
+    /// `None` without `VK_EXT_memory_budget`.
+    pub usage: Option<u64>,
+    /// Whether the heap is device-local memory.
+    pub device_local: bool,
+}
+
 pub struct MemoryAllocator {
+    device: Arc<Device>,
     allocator: Mutex<gpu_allocator::vulkan::Allocator>,
+    stats: Mutex<AllocatorStats>,
 }
 
 impl MemoryAllocator {
@@ -37,10 +169,57 @@ impl MemoryAllocator {
         let allocator = gpu_allocator::vulkan::Allocator::new(&create_desc).unwrap();
 
         Arc::new(Self {
+            device,
             allocator: Mutex::new(allocator),
+            stats: Default::default(),
         })
     }
 
+    /// Returns the memory currently allocated.
+    pub fn statistics(&self) -> AllocatorStats {
+        *self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the budget and usage of every memory heap of the device.
+    ///
+    /// The usage is only reported when `VK_EXT_memory_budget` is enabled.
+    pub fn budget(&self) -> Vec<HeapBudget> {
+        let instance = self.device.instance().ash_handle();
+        let physical_device = self.device.physical_device().vk_handle();
+        let has_budget = self.device.enabled_extensions().ext_memory_budget;
+
+        let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut properties = vk::PhysicalDeviceMemoryProperties2::default();
+        if has_budget {
+            properties = properties.push_next(&mut budget_properties);
+        }
+
+        unsafe {
+            instance.get_physical_device_memory_properties2(physical_device, &mut properties);
+        }
+
+        let heaps = properties
+            .memory_properties
+            .memory_heaps_as_slice()
+            .to_vec();
+
+        heaps
+            .iter()
+            .enumerate()
+            .map(|(index, heap)| HeapBudget {
+                heap_index: index as u32,
+                size: heap.size,
+                budget: if has_budget {
+                    budget_properties.heap_budget[index]
+                } else {
+                    heap.size
+                },
+                usage: has_budget.then(|| budget_properties.heap_usage[index]),
+                device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+            })
+            .collect()
+    }
+
     pub fn allocate(
         self: &Arc<Self>,
         requirements: vk::MemoryRequirements,
@@ -65,9 +244,15 @@ impl MemoryAllocator {
             .allocate(&allocation_desc)
             .unwrap();
 
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record_allocation(location, allocation.size());
+
         MemoryAllocation {
             allocator: Arc::downgrade(&self),
             allocation: Some(allocation),
+            location,
         }
     }
 }
@@ -75,6 +260,7 @@ impl MemoryAllocator {
 pub struct MemoryAllocation {
     pub(crate) allocator: Weak<MemoryAllocator>,
     pub(crate) allocation: Option<gpu_allocator::vulkan::Allocation>,
+    location: MemoryLocation,
 }
 
 impl MemoryAllocation {
@@ -82,6 +268,11 @@ impl MemoryAllocation {
     pub fn destroy(&mut self) {
         if let Some(allocation) = self.allocation.take() {
             if let Some(allocator) = self.allocator.upgrade() {
+                allocator
+                    .stats
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .record_free(self.location, allocation.size());
                 allocator
                     .allocator
                     .lock()
@@ -120,3 +311,48 @@ impl Drop for MemoryAllocation {
         self.destroy();
     }
 }
+
+impl std::fmt::Debug for MemoryAllocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryAllocator")
+            .field("statistics", &self.statistics())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_track_allocations_per_location() {
+        let mut stats = AllocatorStats::default();
+        stats.record_allocation(MemoryLocation::GpuOnly, 4096);
+        stats.record_allocation(MemoryLocation::GpuOnly, 1024);
+        stats.record_allocation(MemoryLocation::CpuToGpu, 256);
+        stats.record_free(MemoryLocation::GpuOnly, 1024);
+
+        assert_eq!(stats.allocated_bytes(), 4352);
+        assert_eq!(stats.allocation_count(), 2);
+        assert_eq!(
+            *stats.location(MemoryLocation::GpuOnly),
+            LocationStats {
+                allocated_bytes: 4096,
+                allocation_count: 1,
+            }
+        );
+        assert_eq!(stats.location(MemoryLocation::GpuToCpu).allocation_count, 0);
+    }
+
+    #[test]
+    fn test_stats_debug_summary() {
+        let mut stats = AllocatorStats::default();
+        stats.record_allocation(MemoryLocation::GpuOnly, 3 * 1024 * 1024 / 2);
+        stats.record_allocation(MemoryLocation::CpuToGpu, 512);
+
+        assert_eq!(
+            format!("{stats:?}"),
+            "1.50 MiB in 2 allocations (GpuOnly: 1.50 MiB in 1, CpuToGpu: 512 B in 1, GpuToCpu: 0 B in 0)"
+        );
+    }
+}
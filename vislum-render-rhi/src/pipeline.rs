@@ -0,0 +1,672 @@
+use std::sync::Arc;
+
+use ash::vk;
+use smallvec::SmallVec;
+
+use crate::{
+    AshHandle, DebugWrapper, Error, VkHandle, descriptor::DescriptorSetLayout, device::Device,
+    image::ImageFormat, sampler::CompareOp, shader::ShaderStage, vk_enum,
+};
+
+pub struct PipelineLayoutCreateInfo {
+    pub set_layouts: Vec<Arc<DescriptorSetLayout>>,
+}
+
+pub struct PipelineLayout {
+    device: Arc<Device>,
+    set_layouts: Vec<Arc<DescriptorSetLayout>>,
+    layout: DebugWrapper<vk::PipelineLayout>,
+}
+
+impl PipelineLayout {
+    pub fn new(device: Arc<Device>, create_info: PipelineLayoutCreateInfo) -> Arc<Self> {
+        let vk_set_layouts = create_info
+            .set_layouts
+            .iter()
+            .map(|layout| layout.vk_handle())
+            .collect::<SmallVec<[vk::DescriptorSetLayout; 4]>>();
+
+        let vk_create_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&vk_set_layouts);
+
+        let layout = unsafe {
+            device
+                .ash_handle()
+                .create_pipeline_layout(&vk_create_info, None)
+                .unwrap()
+        };
+
+        Arc::new(Self {
+            device,
+            set_layouts: create_info.set_layouts,
+            layout: DebugWrapper(layout),
+        })
+    }
+
+    /// Returns the descriptor set layouts of the pipeline layout, by set index.
+    #[inline]
+    pub fn set_layouts(&self) -> &[Arc<DescriptorSetLayout>] {
+        &self.set_layouts
+    }
+}
+
+impl VkHandle for PipelineLayout {
+    type Handle = vk::PipelineLayout;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.layout.0
+    }
+}
+
+impl Drop for PipelineLayout {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .destroy_pipeline_layout(self.layout.0, None);
+        }
+    }
+}
+
+vk_enum! {
+    /// The format of a vertex attribute.
+    pub enum VertexFormat: vk::Format {
+        Float => R32_SFLOAT,
+        Float2 => R32G32_SFLOAT,
+        Float3 => R32G32B32_SFLOAT,
+        Float4 => R32G32B32A32_SFLOAT,
+        Uint4 => R32G32B32A32_UINT,
+        Unorm8x4 => R8G8B8A8_UNORM,
+    }
+}
+
+vk_enum! {
+    /// Whether a vertex buffer is advanced per vertex or per instance.
+    pub enum VertexInputRate: vk::VertexInputRate {
+        Vertex => VERTEX,
+        Instance => INSTANCE,
+    }
+}
+
+/// A single attribute within a vertex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    /// The shader input location of the attribute.
+    pub location: u32,
+    pub format: VertexFormat,
+    /// The offset of the attribute within the vertex, in bytes.
+    pub offset: u32,
+}
+
+/// Describes the layout of the vertices of a vertex buffer binding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VertexBufferLayout {
+    pub binding: u32,
+    /// The size of a single vertex, in bytes.
+    pub stride: u32,
+    pub input_rate: VertexInputRate,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+vk_enum! {
+    #[derive(Default)]
+    pub enum PrimitiveTopology: vk::PrimitiveTopology {
+        PointList => POINT_LIST,
+        LineList => LINE_LIST,
+        LineStrip => LINE_STRIP,
+        #[default]
+        TriangleList => TRIANGLE_LIST,
+        TriangleStrip => TRIANGLE_STRIP,
+    }
+}
+
+vk_enum! {
+    #[derive(Default)]
+    pub enum CullMode: vk::CullModeFlags {
+        #[default]
+        None => NONE,
+        Front => FRONT,
+        Back => BACK,
+        FrontAndBack => FRONT_AND_BACK,
+    }
+}
+
+vk_enum! {
+    #[derive(Default)]
+    pub enum FrontFace: vk::FrontFace {
+        #[default]
+        CounterClockwise => COUNTER_CLOCKWISE,
+        Clockwise => CLOCKWISE,
+    }
+}
+
+vk_enum! {
+    pub enum BlendFactor: vk::BlendFactor {
+        Zero => ZERO,
+        One => ONE,
+        SrcColor => SRC_COLOR,
+        OneMinusSrcColor => ONE_MINUS_SRC_COLOR,
+        DstColor => DST_COLOR,
+        OneMinusDstColor => ONE_MINUS_DST_COLOR,
+        SrcAlpha => SRC_ALPHA,
+        OneMinusSrcAlpha => ONE_MINUS_SRC_ALPHA,
+        DstAlpha => DST_ALPHA,
+        OneMinusDstAlpha => ONE_MINUS_DST_ALPHA,
+    }
+}
+
+vk_enum! {
+    pub enum BlendOp: vk::BlendOp {
+        Add => ADD,
+        Subtract => SUBTRACT,
+        ReverseSubtract => REVERSE_SUBTRACT,
+        Min => MIN,
+        Max => MAX,
+    }
+}
+
+/// How the color or alpha channels of a fragment are blended with the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendComponent {
+    pub src_factor: BlendFactor,
+    pub dst_factor: BlendFactor,
+    pub op: BlendOp,
+}
+
+impl BlendComponent {
+    /// Overwrites the target with the fragment.
+    pub const REPLACE: Self = Self {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::Zero,
+        op: BlendOp::Add,
+    };
+}
+
+/// The blending of a color attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendState {
+    pub color: BlendComponent,
+    pub alpha: BlendComponent,
+}
+
+impl BlendState {
+    /// Blends the fragment over the target by its alpha.
+    pub const ALPHA_BLENDING: Self = Self {
+        color: BlendComponent {
+            src_factor: BlendFactor::SrcAlpha,
+            dst_factor: BlendFactor::OneMinusSrcAlpha,
+            op: BlendOp::Add,
+        },
+        alpha: BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::OneMinusSrcAlpha,
+            op: BlendOp::Add,
+        },
+    };
+
+    /// Adds the fragment to the target.
+    pub const ADDITIVE: Self = Self {
+        color: BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::One,
+            op: BlendOp::Add,
+        },
+        alpha: BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::One,
+            op: BlendOp::Add,
+        },
+    };
+
+    fn to_vk(blend: Option<Self>) -> vk::PipelineColorBlendAttachmentState {
+        let state = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA);
+
+        match blend {
+            Some(blend) => state
+                .blend_enable(true)
+                .src_color_blend_factor(blend.color.src_factor.to_vk())
+                .dst_color_blend_factor(blend.color.dst_factor.to_vk())
+                .color_blend_op(blend.color.op.to_vk())
+                .src_alpha_blend_factor(blend.alpha.src_factor.to_vk())
+                .dst_alpha_blend_factor(blend.alpha.dst_factor.to_vk())
+                .alpha_blend_op(blend.alpha.op.to_vk()),
+            None => state.blend_enable(false),
+        }
+    }
+}
+
+/// A color attachment the pipeline renders into with dynamic rendering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorAttachmentState {
+    pub format: ImageFormat,
+    /// The blending of the attachment, or `None` to overwrite it.
+    pub blend: Option<BlendState>,
+}
+
+/// The depth attachment the pipeline tests fragments against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthAttachmentState {
+    pub format: ImageFormat,
+    /// The comparison a fragment's depth must pass against the stored depth.
+    pub compare_op: CompareOp,
+    /// Whether fragments passing the test write their depth.
+    pub write: bool,
+}
+
+impl DepthAttachmentState {
+    /// Keeps the nearest fragments, for opaque geometry.
+    pub const fn less(format: ImageFormat) -> Self {
+        Self {
+            format,
+            compare_op: CompareOp::Less,
+            write: true,
+        }
+    }
+}
+
+/// Builds a [`GraphicsPipeline`] for dynamic rendering.
+///
+/// The viewport and scissor are always dynamic and must be set before drawing.
+pub struct GraphicsPipelineBuilder {
+    layout: Arc<PipelineLayout>,
+    vertex_shader: ShaderStage,
+    fragment_shader: Option<ShaderStage>,
+    vertex_buffers: Vec<VertexBufferLayout>,
+    topology: PrimitiveTopology,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+    color_attachments: Vec<ColorAttachmentState>,
+    depth_attachment: Option<DepthAttachmentState>,
+}
+
+impl GraphicsPipelineBuilder {
+    pub fn new(layout: Arc<PipelineLayout>, vertex_shader: ShaderStage) -> Self {
+        Self {
+            layout,
+            vertex_shader,
+            fragment_shader: None,
+            vertex_buffers: Vec::new(),
+            topology: Default::default(),
+            cull_mode: Default::default(),
+            front_face: Default::default(),
+            color_attachments: Vec::new(),
+            depth_attachment: None,
+        }
+    }
+
+    pub fn fragment_shader(mut self, fragment_shader: ShaderStage) -> Self {
+        self.fragment_shader = Some(fragment_shader);
+        self
+    }
+
+    /// Adds a vertex buffer binding.
+    pub fn vertex_buffer(mut self, layout: VertexBufferLayout) -> Self {
+        self.vertex_buffers.push(layout);
+        self
+    }
+
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn front_face(mut self, front_face: FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    /// Adds a color attachment, in the order they are bound when rendering.
+    pub fn color_attachment(mut self, format: ImageFormat, blend: Option<BlendState>) -> Self {
+        self.color_attachments
+            .push(ColorAttachmentState { format, blend });
+        self
+    }
+
+    /// Sets the depth attachment, enabling depth testing.
+    pub fn depth_attachment(mut self, depth_attachment: DepthAttachmentState) -> Self {
+        assert!(
+            depth_attachment.format.is_depth(),
+            "the depth attachment format {:?} has no depth",
+            depth_attachment.format
+        );
+
+        self.depth_attachment = Some(depth_attachment);
+        self
+    }
+
+    pub fn build(self, device: Arc<Device>) -> Result<Arc<GraphicsPipeline>, Error> {
+        let stages = std::iter::once((vk::ShaderStageFlags::VERTEX, &self.vertex_shader))
+            .chain(
+                self.fragment_shader
+                    .as_ref()
+                    .map(|stage| (vk::ShaderStageFlags::FRAGMENT, stage)),
+            )
+            .map(|(stage, shader)| {
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(stage)
+                    .module(shader.module.vk_handle())
+                    .name(&shader.entry_point)
+            })
+            .collect::<SmallVec<[_; 2]>>();
+
+        let bindings = self
+            .vertex_buffers
+            .iter()
+            .map(|layout| {
+                vk::VertexInputBindingDescription::default()
+                    .binding(layout.binding)
+                    .stride(layout.stride)
+                    .input_rate(layout.input_rate.to_vk())
+            })
+            .collect::<SmallVec<[_; 2]>>();
+
+        let attributes = self
+            .vertex_buffers
+            .iter()
+            .flat_map(|layout| {
+                layout.attributes.iter().map(|attribute| {
+                    vk::VertexInputAttributeDescription::default()
+                        .binding(layout.binding)
+                        .location(attribute.location)
+                        .format(attribute.format.to_vk())
+                        .offset(attribute.offset)
+                })
+            })
+            .collect::<SmallVec<[_; 8]>>();
+
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let input_assembly =
+            vk::PipelineInputAssemblyStateCreateInfo::default().topology(self.topology.to_vk());
+
+        // The viewport and scissor are dynamic, only their count is fixed.
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+        let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(self.cull_mode.to_vk())
+            .front_face(self.front_face.to_vk())
+            .line_width(1.0);
+
+        let multisample = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+        let blend_attachments = self
+            .color_attachments
+            .iter()
+            .map(|attachment| BlendState::to_vk(attachment.blend))
+            .collect::<SmallVec<[_; 4]>>();
+        let color_blend =
+            vk::PipelineColorBlendStateCreateInfo::default().attachments(&blend_attachments);
+
+        let depth_stencil = match self.depth_attachment {
+            Some(depth) => vk::PipelineDepthStencilStateCreateInfo::default()
+                .depth_test_enable(true)
+                .depth_write_enable(depth.write)
+                .depth_compare_op(depth.compare_op.to_vk()),
+            None => vk::PipelineDepthStencilStateCreateInfo::default(),
+        };
+
+        let color_formats = self
+            .color_attachments
+            .iter()
+            .map(|attachment| attachment.format.to_vk())
+            .collect::<SmallVec<[_; 4]>>();
+        let mut rendering =
+            vk::PipelineRenderingCreateInfo::default().color_attachment_formats(&color_formats);
+        if let Some(depth) = self.depth_attachment {
+            rendering = rendering.depth_attachment_format(depth.format.to_vk());
+            if depth.format.has_stencil() {
+                rendering = rendering.stencil_attachment_format(depth.format.to_vk());
+            }
+        }
+
+        let create_info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blend)
+            .dynamic_state(&dynamic_state)
+            .layout(self.layout.vk_handle())
+            .push_next(&mut rendering);
+
+        let pipeline = unsafe {
+            device
+                .ash_handle()
+                .create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, error)| error)?[0]
+        };
+
+        Ok(Arc::new(GraphicsPipeline {
+            device,
+            layout: self.layout,
+            pipeline: DebugWrapper(pipeline),
+        }))
+    }
+}
+
+pub struct GraphicsPipeline {
+    device: Arc<Device>,
+    layout: Arc<PipelineLayout>,
+    pipeline: DebugWrapper<vk::Pipeline>,
+}
+
+impl GraphicsPipeline {
+    /// Returns a builder for a pipeline with the given layout and vertex shader.
+    #[inline]
+    pub fn builder(
+        layout: Arc<PipelineLayout>,
+        vertex_shader: ShaderStage,
+    ) -> GraphicsPipelineBuilder {
+        GraphicsPipelineBuilder::new(layout, vertex_shader)
+    }
+
+    #[inline]
+    pub fn layout(&self) -> &Arc<PipelineLayout> {
+        &self.layout
+    }
+}
+
+impl VkHandle for GraphicsPipeline {
+    type Handle = vk::Pipeline;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.pipeline.0
+    }
+}
+
+impl Drop for GraphicsPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .destroy_pipeline(self.pipeline.0, None);
+        }
+    }
+}
+
+pub struct ComputePipeline {
+    device: Arc<Device>,
+    layout: Arc<PipelineLayout>,
+    pipeline: DebugWrapper<vk::Pipeline>,
+}
+
+impl ComputePipeline {
+    pub fn new(
+        device: Arc<Device>,
+        layout: Arc<PipelineLayout>,
+        shader: ShaderStage,
+    ) -> Result<Arc<Self>, Error> {
+        let stage = vk::PipelineShaderStageCreateInfo::default()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(shader.module.vk_handle())
+            .name(&shader.entry_point);
+
+        let create_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout.vk_handle());
+
+        let pipeline = unsafe {
+            device
+                .ash_handle()
+                .create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+                .map_err(|(_, error)| error)?[0]
+        };
+
+        Ok(Arc::new(Self {
+            device,
+            layout,
+            pipeline: DebugWrapper(pipeline),
+        }))
+    }
+
+    #[inline]
+    pub fn layout(&self) -> &Arc<PipelineLayout> {
+        &self.layout
+    }
+}
+
+impl VkHandle for ComputePipeline {
+    type Handle = vk::Pipeline;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.pipeline.0
+    }
+}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .destroy_pipeline(self.pipeline.0, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::{Buffer, BufferCreateInfo, BufferUsage},
+        command::{AccessFlags2, BufferMemoryBarrier2, PipelineBindPoint, PipelineStageFlags2},
+        descriptor::{
+            DescriptorInfo, DescriptorPool, DescriptorPoolCreateInfo, DescriptorPoolSizes,
+            DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType,
+            DescriptorWrites,
+        },
+        memory::MemoryLocation,
+        shader::ShaderStageFlags,
+        test_util::TestDevice,
+    };
+
+    const FILL_SHADER: &str = r#"
+        RWStructuredBuffer<uint> output : register(u0);
+
+        [numthreads(64, 1, 1)]
+        void main(uint3 id : SV_DispatchThreadID) {
+            output[id.x] = id.x * 3 + 7;
+        }
+    "#;
+
+    #[test]
+    #[ignore = "requires a Vulkan driver and the DXC library"]
+    fn test_dispatch_writes_storage_buffer() {
+        let test_device = TestDevice::new();
+        let device = test_device.device.clone();
+        let shader = test_device.compile_shader(FILL_SHADER, "cs_6_0");
+
+        const COUNT: u32 = 256;
+        let buffer = Buffer::new(
+            device.clone(),
+            test_device.allocator.clone(),
+            BufferCreateInfo::new(COUNT as u64 * 4).usage(BufferUsage::STORAGE_BUFFER),
+            MemoryLocation::GpuToCpu,
+        );
+
+        let set_layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: vec![DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: DescriptorType::StorageBuffer,
+                    count: 1,
+                    stages: ShaderStageFlags::COMPUTE,
+                }],
+            },
+        );
+        let pool = DescriptorPool::new(
+            device.clone(),
+            DescriptorPoolCreateInfo {
+                sizes: DescriptorPoolSizes {
+                    storage_buffer: 1,
+                    ..Default::default()
+                },
+                max_sets: 1,
+            },
+        );
+        let set = pool.allocate(&set_layout).unwrap();
+        DescriptorWrites::new()
+            .write_info(
+                set.vk_handle(),
+                0,
+                DescriptorType::StorageBuffer,
+                buffer.descriptor_info(),
+            )
+            .update(&device);
+
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineLayoutCreateInfo {
+                set_layouts: vec![set_layout],
+            },
+        );
+        let pipeline = ComputePipeline::new(device.clone(), layout, shader).unwrap();
+
+        test_device.submit_and_wait(|encoder| {
+            encoder.bind_pipeline(PipelineBindPoint::Compute, pipeline.vk_handle());
+            encoder.bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                pipeline.layout().vk_handle(),
+                0,
+                [set.vk_handle()],
+                [],
+            );
+            encoder.dispatch(COUNT / 64, 1, 1);
+            encoder.pipeline_barrier(
+                [],
+                [BufferMemoryBarrier2 {
+                    buffer: buffer.clone(),
+                    src_stage_mask: PipelineStageFlags2::COMPUTE_SHADER,
+                    src_access_mask: AccessFlags2::SHADER_WRITE,
+                    dst_stage_mask: PipelineStageFlags2::HOST,
+                    dst_access_mask: AccessFlags2::HOST_READ,
+                    offset: 0,
+                    size: vk::WHOLE_SIZE,
+                }],
+                [],
+            );
+        });
+
+        let values = unsafe { buffer.mapped_slice_mut::<u32>() };
+        let expected = (0..COUNT).map(|index| index * 3 + 7).collect::<Vec<_>>();
+        assert_eq!(values, expected);
+    }
+}
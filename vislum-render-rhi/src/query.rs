@@ -0,0 +1,139 @@
+use std::{ops::Range, sync::Arc};
+
+use ash::vk;
+
+use crate::{AshHandle, DebugWrapper, Error, VkHandle, device::Device};
+
+/// A pool of timestamp queries, used to measure the GPU time spent between
+/// commands.
+///
+/// Queries must be reset with [`CommandEncoder::reset_query_pool`] before
+/// being written to.
+///
+/// [`CommandEncoder::reset_query_pool`]: crate::command::CommandEncoder::reset_query_pool
+pub struct QueryPool {
+    device: Arc<Device>,
+    pool: DebugWrapper<vk::QueryPool>,
+    query_count: u32,
+    timestamp_valid_bits: u32,
+    timestamp_period: f32,
+}
+
+impl QueryPool {
+    /// Creates a pool of timestamp queries to be written by queues of the
+    /// given family.
+    ///
+    /// Returns [`Error::TimestampsNotSupported`] if the queue family can't
+    /// write timestamps.
+    pub fn timestamps(
+        device: Arc<Device>,
+        queue_family_index: u32,
+        query_count: u32,
+    ) -> Result<Arc<Self>, Error> {
+        let timestamp_valid_bits = device
+            .physical_device()
+            .capabilities()
+            .nth(queue_family_index as usize)
+            .map_or(0, |family| family.timestamp_valid_bits);
+
+        if timestamp_valid_bits == 0 {
+            return Err(Error::TimestampsNotSupported(queue_family_index));
+        }
+
+        let timestamp_period = device.physical_device().properties().timestamp_period;
+
+        let create_info = vk::QueryPoolCreateInfo::default()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(query_count);
+
+        let pool = unsafe { device.ash_handle().create_query_pool(&create_info, None)? };
+
+        Ok(Arc::new(Self {
+            device,
+            pool: DebugWrapper(pool),
+            query_count,
+            timestamp_valid_bits,
+            timestamp_period,
+        }))
+    }
+
+    /// Returns the number of queries in the pool.
+    #[inline]
+    pub fn query_count(&self) -> u32 {
+        self.query_count
+    }
+
+    /// Reads back the timestamps of a range of queries, in nanoseconds.
+    ///
+    /// Blocks until all the queries in the range have been written.
+    pub fn results(&self, range: Range<u32>) -> Vec<u64> {
+        assert!(
+            range.end <= self.query_count,
+            "the query range {:?} is out of bounds of the pool with {} queries",
+            range,
+            self.query_count
+        );
+
+        let mut ticks = vec![0u64; range.len()];
+        if ticks.is_empty() {
+            return ticks;
+        }
+
+        unsafe {
+            self.device
+                .ash_handle()
+                .get_query_pool_results(
+                    self.pool.0,
+                    range.start,
+                    &mut ticks,
+                    vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+                )
+                .unwrap();
+        }
+
+        ticks
+            .into_iter()
+            .map(|ticks| {
+                ticks_to_nanoseconds(ticks, self.timestamp_valid_bits, self.timestamp_period)
+            })
+            .collect()
+    }
+}
+
+impl VkHandle for QueryPool {
+    type Handle = vk::QueryPool;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.pool.0
+    }
+}
+
+impl Drop for QueryPool {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .destroy_query_pool(self.pool.0, None);
+        }
+    }
+}
+
+/// Converts a raw timestamp to nanoseconds, discarding the bits the queue
+/// doesn't write.
+fn ticks_to_nanoseconds(ticks: u64, valid_bits: u32, period: f32) -> u64 {
+    let ticks = ticks & u64::MAX.checked_shr(64 - valid_bits).unwrap_or(0);
+    (ticks as f64 * period as f64) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticks_to_nanoseconds() {
+        assert_eq!(ticks_to_nanoseconds(1000, 64, 1.0), 1000);
+        assert_eq!(ticks_to_nanoseconds(1000, 64, 52.08), 52080);
+        // Only the valid bits are meaningful.
+        assert_eq!(ticks_to_nanoseconds(0xFFFF_0000_0000_0010, 36, 2.0), 32);
+    }
+}
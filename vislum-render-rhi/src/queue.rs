@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use ash::vk;
 
@@ -9,9 +9,16 @@ use crate::{
     sync::{Fence, Semaphore},
 };
 
+/// A callback waiting for the submit guarded by its fence to complete.
+struct PendingCallback {
+    fence: Arc<Fence>,
+    callback: Box<dyn FnOnce() + Send>,
+}
+
 pub struct Queue {
     device: Arc<Device>,
     queue: DebugWrapper<vk::Queue>,
+    pending_callbacks: Mutex<Vec<PendingCallback>>,
 }
 
 impl Queue {
@@ -19,6 +26,7 @@ impl Queue {
         Self {
             device,
             queue: DebugWrapper(queue),
+            pending_callbacks: Mutex::new(Vec::new()),
         }
     }
 
@@ -60,6 +68,129 @@ impl Queue {
                 .unwrap();
         }
     }
+
+    /// Submits a batch without command buffers, which waits on and signals
+    /// semaphores and fences in submission order.
+    pub fn signal(
+        &self,
+        wait_semaphores: Vec<Arc<Semaphore>>,
+        signal_semaphores: Vec<Arc<Semaphore>>,
+        fence: Option<Arc<Fence>>,
+    ) {
+        let wait_semaphore_handles: Vec<_> =
+            wait_semaphores.iter().map(|s| s.vk_handle()).collect();
+        let wait_dst_stage_masks: Vec<_> =
+            vec![vk::PipelineStageFlags::ALL_COMMANDS; wait_semaphore_handles.len()];
+
+        let signal_semaphore_handles: Vec<_> =
+            signal_semaphores.iter().map(|s| s.vk_handle()).collect();
+
+        let submit_info = vk::SubmitInfo::default()
+            .wait_semaphores(&wait_semaphore_handles)
+            .wait_dst_stage_mask(&wait_dst_stage_masks)
+            .signal_semaphores(&signal_semaphore_handles);
+
+        let fence_handle = fence.map(|f| f.vk_handle()).unwrap_or(vk::Fence::null());
+
+        unsafe {
+            self.device
+                .ash_handle()
+                .queue_submit(self.queue.0, &[submit_info], fence_handle)
+                .unwrap();
+        }
+    }
+
+    /// Submits a command buffer to this queue, invoking `callback` once the
+    /// GPU has finished executing it.
+    ///
+    /// Callbacks are not invoked asynchronously; they run during the next
+    /// [`Queue::poll_callbacks`] or [`Queue::wait_callbacks`] after the submit
+    /// completes. This is useful to free staging resources or to notify that
+    /// a frame has been rendered.
+    pub fn submit_with_callback(
+        &self,
+        command_buffer: impl Into<RawCommandBuffer>,
+        wait_semaphores: Vec<Arc<Semaphore>>,
+        signal_semaphores: Vec<Arc<Semaphore>>,
+        callback: impl FnOnce() + Send + 'static,
+    ) {
+        let fence = Fence::unsignaled(self.device.clone());
+
+        self.submit(
+            command_buffer,
+            wait_semaphores,
+            signal_semaphores,
+            Some(fence.clone()),
+        );
+
+        self.pending_callbacks
+            .lock()
+            .unwrap()
+            .push(PendingCallback {
+                fence,
+                callback: Box::new(callback),
+            });
+    }
+
+    /// Invokes `callback` once all the work previously submitted to this queue
+    /// completes.
+    pub fn submit_callback(&self, callback: impl FnOnce() + Send + 'static) {
+        let fence = Fence::unsignaled(self.device.clone());
+
+        // An empty submit signals its fence once all prior work on the queue is done.
+        unsafe {
+            self.device
+                .ash_handle()
+                .queue_submit(self.queue.0, &[], fence.vk_handle())
+                .unwrap();
+        }
+
+        self.pending_callbacks
+            .lock()
+            .unwrap()
+            .push(PendingCallback {
+                fence,
+                callback: Box::new(callback),
+            });
+    }
+
+    /// Invokes the callbacks of all the completed submits.
+    ///
+    /// Returns the number of callbacks invoked.
+    pub fn poll_callbacks(&self) -> usize {
+        let completed = {
+            let mut pending = self.pending_callbacks.lock().unwrap();
+            let (completed, remaining) = std::mem::take(&mut *pending)
+                .into_iter()
+                .partition::<Vec<_>, _>(|pending| pending.fence.status());
+
+            *pending = remaining;
+            completed
+        };
+
+        // Callbacks run outside the lock, so they may submit more work.
+        let count = completed.len();
+        for pending in completed {
+            (pending.callback)();
+        }
+
+        count
+    }
+
+    /// Blocks until all the pending submits complete, invoking their callbacks.
+    pub fn wait_callbacks(&self) {
+        let pending = std::mem::take(&mut *self.pending_callbacks.lock().unwrap());
+
+        for pending in pending {
+            pending.fence.wait(u64::MAX);
+            (pending.callback)();
+        }
+    }
+
+    /// Returns the number of callbacks waiting for their submit to complete.
+    pub fn pending_callbacks(&self) -> usize {
+        self.pending_callbacks.lock().unwrap().len()
+    }
 }
 
 impl VkHandle for Queue {
@@ -69,3 +200,68 @@ impl VkHandle for Queue {
         self.queue.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        command::{CommandBufferLevel, CommandBufferUsageFlags, CommandEncoder, CommandPool},
+        test_util::TestDevice,
+    };
+
+    fn empty_command_buffer(pool: &CommandPool) -> CommandEncoder {
+        let mut encoder = CommandEncoder::new(pool.allocate(CommandBufferLevel::PRIMARY));
+        encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        encoder.end();
+        encoder
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_callback_runs_once_polled_after_completion() {
+        let TestDevice { device, queue, .. } = TestDevice::new();
+        let pool = CommandPool::new(device, 0);
+
+        let done = Arc::new(AtomicBool::new(false));
+        queue.submit_with_callback(empty_command_buffer(&pool), vec![], vec![], {
+            let done = done.clone();
+            move || done.store(true, Ordering::Relaxed)
+        });
+        assert_eq!(queue.pending_callbacks(), 1);
+
+        while !done.load(Ordering::Relaxed) {
+            queue.poll_callbacks();
+            std::thread::yield_now();
+        }
+        assert_eq!(queue.pending_callbacks(), 0);
+        assert_eq!(queue.poll_callbacks(), 0);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_wait_callbacks_runs_all_pending_callbacks() {
+        let TestDevice { device, queue, .. } = TestDevice::new();
+        let pool = CommandPool::new(device, 0);
+
+        let count = Arc::new(AtomicUsize::new(0));
+        for _ in 0..3 {
+            let count = count.clone();
+            queue.submit_with_callback(empty_command_buffer(&pool), vec![], vec![], move || {
+                count.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        queue.submit_callback({
+            let count = count.clone();
+            move || {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        assert_eq!(queue.pending_callbacks(), 4);
+
+        queue.wait_callbacks();
+        assert_eq!(count.load(Ordering::Relaxed), 4);
+        assert_eq!(queue.pending_callbacks(), 0);
+    }
+}
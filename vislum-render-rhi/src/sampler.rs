@@ -2,7 +2,21 @@ use std::sync::Arc;
 
 use ash::vk;
 
-use crate::{AshHandle, DebugWrapper, VkHandle, device::Device};
+use crate::{AshHandle, DebugWrapper, VkHandle, device::Device, vk_enum};
+
+vk_enum! {
+    /// The comparison applied to a sampled value against the reference value.
+    pub enum CompareOp: vk::CompareOp {
+        Never => NEVER,
+        Less => LESS,
+        Equal => EQUAL,
+        LessOrEqual => LESS_OR_EQUAL,
+        Greater => GREATER,
+        NotEqual => NOT_EQUAL,
+        GreaterOrEqual => GREATER_OR_EQUAL,
+        Always => ALWAYS,
+    }
+}
 
 pub struct SamplerCreateInfo {
     pub mag_filter: vk::Filter,
@@ -10,6 +24,12 @@ pub struct SamplerCreateInfo {
     pub address_mode_u: vk::SamplerAddressMode,
     pub address_mode_v: vk::SamplerAddressMode,
     pub address_mode_w: vk::SamplerAddressMode,
+    /// How samples are blended between mip levels.
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    /// The comparison to perform when sampling, e.g. for shadow maps.
+    ///
+    /// Comparison samplers must be used with depth textures.
+    pub compare: Option<CompareOp>,
 }
 
 pub struct Sampler {
@@ -24,7 +44,17 @@ impl Sampler {
             .min_filter(create_info.min_filter)
             .address_mode_u(create_info.address_mode_u)
             .address_mode_v(create_info.address_mode_v)
-            .address_mode_w(create_info.address_mode_w);
+            .address_mode_w(create_info.address_mode_w)
+            .mipmap_mode(create_info.mipmap_mode)
+            // Sample all the mip levels of the image.
+            .max_lod(vk::LOD_CLAMP_NONE)
+            .compare_enable(create_info.compare.is_some())
+            .compare_op(
+                create_info
+                    .compare
+                    .map(CompareOp::to_vk)
+                    .unwrap_or(vk::CompareOp::NEVER),
+            );
 
         let sampler = unsafe {
             device.ash_handle().create_sampler(&vk_create_info, None).unwrap()
@@ -0,0 +1,91 @@
+use std::{ffi::CString, sync::Arc};
+
+use ash::vk;
+
+use crate::{AshHandle, DebugWrapper, VkHandle, device::Device, vk_enum_flags};
+
+vk_enum_flags! {
+    /// The shader stages a resource is visible to.
+    pub struct ShaderStageFlags: vk::ShaderStageFlags {
+        VERTEX => VERTEX,
+        FRAGMENT => FRAGMENT,
+        COMPUTE => COMPUTE,
+        ALL_GRAPHICS => ALL_GRAPHICS,
+        ALL => ALL,
+    }
+}
+
+/// A compiled SPIR-V shader module.
+pub struct ShaderModule {
+    device: Arc<Device>,
+    module: DebugWrapper<vk::ShaderModule>,
+}
+
+impl ShaderModule {
+    /// Creates a shader module from SPIR-V bytecode.
+    ///
+    /// Panics if the length of the code is not a multiple of four bytes.
+    pub fn new(device: Arc<Device>, spirv: &[u8]) -> Arc<Self> {
+        assert!(
+            spirv.len().is_multiple_of(4),
+            "SPIR-V code must be a multiple of four bytes long"
+        );
+
+        // The code may not be aligned to four bytes, so copy it into words.
+        let code = spirv
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect::<Vec<u32>>();
+
+        let create_info = vk::ShaderModuleCreateInfo::default().code(&code);
+
+        let module = unsafe {
+            device
+                .ash_handle()
+                .create_shader_module(&create_info, None)
+                .unwrap()
+        };
+
+        Arc::new(Self {
+            device,
+            module: DebugWrapper(module),
+        })
+    }
+}
+
+impl VkHandle for ShaderModule {
+    type Handle = vk::ShaderModule;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.module.0
+    }
+}
+
+impl Drop for ShaderModule {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .ash_handle()
+                .destroy_shader_module(self.module.0, None);
+        }
+    }
+}
+
+/// A shader module and the entry point a pipeline stage runs.
+#[derive(Clone)]
+pub struct ShaderStage {
+    pub module: Arc<ShaderModule>,
+    pub entry_point: CString,
+}
+
+impl ShaderStage {
+    /// Creates a new shader stage.
+    ///
+    /// Panics if the entry point contains a nul byte.
+    pub fn new(module: Arc<ShaderModule>, entry_point: &str) -> Self {
+        Self {
+            module,
+            entry_point: CString::new(entry_point).expect("entry point contains a nul byte"),
+        }
+    }
+}
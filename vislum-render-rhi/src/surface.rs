@@ -5,7 +5,111 @@ use winit::raw_window_handle::HasDisplayHandle;
 use winit::raw_window_handle::HasWindowHandle;
 use winit::window::Window;
 
-use crate::{AshHandle, DebugWrapper, VkHandle, device::PhysicalDevice, instance::Instance};
+use crate::{
+    AshHandle, DebugWrapper, VkHandle,
+    device::PhysicalDevice,
+    image::{Extent2D, ImageFormat, ImageUsage},
+    instance::Instance,
+    swapchain::PresentMode,
+    vk_enum, vk_enum_flags,
+};
+
+vk_enum_flags! {
+    pub struct SurfaceTransformFlags: vk::SurfaceTransformFlagsKHR {
+        IDENTITY => IDENTITY,
+        ROTATE_90 => ROTATE_90,
+        ROTATE_180 => ROTATE_180,
+        ROTATE_270 => ROTATE_270,
+        HORIZONTAL_MIRROR => HORIZONTAL_MIRROR,
+        HORIZONTAL_MIRROR_ROTATE_90 => HORIZONTAL_MIRROR_ROTATE_90,
+        HORIZONTAL_MIRROR_ROTATE_180 => HORIZONTAL_MIRROR_ROTATE_180,
+        HORIZONTAL_MIRROR_ROTATE_270 => HORIZONTAL_MIRROR_ROTATE_270,
+        INHERIT => INHERIT,
+    }
+}
+
+vk_enum! {
+    pub enum ColorSpace: vk::ColorSpaceKHR {
+        SrgbNonlinear => SRGB_NONLINEAR,
+        DisplayP3Nonlinear => DISPLAY_P3_NONLINEAR_EXT,
+        ExtendedSrgbLinear => EXTENDED_SRGB_LINEAR_EXT,
+        Hdr10St2084 => HDR10_ST2084_EXT,
+    }
+}
+
+/// A format and color space pair the surface can present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SurfaceFormat {
+    pub format: ImageFormat,
+    pub color_space: ColorSpace,
+}
+
+/// The capabilities of a surface on a given physical device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceCapabilities {
+    pub min_image_count: u32,
+    /// The maximum number of images, or `None` if there is no limit.
+    pub max_image_count: Option<u32>,
+    /// The current size of the surface, or `None` if the size is determined
+    /// by the extent of the swapchain targeting it.
+    ///
+    /// Some platforms, like Wayland, report `0xFFFFFFFF` as the current
+    /// extent. Callers should then pick the size of the window, clamped to
+    /// the min and max image extents. See [`SurfaceCapabilities::choose_extent`].
+    pub current_extent: Option<Extent2D>,
+    pub min_image_extent: Extent2D,
+    pub max_image_extent: Extent2D,
+    pub current_transform: SurfaceTransformFlags,
+    pub supported_transforms: SurfaceTransformFlags,
+    pub supported_usage: ImageUsage,
+}
+
+impl SurfaceCapabilities {
+    /// Converts from the Vulkan surface capabilities.
+    pub fn from_vk(capabilities: vk::SurfaceCapabilitiesKHR) -> Self {
+        Self {
+            min_image_count: capabilities.min_image_count,
+            max_image_count: (capabilities.max_image_count > 0)
+                .then_some(capabilities.max_image_count),
+            current_extent: (capabilities.current_extent.width != u32::MAX)
+                .then(|| Extent2D::from_vk(capabilities.current_extent)),
+            min_image_extent: Extent2D::from_vk(capabilities.min_image_extent),
+            max_image_extent: Extent2D::from_vk(capabilities.max_image_extent),
+            current_transform: SurfaceTransformFlags::from_vk(capabilities.current_transform),
+            supported_transforms: SurfaceTransformFlags::from_vk(capabilities.supported_transforms),
+            supported_usage: ImageUsage::from_vk(capabilities.supported_usage_flags),
+        }
+    }
+
+    /// Chooses the extent of a swapchain targeting the surface.
+    ///
+    /// Returns the current extent of the surface if it has one, as the
+    /// swapchain must match it. Otherwise returns the desired extent, usually
+    /// the size of the window, clamped to the supported image extents.
+    pub fn choose_extent(&self, desired: Extent2D) -> Extent2D {
+        if let Some(current_extent) = self.current_extent {
+            return current_extent;
+        }
+
+        Extent2D::new(
+            desired
+                .width
+                .clamp(self.min_image_extent.width, self.max_image_extent.width),
+            desired
+                .height
+                .clamp(self.min_image_extent.height, self.max_image_extent.height),
+        )
+    }
+
+    /// Clamps the requested number of images to the supported range.
+    pub fn clamp_image_count(&self, requested: u32) -> u32 {
+        let count = requested.max(self.min_image_count);
+        match self.max_image_count {
+            Some(max_image_count) => count.min(max_image_count),
+            None => count,
+        }
+    }
+}
 
 pub struct Surface {
     instance: Arc<Instance>,
@@ -76,6 +180,45 @@ impl Surface {
         .unwrap()
     }
 
+    /// Returns the capabilities of the surface on a physical device.
+    pub fn capabilities(&self, physical_device: &PhysicalDevice) -> SurfaceCapabilities {
+        SurfaceCapabilities::from_vk(self.get_capabilities(physical_device))
+    }
+
+    /// Returns the formats the surface can present on a physical device.
+    ///
+    /// Formats without an [`ImageFormat`] or [`ColorSpace`] counterpart are
+    /// skipped.
+    pub fn supported_formats(&self, physical_device: &PhysicalDevice) -> Vec<SurfaceFormat> {
+        self.get_formats(physical_device)
+            .into_iter()
+            .filter_map(|format| {
+                Some(SurfaceFormat {
+                    format: ImageFormat::from_vk(format.format)?,
+                    color_space: ColorSpace::from_vk(format.color_space)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the present modes the surface supports on a physical device.
+    pub fn supported_present_modes(&self, physical_device: &PhysicalDevice) -> Vec<PresentMode> {
+        self.get_present_modes(physical_device)
+            .into_iter()
+            .filter_map(PresentMode::from_vk)
+            .collect()
+    }
+
+    /// Returns the indices of the queue families of a physical device which
+    /// can present to this surface.
+    pub fn supported_present_queue_families(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> impl Iterator<Item = u32> {
+        (0..physical_device.capabilities().len() as u32)
+            .filter(move |&index| self.get_physical_device_surface_support(physical_device, index))
+    }
+
     /// Checks if a queue family supports presentation to this surface.
     pub fn get_physical_device_surface_support(
         &self,
@@ -112,3 +255,66 @@ impl Drop for Surface {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(current_extent: vk::Extent2D) -> SurfaceCapabilities {
+        SurfaceCapabilities::from_vk(
+            vk::SurfaceCapabilitiesKHR::default()
+                .min_image_count(2)
+                .max_image_count(0)
+                .current_extent(current_extent)
+                .min_image_extent(vk::Extent2D {
+                    width: 1,
+                    height: 1,
+                })
+                .max_image_extent(vk::Extent2D {
+                    width: 4096,
+                    height: 4096,
+                }),
+        )
+    }
+
+    #[test]
+    fn test_fixed_extent_is_used_as_is() {
+        let capabilities = capabilities(vk::Extent2D {
+            width: 1280,
+            height: 720,
+        });
+
+        assert_eq!(capabilities.current_extent, Some(Extent2D::new(1280, 720)));
+        assert_eq!(
+            capabilities.choose_extent(Extent2D::new(800, 600)),
+            Extent2D::new(1280, 720)
+        );
+    }
+
+    #[test]
+    fn test_undefined_extent_clamps_the_desired_one() {
+        let capabilities = capabilities(vk::Extent2D {
+            width: u32::MAX,
+            height: u32::MAX,
+        });
+
+        assert_eq!(capabilities.current_extent, None);
+        assert_eq!(
+            capabilities.choose_extent(Extent2D::new(800, 600)),
+            Extent2D::new(800, 600)
+        );
+        assert_eq!(
+            capabilities.choose_extent(Extent2D::new(0, 8192)),
+            Extent2D::new(1, 4096)
+        );
+    }
+
+    #[test]
+    fn test_unbounded_image_count() {
+        let capabilities = capabilities(vk::Extent2D::default());
+
+        assert_eq!(capabilities.max_image_count, None);
+        assert_eq!(capabilities.clamp_image_count(1), 2);
+        assert_eq!(capabilities.clamp_image_count(8), 8);
+    }
+}
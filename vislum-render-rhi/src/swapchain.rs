@@ -1,31 +1,152 @@
-use std::sync::Arc;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
 
 use ash::vk;
 
-use crate::{AshHandle, DebugWrapper, VkHandle, device::Device, surface::Surface, image::{ImageFormat, Extent2D, Image, ImageUsage}, vk_enum};
+use crate::{AshHandle, DebugWrapper, Error, VkHandle, device::Device, surface::{Surface, SurfaceCapabilities}, sync::Fence, image::{ImageFormat, Extent2D, Image, ImageUsage}, vk_enum};
+use crate::command::{
+    AccessFlags2, ImageLayout, ImageMemoryBarrier2, PipelineStageFlags2,
+    QueueFamilyOwnershipTransfer,
+};
 
 vk_enum! {
     #[derive(Default)]
+    #[allow(non_camel_case_types)]
     pub enum PresentMode: vk::PresentModeKHR {
         #[default]
         FIFO => FIFO,
+        FIFO_RELAXED => FIFO_RELAXED,
         MAILBOX => MAILBOX,
         IMMEDIATE => IMMEDIATE,
     }
 }
 
+/// The barriers transferring a rendered swapchain image from the graphics
+/// queue family to the present queue family.
+///
+/// The `release` barrier must be recorded on the graphics queue and the
+/// `acquire` barrier on the present queue, before presenting. Both also
+/// transition the image to [`ImageLayout::PresentSrcKhr`].
+pub struct PresentOwnershipTransfer {
+    pub release: ImageMemoryBarrier2,
+    pub acquire: ImageMemoryBarrier2,
+}
+
+impl PresentOwnershipTransfer {
+    /// Creates the release/acquire barrier pair for a swapchain image.
+    ///
+    /// Returns `None` if the graphics and present queue families are the same,
+    /// in which case a regular layout transition is enough.
+    pub fn new(
+        image: Arc<Image>,
+        graphics_queue_family_index: u32,
+        present_queue_family_index: u32,
+    ) -> Option<Self> {
+        let transfer = QueueFamilyOwnershipTransfer::new(
+            graphics_queue_family_index,
+            present_queue_family_index,
+        )?;
+
+        let release = ImageMemoryBarrier2 {
+            image: image.clone(),
+            src_stage_mask: PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: AccessFlags2::COLOR_ATTACHMENT_WRITE,
+            dst_stage_mask: PipelineStageFlags2::NONE,
+            dst_access_mask: AccessFlags2::NONE,
+            old_layout: ImageLayout::ColorAttachmentOptimal,
+            new_layout: ImageLayout::PresentSrcKhr,
+            ownership_transfer: Some(transfer),
+            subresource_range: None,
+        };
+
+        let acquire = ImageMemoryBarrier2 {
+            image,
+            src_stage_mask: PipelineStageFlags2::NONE,
+            src_access_mask: AccessFlags2::NONE,
+            dst_stage_mask: PipelineStageFlags2::BOTTOM_OF_PIPE,
+            dst_access_mask: AccessFlags2::NONE,
+            old_layout: ImageLayout::ColorAttachmentOptimal,
+            new_layout: ImageLayout::PresentSrcKhr,
+            ownership_transfer: Some(transfer),
+            subresource_range: None,
+        };
+
+        Some(Self { release, acquire })
+    }
+}
+
+/// Bounds the number of frames queued for presentation.
+///
+/// Each entry is the completion marker of a frame submitted for presentation,
+/// oldest first.
+struct FrameLatency<T> {
+    max_frames: u32,
+    in_flight: VecDeque<T>,
+}
+
+impl<T> FrameLatency<T> {
+    fn new(max_frames: u32) -> Self {
+        Self {
+            max_frames,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    /// Registers a frame submitted for presentation.
+    fn push(&mut self, frame: T) {
+        self.in_flight.push_back(frame);
+    }
+
+    /// Retires the oldest frames until another one can be queued without
+    /// exceeding the latency bound. `wait` blocks until a frame completes.
+    fn make_room(&mut self, mut wait: impl FnMut(T)) {
+        while self.in_flight.len() >= self.max_frames as usize {
+            let Some(frame) = self.in_flight.pop_front() else {
+                break;
+            };
+
+            wait(frame);
+        }
+    }
+}
+
+impl FrameLatency<Arc<Fence>> {
+    /// Blocks until the fences of the oldest frames signal, so another frame
+    /// can be queued.
+    fn wait_for_room(&mut self) {
+        self.make_room(|fence| {
+            fence.wait(u64::MAX);
+        });
+    }
+}
+
 pub struct SwapchainCreateInfo {
     /// Minimum number of images in the swapchain.
     /// Defaults to 2 if not specified.
     pub min_image_count: Option<u32>,
-    /// Desired present mode. If not specified, FIFO will be used.
+    /// Desired present mode, falling back to FIFO if the surface doesn't
+    /// support it. If not specified, MAILBOX is preferred.
     pub present_mode: Option<PresentMode>,
     /// Desired image usage flags. If not specified, COLOR_ATTACHMENT will be used.
     pub image_usage: Option<ImageUsage>,
+    /// Desired image extent, used when the surface lets the swapchain pick
+    /// its size. If not specified, 800x600 will be used.
+    pub image_extent: Option<Extent2D>,
     /// Previous swapchain to replace (for resize operations).
     pub old_swapchain: Option<Arc<Swapchain>>,
 }
 
+/// Converts the result of a swapchain operation, surfacing out of date
+/// swapchains as [`Error::SwapchainOutOfDate`].
+fn swapchain_result<T>(result: Result<T, vk::Result>) -> Result<T, Error> {
+    result.map_err(|error| match error {
+        vk::Result::ERROR_OUT_OF_DATE_KHR => Error::SwapchainOutOfDate,
+        error => Error::Vulkan(error),
+    })
+}
+
 pub struct Swapchain {
     device: Arc<Device>,
     swapchain: DebugWrapper<vk::SwapchainKHR>,
@@ -33,12 +154,19 @@ pub struct Swapchain {
     surface: Arc<Surface>,
     image_format: ImageFormat,
     image_extent: Extent2D,
+    min_image_count: Option<u32>,
+    present_mode: Option<PresentMode>,
+    image_usage: Option<ImageUsage>,
+    latency: Mutex<FrameLatency<Arc<Fence>>>,
 }
 
 impl Swapchain {
     /// Creates a new swapchain from a surface.
     /// Most parameters are automatically derived from the surface capabilities.
     /// Returns the swapchain and its images.
+    ///
+    /// Panics if the resulting extent is empty, which happens while the window
+    /// is minimized. Callers should wait until it is restored instead.
     pub fn new(
         device: Arc<Device>,
         surface: Arc<Surface>,
@@ -49,7 +177,7 @@ impl Swapchain {
         // Get surface capabilities and formats
         let capabilities = surface.get_capabilities(physical_device);
         let formats = surface.get_formats(physical_device);
-        let present_modes = surface.get_present_modes(physical_device);
+        let present_modes = surface.supported_present_modes(physical_device);
 
         // Choose format - prefer B8G8R8A8_UNORM with SRGB_NONLINEAR, otherwise first available
         let surface_format = formats
@@ -65,34 +193,29 @@ impl Swapchain {
             .unwrap();
 
         // Choose image count
-        let mut image_count = create_info
-            .min_image_count
-            .unwrap_or(capabilities.min_image_count.max(2));
-        if capabilities.max_image_count > 0 {
-            image_count = image_count.min(capabilities.max_image_count);
-        }
+        let surface_capabilities = SurfaceCapabilities::from_vk(capabilities);
+        let image_count = surface_capabilities.clamp_image_count(
+            create_info
+                .min_image_count
+                .unwrap_or(capabilities.min_image_count.max(2)),
+        );
 
         // Choose extent
-        let image_extent_vk = if capabilities.current_extent.width != u32::MAX {
-            capabilities.current_extent
-        } else {
-            vk::Extent2D {
-                width: capabilities.min_image_extent.width.max(800).min(capabilities.max_image_extent.width),
-                height: capabilities.min_image_extent.height.max(600).min(capabilities.max_image_extent.height),
-            }
+        let image_extent = surface_capabilities
+            .choose_extent(create_info.image_extent.unwrap_or(Extent2D::new(800, 600)));
+        let image_extent_vk = image_extent.to_vk();
+        assert!(
+            !image_extent.is_empty(),
+            "cannot create a swapchain with an empty extent"
+        );
+
+        // Choose present mode
+        let preferred_present_modes = match create_info.present_mode {
+            Some(present_mode) => [present_mode],
+            None => [PresentMode::MAILBOX],
         };
-        let image_extent = Extent2D::from_vk(image_extent_vk);
-
-        // Choose present mode - prefer FIFO (guaranteed), or Mailbox (vsync), or first available
-        let present_mode_vk = create_info.present_mode.map(|p| p.to_vk()).unwrap_or_else(|| {
-            if present_modes.contains(&vk::PresentModeKHR::MAILBOX) {
-                vk::PresentModeKHR::MAILBOX
-            } else if present_modes.contains(&vk::PresentModeKHR::FIFO) {
-                vk::PresentModeKHR::FIFO
-            } else {
-                present_modes[0]
-            }
-        });
+        let present_mode_vk =
+            Self::choose_present_mode(&preferred_present_modes, &present_modes).to_vk();
 
         // Choose image usage - prefer COLOR_ATTACHMENT, but respect capabilities
         let requested_usage = create_info
@@ -163,25 +286,68 @@ impl Swapchain {
             surface,
             image_format,
             image_extent,
+            min_image_count: create_info.min_image_count,
+            present_mode: create_info.present_mode,
+            image_usage: create_info.image_usage,
+            latency: Mutex::new(FrameLatency::new(images_vk.len() as u32)),
         });
 
         // Create Image wrappers for swapchain images
         let images: Vec<Arc<Image>> = images_vk.iter()
-            .map(|&image_vk| Image::from_swapchain_image(swapchain_arc.clone(), image_vk))
+            .map(|&image_vk| Image::from_swapchain_image(swapchain_arc.clone(), image_vk, ImageUsage::from_vk(image_usage_vk)))
             .collect();
 
         (swapchain_arc, images)
     }
 
+    /// Returns the first of the preferred present modes which is supported,
+    /// in order of preference.
+    ///
+    /// Falls back to FIFO, which every surface supports, if none of them are.
+    /// For example, low latency applications prefer `[MAILBOX]`, while power
+    /// constrained ones prefer `[FIFO_RELAXED]`.
+    pub fn choose_present_mode(
+        preferred: &[PresentMode],
+        supported: &[PresentMode],
+    ) -> PresentMode {
+        preferred
+            .iter()
+            .find(|mode| supported.contains(mode))
+            .copied()
+            .unwrap_or(PresentMode::FIFO)
+    }
+
+    /// Recreates the swapchain with a new extent, e.g. after the window was
+    /// resized, keeping the rest of its configuration.
+    ///
+    /// The old swapchain is retired, but its images must no longer be in use
+    /// by the device before it is dropped.
+    pub fn recreate(self: &Arc<Self>, new_extent: Extent2D) -> (Arc<Self>, Vec<Arc<Image>>) {
+        Self::new(
+            self.device.clone(),
+            self.surface.clone(),
+            SwapchainCreateInfo {
+                min_image_count: self.min_image_count,
+                present_mode: self.present_mode,
+                image_usage: self.image_usage,
+                image_extent: Some(new_extent),
+                old_swapchain: Some(self.clone()),
+            },
+        )
+    }
+
     /// Acquires the next image from the swapchain.
+    ///
+    /// Returns the image index and whether the swapchain is suboptimal, or
+    /// [`Error::SwapchainOutOfDate`] if it must be recreated.
     pub fn acquire_next_image(
         &self,
         timeout: u64,
         semaphore: Option<&crate::sync::Semaphore>,
         fence: Option<&crate::sync::Fence>,
-    ) -> (u32, bool) {
+    ) -> Result<(u32, bool), Error> {
         use crate::VkHandle;
-        unsafe {
+        swapchain_result(unsafe {
             self.swapchain_loader
                 .acquire_next_image(
                     self.swapchain.0,
@@ -189,16 +355,19 @@ impl Swapchain {
                     semaphore.map(|s| s.vk_handle()).unwrap_or(vk::Semaphore::null()),
                     fence.map(|f| f.vk_handle()).unwrap_or(vk::Fence::null()),
                 )
-        }.unwrap()
+        })
     }
 
     /// Presents an image to the surface.
+    ///
+    /// Returns whether the swapchain is suboptimal, or
+    /// [`Error::SwapchainOutOfDate`] if it must be recreated.
     pub fn present(
         &self,
         queue: &crate::queue::Queue,
         image_index: u32,
         wait_semaphores: &[&crate::sync::Semaphore],
-    ) -> bool {
+    ) -> Result<bool, Error> {
         use crate::VkHandle;
         let swapchain_handle = self.vk_handle();
         let queue_handle = queue.vk_handle();
@@ -210,12 +379,46 @@ impl Swapchain {
             .image_indices(&image_indices)
             .wait_semaphores(&semaphore_handles);
         
-        unsafe {
+        swapchain_result(unsafe {
             self.swapchain_loader
                 .queue_present(queue_handle, &present_info)
-        }.unwrap()
+        })
+    }
+
+    /// Sets the maximum number of frames which can be queued for presentation.
+    ///
+    /// Lower values reduce input lag, since the CPU waits in
+    /// [`Swapchain::wait_for_present_ready`] and samples input closer to the
+    /// moment the frame is shown. The cost is throughput: with a latency of 1
+    /// the CPU and GPU no longer overlap work across frames. Defaults to the
+    /// number of swapchain images.
+    pub fn set_max_frame_latency(&self, max_frames: u32) {
+        assert!(max_frames > 0, "the maximum frame latency must be at least 1");
+
+        self.latency.lock().unwrap().max_frames = max_frames;
     }
 
+    /// Returns the maximum number of frames which can be queued for presentation.
+    pub fn max_frame_latency(&self) -> u32 {
+        self.latency.lock().unwrap().max_frames
+    }
+
+    /// Registers the fence signaled by the submit rendering a frame which is
+    /// about to be presented.
+    ///
+    /// Only frames registered here count towards the frame latency.
+    pub fn track_frame(&self, fence: Arc<Fence>) {
+        self.latency.lock().unwrap().push(fence);
+    }
+
+    /// Blocks until queueing another frame does not exceed the maximum frame
+    /// latency. Call it before acquiring the next image and sampling input.
+    ///
+    /// `VK_KHR_present_wait` is not used; instead this waits on the fences of
+    /// the tracked frames, which bounds the queue depth the same way under FIFO.
+    pub fn wait_for_present_ready(&self) {
+        self.latency.lock().unwrap().wait_for_room();
+    }
 
     /// Gets the image format.
     pub fn image_format(&self) -> ImageFormat {
@@ -254,3 +457,110 @@ impl Drop for Swapchain {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestDevice;
+
+    #[test]
+    fn test_frame_latency_bounds_in_flight_frames() {
+        let mut latency = FrameLatency::new(2);
+        let mut retired = Vec::new();
+
+        for frame in 0..5 {
+            latency.make_room(|frame| retired.push(frame));
+            assert!(latency.in_flight.len() < 2);
+
+            latency.push(frame);
+            assert!(latency.in_flight.len() <= 2);
+        }
+
+        assert_eq!(retired, [0, 1, 2]);
+        assert_eq!(latency.in_flight, [3, 4]);
+    }
+
+    #[test]
+    fn test_choose_present_mode_falls_back_to_fifo() {
+        let supported = [PresentMode::FIFO, PresentMode::IMMEDIATE];
+
+        assert_eq!(
+            Swapchain::choose_present_mode(&[PresentMode::MAILBOX], &supported),
+            PresentMode::FIFO
+        );
+        assert_eq!(
+            Swapchain::choose_present_mode(
+                &[PresentMode::MAILBOX, PresentMode::IMMEDIATE],
+                &supported
+            ),
+            PresentMode::IMMEDIATE
+        );
+        assert_eq!(
+            Swapchain::choose_present_mode(&[], &supported),
+            PresentMode::FIFO
+        );
+    }
+
+    #[test]
+    fn test_frame_latency_of_one_waits_for_every_frame() {
+        let mut latency = FrameLatency::new(1);
+        latency.push(0);
+
+        let mut retired = Vec::new();
+        latency.make_room(|frame| retired.push(frame));
+
+        assert_eq!(retired, [0]);
+        assert!(latency.in_flight.is_empty());
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_frame_latency_waits_for_frame_fences() {
+        let TestDevice { device, queue, .. } = TestDevice::new();
+        let mut latency = FrameLatency::new(2);
+
+        let fences = (0..4)
+            .map(|_| {
+                latency.wait_for_room();
+                assert!(latency.in_flight.len() < 2);
+
+                let fence = Fence::unsignaled(device.clone());
+                queue.signal(vec![], vec![], Some(fence.clone()));
+                latency.push(fence.clone());
+                fence
+            })
+            .collect::<Vec<_>>();
+
+        // Only the last two frames may still be queued.
+        assert_eq!(latency.in_flight.len(), 2);
+        assert!(fences[..2].iter().all(|fence| fence.status()));
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_present_ownership_transfer_between_families() {
+        let test_device = TestDevice::new();
+        let target = test_device.create_render_target(ImageFormat::Bgra8Unorm, Extent2D::new(1, 1));
+
+        assert!(PresentOwnershipTransfer::new(target.image.clone(), 0, 0).is_none());
+
+        let transfer = PresentOwnershipTransfer::new(target.image.clone(), 0, 2).unwrap();
+        let release = transfer.release.to_vk();
+        let acquire = transfer.acquire.to_vk();
+        for barrier in [release, acquire] {
+            assert_eq!(barrier.src_queue_family_index, 0);
+            assert_eq!(barrier.dst_queue_family_index, 2);
+            assert_eq!(
+                barrier.old_layout,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+            );
+            assert_eq!(barrier.new_layout, vk::ImageLayout::PRESENT_SRC_KHR);
+        }
+
+        // The release makes the rendering available, the acquire waits for nothing else.
+        assert_eq!(
+            release.src_access_mask,
+            vk::AccessFlags2::COLOR_ATTACHMENT_WRITE
+        );
+        assert_eq!(acquire.src_stage_mask, vk::PipelineStageFlags2::NONE);
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use ash::vk;
 
-use crate::{AshHandle, DebugWrapper, device::Device};
+use crate::{AshHandle, DebugWrapper, Error, device::Device};
 
 pub struct Fence {
     device: Arc<Device>,
@@ -123,3 +123,124 @@ impl Drop for Semaphore {
     }
 }
 
+/// A timeline semaphore, holding a monotonically increasing counter.
+///
+/// Unlike binary semaphores, a single timeline can track the completion of
+/// many submissions, each signaling a greater value.
+pub struct TimelineSemaphore {
+    device: Arc<Device>,
+    semaphore: DebugWrapper<vk::Semaphore>,
+}
+
+impl TimelineSemaphore {
+    /// Creates a new timeline semaphore with the counter at `initial_value`.
+    ///
+    /// Requires the `timeline_semaphore` device feature.
+    pub fn new(device: Arc<Device>, initial_value: u64) -> Result<Arc<Self>, Error> {
+        if !device.enabled_features().timeline_semaphore {
+            return Err(Error::MissingFeature("timeline_semaphore"));
+        }
+
+        let mut type_create_info = vk::SemaphoreTypeCreateInfo::default()
+            .semaphore_type(vk::SemaphoreType::TIMELINE)
+            .initial_value(initial_value);
+        let create_info = vk::SemaphoreCreateInfo::default().push_next(&mut type_create_info);
+
+        let semaphore = unsafe { device.ash_handle().create_semaphore(&create_info, None) }?;
+
+        Ok(Arc::new(Self {
+            device,
+            semaphore: DebugWrapper(semaphore),
+        }))
+    }
+
+    /// Signals the counter to `value` from the host.
+    ///
+    /// The value must be greater than the current value of the counter.
+    pub fn signal(&self, value: u64) {
+        let signal_info = vk::SemaphoreSignalInfo::default()
+            .semaphore(self.semaphore.0)
+            .value(value);
+
+        unsafe { self.device.ash_handle().signal_semaphore(&signal_info) }.unwrap();
+    }
+
+    /// Waits for the counter to reach at least `value`.
+    /// Returns true if the value was reached, false if the wait timed out.
+    pub fn wait(&self, value: u64, timeout: u64) -> bool {
+        let semaphores = [self.semaphore.0];
+        let values = [value];
+        let wait_info = vk::SemaphoreWaitInfo::default()
+            .semaphores(&semaphores)
+            .values(&values);
+
+        match unsafe { self.device.ash_handle().wait_semaphores(&wait_info, timeout) } {
+            Ok(()) => true,
+            Err(vk::Result::TIMEOUT) => false,
+            Err(e) => panic!("wait_semaphores failed: {:?}", e),
+        }
+    }
+
+    /// Returns the current value of the counter.
+    pub fn value(&self) -> u64 {
+        unsafe {
+            self.device
+                .ash_handle()
+                .get_semaphore_counter_value(self.semaphore.0)
+        }
+        .unwrap()
+    }
+}
+
+impl crate::VkHandle for TimelineSemaphore {
+    type Handle = vk::Semaphore;
+
+    fn vk_handle(&self) -> Self::Handle {
+        self.semaphore.0
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.ash_handle().destroy_semaphore(self.semaphore.0, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{device::DeviceFeatures, test_util::TestDevice};
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_timeline_signal_is_observed_by_value_and_wait() {
+        let TestDevice { device, .. } = TestDevice::with_features(DeviceFeatures {
+            synchronization2: true,
+            timeline_semaphore: true,
+            ..Default::default()
+        });
+
+        let timeline = TimelineSemaphore::new(device, 1).unwrap();
+        assert_eq!(timeline.value(), 1);
+        assert!(timeline.wait(1, 0));
+        assert!(!timeline.wait(5, 0));
+
+        timeline.signal(5);
+        assert_eq!(timeline.value(), 5);
+        assert!(timeline.wait(3, 0));
+        assert!(timeline.wait(5, u64::MAX));
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_timeline_requires_feature() {
+        let TestDevice { device, .. } = TestDevice::new();
+
+        assert!(matches!(
+            TimelineSemaphore::new(device, 0),
+            Err(Error::MissingFeature("timeline_semaphore"))
+        ));
+    }
+}
@@ -0,0 +1,326 @@
+//! Helpers shared by the tests running on a Vulkan device.
+//!
+//! These tests need a Vulkan driver, so they're marked `#[ignore]`; run them
+//! with `cargo test -- --ignored` on a machine with a GPU.
+
+use std::sync::Arc;
+
+use ash::vk;
+use vislum_dxc::{DxcCompileOptions, DxcCompiler, DxcIncludeHandler, DxcLoader};
+
+use crate::{
+    AshHandle, Version, VkHandle,
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command::{
+        AccessFlags2, BufferImageCopy, CommandBufferLevel, CommandBufferUsageFlags, CommandEncoder,
+        CommandPool, ImageAspectFlags, ImageLayout, ImageMemoryBarrier2, ImageSubresourceLayers,
+        PipelineStageFlags2, Rect2D, Viewport,
+    },
+    descriptor::DescriptorSetLayout,
+    device::{Device, DeviceCreateInfo, DeviceExtensions, DeviceFeatures, QueueFlags},
+    image::{
+        Extent2D, Extent3D, Image, ImageCreateInfo, ImageFormat, ImageUsage, ImageView,
+        ImageViewCreateInfo, ImageViewType,
+    },
+    instance::{Instance, InstanceExtensions, Library},
+    memory::{MemoryAllocator, MemoryLocation},
+    pipeline::{GraphicsPipeline, PipelineLayout, PipelineLayoutCreateInfo},
+    queue::Queue,
+    shader::{ShaderModule, ShaderStage},
+    sync::Fence,
+};
+
+/// A vertex shader drawing a triangle covering the whole viewport from 3
+/// vertices without a vertex buffer.
+///
+/// It outputs the texture coordinates of the viewport at location 0, with
+/// `(0, 0)` in the top left corner.
+pub const FULL_SCREEN_VERTEX_SHADER: &str = r#"
+    struct Output {
+        float4 position : SV_Position;
+        [[vk::location(0)]] float2 uv : TEXCOORD0;
+    };
+
+    Output main(uint id : SV_VertexID) {
+        Output output;
+        output.uv = float2((id << 1) & 2, id & 2);
+        output.position = float4(output.uv * 2.0 - 1.0, 0.0, 1.0);
+        return output;
+    }
+"#;
+
+/// A headless device, with its first queue and an allocator.
+pub struct TestDevice {
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
+    pub allocator: Arc<MemoryAllocator>,
+}
+
+impl TestDevice {
+    /// Creates a device with `synchronization2` and `dynamic_rendering`
+    /// enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no Vulkan driver, or no device with a graphics and
+    /// compute queue.
+    pub fn new() -> Self {
+        Self::with_features(DeviceFeatures {
+            synchronization2: true,
+            dynamic_rendering: true,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a device with the given features enabled.
+    ///
+    /// # Panics
+    ///
+    /// See [`TestDevice::new`].
+    pub fn with_features(features: DeviceFeatures) -> Self {
+        unsafe { ash::Entry::load() }.expect("the test requires a Vulkan driver");
+
+        let instance = Instance::new(Library::new(), InstanceExtensions::default());
+        // Devices are always created with queue family 0.
+        let physical_device = instance
+            .enumerate_physical_devices()
+            .find(|physical_device| {
+                physical_device.capabilities().next().is_some_and(|family| {
+                    family
+                        .queue_flags
+                        .contains(QueueFlags::GRAPHICS | QueueFlags::COMPUTE)
+                })
+            })
+            .expect("the test requires a device with a graphics and compute queue");
+
+        let device = Device::new(
+            instance,
+            DeviceCreateInfo {
+                api_version: Version::V1_3,
+                physical_device,
+                extensions: DeviceExtensions::default(),
+                features,
+                additional_queue_families: Vec::new(),
+            },
+        );
+
+        let queue = unsafe { device.ash_handle().get_device_queue(0, 0) };
+        let queue = Arc::new(Queue::new(device.clone(), queue));
+        let allocator = MemoryAllocator::new(device.clone());
+
+        Self {
+            device,
+            queue,
+            allocator,
+        }
+    }
+
+    /// Records commands with `record`, submits them and waits for them to
+    /// complete.
+    pub fn submit_and_wait(&self, record: impl FnOnce(&mut CommandEncoder)) {
+        let pool = CommandPool::new(self.device.clone(), 0);
+        let mut encoder = CommandEncoder::new(pool.allocate(CommandBufferLevel::PRIMARY));
+        encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        record(&mut encoder);
+        encoder.end();
+
+        let fence = Fence::unsignaled(self.device.clone());
+        self.queue
+            .submit(encoder, vec![], vec![], Some(fence.clone()));
+        assert!(fence.wait(u64::MAX));
+    }
+
+    /// Creates a 2D color image to render into and read back.
+    pub fn create_render_target(&self, format: ImageFormat, extent: Extent2D) -> RenderTarget {
+        let image = Image::new(
+            self.device.clone(),
+            self.allocator.clone(),
+            ImageCreateInfo::new(
+                format,
+                Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+            )
+            .usage(ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC),
+            MemoryLocation::GpuOnly,
+        );
+        let view = ImageView::new(
+            self.device.clone(),
+            ImageViewCreateInfo {
+                image: image.clone(),
+                view_type: ImageViewType::D2,
+                format,
+                components: vk::ComponentMapping::default(),
+                subresource_range: vk::ImageSubresourceRange::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .level_count(1)
+                    .layer_count(1),
+            },
+        );
+
+        RenderTarget {
+            image,
+            view,
+            extent,
+        }
+    }
+
+    /// Reads back the first mip level of a color image with 4 byte texels,
+    /// currently in `layout`.
+    pub fn read_image(&self, image: &Arc<Image>, layout: ImageLayout) -> Vec<[u8; 4]> {
+        let extent = image.extent();
+        let readback = Buffer::new(
+            self.device.clone(),
+            self.allocator.clone(),
+            BufferCreateInfo::new(u64::from(extent.width * extent.height * extent.depth) * 4)
+                .usage(BufferUsage::TRANSFER_DST),
+            MemoryLocation::GpuToCpu,
+        );
+
+        self.submit_and_wait(|encoder| {
+            encoder.pipeline_barrier(
+                [],
+                [],
+                [ImageMemoryBarrier2 {
+                    image: image.clone(),
+                    src_stage_mask: PipelineStageFlags2::ALL_COMMANDS,
+                    src_access_mask: AccessFlags2::MEMORY_WRITE,
+                    dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                    dst_access_mask: AccessFlags2::TRANSFER_READ,
+                    old_layout: layout,
+                    new_layout: ImageLayout::TransferSrcOptimal,
+                    ownership_transfer: None,
+                    subresource_range: None,
+                }],
+            );
+            encoder.copy_image_to_buffer(
+                image.clone(),
+                ImageLayout::TransferSrcOptimal,
+                readback.clone(),
+                [BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: ImageSubresourceLayers {
+                        aspect_mask: ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: [0; 3],
+                    image_extent: extent,
+                }],
+            );
+        });
+
+        bytemuck::cast_slice(&readback.map()).to_vec()
+    }
+
+    /// Creates a pipeline drawing a full screen triangle with `fragment_shader`
+    /// into a single color attachment of `format`.
+    ///
+    /// Draw it with `encoder.draw(3, 1, 0, 0)`. The fragment shader receives
+    /// the texture coordinates of [`FULL_SCREEN_VERTEX_SHADER`].
+    pub fn create_full_screen_pipeline(
+        &self,
+        fragment_shader: &str,
+        set_layouts: Vec<Arc<DescriptorSetLayout>>,
+        format: ImageFormat,
+    ) -> Arc<GraphicsPipeline> {
+        let layout = PipelineLayout::new(
+            self.device.clone(),
+            PipelineLayoutCreateInfo { set_layouts },
+        );
+
+        GraphicsPipeline::builder(
+            layout,
+            self.compile_shader(FULL_SCREEN_VERTEX_SHADER, "vs_6_0"),
+        )
+        .fragment_shader(self.compile_shader(fragment_shader, "ps_6_0"))
+        .color_attachment(format, None)
+        .build(self.device.clone())
+        .unwrap()
+    }
+
+    /// Compiles an HLSL shader with a `main` entry point.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the DXC library isn't installed, or the shader doesn't
+    /// compile.
+    pub fn compile_shader(&self, source: &str, profile: &str) -> ShaderStage {
+        let loader = DxcLoader::new().expect("the test requires the DXC library");
+        let spirv = DxcCompiler::new(loader)
+            .unwrap()
+            .compile(
+                source,
+                &DxcCompileOptions::new(profile, "main"),
+                &NoIncludes,
+            )
+            .unwrap();
+
+        ShaderStage::new(ShaderModule::new(self.device.clone(), &spirv), "main")
+    }
+}
+
+/// A color image rendered into by a test.
+pub struct RenderTarget {
+    pub image: Arc<Image>,
+    pub view: Arc<ImageView>,
+    pub extent: Extent2D,
+}
+
+impl RenderTarget {
+    /// Transitions the image to [`ImageLayout::ColorAttachmentOptimal`] and
+    /// begins rendering into all of it, cleared to transparent black.
+    pub fn begin_rendering(&self, encoder: &mut CommandEncoder) {
+        encoder.transition_image(
+            self.image.clone(),
+            ImageLayout::ColorAttachmentOptimal,
+            PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+            AccessFlags2::COLOR_ATTACHMENT_WRITE,
+        );
+
+        let color_attachments = [vk::RenderingAttachmentInfo::default()
+            .image_view(self.view.vk_handle())
+            .image_layout(ImageLayout::ColorAttachmentOptimal.to_vk())
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        encoder.begin_rendering(
+            &vk::RenderingInfo::default()
+                .color_attachments(&color_attachments)
+                .render_area(vk::Rect2D::default().extent(self.extent.to_vk()))
+                .layer_count(1),
+        );
+
+        encoder.set_viewport(
+            0,
+            [Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: self.extent.width as f32,
+                height: self.extent.height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            }],
+        );
+        encoder.set_scissor(0, [Rect2D::new([0, 0], self.extent)]);
+    }
+}
+
+impl Default for TestDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An include handler for shaders without includes.
+pub struct NoIncludes;
+
+impl DxcIncludeHandler for NoIncludes {
+    fn load_source(&self, _filename: &str) -> Option<String> {
+        None
+    }
+}
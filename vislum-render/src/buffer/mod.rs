@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+
+use vislum_render_rhi::command::{
+    AccessFlags2, CommandEncoder, MemoryBarrier2, PipelineStageFlags2,
+};
+
+pub mod uniform;
+
+pub use uniform::Uniform;
+
+/// Returns the shader stages uniforms may be read from.
+fn uniform_read_stages() -> PipelineStageFlags2 {
+    PipelineStageFlags2::VERTEX_SHADER
+        | PipelineStageFlags2::FRAGMENT_SHADER
+        | PipelineStageFlags2::COMPUTE_SHADER
+}
+
+/// A buffer with contents waiting to be copied to the device.
+pub(crate) trait PendingUpload {
+    /// Records the write of the pending contents into the device buffer.
+    ///
+    /// The contents must be stored in the command buffer, as previous frames
+    /// may still be executing when the next one is recorded.
+    ///
+    /// Returns false if there was nothing left to upload.
+    fn record(&self, encoder: &mut CommandEncoder) -> bool;
+}
+
+/// Collects the buffers modified since the last frame, to upload them all at
+/// once at the start of the next frame.
+#[derive(Default)]
+pub struct PendingUploadsManager {
+    pending: Mutex<Vec<Arc<dyn PendingUpload>>>,
+}
+
+impl PendingUploadsManager {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Schedules a buffer to be uploaded on the next flush.
+    pub(crate) fn push(&self, upload: Arc<dyn PendingUpload>) {
+        self.pending.lock().unwrap().push(upload);
+    }
+
+    /// Returns the number of uploads waiting for the next flush.
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Returns true if there are no uploads waiting for the next flush.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Records the copies of all the pending uploads into their device
+    /// buffers, followed by a single barrier making them visible to shaders.
+    pub fn flush(&self, encoder: &mut CommandEncoder) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return;
+        }
+
+        // Wait for the previous frames to stop reading the device buffers.
+        encoder.pipeline_barrier(
+            [MemoryBarrier2 {
+                src_stage_mask: uniform_read_stages(),
+                src_access_mask: AccessFlags2::UNIFORM_READ,
+                dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                dst_access_mask: AccessFlags2::TRANSFER_WRITE,
+            }],
+            [],
+            [],
+        );
+
+        let mut recorded = false;
+        for upload in pending {
+            recorded |= upload.record(encoder);
+        }
+
+        if recorded {
+            encoder.pipeline_barrier(
+                [MemoryBarrier2 {
+                    src_stage_mask: PipelineStageFlags2::TRANSFER,
+                    src_access_mask: AccessFlags2::TRANSFER_WRITE,
+                    dst_stage_mask: uniform_read_stages(),
+                    dst_access_mask: AccessFlags2::UNIFORM_READ,
+                }],
+                [],
+                [],
+            );
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::sync::{Arc, Mutex};
+
+use bytemuck::Pod;
+use vislum_render_rhi::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command::CommandEncoder,
+    device::Device,
+    memory::{MemoryAllocator, MemoryLocation},
+};
+
+use crate::{
+    buffer::{PendingUpload, PendingUploadsManager},
+    context::RenderContext,
+};
+
+/// A value of type `T` stored in a device-local uniform buffer.
+///
+/// New values are written into the device buffer when the pending uploads are
+/// flushed at the start of the next frame. The value is stored in the command
+/// buffer of that frame, so setting it again while previous frames are in
+/// flight doesn't change what they upload.
+///
+/// The size of `T` must be a multiple of 4 bytes, up to 65536 bytes.
+pub struct Uniform<T: Pod> {
+    inner: Arc<UniformInner<T>>,
+    pending_uploads: Arc<PendingUploadsManager>,
+}
+
+struct UniformInner<T> {
+    buffer: Arc<Buffer>,
+    pending: Mutex<Option<T>>,
+}
+
+impl<T: Pod> Uniform<T> {
+    /// Creates a uniform holding `data`, uploaded on the next frame.
+    pub fn new(context: &RenderContext, data: T) -> Self {
+        Self::with_uploads(
+            context.device().clone(),
+            context.allocator().clone(),
+            context.pending_uploads().clone(),
+            data,
+        )
+    }
+
+    /// Creates a uniform holding `data`, uploaded on the next flush of
+    /// `pending_uploads`.
+    pub fn with_uploads(
+        device: Arc<Device>,
+        allocator: Arc<MemoryAllocator>,
+        pending_uploads: Arc<PendingUploadsManager>,
+        data: T,
+    ) -> Self {
+        let size = std::mem::size_of::<T>() as u64;
+        assert!(size > 0, "uniforms can't be zero-sized");
+        assert!(
+            size.is_multiple_of(4) && size <= 65536,
+            "uniforms must be a multiple of 4 bytes, up to 65536 bytes"
+        );
+
+        let buffer = Buffer::new(
+            device,
+            allocator,
+            BufferCreateInfo::new(size).usage(
+                BufferUsage::UNIFORM_BUFFER | BufferUsage::TRANSFER_DST | BufferUsage::TRANSFER_SRC,
+            ),
+            MemoryLocation::GpuOnly,
+        );
+
+        let uniform = Self {
+            inner: Arc::new(UniformInner {
+                buffer,
+                pending: Mutex::new(None),
+            }),
+            pending_uploads,
+        };
+
+        uniform.set(data);
+        uniform
+    }
+
+    /// Sets the value of the uniform, uploaded on the next flush.
+    ///
+    /// Setting it again before the flush overrides the previous value.
+    pub fn set(&self, data: T) {
+        let already_pending = self.inner.pending.lock().unwrap().replace(data).is_some();
+
+        if !already_pending {
+            self.pending_uploads.push(self.inner.clone());
+        }
+    }
+
+    /// Returns true if a value is waiting to be uploaded.
+    pub fn is_pending(&self) -> bool {
+        self.inner.pending.lock().unwrap().is_some()
+    }
+
+    /// Returns the device-local buffer holding the uploaded value.
+    #[inline]
+    pub fn buffer(&self) -> &Arc<Buffer> {
+        &self.inner.buffer
+    }
+}
+
+impl<T: Pod> PendingUpload for UniformInner<T> {
+    fn record(&self, encoder: &mut CommandEncoder) -> bool {
+        let Some(data) = self.pending.lock().unwrap().take() else {
+            return false;
+        };
+
+        encoder.update_buffer(self.buffer.clone(), 0, bytemuck::bytes_of(&data));
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        command::{
+            AccessFlags2, BufferMemoryBarrier2, CommandBufferLevel, CommandBufferUsageFlags,
+            CommandPool, MemoryBarrier2, PipelineStageFlags2,
+        },
+        sync::Fence,
+        test_util::TestDevice,
+    };
+
+    use super::*;
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_set_and_flush_uploads_the_latest_value() {
+        let TestDevice { device, queue, .. } = TestDevice::new();
+        let context = RenderContext::new(device, queue);
+        let device = context.device().clone();
+
+        let uniform = Uniform::new(&context, [1.0f32, 2.0, 3.0, 4.0]);
+        uniform.set([5.0, 6.0, 7.0, 8.0]);
+        assert!(uniform.is_pending());
+        assert_eq!(context.pending_uploads().len(), 1);
+
+        let readback = Buffer::new(
+            device.clone(),
+            context.allocator().clone(),
+            BufferCreateInfo::new(uniform.buffer().size()).usage(BufferUsage::TRANSFER_DST),
+            MemoryLocation::GpuToCpu,
+        );
+
+        let command_pool = CommandPool::new(device.clone(), 0);
+        let mut encoder = CommandEncoder::new(command_pool.allocate(CommandBufferLevel::PRIMARY));
+        encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        context.pending_uploads().flush(&mut encoder);
+        encoder.pipeline_barrier(
+            [],
+            [BufferMemoryBarrier2 {
+                buffer: uniform.buffer().clone(),
+                src_stage_mask: PipelineStageFlags2::TRANSFER,
+                src_access_mask: AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                dst_access_mask: AccessFlags2::TRANSFER_READ,
+                offset: 0,
+                size: uniform.buffer().size(),
+            }],
+            [],
+        );
+        encoder.copy_buffer(
+            uniform.buffer().clone(),
+            readback.clone(),
+            0,
+            0,
+            uniform.buffer().size(),
+        );
+        encoder.end();
+
+        assert!(!uniform.is_pending());
+        assert!(context.pending_uploads().is_empty());
+
+        let fence = Fence::unsignaled(device.clone());
+        context
+            .queue()
+            .submit(encoder, vec![], vec![], Some(fence.clone()));
+        assert!(fence.wait(u64::MAX));
+
+        let mapped = readback.map();
+        let values: &[f32] = bytemuck::cast_slice(&mapped[..16]);
+        assert_eq!(values, [5.0, 6.0, 7.0, 8.0]);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_set_while_previous_frame_is_in_flight() {
+        let TestDevice { device, queue, .. } = TestDevice::new();
+        let context = RenderContext::new(device, queue);
+        let device = context.device().clone();
+        let command_pool = CommandPool::new(device.clone(), 0);
+
+        let uniform = Uniform::new(&context, [0u32; 4]);
+
+        // Both frames are recorded before either is submitted, so the upload
+        // of the first one can't be overwritten by the value of the second.
+        let frames = [[1u32, 2, 3, 4], [5, 6, 7, 8]].map(|value| {
+            uniform.set(value);
+
+            let readback = Buffer::new(
+                device.clone(),
+                context.allocator().clone(),
+                BufferCreateInfo::new(uniform.buffer().size()).usage(BufferUsage::TRANSFER_DST),
+                MemoryLocation::GpuToCpu,
+            );
+
+            let mut encoder =
+                CommandEncoder::new(command_pool.allocate(CommandBufferLevel::PRIMARY));
+            encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            // Wait for the previous frame to read back the buffer.
+            encoder.pipeline_barrier(
+                [MemoryBarrier2 {
+                    src_stage_mask: PipelineStageFlags2::TRANSFER,
+                    src_access_mask: AccessFlags2::TRANSFER_READ,
+                    dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                    dst_access_mask: AccessFlags2::TRANSFER_WRITE,
+                }],
+                [],
+                [],
+            );
+            context.pending_uploads().flush(&mut encoder);
+            encoder.pipeline_barrier(
+                [MemoryBarrier2 {
+                    src_stage_mask: PipelineStageFlags2::TRANSFER,
+                    src_access_mask: AccessFlags2::TRANSFER_WRITE,
+                    dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                    dst_access_mask: AccessFlags2::TRANSFER_READ,
+                }],
+                [],
+                [],
+            );
+            encoder.copy_buffer(
+                uniform.buffer().clone(),
+                readback.clone(),
+                0,
+                0,
+                uniform.buffer().size(),
+            );
+            encoder.end();
+
+            (encoder, readback)
+        });
+
+        let fence = Fence::unsignaled(device.clone());
+        let [(first, first_readback), (second, second_readback)] = frames;
+        context.queue().submit(first, vec![], vec![], None);
+        context
+            .queue()
+            .submit(second, vec![], vec![], Some(fence.clone()));
+        assert!(fence.wait(u64::MAX));
+
+        let values =
+            |readback: &Buffer| bytemuck::cast_slice::<u8, u32>(&readback.map()[..16]).to_vec();
+        assert_eq!(values(&first_readback), [1, 2, 3, 4]);
+        assert_eq!(values(&second_readback), [5, 6, 7, 8]);
+    }
+}
@@ -4,10 +4,10 @@ use vislum_render_rhi::{
     device::Device,
     memory::MemoryAllocator,
     queue::Queue,
-    image::{Image, ImageView},
+    image::{Extent3D, Image, ImageView},
 };
 
-use crate::{graph::{FrameGraph, pass::FrameGraphSubmitInfo, FrameNode}, resource::{ResourceManager, pool::ResourceId, texture::{Texture, TextureCreateInfo}, mesh::{Mesh, Vertex}}};
+use crate::{buffer::PendingUploadsManager, graph::{FrameGraph, PassTiming, pass::FrameGraphSubmitInfo, FrameNode}, resource::{ResourceManager, material::MaterialManager, pool::ResourceId, texture::{Texture, TextureCreateInfo, TextureError}, mesh::{Mesh, MeshIndex}, vertex::MeshVertex}};
 
 pub struct RenderContext {
     device: Arc<Device>,
@@ -23,9 +23,16 @@ impl RenderContext {
         queue: Arc<Queue>,
     ) -> Self {
         let allocator = MemoryAllocator::new(device.clone());
-        let resource_manager = ResourceManager::new(device.clone(), allocator.clone());
+        let mut resource_manager =
+            ResourceManager::new(device.clone(), queue.clone(), allocator.clone());
         let frame_graph = FrameGraph::new(device.clone(), queue.clone(), allocator.clone());
 
+        // Enqueue the fallbacks first, so they are uploaded by the first frame.
+        // Substituting them keeps passes from sampling textures still
+        // uploading.
+        resource_manager.create_fallbacks();
+        resource_manager.set_use_fallbacks(true);
+
         Self {
             device,
             queue,
@@ -35,6 +42,27 @@ impl RenderContext {
         }
     }
 
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    #[inline]
+    pub fn allocator(&self) -> &Arc<MemoryAllocator> {
+        &self.allocator
+    }
+
+    /// Returns the uploads flushed at the start of the next frame.
+    #[inline]
+    pub fn pending_uploads(&self) -> &Arc<PendingUploadsManager> {
+        self.frame_graph.pending_uploads()
+    }
+
     /// Adds a new pass to the frame graph.
     pub fn add_pass<F>(&mut self, node: F)
     where
@@ -43,32 +71,103 @@ impl RenderContext {
         self.frame_graph.add_pass(node);
     }
 
+    /// Exports the pending frame graph passes as a Graphviz DOT diagram.
+    pub fn frame_graph_to_dot(&self) -> String {
+        self.frame_graph.to_dot(&self.resource_manager)
+    }
+
+    /// Enables measuring the GPU time spent executing each pass.
+    pub fn enable_pass_timings(&mut self) -> Result<(), vislum_render_rhi::Error> {
+        self.frame_graph.enable_pass_timings()
+    }
+
+    /// Returns the GPU time spent executing each pass of the latest timed
+    /// frame.
+    pub fn pass_timings(&self) -> &[PassTiming] {
+        self.frame_graph.pass_timings()
+    }
+
+    /// Uploads the contents of the resources created since the last frame,
+    /// then executes the frame graph.
     pub fn execute_and_submit(&mut self, submit_info: FrameGraphSubmitInfo) {
+        self.resource_manager.flush_uploads();
         self.frame_graph.execute(&self.resource_manager, submit_info);
     }
 
     /// Creates a texture with data and returns the resource id.
-    /// The upload task is automatically added to the frame graph.
+    /// The data is uploaded at the start of the next frame.
     pub fn create_texture_with_data(
         &mut self,
         info: TextureCreateInfo,
         data: &[u8],
     ) -> ResourceId<Texture> {
-        let (id, upload_task) = self.resource_manager.create_texture_with_data(info, data);
-        self.frame_graph.add_pass(upload_task);
-        id
+        self.resource_manager.create_texture_with_data(info, data)
+    }
+
+    /// Creates a storage texture and returns the resource id.
+    /// The layout transition task is automatically added to the frame graph.
+    pub fn create_storage_texture(
+        &mut self,
+        info: TextureCreateInfo,
+    ) -> Result<ResourceId<Texture>, TextureError> {
+        let (id, init_task) = self.resource_manager.create_storage_texture(info)?;
+        self.frame_graph.add_pass(init_task);
+        Ok(id)
+    }
+
+    /// Creates a depth texture and returns the resource id.
+    pub fn create_depth_texture(&mut self, extent: Extent3D) -> ResourceId<Texture> {
+        self.resource_manager.create_depth_texture(extent)
+    }
+
+    /// Destroys a texture, returning it if it existed.
+    pub fn destroy_texture(&mut self, id: ResourceId<Texture>) -> Option<Texture> {
+        self.resource_manager.destroy_texture(id)
     }
 
     /// Creates a mesh with data and returns the resource id.
-    /// The upload task is automatically added to the frame graph.
-    pub fn create_mesh(
+    /// The data is uploaded at the start of the next frame.
+    pub fn create_mesh<V, I>(
         &mut self,
-        vertices: impl IntoIterator<Item = Vertex>,
-        indices: impl IntoIterator<Item = u16>,
-    ) -> ResourceId<Mesh> {
-        let (id, upload_task) = self.resource_manager.create_mesh(vertices, indices);
-        self.frame_graph.add_pass(upload_task);
-        id
+        vertices: impl IntoIterator<Item = V>,
+        indices: impl IntoIterator<Item = I>,
+    ) -> ResourceId<Mesh>
+    where
+        V: MeshVertex,
+        I: MeshIndex,
+    {
+        self.resource_manager.create_mesh(vertices, indices)
+    }
+
+    /// Returns true if the texture finished uploading its initial contents.
+    pub fn is_texture_ready(&self, id: ResourceId<Texture>) -> bool {
+        self.resource_manager.is_texture_ready(id)
+    }
+
+    /// Returns the checkerboard texture substituted for missing textures.
+    pub fn missing_texture(&self) -> ResourceId<Texture> {
+        self.resource_manager
+            .missing_texture()
+            .expect("fallbacks are created with the render context")
+    }
+
+    /// Enables or disables substituting fallbacks for missing or not yet
+    /// uploaded resources.
+    ///
+    /// Enabled by default.
+    pub fn set_use_fallbacks(&mut self, use_fallbacks: bool) {
+        self.resource_manager.set_use_fallbacks(use_fallbacks);
+    }
+
+    /// Returns the material definitions and instances.
+    #[inline]
+    pub fn materials(&self) -> &MaterialManager {
+        self.resource_manager.materials()
+    }
+
+    #[inline]
+    pub fn materials_mut(&mut self) -> &mut MaterialManager {
+        self.resource_manager.materials_mut()
     }
 
     pub fn get_texture_image(&self, id: ResourceId<Texture>) -> Option<Arc<Image>> {
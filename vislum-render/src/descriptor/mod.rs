@@ -0,0 +1,333 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ash::vk;
+use smallvec::SmallVec;
+use vislum_render_rhi::{
+    VkHandle,
+    descriptor::{DescriptorPool, DescriptorSet, DescriptorSetLayout, DescriptorWrites},
+    device::Device,
+};
+
+use crate::resource::{ResourceManager, pool::ErasedResourceId, texture::Texture};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DescriptorSetCacheError {
+    #[error("resource {0:?} does not exist")]
+    UnknownResource(ErasedResourceId),
+    #[error("resource {0:?} can't be bound to a descriptor set")]
+    UnsupportedResource(ErasedResourceId),
+    #[error("failed to allocate the descriptor set: {0}")]
+    Allocation(#[from] vislum_render_rhi::Error),
+}
+
+/// Identifies a cached descriptor set by its layout and the resources bound
+/// to it, in binding order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DescriptorSetKey {
+    /// The cached set keeps its layout alive, so the handle can't be reused
+    /// by another layout while the entry exists.
+    layout: vk::DescriptorSetLayout,
+    resources: SmallVec<[ErasedResourceId; 4]>,
+}
+
+/// Caches the descriptor sets binding a given list of resources, so materials
+/// binding the same textures every frame reuse the same set.
+pub struct DescriptorSetCache {
+    device: Arc<Device>,
+    allocator: Arc<DescriptorPool>,
+    sets: HashMap<DescriptorSetKey, Arc<DescriptorSet>>,
+}
+
+impl DescriptorSetCache {
+    pub fn new(device: Arc<Device>, allocator: Arc<DescriptorPool>) -> Self {
+        Self {
+            device,
+            allocator,
+            sets: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of cached descriptor sets.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Returns true if no descriptor set is cached.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// Returns the descriptor set binding the given resources with the given
+    /// layout, allocating it on the first request.
+    ///
+    /// Each resource is written to the binding matching its position in
+    /// `resources`, so the same resources in another order result in another
+    /// set. Textures are written as sampled images.
+    ///
+    /// Sets binding textures which haven't finished uploading are not cached,
+    /// as they may be bound to a fallback which must be replaced once the
+    /// texture is ready.
+    pub fn get_or_insert(
+        &mut self,
+        resource_manager: &ResourceManager,
+        layout: &Arc<DescriptorSetLayout>,
+        resources: &[ErasedResourceId],
+    ) -> Result<Arc<DescriptorSet>, DescriptorSetCacheError> {
+        let key = DescriptorSetKey {
+            layout: layout.vk_handle(),
+            resources: resources.into(),
+        };
+
+        if let Some(set) = self.sets.get(&key) {
+            return Ok(set.clone());
+        }
+
+        let mut ready = true;
+        let views = resources
+            .iter()
+            .map(|&id| {
+                let texture = id
+                    .downcast::<Texture>()
+                    .ok_or(DescriptorSetCacheError::UnsupportedResource(id))?;
+
+                ready &= resource_manager.is_texture_ready(texture);
+                resource_manager
+                    .resolve_texture_view(texture)
+                    .ok_or(DescriptorSetCacheError::UnknownResource(id))
+            })
+            .collect::<Result<SmallVec<[_; 4]>, _>>()?;
+
+        let set = self.allocator.allocate(layout)?;
+
+        let mut writes = DescriptorWrites::new();
+        for (binding, view) in views.iter().enumerate() {
+            writes.write(set.vk_handle(), binding as u32, &**view);
+        }
+        writes.update(&self.device);
+
+        if ready {
+            self.sets.insert(key, set.clone());
+        }
+
+        Ok(set)
+    }
+
+    /// Evicts the descriptor sets binding resources which no longer exist.
+    ///
+    /// Returns the number of evicted sets.
+    pub fn cleanup_removed_resources(&mut self, resource_manager: &ResourceManager) -> usize {
+        let len = self.sets.len();
+        self.sets.retain(|key, _| {
+            key.resources
+                .iter()
+                .all(|&id| resource_manager.contains(id))
+        });
+
+        len - self.sets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        descriptor::{
+            DescriptorPoolCreateInfo, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+            DescriptorType,
+        },
+        image::Extent3D,
+        shader::ShaderStageFlags,
+        test_util::TestDevice,
+    };
+
+    use bytemuck::Zeroable;
+
+    use super::*;
+    use crate::resource::{
+        mesh::Vertex,
+        pool::ResourceId,
+        texture::{TextureCreateInfo, TextureDimensions, TextureFormat},
+    };
+
+    struct Fixture {
+        resource_manager: ResourceManager,
+        layout: Arc<DescriptorSetLayout>,
+        cache: DescriptorSetCache,
+    }
+
+    impl Fixture {
+        fn create_texture(&mut self) -> ResourceId<Texture> {
+            self.resource_manager.create_texture_with_data(
+                TextureCreateInfo {
+                    format: TextureFormat::Rgba8Unorm,
+                    dimensions: TextureDimensions::D2,
+                    extent: Extent3D {
+                        width: 1,
+                        height: 1,
+                        depth: 1,
+                    },
+                    generate_mipmaps: false,
+                },
+                &[255; 4],
+            )
+        }
+    }
+
+    /// Creates a cache with a two texture layout.
+    fn create_fixture() -> Fixture {
+        let TestDevice {
+            device,
+            queue,
+            allocator,
+        } = TestDevice::new();
+
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: (0..2)
+                    .map(|binding| DescriptorSetLayoutBinding {
+                        binding,
+                        descriptor_type: DescriptorType::SampledImage,
+                        count: 1,
+                        stages: ShaderStageFlags::FRAGMENT,
+                    })
+                    .collect(),
+            },
+        );
+        let pool = DescriptorPool::new(device.clone(), DescriptorPoolCreateInfo::default());
+
+        Fixture {
+            resource_manager: ResourceManager::new(device.clone(), queue, allocator),
+            layout,
+            cache: DescriptorSetCache::new(device, pool),
+        }
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_identical_requests_hit_the_cache() {
+        let mut fixture = create_fixture();
+        let a = fixture.create_texture().into();
+        let b = fixture.create_texture().into();
+        fixture.resource_manager.flush_uploads();
+        fixture.resource_manager.wait_for_uploads();
+
+        let Fixture {
+            resource_manager,
+            layout,
+            cache,
+        } = &mut fixture;
+
+        let first = cache
+            .get_or_insert(resource_manager, layout, &[a, b])
+            .unwrap();
+        let second = cache
+            .get_or_insert(resource_manager, layout, &[a, b])
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+
+        // The key is order-sensitive.
+        let swapped = cache
+            .get_or_insert(resource_manager, layout, &[b, a])
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first, &swapped));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_uploading_textures_miss_the_cache() {
+        let mut fixture = create_fixture();
+        let a = fixture.create_texture().into();
+        let b = fixture.create_texture().into();
+
+        let Fixture {
+            resource_manager,
+            layout,
+            cache,
+        } = &mut fixture;
+
+        let first = cache
+            .get_or_insert(resource_manager, layout, &[a, b])
+            .unwrap();
+        let second = cache
+            .get_or_insert(resource_manager, layout, &[a, b])
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert!(cache.is_empty());
+
+        resource_manager.flush_uploads();
+        assert!(
+            cache
+                .get_or_insert(resource_manager, layout, &[a, b])
+                .is_ok()
+        );
+        assert!(cache.is_empty());
+
+        resource_manager.wait_for_uploads();
+        cache
+            .get_or_insert(resource_manager, layout, &[a, b])
+            .unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_unbindable_resources_are_rejected() {
+        let mut fixture = create_fixture();
+        let texture = fixture.create_texture();
+        let mesh = fixture
+            .resource_manager
+            .create_mesh([Vertex::zeroed(); 3], [0u16, 1, 2]);
+        fixture.resource_manager.destroy_texture(texture);
+
+        let Fixture {
+            resource_manager,
+            layout,
+            cache,
+        } = &mut fixture;
+
+        assert!(matches!(
+            cache.get_or_insert(resource_manager, layout, &[texture.into()]),
+            Err(DescriptorSetCacheError::UnknownResource(_))
+        ));
+        assert!(matches!(
+            cache.get_or_insert(resource_manager, layout, &[mesh.into()]),
+            Err(DescriptorSetCacheError::UnsupportedResource(_))
+        ));
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_cleanup_evicts_sets_of_removed_resources() {
+        let mut fixture = create_fixture();
+        let a = fixture.create_texture();
+        let b = fixture.create_texture();
+        let c = fixture.create_texture();
+        fixture.resource_manager.flush_uploads();
+        fixture.resource_manager.wait_for_uploads();
+
+        let Fixture {
+            resource_manager,
+            layout,
+            cache,
+        } = &mut fixture;
+
+        cache
+            .get_or_insert(resource_manager, layout, &[a.into(), b.into()])
+            .unwrap();
+        cache
+            .get_or_insert(resource_manager, layout, &[b.into(), c.into()])
+            .unwrap();
+        cache
+            .get_or_insert(resource_manager, layout, &[c.into(), a.into()])
+            .unwrap();
+        assert_eq!(cache.cleanup_removed_resources(resource_manager), 0);
+
+        resource_manager.destroy_texture(a);
+        assert_eq!(cache.cleanup_removed_resources(resource_manager), 2);
+        assert_eq!(cache.len(), 1);
+    }
+}
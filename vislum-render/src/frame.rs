@@ -0,0 +1,284 @@
+use std::{ops::Deref, sync::Arc};
+
+use vislum_render_rhi::{
+    command::CommandPool,
+    device::Device,
+    sync::{Fence, Semaphore},
+};
+
+use crate::graph::pass::FrameGraphSubmitInfo;
+
+/// The objects owned by a frame in flight, reused once the device finishes
+/// executing the frame.
+pub trait InFlightFrame: Clone {
+    /// How the frame is submitted.
+    type SubmitInfo;
+
+    /// Blocks until the device finishes executing the previous submission of
+    /// the frame.
+    fn wait(&self);
+
+    /// Releases the commands recorded for the previous submission of the
+    /// frame, once it has finished.
+    fn reset_commands(&self);
+
+    /// Prepares the frame to be submitted, returning how to submit it.
+    fn prepare_submit(&self) -> Self::SubmitInfo;
+}
+
+/// The synchronization objects of a frame rendered to a swapchain.
+#[derive(Clone)]
+pub struct FrameSyncObjects {
+    /// The pool the frame graph allocates the commands of the frame from.
+    /// It's reset when the frame begins.
+    pub command_pool: Arc<CommandPool>,
+    /// Signaled once the swapchain image the frame renders to is acquired.
+    pub acquire_semaphore: Arc<Semaphore>,
+    /// Signaled once the frame is rendered, and waited by the presentation.
+    pub render_semaphore: Arc<Semaphore>,
+    /// Signaled once the device finishes executing the frame.
+    pub fence: Arc<Fence>,
+}
+
+impl FrameSyncObjects {
+    pub fn new(device: Arc<Device>, queue_family_index: u32) -> Self {
+        Self {
+            command_pool: CommandPool::new(device.clone(), queue_family_index),
+            acquire_semaphore: Semaphore::new(device.clone()),
+            render_semaphore: Semaphore::new(device.clone()),
+            // Signaled, so the first frame doesn't wait for a submission
+            // which never happened.
+            fence: Fence::signaled(device),
+        }
+    }
+}
+
+impl InFlightFrame for FrameSyncObjects {
+    type SubmitInfo = FrameGraphSubmitInfo;
+
+    fn wait(&self) {
+        self.fence.wait(u64::MAX);
+    }
+
+    fn reset_commands(&self) {
+        self.command_pool.reset();
+    }
+
+    fn prepare_submit(&self) -> FrameGraphSubmitInfo {
+        // The fence is only reset once the frame is going to signal it, so
+        // frames abandoned before their submission don't deadlock.
+        self.fence.reset();
+
+        FrameGraphSubmitInfo {
+            command_pool: self.command_pool.clone(),
+            wait_semaphores: vec![self.acquire_semaphore.clone()],
+            signal_semaphores: vec![self.render_semaphore.clone()],
+            signal_fence: Some(self.fence.clone()),
+        }
+    }
+}
+
+/// A frame being recorded, returned by [`FramesInFlight::begin_frame`].
+///
+/// Dereferences to the objects of the frame.
+pub struct FrameContext<F = FrameSyncObjects> {
+    index: usize,
+    frame: F,
+}
+
+impl<F> FrameContext<F> {
+    /// Returns the index of the frame, in `0..FramesInFlight::len()`.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<F> Deref for FrameContext<F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.frame
+    }
+}
+
+/// Cycles through a fixed number of frames, so the CPU records a frame while
+/// the device executes the previous ones.
+///
+/// With N frames, recording a frame first waits for the device to finish the
+/// frame submitted N frames earlier, which owns the same objects. Two frames
+/// double buffer, three triple buffer.
+///
+/// The frame index is independent of the index of the swapchain image the
+/// frame renders to: the presentation engine may hand out images in any
+/// order. Resources tied to a swapchain image, like its views, must be
+/// indexed by the image index instead.
+pub struct FramesInFlight<F = FrameSyncObjects> {
+    frames: Vec<F>,
+    current: usize,
+}
+
+impl FramesInFlight {
+    /// Creates `count` frames, with their command pools allocating from the
+    /// given queue family.
+    pub fn new(device: Arc<Device>, queue_family_index: u32, count: usize) -> Self {
+        Self::from_frames(
+            (0..count)
+                .map(|_| FrameSyncObjects::new(device.clone(), queue_family_index))
+                .collect(),
+        )
+    }
+}
+
+impl<F: InFlightFrame> FramesInFlight<F> {
+    /// Cycles through the given frames, in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no frames.
+    pub fn from_frames(frames: Vec<F>) -> Self {
+        assert!(!frames.is_empty(), "at least one frame must be in flight");
+        Self { frames, current: 0 }
+    }
+
+    /// Returns the number of frames in flight.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Always false, as there is at least one frame in flight.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the index of the frame recorded next.
+    #[inline]
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Begins recording the current frame.
+    ///
+    /// Waits for the device to finish the previous submission of the frame
+    /// and releases its commands. Beginning a frame again without ending it,
+    /// e.g. when the swapchain went out of date, reuses the same frame.
+    pub fn begin_frame(&mut self) -> FrameContext<F> {
+        let frame = &self.frames[self.current];
+        frame.wait();
+        frame.reset_commands();
+
+        FrameContext {
+            index: self.current,
+            frame: frame.clone(),
+        }
+    }
+
+    /// Ends recording the current frame, advancing to the next one.
+    ///
+    /// Returns how to submit the frame, which must be submitted before the
+    /// frame begins again.
+    pub fn end_frame(&mut self) -> F::SubmitInfo {
+        let submit_info = self.frames[self.current].prepare_submit();
+        self.current = (self.current + 1) % self.frames.len();
+        submit_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    /// A queue executing submissions when their frame is waited for.
+    #[derive(Default)]
+    struct FakeQueue {
+        /// The frames submitted and not executed yet.
+        pending: Vec<usize>,
+        /// The frames waited for, in order.
+        waits: Vec<usize>,
+        /// The frames whose commands were reset, in order.
+        resets: Vec<usize>,
+    }
+
+    #[derive(Clone)]
+    struct FakeFrame {
+        index: usize,
+        queue: Rc<RefCell<FakeQueue>>,
+    }
+
+    impl InFlightFrame for FakeFrame {
+        type SubmitInfo = usize;
+
+        fn wait(&self) {
+            let mut queue = self.queue.borrow_mut();
+            queue.pending.retain(|&frame| frame != self.index);
+            queue.waits.push(self.index);
+        }
+
+        fn reset_commands(&self) {
+            let mut queue = self.queue.borrow_mut();
+            assert!(
+                !queue.pending.contains(&self.index),
+                "frame {} was reset while pending",
+                self.index
+            );
+            queue.resets.push(self.index);
+        }
+
+        fn prepare_submit(&self) -> usize {
+            self.index
+        }
+    }
+
+    fn create_frames(count: usize) -> (Rc<RefCell<FakeQueue>>, FramesInFlight<FakeFrame>) {
+        let queue = Rc::new(RefCell::new(FakeQueue::default()));
+        let frames = (0..count)
+            .map(|index| FakeFrame {
+                index,
+                queue: queue.clone(),
+            })
+            .collect();
+
+        (queue, FramesInFlight::from_frames(frames))
+    }
+
+    #[test]
+    fn test_frames_are_cycled_in_order() {
+        let (queue, mut frames) = create_frames(3);
+
+        let mut indices = Vec::new();
+        for _ in 0..7 {
+            let frame = frames.begin_frame();
+            indices.push(frame.index());
+
+            let submitted = frames.end_frame();
+            assert_eq!(submitted, frame.index());
+            queue.borrow_mut().pending.push(submitted);
+
+            // Only the frames submitted since the current one are in flight.
+            assert!(queue.borrow().pending.len() <= 3);
+        }
+
+        assert_eq!(indices, [0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(queue.borrow().waits, indices);
+        assert_eq!(queue.borrow().resets, indices);
+        assert_eq!(queue.borrow().pending, [1, 2, 0]);
+    }
+
+    #[test]
+    fn test_abandoned_frame_is_reused() {
+        let (queue, mut frames) = create_frames(2);
+
+        // The frame is abandoned before its submission, e.g. because the
+        // swapchain went out of date.
+        assert_eq!(frames.begin_frame().index(), 0);
+        assert_eq!(frames.begin_frame().index(), 0);
+        frames.end_frame();
+
+        assert_eq!(frames.current_index(), 1);
+        assert_eq!(queue.borrow().waits, [0, 0]);
+    }
+}
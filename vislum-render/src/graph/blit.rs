@@ -0,0 +1,552 @@
+use std::{borrow::Cow, sync::Arc};
+
+use ash::vk;
+use vislum_render_rhi::{
+    command::{
+        AccessFlags2, ImageAspectFlags, ImageBlit, ImageLayout, ImageSubresourceLayers,
+        PipelineStageFlags2,
+    },
+    image::{Extent3D, FormatFeatures, Image, ImageFormat, ImageUsage},
+};
+
+use crate::{
+    graph::{ExecuteContext, FrameNode, PrepareContext},
+    resource::{pool::ResourceId, texture::Texture},
+};
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum BlitError {
+    #[error("{0:?} images can't be blitted")]
+    DepthFormat(ImageFormat),
+    #[error("the device can't blit from {0:?} images")]
+    UnsupportedSource(ImageFormat),
+    #[error("the device can't blit to {0:?} images")]
+    UnsupportedDestination(ImageFormat),
+    #[error("the device can't filter {0:?} images linearly")]
+    UnsupportedLinearFilter(ImageFormat),
+    #[error("the source image wasn't created with the TRANSFER_SRC usage")]
+    SourceNotTransferSrc,
+    #[error("the destination image wasn't created with the TRANSFER_DST usage")]
+    DestinationNotTransferDst,
+}
+
+/// Copies a texture to an image, scaling it to the image extent.
+///
+/// This is the final composite step when rendering to an intermediate
+/// texture, blitting it to the swapchain image before presenting it. The
+/// blit converts between color formats, so e.g. a linear `Rgba8Unorm` texture
+/// can be blitted to an `Bgra8Srgb` swapchain image.
+///
+/// The source texture is transitioned back to its layout once blitted, and
+/// the destination is left in the final layout, [`ImageLayout::PresentSrcKhr`]
+/// by default. The previous contents of the destination are discarded.
+///
+/// The source must have the [`ImageUsage::TRANSFER_SRC`] usage, and the
+/// destination the [`ImageUsage::TRANSFER_DST`] one.
+pub struct BlitPass {
+    source: ResourceId<Texture>,
+    source_layout: Option<ImageLayout>,
+    destination: Arc<Image>,
+    surface: bool,
+    final_layout: ImageLayout,
+    filter: vk::Filter,
+    flip_y: bool,
+}
+
+impl BlitPass {
+    pub fn new(source: ResourceId<Texture>, destination: Arc<Image>) -> Self {
+        Self {
+            source,
+            source_layout: None,
+            destination,
+            surface: false,
+            final_layout: ImageLayout::PresentSrcKhr,
+            filter: vk::Filter::LINEAR,
+            flip_y: false,
+        }
+    }
+
+    /// Sets the layout the source texture is in, unless a previous pass of
+    /// the frame transitioned it.
+    ///
+    /// Defaults to the layout the texture is kept in:
+    /// [`ImageLayout::General`] for storage textures, and
+    /// [`ImageLayout::ShaderReadOnlyOptimal`] for the others.
+    pub fn source_layout(mut self, source_layout: ImageLayout) -> Self {
+        self.source_layout = Some(source_layout);
+        self
+    }
+
+    /// Declares the destination as the surface the frame is presented to,
+    /// e.g. a swapchain image, instead of an ordinary image.
+    pub fn surface(mut self, surface: bool) -> Self {
+        self.surface = surface;
+        self
+    }
+
+    /// Sets the layout the destination is left in.
+    pub fn final_layout(mut self, final_layout: ImageLayout) -> Self {
+        self.final_layout = final_layout;
+        self
+    }
+
+    /// Sets the filter used when the extents differ.
+    pub fn filter(mut self, filter: vk::Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Flips the texture vertically, e.g. when it was rendered with the y
+    /// axis pointing up.
+    pub fn flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+}
+
+impl FrameNode for BlitPass {
+    fn name(&self) -> Cow<'static, str> {
+        "blit".into()
+    }
+
+    fn prepare(&self, context: &mut PrepareContext) -> Box<dyn FnMut(&mut ExecuteContext)> {
+        if self.surface {
+            context.write_surface();
+        } else {
+            context.write_image(&self.destination);
+        }
+
+        let Some(source) = context.read_texture(self.source) else {
+            log::warn!("Skipping the blit of missing texture {:?}", self.source);
+            return Box::new(|_| {});
+        };
+
+        let destination = self.destination.clone();
+        let physical_device = destination.device().physical_device();
+        let checked = check_usage(source.usage(), destination.usage()).and_then(|()| {
+            check_formats(
+                source.format(),
+                physical_device.format_features(source.format()),
+                destination.format(),
+                physical_device.format_features(destination.format()),
+                self.filter,
+            )
+        });
+        if let Err(error) = checked {
+            log::error!("Skipping the blit of texture {:?}: {error}", self.source);
+            return Box::new(|_| {});
+        }
+
+        let region = blit_region(source.extent(), destination.extent(), self.flip_y);
+        let source_layout =
+            self.source_layout
+                .unwrap_or(if context.is_storage_texture(self.source) {
+                    ImageLayout::General
+                } else {
+                    ImageLayout::ShaderReadOnlyOptimal
+                });
+        let final_layout = self.final_layout;
+        let filter = self.filter;
+
+        Box::new(move |execute_context| {
+            let cmd = &mut execute_context.command_buffer;
+
+            let (stage, access) = layout_access(source_layout);
+            cmd.assume_image_state(&source, source_layout, stage, access);
+
+            cmd.transition_image(
+                source.clone(),
+                ImageLayout::TransferSrcOptimal,
+                PipelineStageFlags2::TRANSFER,
+                AccessFlags2::TRANSFER_READ,
+            );
+            cmd.transition_image(
+                destination.clone(),
+                ImageLayout::TransferDstOptimal,
+                PipelineStageFlags2::TRANSFER,
+                AccessFlags2::TRANSFER_WRITE,
+            );
+
+            cmd.blit_image(
+                source.clone(),
+                ImageLayout::TransferSrcOptimal,
+                destination.clone(),
+                ImageLayout::TransferDstOptimal,
+                [region],
+                filter,
+            );
+
+            let (stage, access) = layout_access(source_layout);
+            cmd.transition_image(source.clone(), source_layout, stage, access);
+            let (stage, access) = layout_access(final_layout);
+            cmd.transition_image(destination.clone(), final_layout, stage, access);
+        })
+    }
+}
+
+/// Checks the images were created with the usages blits require.
+fn check_usage(source: ImageUsage, destination: ImageUsage) -> Result<(), BlitError> {
+    if !source.contains(ImageUsage::TRANSFER_SRC) {
+        return Err(BlitError::SourceNotTransferSrc);
+    }
+
+    if !destination.contains(ImageUsage::TRANSFER_DST) {
+        return Err(BlitError::DestinationNotTransferDst);
+    }
+
+    Ok(())
+}
+
+/// Checks the device can blit between images of the given formats.
+fn check_formats(
+    source: ImageFormat,
+    source_features: FormatFeatures,
+    destination: ImageFormat,
+    destination_features: FormatFeatures,
+    filter: vk::Filter,
+) -> Result<(), BlitError> {
+    // Depth blits require matching formats and nearest filtering, which
+    // isn't worth supporting for a composite pass.
+    if let Some(format) = [source, destination].into_iter().find(|f| f.is_depth()) {
+        return Err(BlitError::DepthFormat(format));
+    }
+
+    if !source_features.contains(FormatFeatures::BLIT_SRC) {
+        return Err(BlitError::UnsupportedSource(source));
+    }
+
+    if !destination_features.contains(FormatFeatures::BLIT_DST) {
+        return Err(BlitError::UnsupportedDestination(destination));
+    }
+
+    if filter == vk::Filter::LINEAR
+        && !source_features.contains(FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR)
+    {
+        return Err(BlitError::UnsupportedLinearFilter(source));
+    }
+
+    Ok(())
+}
+
+/// Returns the region blitting the first mip level of the source over the
+/// whole destination.
+fn blit_region(source: Extent3D, destination: Extent3D, flip_y: bool) -> ImageBlit {
+    let layers = ImageSubresourceLayers {
+        aspect_mask: ImageAspectFlags::COLOR,
+        mip_level: 0,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    let corner = |extent: Extent3D| [extent.width as i32, extent.height as i32, 1];
+    let [width, height, _] = corner(destination);
+
+    // Swapping the destination rows mirrors the image vertically.
+    let dst_offsets = if flip_y {
+        [[0, height, 0], [width, 0, 1]]
+    } else {
+        [[0, 0, 0], [width, height, 1]]
+    };
+
+    ImageBlit {
+        src_subresource: layers,
+        src_offsets: [[0, 0, 0], corner(source)],
+        dst_subresource: layers,
+        dst_offsets,
+    }
+}
+
+/// Returns the stages and accesses an image is used with in a layout.
+fn layout_access(layout: ImageLayout) -> (PipelineStageFlags2, AccessFlags2) {
+    match layout {
+        ImageLayout::ShaderReadOnlyOptimal => (
+            PipelineStageFlags2::FRAGMENT_SHADER,
+            AccessFlags2::SHADER_READ,
+        ),
+        ImageLayout::ColorAttachmentOptimal => (
+            PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+            AccessFlags2::COLOR_ATTACHMENT_READ | AccessFlags2::COLOR_ATTACHMENT_WRITE,
+        ),
+        ImageLayout::TransferSrcOptimal => {
+            (PipelineStageFlags2::TRANSFER, AccessFlags2::TRANSFER_READ)
+        }
+        ImageLayout::TransferDstOptimal => {
+            (PipelineStageFlags2::TRANSFER, AccessFlags2::TRANSFER_WRITE)
+        }
+        // Presentation is synchronized with semaphores.
+        ImageLayout::PresentSrcKhr => (PipelineStageFlags2::BOTTOM_OF_PIPE, AccessFlags2::NONE),
+        _ => (
+            PipelineStageFlags2::ALL_COMMANDS,
+            AccessFlags2::MEMORY_READ | AccessFlags2::MEMORY_WRITE,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        VkHandle,
+        command::{CommandPool, PipelineBindPoint},
+        descriptor::{
+            DescriptorPool, DescriptorPoolCreateInfo, DescriptorPoolSizes, DescriptorResourceInfo,
+            DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+            DescriptorType, DescriptorWrites,
+        },
+        image::ImageCreateInfo,
+        memory::MemoryLocation,
+        pipeline::{ComputePipeline, PipelineLayout, PipelineLayoutCreateInfo},
+        shader::ShaderStageFlags,
+        sync::Fence,
+        test_util::TestDevice,
+    };
+
+    use super::*;
+    use crate::{
+        graph::{FrameGraph, pass::FrameGraphSubmitInfo},
+        resource::{
+            ResourceManager,
+            texture::{TextureCreateInfo, TextureDimensions, TextureFormat},
+        },
+    };
+
+    /// Writes a horizontal gradient of red into a storage texture.
+    const GRADIENT_SHADER: &str = r#"
+        [[vk::binding(0)]] RWTexture2D<float4> output;
+
+        [numthreads(4, 1, 1)]
+        void main(uint3 id : SV_DispatchThreadID) {
+            output[id.xy] = float4(id.x / 3.0, 0.0, 0.0, 1.0);
+        }
+    "#;
+
+    fn blittable() -> FormatFeatures {
+        FormatFeatures::BLIT_SRC
+            | FormatFeatures::BLIT_DST
+            | FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR
+    }
+
+    #[test]
+    fn test_color_formats_can_be_converted() {
+        assert_eq!(
+            check_formats(
+                ImageFormat::Rgba8Unorm,
+                blittable(),
+                ImageFormat::Bgra8Srgb,
+                blittable(),
+                vk::Filter::LINEAR,
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_unsupported_formats_are_rejected() {
+        assert_eq!(
+            check_formats(
+                ImageFormat::D32Sfloat,
+                blittable(),
+                ImageFormat::Bgra8Srgb,
+                blittable(),
+                vk::Filter::NEAREST,
+            ),
+            Err(BlitError::DepthFormat(ImageFormat::D32Sfloat))
+        );
+        assert_eq!(
+            check_formats(
+                ImageFormat::Rgb8Unorm,
+                FormatFeatures::BLIT_DST,
+                ImageFormat::Bgra8Srgb,
+                blittable(),
+                vk::Filter::NEAREST,
+            ),
+            Err(BlitError::UnsupportedSource(ImageFormat::Rgb8Unorm))
+        );
+        assert_eq!(
+            check_formats(
+                ImageFormat::Rgba8Unorm,
+                FormatFeatures::BLIT_SRC,
+                ImageFormat::Bgra8Srgb,
+                blittable(),
+                vk::Filter::LINEAR,
+            ),
+            Err(BlitError::UnsupportedLinearFilter(ImageFormat::Rgba8Unorm))
+        );
+    }
+
+    #[test]
+    fn test_usage_without_transfer_is_rejected() {
+        let transfer = ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST;
+        assert_eq!(check_usage(transfer, transfer), Ok(()));
+        assert_eq!(
+            check_usage(ImageUsage::SAMPLED, transfer),
+            Err(BlitError::SourceNotTransferSrc)
+        );
+        assert_eq!(
+            check_usage(transfer, ImageUsage::COLOR_ATTACHMENT),
+            Err(BlitError::DestinationNotTransferDst)
+        );
+    }
+
+    #[test]
+    fn test_blit_region_scales_and_flips() {
+        let source = Extent3D {
+            width: 640,
+            height: 360,
+            depth: 1,
+        };
+        let destination = Extent3D {
+            width: 1920,
+            height: 1080,
+            depth: 1,
+        };
+
+        let region = blit_region(source, destination, false);
+        assert_eq!(region.src_offsets, [[0, 0, 0], [640, 360, 1]]);
+        assert_eq!(region.dst_offsets, [[0, 0, 0], [1920, 1080, 1]]);
+
+        let flipped = blit_region(source, destination, true);
+        assert_eq!(flipped.src_offsets, region.src_offsets);
+        assert_eq!(flipped.dst_offsets, [[0, 1080, 0], [1920, 0, 1]]);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver and the DXC library"]
+    fn test_storage_texture_is_blitted() {
+        let test_device = TestDevice::new();
+        let device = test_device.device.clone();
+        let mut resource_manager = ResourceManager::new(
+            device.clone(),
+            test_device.queue.clone(),
+            test_device.allocator.clone(),
+        );
+        let mut frame_graph = FrameGraph::new(
+            device.clone(),
+            test_device.queue.clone(),
+            test_device.allocator.clone(),
+        );
+        let command_pool = CommandPool::new(device.clone(), 0);
+        let execute = |frame_graph: &mut FrameGraph, resource_manager: &ResourceManager| {
+            let fence = Fence::unsignaled(device.clone());
+            frame_graph.execute(
+                resource_manager,
+                FrameGraphSubmitInfo {
+                    command_pool: command_pool.clone(),
+                    wait_semaphores: vec![],
+                    signal_semaphores: vec![],
+                    signal_fence: Some(fence.clone()),
+                },
+            );
+            assert!(fence.wait(u64::MAX));
+        };
+
+        let extent = Extent3D {
+            width: 4,
+            height: 1,
+            depth: 1,
+        };
+        let (source, init_task) = resource_manager
+            .create_storage_texture(TextureCreateInfo {
+                format: TextureFormat::Rgba8Unorm,
+                dimensions: TextureDimensions::D2,
+                extent,
+                generate_mipmaps: false,
+            })
+            .unwrap();
+        frame_graph.add_pass(init_task);
+        execute(&mut frame_graph, &resource_manager);
+
+        // Fill the texture, which stays in the general layout.
+        let set_layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: vec![DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: DescriptorType::StorageImage,
+                    count: 1,
+                    stages: ShaderStageFlags::COMPUTE,
+                }],
+            },
+        );
+        let pool = DescriptorPool::new(
+            device.clone(),
+            DescriptorPoolCreateInfo {
+                sizes: DescriptorPoolSizes {
+                    storage_image: 1,
+                    ..Default::default()
+                },
+                max_sets: 1,
+            },
+        );
+        let set = pool.allocate(&set_layout).unwrap();
+        DescriptorWrites::new()
+            .write_info(
+                set.vk_handle(),
+                0,
+                DescriptorType::StorageImage,
+                DescriptorResourceInfo::Image(
+                    vk::DescriptorImageInfo::default()
+                        .image_layout(ImageLayout::General.to_vk())
+                        .image_view(
+                            resource_manager
+                                .resolve_texture_view(source)
+                                .unwrap()
+                                .vk_handle(),
+                        ),
+                ),
+            )
+            .update(&device);
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutCreateInfo {
+                    set_layouts: vec![set_layout],
+                },
+            ),
+            test_device.compile_shader(GRADIENT_SHADER, "cs_6_0"),
+        )
+        .unwrap();
+        test_device.submit_and_wait(|encoder| {
+            encoder.bind_pipeline(PipelineBindPoint::Compute, pipeline.vk_handle());
+            encoder.bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                pipeline.layout().vk_handle(),
+                0,
+                [set.vk_handle()],
+                [],
+            );
+            encoder.dispatch(1, 1, 1);
+        });
+
+        let destination = Image::new(
+            device.clone(),
+            test_device.allocator.clone(),
+            ImageCreateInfo::new(ImageFormat::Rgba8Unorm, extent)
+                .usage(ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC),
+            MemoryLocation::GpuOnly,
+        );
+        frame_graph.add_pass(
+            BlitPass::new(source, destination.clone())
+                .final_layout(ImageLayout::TransferSrcOptimal)
+                .filter(vk::Filter::NEAREST),
+        );
+
+        // The blit writes the destination image, not the surface.
+        let dot = frame_graph.to_dot(&resource_manager);
+        assert!(dot.contains("W: image"), "{dot}");
+        assert!(!dot.contains("surface"), "{dot}");
+
+        execute(&mut frame_graph, &resource_manager);
+
+        let pixels = test_device.read_image(&destination, ImageLayout::TransferSrcOptimal);
+        assert_eq!(
+            pixels,
+            [
+                [0, 0, 0, 255],
+                [85, 0, 0, 255],
+                [170, 0, 0, 255],
+                [255, 0, 0, 255]
+            ]
+        );
+    }
+}
@@ -1,4 +1,6 @@
+pub mod blit;
 pub mod pass;
 pub mod tracker;
 
-pub use pass::{ExecuteContext, FrameGraph, PreparedFrameNode, FramePassResource, PrepareContext, FrameNode};
\ No newline at end of file
+pub use blit::{BlitError, BlitPass};
+pub use pass::{ExecuteContext, FrameGraph, PassTiming, PreparedFrameNode, FramePassResource, PrepareContext, FrameNode};
\ No newline at end of file
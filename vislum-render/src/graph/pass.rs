@@ -1,20 +1,46 @@
-use std::{borrow::Cow, fmt::Debug, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{Debug, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
+use ash::vk;
 use smallvec::SmallVec;
 use vislum_render_rhi::{
-    buffer::Buffer, command::{CommandEncoder, CommandPool}, device::Device, image::Image, memory::MemoryAllocator, queue::Queue, sync::{Fence, Semaphore}
+    VkHandle, buffer::Buffer, command::{CommandEncoder, CommandPool, PipelineStageFlags2}, device::Device, image::Image, memory::MemoryAllocator, query::QueryPool, queue::Queue, sync::{Fence, Semaphore}
 };
 
 use crate::{
+    buffer::PendingUploadsManager,
     resource::{ResourceManager, mesh::Mesh, pool::ResourceId, texture::Texture},
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FramePassResource {
     Texture(ResourceId<Texture>),
     Mesh(ResourceId<Mesh>),
+    /// An image which isn't managed by the resource manager.
+    Image(vk::Image),
     Surface,
 }
 
+impl FramePassResource {
+    /// Returns a short label for the resource, used in diagrams.
+    fn label(&self) -> String {
+        match self {
+            FramePassResource::Texture(id) => format!("texture {:?}", id),
+            FramePassResource::Mesh(id) => format!("mesh {:?}", id),
+            FramePassResource::Image(image) => format!("image {:?}", image),
+            FramePassResource::Surface => "surface".to_string(),
+        }
+    }
+}
+
 pub trait Resolve {
     type Resolved;
 
@@ -87,6 +113,34 @@ impl<'a> PrepareContext<'a> {
         self.resource_manager.resolve_texture_image(id)
     }
 
+    /// Declares that the pass writes to the surface the frame is presented
+    /// to.
+    pub fn write_surface(&mut self) {
+        self.write.push(FramePassResource::Surface);
+    }
+
+    /// Declares that the pass writes to an image which isn't a texture, e.g.
+    /// an offscreen render target.
+    pub fn write_image(&mut self, image: &Image) {
+        self.write.push(FramePassResource::Image(image.vk_handle()));
+    }
+
+    /// Returns true if the texture is a storage texture, which is kept in
+    /// [`ImageLayout::General`].
+    ///
+    /// [`ImageLayout::General`]: vislum_render_rhi::command::ImageLayout::General
+    pub fn is_storage_texture(&self, id: ResourceId<Texture>) -> bool {
+        self.resource_manager.is_storage_texture(id)
+    }
+
+    /// Returns true if the texture finished uploading its initial contents.
+    ///
+    /// Passes sampling the texture should skip it or substitute a placeholder
+    /// until it is ready.
+    pub fn is_texture_ready(&self, id: ResourceId<Texture>) -> bool {
+        self.resource_manager.is_texture_ready(id)
+    }
+
     pub fn read_mesh(&mut self, id: ResourceId<Mesh>) -> Option<&Mesh> {
         self.read.push(FramePassResource::Mesh(id));
         self.resource_manager.get_mesh(id)
@@ -97,6 +151,15 @@ impl<'a> PrepareContext<'a> {
 pub struct ExecuteContext {
     /// The command buffer to use for executing the pass.
     pub command_buffer: CommandEncoder,
+    completion_callbacks: Vec<Box<dyn FnOnce() + Send>>,
+}
+
+impl ExecuteContext {
+    /// Registers a callback invoked once the GPU finishes executing the
+    /// recorded frame.
+    pub fn on_complete(&mut self, callback: impl FnOnce() + Send + 'static) {
+        self.completion_callbacks.push(Box::new(callback));
+    }
 }
 
 type ExecuteFn = Box<dyn FnMut(&mut ExecuteContext) + 'static>;
@@ -148,15 +211,72 @@ impl Debug for PreparedFrameNode {
     }
 }
 
+/// The GPU time spent executing a pass.
+#[derive(Debug, Clone)]
+pub struct PassTiming {
+    pub name: Cow<'static, str>,
+    pub duration: Duration,
+}
+
+/// The maximum number of passes timed per frame.
+const MAX_TIMED_PASSES: u32 = 64;
+
+/// Records a begin and end timestamp around every pass of a frame.
+///
+/// Only one frame is timed at a time: frames executed while the previous
+/// timed frame is still in flight aren't timed.
+struct PassTimestamps {
+    pool: Arc<QueryPool>,
+    /// The names of the passes of the timed frame still in flight, and
+    /// whether the GPU finished executing it.
+    pending: Option<(Vec<Cow<'static, str>>, Arc<AtomicBool>)>,
+    timings: Vec<PassTiming>,
+}
+
+impl PassTimestamps {
+    /// Reads back the timestamps of the frame in flight if it completed.
+    ///
+    /// Returns true if the pool is free to time a new frame.
+    fn collect(&mut self) -> bool {
+        let Some((names, complete)) = &self.pending else {
+            return true;
+        };
+
+        if !complete.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let timestamps = self.pool.results(0..names.len() as u32 * 2);
+        self.timings = names
+            .iter()
+            .zip(timestamps.chunks_exact(2))
+            .map(|(name, timestamps)| PassTiming {
+                name: name.clone(),
+                duration: Duration::from_nanos(timestamps[1].saturating_sub(timestamps[0])),
+            })
+            .collect();
+        self.pending = None;
+
+        true
+    }
+}
+
 pub struct FrameGraph {
     device: Arc<Device>,
     queue: Arc<Queue>,
-    command_pool: Arc<CommandPool>,
     nodes: Vec<Box<dyn FrameNode + 'static>>,
     queue_family_index: u32,
+    timestamps: Option<PassTimestamps>,
+    pending_uploads: Arc<PendingUploadsManager>,
 }
 
 pub struct FrameGraphSubmitInfo {
+    /// The pool the commands of the frame are allocated from, e.g.
+    /// [`FrameSyncObjects::command_pool`]. It must not be reset before the
+    /// frame finishes executing.
+    ///
+    /// [`FrameSyncObjects::command_pool`]: crate::frame::FrameSyncObjects::command_pool
+    pub command_pool: Arc<CommandPool>,
     pub wait_semaphores: Vec<Arc<Semaphore>>,
     pub signal_semaphores: Vec<Arc<Semaphore>>,
     pub signal_fence: Option<Arc<Fence>>,
@@ -166,17 +286,55 @@ impl FrameGraph {
     pub fn new(device: Arc<Device>, queue: Arc<Queue>, _allocator: Arc<MemoryAllocator>) -> Self {
         // TODO: Get actual queue family index
         let queue_family_index = 0;
-        let command_pool = CommandPool::new(device.clone(), queue_family_index);
-        
+
         Self {
             device,
             queue,
-            command_pool,
             nodes: Default::default(),
             queue_family_index,
+            timestamps: None,
+            pending_uploads: PendingUploadsManager::new(),
         }
     }
 
+    /// Returns the uploads flushed at the start of every frame, before the
+    /// passes execute.
+    #[inline]
+    pub fn pending_uploads(&self) -> &Arc<PendingUploadsManager> {
+        &self.pending_uploads
+    }
+
+    /// Enables measuring the GPU time spent executing each pass.
+    ///
+    /// Fails if the queue doesn't support timestamp queries.
+    pub fn enable_pass_timings(&mut self) -> Result<(), vislum_render_rhi::Error> {
+        if self.timestamps.is_none() {
+            let pool = QueryPool::timestamps(
+                self.device.clone(),
+                self.queue_family_index,
+                MAX_TIMED_PASSES * 2,
+            )?;
+
+            self.timestamps = Some(PassTimestamps {
+                pool,
+                pending: None,
+                timings: Vec::new(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the GPU time spent executing each pass of the latest timed
+    /// frame.
+    ///
+    /// Empty until pass timings are enabled and a timed frame completes.
+    pub fn pass_timings(&self) -> &[PassTiming] {
+        self.timestamps
+            .as_ref()
+            .map_or(&[], |timestamps| &timestamps.timings)
+    }
+
     /// Adds a new pass to the frame graph.
     pub fn add_pass<F>(&mut self, node: F) 
     where 
@@ -186,44 +344,117 @@ impl FrameGraph {
     }
 
     pub fn execute(&mut self, resource_manager: &ResourceManager, submit_info: FrameGraphSubmitInfo) {
+        // Run the callbacks of the submits that completed since the last frame
+        self.queue.poll_callbacks();
+
         // Prepare the nodes
         let prepared: SmallVec<[PreparedFrameNode; 8]> = self.nodes
             .drain(..)
-            .map(|node| {
-                let mut prepare_context = PrepareContext::new(resource_manager);
-                let execute = node.prepare(&mut prepare_context);
-
-                PreparedFrameNode {
-                    name: node.name(),
-                    write: prepare_context.write,
-                    read: prepare_context.read,
-                    execute,
-                }
-            })
+            .map(|node| Self::prepare_node(&*node, resource_manager))
             .collect();
 
         // Allocate and begin recording the command buffer
         use vislum_render_rhi::command::{CommandBufferLevel, CommandBufferUsageFlags};
-        let mut raw_command_buffer = self.command_pool.allocate(CommandBufferLevel::PRIMARY);
+        let mut raw_command_buffer = submit_info.command_pool.allocate(CommandBufferLevel::PRIMARY);
         raw_command_buffer.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
         
         let auto_command_buffer = CommandEncoder::new(raw_command_buffer);
 
         // Prepare the execute context
-        let mut execute_context = ExecuteContext { command_buffer: auto_command_buffer };
+        let mut execute_context = ExecuteContext {
+            command_buffer: auto_command_buffer,
+            completion_callbacks: Vec::new(),
+        };
+
+        // Upload the buffers modified since the last frame
+        self.pending_uploads.flush(&mut execute_context.command_buffer);
+
+        // Time the first passes, if the previous timed frame completed
+        let mut timed_passes = 0;
+        if let Some(timestamps) = &mut self.timestamps
+            && timestamps.collect()
+        {
+            timed_passes = (prepared.len() as u32).min(MAX_TIMED_PASSES);
+            execute_context
+                .command_buffer
+                .reset_query_pool(&timestamps.pool, 0..timed_passes * 2);
+        }
+        let mut timed_names = Vec::with_capacity(timed_passes as usize);
 
         // Execute the prepared nodes
-        for mut node in prepared {
+        for (index, mut node) in prepared.into_iter().enumerate() {
+            let index = index as u32;
+            let timestamps = self.timestamps.as_ref().filter(|_| index < timed_passes);
+
+            if let Some(timestamps) = timestamps {
+                execute_context.command_buffer.write_timestamp(
+                    PipelineStageFlags2::TOP_OF_PIPE,
+                    &timestamps.pool,
+                    index * 2,
+                );
+            }
+
             node.execute(&mut execute_context);
             // std::mem::forget(node);
+
+            if let Some(timestamps) = timestamps {
+                execute_context.command_buffer.write_timestamp(
+                    PipelineStageFlags2::BOTTOM_OF_PIPE,
+                    &timestamps.pool,
+                    index * 2 + 1,
+                );
+                timed_names.push(node.name.clone());
+            }
+        }
+
+        if let Some(timestamps) = self.timestamps.as_mut().filter(|_| timed_passes > 0) {
+            let complete = Arc::new(AtomicBool::new(false));
+            timestamps.pending = Some((timed_names, complete.clone()));
+            execute_context.on_complete(move || complete.store(true, Ordering::Release));
         }
 
         // Get the command buffer back and end recording
-        let mut auto_command_buffer = execute_context.command_buffer;
+        let ExecuteContext {
+            command_buffer: mut auto_command_buffer,
+            completion_callbacks,
+        } = execute_context;
         auto_command_buffer.command_buffer_mut().end();
         let raw_command_buffer = auto_command_buffer.into_command_buffer();
 
         self.submit(raw_command_buffer, submit_info);
+
+        if !completion_callbacks.is_empty() {
+            self.queue.submit_callback(move || {
+                for callback in completion_callbacks {
+                    callback();
+                }
+            });
+        }
+    }
+
+    /// Exports the pending passes and their dependencies as a Graphviz DOT diagram.
+    ///
+    /// The passes are prepared to collect their resources, but not executed.
+    pub fn to_dot(&self, resource_manager: &ResourceManager) -> String {
+        let prepared = self
+            .nodes
+            .iter()
+            .map(|node| Self::prepare_node(&**node, resource_manager))
+            .collect::<Vec<_>>();
+
+        write_dot(&prepared)
+    }
+
+    fn prepare_node(node: &dyn FrameNode, resource_manager: &ResourceManager) -> PreparedFrameNode {
+        let mut prepare_context = PrepareContext::new(resource_manager);
+        let execute = node.prepare(&mut prepare_context);
+
+        PreparedFrameNode {
+            name: node.name(),
+            write: prepare_context.write,
+            read: prepare_context.read,
+            execute,
+        }
     }
 
     fn submit(&self, command_buffer: vislum_render_rhi::command::RawCommandBuffer, submit_info: FrameGraphSubmitInfo) {
@@ -235,3 +466,124 @@ impl FrameGraph {
         );
     }
 }
+
+/// Writes the passes as a DOT diagram, inferring the dependencies between
+/// passes from the order of their resource accesses.
+///
+/// Edges are annotated with the resource and the hazard the barrier between
+/// both passes protects against.
+fn write_dot(nodes: &[PreparedFrameNode]) -> String {
+    let mut output = String::new();
+    writeln!(output, "digraph FrameGraph {{").unwrap();
+    writeln!(output, "    node [shape=box];").unwrap();
+
+    for (index, node) in nodes.iter().enumerate() {
+        let resources = node
+            .read()
+            .map(|resource| format!("R: {}", resource.label()))
+            .chain(node.write().map(|resource| format!("W: {}", resource.label())))
+            .collect::<Vec<_>>();
+
+        let mut label = node.name().to_string();
+        for resource in resources {
+            label.push_str("\\n");
+            label.push_str(&resource);
+        }
+
+        writeln!(output, "    pass{} [label=\"{}\"];", index, escape_dot(&label)).unwrap();
+    }
+
+    let mut last_writer = HashMap::<FramePassResource, usize>::new();
+    let mut readers = HashMap::<FramePassResource, Vec<usize>>::new();
+    let mut edge = |from: usize, to: usize, resource: &FramePassResource, hazard: &str| {
+        writeln!(
+            output,
+            "    pass{} -> pass{} [label=\"{} ({})\"];",
+            from,
+            to,
+            escape_dot(&resource.label()),
+            hazard,
+        )
+        .unwrap();
+    };
+
+    for (index, node) in nodes.iter().enumerate() {
+        for resource in node.read() {
+            if let Some(&writer) = last_writer.get(resource) {
+                edge(writer, index, resource, "read after write");
+            }
+
+            readers.entry(*resource).or_default().push(index);
+        }
+
+        for resource in node.write() {
+            if let Some(&writer) = last_writer.get(resource) {
+                edge(writer, index, resource, "write after write");
+            }
+
+            for reader in readers.remove(resource).unwrap_or_default() {
+                if reader != index {
+                    edge(reader, index, resource, "write after read");
+                }
+            }
+
+            last_writer.insert(*resource, index);
+        }
+    }
+
+    writeln!(output, "}}").unwrap();
+    output
+}
+
+/// Escapes a string to be used inside a quoted DOT label.
+fn escape_dot(label: &str) -> String {
+    label.replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(
+        name: &'static str,
+        read: impl IntoIterator<Item = FramePassResource>,
+        write: impl IntoIterator<Item = FramePassResource>,
+    ) -> PreparedFrameNode {
+        PreparedFrameNode {
+            name: name.into(),
+            execute: Box::new(|_| {}),
+            read: read.into_iter().collect(),
+            write: write.into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_dot_contains_passes_and_dependencies() {
+        let nodes = [
+            node("render_scene", [], [FramePassResource::Surface]),
+            node("overlay", [FramePassResource::Surface], []),
+            node("unrelated", [], []),
+        ];
+
+        let dot = write_dot(&nodes);
+
+        assert!(dot.starts_with("digraph FrameGraph {"));
+        assert!(dot.contains("pass0 [label=\"render_scene\\nW: surface\"];"));
+        assert!(dot.contains("pass1 [label=\"overlay\\nR: surface\"];"));
+        assert!(dot.contains("pass2 [label=\"unrelated\"];"));
+        assert!(dot.contains("pass0 -> pass1 [label=\"surface (read after write)\"];"));
+        assert!(!dot.contains("-> pass2"));
+    }
+
+    #[test]
+    fn test_dot_write_after_read() {
+        let nodes = [
+            node("sample", [FramePassResource::Surface], []),
+            node("clear", [], [FramePassResource::Surface]),
+        ];
+
+        let dot = write_dot(&nodes);
+
+        assert!(dot.contains("pass0 -> pass1 [label=\"surface (write after read)\"];"));
+    }
+}
@@ -1,5 +1,10 @@
+pub mod buffer;
 pub mod context;
+pub mod descriptor;
+pub mod frame;
 pub mod graph;
+pub mod pipeline;
 pub mod resource;
-// pub mod scene;
+pub mod scene;
+pub mod screen;
 // pub mod renderer;
\ No newline at end of file
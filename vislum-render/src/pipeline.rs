@@ -0,0 +1,192 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ash::vk;
+use smallvec::SmallVec;
+use vislum_render_rhi::{
+    VkHandle,
+    descriptor::DescriptorSetLayout,
+    device::Device,
+    pipeline::{
+        ColorAttachmentState, CullMode, DepthAttachmentState, FrontFace, GraphicsPipeline,
+        PipelineLayout, PipelineLayoutCreateInfo, PrimitiveTopology,
+    },
+    shader::ShaderStage,
+};
+
+use crate::resource::vertex::VertexLayout;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("graphics pipelines need at least one color or depth attachment")]
+    NoAttachments,
+    #[error("failed to create the pipeline: {0}")]
+    Creation(#[from] vislum_render_rhi::Error),
+}
+
+/// Describes a graphics pipeline rendering meshes with dynamic rendering.
+pub struct GraphicsPipelineDescriptor {
+    pub vertex_shader: ShaderStage,
+    pub fragment_shader: Option<ShaderStage>,
+    /// The descriptor set layouts of the pipeline layout, by set index.
+    pub set_layouts: Vec<Arc<DescriptorSetLayout>>,
+    /// The layout of the vertex buffer bound to binding 0, or `None` if the
+    /// vertex shader doesn't read vertex attributes.
+    pub vertex_layout: Option<VertexLayout>,
+    pub topology: PrimitiveTopology,
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    /// The color attachments, in the order they are bound when rendering.
+    pub color_attachments: Vec<ColorAttachmentState>,
+    pub depth_attachment: Option<DepthAttachmentState>,
+}
+
+/// Creates the graphics pipelines of the renderer.
+///
+/// Pipeline layouts are shared between the pipelines using the same
+/// descriptor set layouts, so their descriptor sets stay compatible when
+/// switching pipelines.
+pub struct PipelineManager {
+    device: Arc<Device>,
+    layouts: HashMap<SmallVec<[vk::DescriptorSetLayout; 4]>, Arc<PipelineLayout>>,
+}
+
+impl PipelineManager {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            device,
+            layouts: HashMap::new(),
+        }
+    }
+
+    /// Returns the pipeline layout with the given descriptor set layouts,
+    /// creating it on the first request.
+    pub fn pipeline_layout(
+        &mut self,
+        set_layouts: &[Arc<DescriptorSetLayout>],
+    ) -> Arc<PipelineLayout> {
+        // The cached layout keeps its set layouts alive, so their handles
+        // can't be reused while the entry exists.
+        let key = set_layouts
+            .iter()
+            .map(|layout| layout.vk_handle())
+            .collect::<SmallVec<_>>();
+
+        self.layouts
+            .entry(key)
+            .or_insert_with(|| {
+                PipelineLayout::new(
+                    self.device.clone(),
+                    PipelineLayoutCreateInfo {
+                        set_layouts: set_layouts.to_vec(),
+                    },
+                )
+            })
+            .clone()
+    }
+
+    /// Creates a graphics pipeline, with the vertex input derived from the
+    /// vertex layout of the meshes it renders.
+    pub fn create_graphics_pipeline(
+        &mut self,
+        descriptor: GraphicsPipelineDescriptor,
+    ) -> Result<Arc<GraphicsPipeline>, PipelineError> {
+        if descriptor.color_attachments.is_empty() && descriptor.depth_attachment.is_none() {
+            return Err(PipelineError::NoAttachments);
+        }
+
+        let layout = self.pipeline_layout(&descriptor.set_layouts);
+
+        let mut builder = GraphicsPipeline::builder(layout, descriptor.vertex_shader)
+            .topology(descriptor.topology)
+            .cull_mode(descriptor.cull_mode)
+            .front_face(descriptor.front_face);
+
+        if let Some(fragment_shader) = descriptor.fragment_shader {
+            builder = builder.fragment_shader(fragment_shader);
+        }
+
+        if let Some(vertex_layout) = &descriptor.vertex_layout {
+            builder = builder.vertex_buffer(vertex_layout.buffer_layout(0));
+        }
+
+        for attachment in descriptor.color_attachments {
+            builder = builder.color_attachment(attachment.format, attachment.blend);
+        }
+
+        if let Some(depth_attachment) = descriptor.depth_attachment {
+            builder = builder.depth_attachment(depth_attachment);
+        }
+
+        Ok(builder.build(self.device.clone())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        descriptor::{DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType},
+        image::ImageFormat,
+        shader::ShaderStageFlags,
+        test_util::TestDevice,
+    };
+
+    use super::*;
+    use crate::resource::{mesh::Vertex, vertex::MeshVertex};
+
+    const VERTEX_SHADER: &str = r#"
+        float4 main(float3 position : POSITION) : SV_Position {
+            return float4(position, 1.0);
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &str = r#"
+        float4 main() : SV_Target {
+            return float4(1.0, 0.0, 1.0, 1.0);
+        }
+    "#;
+
+    #[test]
+    #[ignore = "requires a Vulkan driver and the DXC library"]
+    fn test_create_graphics_pipeline_from_two_shaders() {
+        let test_device = TestDevice::new();
+        let device = test_device.device.clone();
+        let vertex_shader = test_device.compile_shader(VERTEX_SHADER, "vs_6_0");
+        let fragment_shader = test_device.compile_shader(FRAGMENT_SHADER, "ps_6_0");
+
+        let set_layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: vec![DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: DescriptorType::SampledImage,
+                    count: 1,
+                    stages: ShaderStageFlags::FRAGMENT,
+                }],
+            },
+        );
+
+        let mut manager = PipelineManager::new(device);
+        let pipeline = manager
+            .create_graphics_pipeline(GraphicsPipelineDescriptor {
+                vertex_shader,
+                fragment_shader: Some(fragment_shader),
+                set_layouts: vec![set_layout.clone()],
+                vertex_layout: Some(Vertex::layout()),
+                topology: PrimitiveTopology::TriangleList,
+                cull_mode: CullMode::None,
+                front_face: FrontFace::CounterClockwise,
+                color_attachments: vec![ColorAttachmentState {
+                    format: ImageFormat::Rgba8Unorm,
+                    blend: None,
+                }],
+                depth_attachment: None,
+            })
+            .unwrap();
+
+        // The layout is shared with the pipelines using the same set layouts.
+        assert!(Arc::ptr_eq(
+            pipeline.layout(),
+            &manager.pipeline_layout(&[set_layout])
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use vislum_render_rhi::image::Extent3D;
+
+use crate::resource::{
+    mesh::{Mesh, Vertex},
+    pool::ResourceId,
+    texture::{Texture, TextureCreateInfo, TextureDimensions, TextureFormat},
+};
+
+/// The built-in resources substituted for missing or not yet uploaded ones.
+#[derive(Debug, Clone, Copy)]
+pub struct FallbackResources {
+    /// A 2×2 magenta and black checkerboard, standing in for missing textures.
+    pub missing_texture: ResourceId<Texture>,
+    /// A 1×1 white texture.
+    pub white_texture: ResourceId<Texture>,
+    /// A unit quad on the XY plane, facing +Z.
+    pub quad_mesh: ResourceId<Mesh>,
+}
+
+const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+pub(crate) fn missing_texture_info() -> TextureCreateInfo {
+    TextureCreateInfo {
+        format: TextureFormat::Rgba8Unorm,
+        dimensions: TextureDimensions::D2,
+        extent: Extent3D {
+            width: 2,
+            height: 2,
+            depth: 1,
+        },
+        // The missing texture is kept sharp to be easy to spot.
+        generate_mipmaps: false,
+    }
+}
+
+pub(crate) fn missing_texture_data() -> Vec<u8> {
+    [MAGENTA, BLACK, BLACK, MAGENTA].concat()
+}
+
+pub(crate) fn white_texture_info() -> TextureCreateInfo {
+    TextureCreateInfo {
+        format: TextureFormat::Rgba8Unorm,
+        dimensions: TextureDimensions::D2,
+        extent: Extent3D {
+            width: 1,
+            height: 1,
+            depth: 1,
+        },
+        generate_mipmaps: false,
+    }
+}
+
+pub(crate) fn white_texture_data() -> Vec<u8> {
+    vec![255; 4]
+}
+
+pub(crate) fn quad_vertices() -> [Vertex; 4] {
+    let normal = [0.0, 0.0, 1.0];
+
+    [
+        Vertex {
+            position: [-0.5, -0.5, 0.0],
+            normal,
+            uv: [0.0, 1.0],
+        },
+        Vertex {
+            position: [0.5, -0.5, 0.0],
+            normal,
+            uv: [1.0, 1.0],
+        },
+        Vertex {
+            position: [0.5, 0.5, 0.0],
+            normal,
+            uv: [1.0, 0.0],
+        },
+        Vertex {
+            position: [-0.5, 0.5, 0.0],
+            normal,
+            uv: [0.0, 0.0],
+        },
+    ]
+}
+
+pub(crate) fn quad_indices() -> [u16; 6] {
+    [0, 1, 2, 2, 3, 0]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use vislum_render_rhi::test_util::TestDevice;
+
+    use super::*;
+    use crate::resource::ResourceManager;
+
+    #[test]
+    fn test_missing_texture_is_a_checkerboard() {
+        let info = missing_texture_info();
+        let data = missing_texture_data();
+        assert_eq!(
+            data.len(),
+            (info.extent.width * info.extent.height * 4) as usize
+        );
+
+        let texels = data.chunks_exact(4).collect::<Vec<_>>();
+        assert_eq!(texels, [&MAGENTA, &BLACK, &BLACK, &MAGENTA]);
+    }
+
+    #[test]
+    fn test_quad_indices_are_in_bounds() {
+        let vertices = quad_vertices();
+        assert!(
+            quad_indices()
+                .iter()
+                .all(|&index| (index as usize) < vertices.len())
+        );
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_dangling_ids_resolve_to_fallbacks() {
+        let TestDevice {
+            device,
+            queue,
+            allocator,
+        } = TestDevice::new();
+        let mut manager = ResourceManager::new(device, queue, allocator);
+        manager.create_fallbacks();
+        manager.flush_uploads();
+        let fallbacks = *manager.fallbacks().unwrap();
+
+        let texture = ResourceId::<Texture>::dangling();
+        let mesh = ResourceId::<Mesh>::dangling();
+
+        // Fallbacks are opt-in.
+        assert!(manager.resolve_texture_view(texture).is_none());
+        assert!(manager.resolve_texture_image(texture).is_none());
+        assert!(manager.get_mesh(mesh).is_none());
+
+        manager.set_use_fallbacks(true);
+        let missing_view = manager
+            .resolve_texture_view(fallbacks.missing_texture)
+            .unwrap();
+        let missing_image = manager
+            .resolve_texture_image(fallbacks.missing_texture)
+            .unwrap();
+        assert!(Arc::ptr_eq(
+            &manager.resolve_texture_view(texture).unwrap(),
+            &missing_view
+        ));
+        assert!(Arc::ptr_eq(
+            &manager.resolve_texture_image(texture).unwrap(),
+            &missing_image
+        ));
+        assert!(std::ptr::eq(
+            manager.get_mesh(mesh).unwrap(),
+            manager.get_mesh(fallbacks.quad_mesh).unwrap()
+        ));
+    }
+}
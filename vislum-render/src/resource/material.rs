@@ -0,0 +1,651 @@
+use std::sync::Arc;
+
+use vislum_render_rhi::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    device::Device,
+    memory::{MemoryAllocator, MemoryLocation},
+};
+use vislum_shader::material::{MaterialLayout, MaterialProperty, MaterialPropertyType};
+
+use crate::resource::{
+    pool::{ResourceId, ResourcePool},
+    texture::Texture,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum MaterialError {
+    #[error("unknown material definition")]
+    UnknownDefinition,
+    #[error("unknown material instance")]
+    UnknownInstance,
+    #[error("the material has no property named {0:?}")]
+    UnknownProperty(String),
+    #[error("the value doesn't match the type of property {0:?}")]
+    PropertyTypeMismatch(String),
+}
+
+/// The binding of the uniform buffer in the descriptor set of a material.
+/// Texture properties are bound after it, in declaration order.
+pub const MATERIAL_UNIFORM_BINDING: u32 = 0;
+
+/// A property declared by a material definition.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MaterialPropertyDefinition {
+    Float(String),
+    Vec2(String),
+    Vec3(String),
+    Vec4(String),
+    /// A linear RGBA color, laid out as a `float4`.
+    Color(String),
+    /// A boolean, laid out as a 32-bit `uint` as HLSL `bool`s are.
+    Bool(String),
+    /// A texture, bound to the descriptor set of the material instead of
+    /// stored in its uniform data.
+    Texture(String),
+}
+
+impl MaterialPropertyDefinition {
+    /// Returns the name of the property.
+    pub fn name(&self) -> &str {
+        match self {
+            MaterialPropertyDefinition::Float(name)
+            | MaterialPropertyDefinition::Vec2(name)
+            | MaterialPropertyDefinition::Vec3(name)
+            | MaterialPropertyDefinition::Vec4(name)
+            | MaterialPropertyDefinition::Color(name)
+            | MaterialPropertyDefinition::Bool(name)
+            | MaterialPropertyDefinition::Texture(name) => name,
+        }
+    }
+
+    /// Returns the size and alignment of the property in the uniform data,
+    /// in bytes, or `None` for textures.
+    fn size_and_alignment(&self) -> Option<(usize, usize)> {
+        match self {
+            MaterialPropertyDefinition::Float(_) | MaterialPropertyDefinition::Bool(_) => {
+                Some((4, 4))
+            }
+            MaterialPropertyDefinition::Vec2(_) => Some((8, 8)),
+            // Three component vectors are aligned like four component ones.
+            MaterialPropertyDefinition::Vec3(_) => Some((12, 16)),
+            MaterialPropertyDefinition::Vec4(_) | MaterialPropertyDefinition::Color(_) => {
+                Some((16, 16))
+            }
+            MaterialPropertyDefinition::Texture(_) => None,
+        }
+    }
+
+    /// Returns true if the value can be assigned to the property.
+    fn accepts(&self, value: &MaterialValue) -> bool {
+        use MaterialPropertyDefinition as Definition;
+        use MaterialValue as Value;
+
+        matches!(
+            (self, value),
+            (Definition::Float(_), Value::Float(_))
+                | (Definition::Vec2(_), Value::Vec2(_))
+                | (Definition::Vec3(_), Value::Vec3(_))
+                | (Definition::Vec4(_), Value::Vec4(_))
+                | (Definition::Color(_), Value::Color(_))
+                | (Definition::Bool(_), Value::Bool(_))
+                | (Definition::Texture(_), Value::Texture(_))
+        )
+    }
+}
+
+/// The value of a material property.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterialValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Color([f32; 4]),
+    Bool(bool),
+    Texture(ResourceId<Texture>),
+}
+
+impl MaterialValue {
+    /// Returns the bytes of the value in the uniform data, or `None` for
+    /// textures.
+    fn to_bytes(self) -> Option<Vec<u8>> {
+        let bytes = match self {
+            MaterialValue::Float(value) => bytemuck::bytes_of(&value).to_vec(),
+            MaterialValue::Vec2(value) => bytemuck::bytes_of(&value).to_vec(),
+            MaterialValue::Vec3(value) => bytemuck::bytes_of(&value).to_vec(),
+            MaterialValue::Vec4(value) | MaterialValue::Color(value) => {
+                bytemuck::bytes_of(&value).to_vec()
+            }
+            MaterialValue::Bool(value) => bytemuck::bytes_of(&(value as u32)).to_vec(),
+            MaterialValue::Texture(_) => return None,
+        };
+
+        Some(bytes)
+    }
+}
+
+/// Where the value of a material property is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyLocation {
+    /// In the uniform data, at the given offset in bytes.
+    Uniform { offset: usize, size: usize },
+    /// In a texture bound to the given binding of the material's descriptor
+    /// set.
+    Texture { binding: u32 },
+}
+
+/// The layout of the properties of a material definition.
+///
+/// Properties are laid out in declaration order following the std140 rules,
+/// which match the std430 ones for scalars and vectors: each property is
+/// aligned to its size, except three component vectors, which are aligned
+/// like four component ones. Shaders must declare the properties of their
+/// material block in the same order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformLayout {
+    locations: Vec<(String, PropertyLocation)>,
+    size: usize,
+}
+
+impl UniformLayout {
+    /// Computes the layout of the given properties.
+    pub fn new<'a>(properties: impl IntoIterator<Item = &'a MaterialPropertyDefinition>) -> Self {
+        let mut locations = Vec::new();
+        let mut size = 0usize;
+        let mut binding = MATERIAL_UNIFORM_BINDING + 1;
+
+        for property in properties {
+            let location = match property.size_and_alignment() {
+                Some((property_size, alignment)) => {
+                    let offset = size.next_multiple_of(alignment);
+                    size = offset + property_size;
+                    PropertyLocation::Uniform {
+                        offset,
+                        size: property_size,
+                    }
+                }
+                None => {
+                    binding += 1;
+                    PropertyLocation::Texture {
+                        binding: binding - 1,
+                    }
+                }
+            };
+
+            locations.push((property.name().to_string(), location));
+        }
+
+        Self { locations, size }
+    }
+
+    /// Returns where the value of a property is stored.
+    pub fn location(&self, name: &str) -> Option<PropertyLocation> {
+        self.locations
+            .iter()
+            .find(|(property, _)| property == name)
+            .map(|(_, location)| *location)
+    }
+
+    /// Returns the size of the uniform data, in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the names and bindings of the texture properties.
+    pub fn texture_bindings(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.locations
+            .iter()
+            .filter_map(|(name, location)| match location {
+                PropertyLocation::Texture { binding } => Some((name.as_str(), *binding)),
+                PropertyLocation::Uniform { .. } => None,
+            })
+    }
+}
+
+/// Describes the properties of a material.
+///
+/// Definitions are shared by all of their instances, which only differ in
+/// their property values.
+#[derive(Debug, Clone)]
+pub struct MaterialDefinition {
+    name: String,
+    properties: Vec<MaterialPropertyDefinition>,
+    layout: UniformLayout,
+}
+
+impl MaterialDefinition {
+    pub fn new(
+        name: impl Into<String>,
+        properties: impl IntoIterator<Item = MaterialPropertyDefinition>,
+    ) -> Self {
+        let properties = properties.into_iter().collect::<Vec<_>>();
+
+        Self {
+            name: name.into(),
+            layout: UniformLayout::new(&properties),
+            properties,
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn properties(&self) -> &[MaterialPropertyDefinition] {
+        &self.properties
+    }
+
+    /// Returns the layout of the properties.
+    #[inline]
+    pub fn uniform_layout(&self) -> &UniformLayout {
+        &self.layout
+    }
+
+    /// Returns the offset of the property within the uniform data, or `None`
+    /// if there is no such property or it's a texture.
+    pub fn property_offset(&self, name: &str) -> Option<usize> {
+        match self.layout.location(name)? {
+            PropertyLocation::Uniform { offset, .. } => Some(offset),
+            PropertyLocation::Texture { .. } => None,
+        }
+    }
+
+    /// Returns the size of the uniform data of an instance, in bytes.
+    #[inline]
+    pub fn uniform_size(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Returns the layout shaders declare the uniform data with, bound to
+    /// descriptor set `set`.
+    ///
+    /// Properties keep the offsets of the [`UniformLayout`], so the generated
+    /// HLSL block reads the data as written by the [`MaterialManager`]. The
+    /// name of the definition is used as the name of the HLSL struct.
+    pub fn shader_layout(&self, set: u32) -> MaterialLayout {
+        let properties =
+            self.properties
+                .iter()
+                .filter_map(|property| {
+                    let ty = match property {
+                        MaterialPropertyDefinition::Float(_) => MaterialPropertyType::Float,
+                        MaterialPropertyDefinition::Vec2(_) => MaterialPropertyType::Float2,
+                        MaterialPropertyDefinition::Vec3(_) => MaterialPropertyType::Float3,
+                        MaterialPropertyDefinition::Vec4(_)
+                        | MaterialPropertyDefinition::Color(_) => MaterialPropertyType::Float4,
+                        MaterialPropertyDefinition::Bool(_) => MaterialPropertyType::Uint,
+                        MaterialPropertyDefinition::Texture(_) => return None,
+                    };
+
+                    Some(MaterialProperty {
+                        name: property.name().to_string(),
+                        ty,
+                        offset: self.property_offset(property.name())? as u32,
+                    })
+                })
+                .collect();
+
+        MaterialLayout {
+            name: self.name.clone(),
+            set,
+            binding: MATERIAL_UNIFORM_BINDING,
+            properties,
+        }
+    }
+}
+
+/// An instance of a material definition with its own property values.
+#[derive(Debug)]
+pub struct MaterialInstance {
+    definition: ResourceId<MaterialDefinition>,
+    offset: usize,
+    size: usize,
+    /// The textures bound to the texture properties, by binding.
+    textures: Vec<(u32, Option<ResourceId<Texture>>)>,
+}
+
+impl MaterialInstance {
+    #[inline]
+    pub fn definition(&self) -> ResourceId<MaterialDefinition> {
+        self.definition
+    }
+
+    /// Returns the textures bound to the texture properties, by binding, or
+    /// `None` for the properties which haven't been set.
+    #[inline]
+    pub fn textures(&self) -> &[(u32, Option<ResourceId<Texture>>)] {
+        &self.textures
+    }
+}
+
+pub type MaterialInstanceId = ResourceId<MaterialInstance>;
+
+/// Manages material definitions and their instances.
+///
+/// The property values of every instance are packed in a single uniform
+/// buffer, each instance in its own slot. Draws bind the buffer once per
+/// definition and select the instance with [`MaterialManager::dynamic_offset`].
+pub struct MaterialManager {
+    definitions: ResourcePool<MaterialDefinition>,
+    instances: ResourcePool<MaterialInstance>,
+    slot_alignment: usize,
+    uniform_data: Vec<u8>,
+}
+
+impl MaterialManager {
+    /// Creates a new material manager.
+    ///
+    /// `slot_alignment` is the alignment of each instance's slot, which must
+    /// satisfy the device's minimum uniform buffer offset alignment.
+    pub fn new(slot_alignment: u64) -> Self {
+        assert!(
+            slot_alignment.is_power_of_two(),
+            "the slot alignment must be a power of two"
+        );
+
+        Self {
+            definitions: Default::default(),
+            instances: Default::default(),
+            slot_alignment: slot_alignment as usize,
+            uniform_data: Vec::new(),
+        }
+    }
+
+    /// Registers a material definition.
+    pub fn create_definition(
+        &mut self,
+        definition: MaterialDefinition,
+    ) -> ResourceId<MaterialDefinition> {
+        self.definitions.insert(definition)
+    }
+
+    pub fn get_definition(
+        &self,
+        id: ResourceId<MaterialDefinition>,
+    ) -> Option<&MaterialDefinition> {
+        self.definitions.get(id)
+    }
+
+    /// Creates an instance of a definition, with all of its properties zeroed
+    /// and no texture bound.
+    pub fn instantiate(
+        &mut self,
+        definition: ResourceId<MaterialDefinition>,
+    ) -> Result<MaterialInstanceId, MaterialError> {
+        let layout = self
+            .definitions
+            .get(definition)
+            .ok_or(MaterialError::UnknownDefinition)?
+            .uniform_layout();
+        let size = layout.size();
+        let textures = layout
+            .texture_bindings()
+            .map(|(_, binding)| (binding, None))
+            .collect();
+
+        let offset = self
+            .uniform_data
+            .len()
+            .next_multiple_of(self.slot_alignment);
+        self.uniform_data.resize(offset + size, 0);
+
+        Ok(self.instances.insert(MaterialInstance {
+            definition,
+            offset,
+            size,
+            textures,
+        }))
+    }
+
+    pub fn get_instance(&self, id: MaterialInstanceId) -> Option<&MaterialInstance> {
+        self.instances.get(id)
+    }
+
+    /// Sets the value of a property of an instance.
+    ///
+    /// Values are written to the instance's uniform data, except textures,
+    /// which are bound to the property's binding.
+    pub fn set_instance_property(
+        &mut self,
+        id: MaterialInstanceId,
+        name: &str,
+        value: MaterialValue,
+    ) -> Result<(), MaterialError> {
+        let instance = self
+            .instances
+            .get_mut(id)
+            .ok_or(MaterialError::UnknownInstance)?;
+        let definition = self
+            .definitions
+            .get(instance.definition)
+            .ok_or(MaterialError::UnknownDefinition)?;
+
+        let property = definition
+            .properties()
+            .iter()
+            .find(|property| property.name() == name)
+            .ok_or_else(|| MaterialError::UnknownProperty(name.to_string()))?;
+        if !property.accepts(&value) {
+            return Err(MaterialError::PropertyTypeMismatch(name.to_string()));
+        }
+
+        match (definition.uniform_layout().location(name), value) {
+            (Some(PropertyLocation::Uniform { offset, .. }), value) => {
+                let bytes = value.to_bytes().expect("uniform values have bytes");
+                let start = instance.offset + offset;
+                self.uniform_data[start..start + bytes.len()].copy_from_slice(&bytes);
+            }
+            (Some(PropertyLocation::Texture { binding }), MaterialValue::Texture(texture)) => {
+                for (texture_binding, bound) in &mut instance.textures {
+                    if *texture_binding == binding {
+                        *bound = Some(texture);
+                    }
+                }
+            }
+            _ => unreachable!("the property accepts the value"),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the uniform data of an instance.
+    pub fn instance_data(&self, id: MaterialInstanceId) -> Option<&[u8]> {
+        let instance = self.instances.get(id)?;
+
+        Some(&self.uniform_data[instance.offset..instance.offset + instance.size])
+    }
+
+    /// Returns the dynamic offset to bind the instance's slot of the uniform
+    /// buffer with.
+    pub fn dynamic_offset(&self, id: MaterialInstanceId) -> Option<u32> {
+        self.instances
+            .get(id)
+            .map(|instance| instance.offset as u32)
+    }
+
+    /// Returns the packed uniform data of all the instances.
+    #[inline]
+    pub fn uniform_data(&self) -> &[u8] {
+        &self.uniform_data
+    }
+
+    /// Creates a host-visible uniform buffer holding the uniform data of all
+    /// the instances.
+    pub fn create_uniform_buffer(
+        &self,
+        device: Arc<Device>,
+        allocator: Arc<MemoryAllocator>,
+    ) -> Arc<Buffer> {
+        // Empty buffers are invalid, so always allocate at least one slot.
+        let size = self.uniform_data.len().max(self.slot_alignment);
+
+        let buffer = Buffer::new(
+            device,
+            allocator,
+            BufferCreateInfo {
+                size: size as u64,
+                usage: BufferUsage::UNIFORM_BUFFER,
+            },
+            MemoryLocation::CpuToGpu,
+        );
+
+        unsafe {
+            buffer.write(&self.uniform_data);
+        }
+
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instances_have_distinct_uniform_data() {
+        let mut manager = MaterialManager::new(256);
+        let definition = manager.create_definition(MaterialDefinition::new(
+            "unlit",
+            [
+                MaterialPropertyDefinition::Float("opacity".into()),
+                MaterialPropertyDefinition::Float("roughness".into()),
+            ],
+        ));
+
+        let first = manager.instantiate(definition).unwrap();
+        let second = manager.instantiate(definition).unwrap();
+
+        manager
+            .set_instance_property(first, "roughness", MaterialValue::Float(0.25))
+            .unwrap();
+        manager
+            .set_instance_property(second, "roughness", MaterialValue::Float(0.75))
+            .unwrap();
+
+        let first_data: &[f32] = bytemuck::cast_slice(manager.instance_data(first).unwrap());
+        let second_data: &[f32] = bytemuck::cast_slice(manager.instance_data(second).unwrap());
+        assert_eq!(first_data, [0.0, 0.25]);
+        assert_eq!(second_data, [0.0, 0.75]);
+
+        assert_eq!(manager.dynamic_offset(first), Some(0));
+        assert_eq!(manager.dynamic_offset(second), Some(256));
+    }
+
+    #[test]
+    fn test_unknown_property() {
+        let mut manager = MaterialManager::new(256);
+        let definition = manager.create_definition(MaterialDefinition::new("empty", []));
+        let instance = manager.instantiate(definition).unwrap();
+
+        assert!(matches!(
+            manager.set_instance_property(instance, "missing", MaterialValue::Float(1.0)),
+            Err(MaterialError::UnknownProperty(_))
+        ));
+    }
+
+    #[test]
+    fn test_uniform_layout_follows_std140() {
+        let definition = MaterialDefinition::new(
+            "lit",
+            [
+                MaterialPropertyDefinition::Float("opacity".into()),
+                MaterialPropertyDefinition::Vec3("emissive".into()),
+                MaterialPropertyDefinition::Texture("albedo_map".into()),
+                MaterialPropertyDefinition::Vec2("uv_scale".into()),
+                MaterialPropertyDefinition::Bool("unlit".into()),
+                MaterialPropertyDefinition::Color("albedo".into()),
+                MaterialPropertyDefinition::Texture("normal_map".into()),
+            ],
+        );
+
+        let layout = definition.uniform_layout();
+        let uniform = |offset, size| Some(PropertyLocation::Uniform { offset, size });
+        assert_eq!(layout.location("opacity"), uniform(0, 4));
+        // Three component vectors are aligned to 16 bytes.
+        assert_eq!(layout.location("emissive"), uniform(16, 12));
+        assert_eq!(layout.location("uv_scale"), uniform(32, 8));
+        assert_eq!(layout.location("unlit"), uniform(40, 4));
+        assert_eq!(layout.location("albedo"), uniform(48, 16));
+        assert_eq!(layout.size(), 64);
+
+        assert_eq!(
+            layout.location("albedo_map"),
+            Some(PropertyLocation::Texture { binding: 1 })
+        );
+        assert_eq!(
+            layout.texture_bindings().collect::<Vec<_>>(),
+            [("albedo_map", 1), ("normal_map", 2)]
+        );
+        assert_eq!(definition.property_offset("albedo_map"), None);
+    }
+
+    #[test]
+    fn test_shader_layout_keeps_uniform_offsets() {
+        let definition = MaterialDefinition::new(
+            "lit",
+            [
+                MaterialPropertyDefinition::Float("opacity".into()),
+                MaterialPropertyDefinition::Texture("albedo_map".into()),
+                MaterialPropertyDefinition::Vec3("emissive".into()),
+                MaterialPropertyDefinition::Bool("unlit".into()),
+            ],
+        );
+
+        let layout = definition.shader_layout(1);
+        assert_eq!((layout.set, layout.binding), (1, MATERIAL_UNIFORM_BINDING));
+        assert_eq!(
+            layout
+                .properties
+                .iter()
+                .map(|property| (property.name.as_str(), property.ty, property.offset))
+                .collect::<Vec<_>>(),
+            [
+                ("opacity", MaterialPropertyType::Float, 0),
+                ("emissive", MaterialPropertyType::Float3, 16),
+                ("unlit", MaterialPropertyType::Uint, 28),
+            ]
+        );
+
+        // DXC would pack the float3 right after the float without padding.
+        let hlsl = layout.to_hlsl().unwrap();
+        let opacity = hlsl.find("float opacity;").unwrap();
+        let emissive = hlsl.find("float3 emissive;").unwrap();
+        assert_eq!(hlsl[opacity..emissive].matches("_padding").count(), 3);
+    }
+
+    #[test]
+    fn test_property_type_mismatch() {
+        let mut manager = MaterialManager::new(256);
+        let definition = manager.create_definition(MaterialDefinition::new(
+            "unlit",
+            [
+                MaterialPropertyDefinition::Bool("unlit".into()),
+                MaterialPropertyDefinition::Color("albedo".into()),
+            ],
+        ));
+        let instance = manager.instantiate(definition).unwrap();
+
+        assert!(matches!(
+            manager.set_instance_property(instance, "albedo", MaterialValue::Float(1.0)),
+            Err(MaterialError::PropertyTypeMismatch(_))
+        ));
+
+        manager
+            .set_instance_property(instance, "unlit", MaterialValue::Bool(true))
+            .unwrap();
+        manager
+            .set_instance_property(
+                instance,
+                "albedo",
+                MaterialValue::Color([1.0, 0.5, 0.0, 1.0]),
+            )
+            .unwrap();
+
+        let data: &[u32] = bytemuck::cast_slice(manager.instance_data(instance).unwrap());
+        assert_eq!(data[0], 1);
+        assert_eq!(f32::from_bits(data[5]), 0.5);
+    }
+}
@@ -1,9 +1,12 @@
-use std::{borrow::Cow, sync::Arc};
+use std::sync::Arc;
 
-use crate::graph::{ExecuteContext, FrameNode, PrepareContext};
+use crate::resource::{
+    upload::{PendingCopy, UploadDestination, UploadQueue},
+    vertex::{MeshVertex, VertexFormat, VertexLayout, VertexSemantic},
+};
 use vislum_render_rhi::{
     buffer::{Buffer, BufferCreateInfo, BufferUsage},
-    command::{AccessFlags2, BufferMemoryBarrier2, PipelineStageFlags2},
+    command::{AccessFlags2, CommandEncoder, IndexType, PipelineStageFlags2},
     memory::{MemoryAllocator, MemoryLocation},
 };
 
@@ -16,29 +19,94 @@ pub struct Vertex {
     pub uv: [f32; 2],
 }
 
+impl MeshVertex for Vertex {
+    fn layout() -> VertexLayout {
+        VertexLayout::from_semantics([
+            (VertexSemantic::Position, VertexFormat::Float3),
+            (VertexSemantic::Normal, VertexFormat::Float3),
+            (VertexSemantic::Uv, VertexFormat::Float2),
+        ])
+    }
+}
+
+/// A vertex with only a position, e.g. for unlit or depth-only meshes.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PositionVertex {
+    pub position: [f32; 3],
+}
+
+impl MeshVertex for PositionVertex {
+    fn layout() -> VertexLayout {
+        VertexLayout::from_semantics([(VertexSemantic::Position, VertexFormat::Float3)])
+    }
+}
+
+/// An index type that can be stored in a [`Mesh`].
+///
+/// Meshes with fewer than 65536 vertices can use `u16` indices, halving the
+/// size of their index buffer.
+pub trait MeshIndex: bytemuck::Pod {
+    /// The type the index buffer is bound with.
+    const INDEX_TYPE: IndexType;
+}
+
+impl MeshIndex for u16 {
+    const INDEX_TYPE: IndexType = IndexType::Uint16;
+}
+
+impl MeshIndex for u32 {
+    const INDEX_TYPE: IndexType = IndexType::Uint32;
+}
+
 /// A mesh containing vertex and index data.
+///
+/// Cloning a mesh shares its buffers, e.g. to move it into the closure
+/// recording a frame graph pass.
+#[derive(Clone)]
 pub struct Mesh {
     vertex_buffer: Arc<Buffer>,
     index_buffer: Arc<Buffer>,
+    index_type: IndexType,
+    vertex_layout: VertexLayout,
     vertex_count: usize,
     index_count: usize,
 }
 
 impl Mesh {
-    pub fn new(
+    /// Creates a mesh from vertices of any [`MeshVertex`] type and indices of
+    /// any [`MeshIndex`] type.
+    ///
+    /// The layout of the vertex type and the index type are stored in the
+    /// mesh, so the draw path can bind the vertex buffer with the right stride
+    /// and attributes, and the index buffer with the right format. The upload
+    /// of the vertices and indices is enqueued into `uploads`.
+    pub fn new<V, I>(
         device: Arc<vislum_render_rhi::device::Device>,
         allocator: Arc<MemoryAllocator>,
-        vertices: impl IntoIterator<Item = Vertex>,
-        indices: impl IntoIterator<Item = u16>,
-    ) -> (Self, MeshUploadTask) {
+        uploads: &mut UploadQueue,
+        vertices: impl IntoIterator<Item = V>,
+        indices: impl IntoIterator<Item = I>,
+    ) -> Self
+    where
+        V: MeshVertex,
+        I: MeshIndex,
+    {
+        let vertex_layout = V::layout();
+        debug_assert_eq!(
+            vertex_layout.stride as usize,
+            std::mem::size_of::<V>(),
+            "the stride of the vertex layout doesn't match the vertex size",
+        );
+
         let vertices = vertices.into_iter().collect::<Vec<_>>();
         let indices = indices.into_iter().collect::<Vec<_>>();
 
         let vertex_count = vertices.len();
         let index_count = indices.len();
 
-        let vertex_data_size = (vertex_count * std::mem::size_of::<Vertex>()) as u64;
-        let index_data_size = (index_count * std::mem::size_of::<u16>()) as u64;
+        let vertex_data_size = (vertex_count * std::mem::size_of::<V>()) as u64;
+        let index_data_size = index_count as u64 * I::INDEX_TYPE.size();
 
         // Create GPU buffers
         let vertex_buffer = Buffer::new(
@@ -71,23 +139,33 @@ impl Mesh {
         let index_staging =
             Buffer::new_staging_with_data(device, allocator, bytemuck::cast_slice(&indices));
 
-        let mesh = Mesh {
-            vertex_buffer: vertex_buffer.clone(),
-            index_buffer: index_buffer.clone(),
-            vertex_count,
-            index_count,
-        };
+        uploads.push(PendingCopy {
+            staging: vertex_staging,
+            destination: UploadDestination::Buffer {
+                buffer: vertex_buffer.clone(),
+                size: vertex_data_size,
+                dst_stage_mask: PipelineStageFlags2::VERTEX_INPUT,
+                dst_access_mask: AccessFlags2::VERTEX_ATTRIBUTE_READ,
+            },
+        });
+        uploads.push(PendingCopy {
+            staging: index_staging,
+            destination: UploadDestination::Buffer {
+                buffer: index_buffer.clone(),
+                size: index_data_size,
+                dst_stage_mask: PipelineStageFlags2::VERTEX_INPUT,
+                dst_access_mask: AccessFlags2::INDEX_READ,
+            },
+        });
 
-        let upload_task = MeshUploadTask {
+        Mesh {
             vertex_buffer,
             index_buffer,
-            vertex_staging,
-            index_staging,
+            index_type: I::INDEX_TYPE,
+            vertex_layout,
             vertex_count,
             index_count,
-        };
-
-        (mesh, upload_task)
+        }
     }
 
     #[inline]
@@ -100,6 +178,12 @@ impl Mesh {
         self.index_count
     }
 
+    /// Returns the layout of the vertices in the vertex buffer.
+    #[inline]
+    pub fn vertex_layout(&self) -> &VertexLayout {
+        &self.vertex_layout
+    }
+
     #[inline]
     pub fn vertex_buffer(&self) -> Arc<Buffer> {
         self.vertex_buffer.clone()
@@ -109,116 +193,112 @@ impl Mesh {
     pub fn index_buffer(&self) -> Arc<Buffer> {
         self.index_buffer.clone()
     }
-}
 
-pub struct MeshUploadTask {
-    vertex_buffer: Arc<Buffer>,
-    index_buffer: Arc<Buffer>,
-    vertex_staging: Arc<Buffer>,
-    index_staging: Arc<Buffer>,
-    vertex_count: usize,
-    index_count: usize,
+    /// Returns the type of the indices in the index buffer.
+    #[inline]
+    pub fn index_type(&self) -> IndexType {
+        self.index_type
+    }
+
+    /// Binds the index buffer with the type of its indices.
+    pub fn bind_index_buffer(&self, cmd: &mut CommandEncoder) {
+        cmd.bind_index_buffer(self.index_buffer.clone(), 0, self.index_type);
+    }
+
+    /// Binds the vertex buffer to binding 0 and the index buffer, then draws
+    /// all the indices of the mesh.
+    pub fn draw(&self, cmd: &mut CommandEncoder, instance_count: u32) {
+        cmd.bind_vertex_buffers(0, [self.vertex_buffer.clone()], [0]);
+        self.bind_index_buffer(cmd);
+        cmd.draw_indexed(self.index_count as u32, instance_count, 0, 0, 0);
+    }
 }
 
-impl FrameNode for MeshUploadTask {
-    fn name(&self) -> Cow<'static, str> {
-        "upload_mesh".into()
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        VkHandle,
+        command::{ImageLayout, PipelineBindPoint},
+        image::{Extent2D, ImageFormat},
+        pipeline::{GraphicsPipeline, PipelineLayout, PipelineLayoutCreateInfo},
+        test_util::TestDevice,
+    };
+
+    use super::*;
+
+    const VERTEX_SHADER: &str = r#"
+        float4 main([[vk::location(0)]] float3 position : POSITION) : SV_Position {
+            return float4(position, 1.0);
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &str = r#"
+        float4 main() : SV_Target {
+            return float4(0.0, 1.0, 0.0, 1.0);
+        }
+    "#;
+
+    /// Draws `mesh` into a 4x1 target and reads it back.
+    fn draw_mesh(test_device: &TestDevice, mesh: &Mesh) -> Vec<[u8; 4]> {
+        let layout = PipelineLayout::new(
+            test_device.device.clone(),
+            PipelineLayoutCreateInfo {
+                set_layouts: vec![],
+            },
+        );
+        let pipeline =
+            GraphicsPipeline::builder(layout, test_device.compile_shader(VERTEX_SHADER, "vs_6_0"))
+                .fragment_shader(test_device.compile_shader(FRAGMENT_SHADER, "ps_6_0"))
+                .vertex_buffer(mesh.vertex_layout().buffer_layout(0))
+                .color_attachment(ImageFormat::Rgba8Unorm, None)
+                .build(test_device.device.clone())
+                .unwrap();
+
+        let target = test_device.create_render_target(ImageFormat::Rgba8Unorm, Extent2D::new(4, 1));
+        test_device.submit_and_wait(|encoder| {
+            target.begin_rendering(encoder);
+            encoder.bind_pipeline(PipelineBindPoint::Graphics, pipeline.vk_handle());
+            mesh.draw(encoder, 1);
+            encoder.end_rendering();
+        });
+
+        test_device.read_image(&target.image, ImageLayout::ColorAttachmentOptimal)
     }
 
-    fn prepare(
-        &self,
-        _context: &mut PrepareContext,
-    ) -> Box<dyn FnMut(&mut ExecuteContext) + 'static> {
-        let vertex_buffer = self.vertex_buffer.clone();
-        let index_buffer = self.index_buffer.clone();
-        let vertex_staging = self.vertex_staging.clone();
-        let index_staging = self.index_staging.clone();
-        let vertex_size = (self.vertex_count * std::mem::size_of::<Vertex>()) as u64;
-        let index_size = (self.index_count * std::mem::size_of::<u16>()) as u64;
-
-        Box::new(move |execute_context| {
-            let cmd = &mut execute_context.command_buffer;
-
-            // Transition the vertex bufer and index buffer to be read.
-            cmd.pipeline_barrier(
-                std::iter::empty(),
-                [vertex_buffer.clone(), index_buffer.clone()]
-                    .into_iter()
-                    .map(|buffer| BufferMemoryBarrier2 {
-                        size: buffer.size(),
-                        buffer,
-                        src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
-                        src_access_mask: AccessFlags2::NONE,
-                        dst_stage_mask: PipelineStageFlags2::TRANSFER,
-                        dst_access_mask: AccessFlags2::TRANSFER_WRITE,
-                        offset: 0,
-                    }),
-                std::iter::empty(),
-            );
-
-            // Transition the vertex staging buffer and index staging buffer to
-            // be read from.
-            cmd.pipeline_barrier(
-                std::iter::empty(),
-                [vertex_staging.clone(), index_staging.clone()]
-                    .into_iter()
-                    .map(|buffer| BufferMemoryBarrier2 {
-                        size: buffer.size(),
-                        buffer,
-                        src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
-                        src_access_mask: AccessFlags2::NONE,
-                        dst_stage_mask: PipelineStageFlags2::TRANSFER,
-                        dst_access_mask: AccessFlags2::TRANSFER_READ,
-                        offset: 0,
-                    }),
-                std::iter::empty(),
-            );
-
-            // Copy vertex buffer
-            cmd.copy_buffer(
-                vertex_staging.clone(),
-                vertex_buffer.clone(),
-                0,
-                0,
-                vertex_size,
-            );
-
-            // Copy index buffer
-            cmd.copy_buffer(
-                index_staging.clone(),
-                index_buffer.clone(),
-                0,
-                0,
-                index_size,
-            );
-
-            // Transition the vertex buffer and index buffer to be used as vertex and index buffers.
-            cmd.pipeline_barrier(
-                std::iter::empty(),
-                [
-                    (
-                        vertex_buffer.clone(),
-                        PipelineStageFlags2::VERTEX_INPUT,
-                        AccessFlags2::VERTEX_ATTRIBUTE_READ,
-                    ),
-                    (
-                        index_buffer.clone(),
-                        PipelineStageFlags2::VERTEX_INPUT,
-                        AccessFlags2::INDEX_READ,
-                    ),
-                ]
-                .into_iter()
-                .map(|(buffer, dst_stage_mask, dst_access_mask)| BufferMemoryBarrier2 {
-                    size: buffer.size(),
-                    buffer,
-                    src_stage_mask: PipelineStageFlags2::TRANSFER,
-                    src_access_mask: AccessFlags2::TRANSFER_WRITE,
-                    dst_stage_mask,
-                    dst_access_mask,
-                    offset: 0,
-                }),
-                std::iter::empty(),
-            );
-        })
+    #[test]
+    #[ignore = "requires a Vulkan driver and the DXC library"]
+    fn test_draw_binds_the_index_type_of_the_mesh() {
+        let test_device = TestDevice::new();
+        let mut uploads = UploadQueue::new(test_device.device.clone(), test_device.queue.clone());
+
+        // A triangle covering the right half of the target. Bound with the
+        // wrong index type, its indices would make it degenerate.
+        let vertices = [[0.0, -1.0, 0.0], [0.0, 3.0, 0.0], [2.0, -1.0, 0.0]]
+            .map(|position| PositionVertex { position });
+        let small = Mesh::new(
+            test_device.device.clone(),
+            test_device.allocator.clone(),
+            &mut uploads,
+            vertices,
+            [0u16, 1, 2],
+        );
+        let large = Mesh::new(
+            test_device.device.clone(),
+            test_device.allocator.clone(),
+            &mut uploads,
+            vertices,
+            [0u32, 1, 2],
+        );
+        // The copies are submitted before the draws, on the same queue.
+        uploads.flush();
+
+        let expected = [
+            [0, 0, 0, 0],
+            [0, 0, 0, 0],
+            [0, 255, 0, 255],
+            [0, 255, 0, 255],
+        ];
+        assert_eq!(draw_mesh(&test_device, &small), expected);
+        assert_eq!(draw_mesh(&test_device, &large), expected);
     }
 }
@@ -3,77 +3,274 @@ use std::sync::Arc;
 use vislum_render_rhi::{
     device::Device,
     memory::MemoryAllocator,
-    image::Image,
+    image::{Extent3D, Image},
+    queue::Queue,
 };
 
 use crate::resource::{
-    pool::{ResourceId, ResourcePool},
-    texture::{Texture, TextureUploadTask, TextureCreateInfo},
-    mesh::{Mesh, MeshUploadTask, Vertex},
+    fallback::FallbackResources,
+    pool::{ErasedResourceId, ResourceId, ResourcePool},
+    texture::{StorageTextureInitTask, Texture, TextureCreateInfo, TextureError},
+    material::MaterialManager,
+    mesh::{Mesh, MeshIndex},
+    upload::UploadQueue,
+    vertex::MeshVertex,
 };
 
+pub mod fallback;
+pub mod material;
 pub mod pool;
 pub mod texture;
 pub mod mesh;
+pub mod upload;
+pub mod vertex;
 
 pub struct ResourceManager {
     device: Arc<Device>,
     allocator: Arc<MemoryAllocator>,
+    uploads: UploadQueue,
     textures: ResourcePool<Texture>,
     meshes: ResourcePool<Mesh>,
+    materials: MaterialManager,
+    fallbacks: Option<FallbackResources>,
+    use_fallbacks: bool,
 }
 
 impl ResourceManager {
-    pub fn new(device: Arc<Device>, allocator: Arc<MemoryAllocator>) -> Self {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>, allocator: Arc<MemoryAllocator>) -> Self {
+        let materials = MaterialManager::new(
+            device
+                .physical_device()
+                .properties()
+                .min_uniform_buffer_offset_alignment,
+        );
+
         Self {
+            uploads: UploadQueue::new(device.clone(), queue),
             device,
             allocator,
             textures: Default::default(),
             meshes: Default::default(),
+            materials,
+            fallbacks: None,
+            use_fallbacks: false,
+        }
+    }
+
+    /// Creates the built-in fallback resources, enqueuing the upload of their
+    /// contents.
+    ///
+    /// Subsequent calls return false, as the fallbacks are only created once.
+    pub fn create_fallbacks(&mut self) -> bool {
+        if self.fallbacks.is_some() {
+            return false;
+        }
+
+        let missing_texture = self.create_texture_with_data(
+            fallback::missing_texture_info(),
+            &fallback::missing_texture_data(),
+        );
+        let white_texture = self.create_texture_with_data(
+            fallback::white_texture_info(),
+            &fallback::white_texture_data(),
+        );
+        let quad_mesh = self.create_mesh(fallback::quad_vertices(), fallback::quad_indices());
+
+        self.fallbacks = Some(FallbackResources {
+            missing_texture,
+            white_texture,
+            quad_mesh,
+        });
+
+        true
+    }
+
+    /// Returns the queue batching the uploads of the resources' contents.
+    #[inline]
+    pub fn uploads(&self) -> &UploadQueue {
+        &self.uploads
+    }
+
+    /// Copies the contents of all the resources created since the last flush
+    /// with a single submission, without waiting for it to complete.
+    ///
+    /// Textures become ready once a later flush or [`Self::wait_for_uploads`]
+    /// observes the copy has finished. Returns the number of copies flushed.
+    pub fn flush_uploads(&mut self) -> usize {
+        self.uploads.flush()
+    }
+
+    /// Blocks until all the flushed uploads complete.
+    ///
+    /// Returns the number of copies completed.
+    pub fn wait_for_uploads(&mut self) -> usize {
+        self.uploads.wait()
+    }
+
+    /// Returns the built-in fallback resources, if created.
+    #[inline]
+    pub fn fallbacks(&self) -> Option<&FallbackResources> {
+        self.fallbacks.as_ref()
+    }
+
+    /// Returns the checkerboard texture substituted for missing textures.
+    pub fn missing_texture(&self) -> Option<ResourceId<Texture>> {
+        self.fallbacks.map(|fallbacks| fallbacks.missing_texture)
+    }
+
+    /// Enables or disables substituting fallbacks for missing resources.
+    ///
+    /// When enabled, resolving a texture which doesn't exist or hasn't
+    /// finished uploading returns the missing texture, and resolving a mesh
+    /// which doesn't exist returns the quad mesh. Disabled by default, so
+    /// invalid ids surface as `None`.
+    pub fn set_use_fallbacks(&mut self, use_fallbacks: bool) {
+        self.use_fallbacks = use_fallbacks;
+    }
+
+    /// Returns the texture to use in place of the given one.
+    fn texture_or_fallback(&self, id: ResourceId<Texture>) -> Option<&Texture> {
+        let texture = self.textures.get(id);
+        if !self.use_fallbacks || texture.is_some_and(Texture::is_ready) {
+            return texture;
+        }
+
+        match self.missing_texture() {
+            Some(missing) if missing != id => self.textures.get(missing),
+            _ => texture,
         }
     }
 
-    /// Creates a texture with data and returns the resource id and upload task.
+    /// Creates a texture with data and returns the resource id.
+    ///
+    /// The upload of the data is enqueued until the next flush.
     pub fn create_texture_with_data(
         &mut self,
         info: TextureCreateInfo,
         data: &[u8],
-    ) -> (ResourceId<Texture>, TextureUploadTask) {
-        let (texture, upload_task) = Texture::new_with_data(
+    ) -> ResourceId<Texture> {
+        let texture = Texture::new_with_data(
             self.device.clone(),
             self.allocator.clone(),
+            &mut self.uploads,
             info,
             data,
         );
+        self.textures.insert(texture)
+    }
+
+    /// Creates a storage texture and returns the resource id and the task
+    /// transitioning it to the general layout.
+    ///
+    /// Fails if the texture format cannot be used as a storage image.
+    pub fn create_storage_texture(
+        &mut self,
+        info: TextureCreateInfo,
+    ) -> Result<(ResourceId<Texture>, StorageTextureInitTask), TextureError> {
+        let (texture, init_task) =
+            Texture::new_storage(self.device.clone(), self.allocator.clone(), info)?;
         let id = self.textures.insert(texture);
-        (id, upload_task)
+        Ok((id, init_task))
+    }
+
+    /// Creates a depth texture and returns the resource id.
+    pub fn create_depth_texture(&mut self, extent: Extent3D) -> ResourceId<Texture> {
+        let texture = Texture::new_depth(self.device.clone(), self.allocator.clone(), extent);
+        self.textures.insert(texture)
+    }
+
+    /// Destroys a texture, returning it if it existed.
+    pub fn destroy_texture(&mut self, id: ResourceId<Texture>) -> Option<Texture> {
+        self.textures.remove(id)
     }
 
     pub fn resolve_texture_image(&self, id: ResourceId<Texture>) -> Option<Arc<Image>> {
-        self.textures.get(id).map(|texture| texture.image().clone())
+        self.texture_or_fallback(id).map(|texture| texture.image().clone())
     }
 
     pub fn resolve_texture_view(&self, id: ResourceId<Texture>) -> Option<std::sync::Arc<vislum_render_rhi::image::ImageView>> {
-        self.textures.get(id).map(|texture| texture.view().clone())
+        self.texture_or_fallback(id).map(|texture| texture.view().clone())
     }
 
-    /// Creates a mesh with data and returns the resource id and upload task.
-    pub fn create_mesh(
+    /// Returns true if the texture finished uploading its initial contents.
+    ///
+    /// Unknown textures are never ready.
+    pub fn is_texture_ready(&self, id: ResourceId<Texture>) -> bool {
+        self.textures.get(id).is_some_and(|texture| texture.is_ready())
+    }
+
+    /// Returns true if the texture, or the fallback substituted for it, is a
+    /// storage texture.
+    pub fn is_storage_texture(&self, id: ResourceId<Texture>) -> bool {
+        self.texture_or_fallback(id).is_some_and(Texture::is_storage)
+    }
+
+    /// Creates a mesh with data and returns the resource id.
+    ///
+    /// The upload of the data is enqueued until the next flush.
+    pub fn create_mesh<V, I>(
         &mut self,
-        vertices: impl IntoIterator<Item = Vertex>,
-        indices: impl IntoIterator<Item = u16>,
-    ) -> (ResourceId<Mesh>, MeshUploadTask) {
-        let (mesh, upload_task) = Mesh::new(
+        vertices: impl IntoIterator<Item = V>,
+        indices: impl IntoIterator<Item = I>,
+    ) -> ResourceId<Mesh>
+    where
+        V: MeshVertex,
+        I: MeshIndex,
+    {
+        let mesh = Mesh::new(
             self.device.clone(),
             self.allocator.clone(),
+            &mut self.uploads,
             vertices,
             indices,
         );
-        let id = self.meshes.insert(mesh);
-        (id, upload_task)
+        self.meshes.insert(mesh)
+    }
+
+    /// Returns true if the texture or mesh referred to by the id exists.
+    pub fn contains(&self, id: ErasedResourceId) -> bool {
+        if let Some(texture) = id.downcast::<Texture>() {
+            return self.textures.contains(texture);
+        }
+
+        id.downcast::<Mesh>()
+            .is_some_and(|mesh| self.meshes.contains(mesh))
+    }
+
+    /// Destroys a mesh, returning it if it existed.
+    pub fn destroy_mesh(&mut self, id: ResourceId<Mesh>) -> Option<Mesh> {
+        self.meshes.remove(id)
     }
 
     pub fn get_mesh(&self, id: ResourceId<Mesh>) -> Option<&Mesh> {
-        self.meshes.get(id)
+        match (self.meshes.get(id), self.fallbacks) {
+            (None, Some(fallbacks)) if self.use_fallbacks => self.meshes.get(fallbacks.quad_mesh),
+            (mesh, _) => mesh,
+        }
+    }
+
+    #[inline]
+    pub fn materials(&self) -> &MaterialManager {
+        &self.materials
+    }
+
+    #[inline]
+    pub fn materials_mut(&mut self) -> &mut MaterialManager {
+        &mut self.materials
+    }
+}
+
+#[cfg(feature = "leak-tracking")]
+impl Drop for ResourceManager {
+    fn drop(&mut self) {
+        // The fallbacks live as long as the manager, so they are not leaks.
+        if let Some(fallbacks) = self.fallbacks.take() {
+            self.textures.remove(fallbacks.missing_texture);
+            self.textures.remove(fallbacks.white_texture);
+            self.meshes.remove(fallbacks.quad_mesh);
+        }
+
+        self.textures.report_leaks();
+        self.meshes.report_leaks();
     }
 }
@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{any::TypeId, marker::PhantomData};
 
 use slotmap::SlotMap;
 
@@ -11,6 +11,17 @@ pub struct ResourceId<T> {
     phantom: PhantomData<fn() -> T>,
 }
 
+impl<T> ResourceId<T> {
+    /// Returns an id which never refers to a live resource.
+    #[cfg(test)]
+    pub(crate) fn dangling() -> Self {
+        Self {
+            key: ResourceKey::default(),
+            phantom: PhantomData,
+        }
+    }
+}
+
 impl<T> Copy for ResourceId<T> {}
 
 impl<T> Clone for ResourceId<T> {
@@ -53,14 +64,46 @@ impl<T> std::fmt::Debug for ResourceId<T> {
     }
 }
 
+/// A [`ResourceId`] with its resource type erased, so ids of different
+/// resource types can be stored together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErasedResourceId {
+    key: ResourceKey,
+    type_id: TypeId,
+}
+
+impl ErasedResourceId {
+    /// Returns the typed id, or `None` if it refers to another resource type.
+    pub fn downcast<T: 'static>(self) -> Option<ResourceId<T>> {
+        (self.type_id == TypeId::of::<T>()).then_some(ResourceId {
+            key: self.key,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<T: 'static> From<ResourceId<T>> for ErasedResourceId {
+    fn from(id: ResourceId<T>) -> Self {
+        Self {
+            key: id.key,
+            type_id: TypeId::of::<T>(),
+        }
+    }
+}
+
 pub(crate) struct ResourcePool<T> {
     resources: SlotMap<ResourceKey, T>,
+    /// Where each resource was created, to report leaks.
+    #[cfg(feature = "leak-tracking")]
+    origins: slotmap::SecondaryMap<ResourceKey, std::backtrace::Backtrace>,
 }
 
 impl<T> Default for ResourcePool<T> {
     fn default() -> Self {
         Self {
             resources: Default::default(),
+            #[cfg(feature = "leak-tracking")]
+            origins: Default::default(),
         }
     }
 }
@@ -68,6 +111,10 @@ impl<T> Default for ResourcePool<T> {
 impl<T> ResourcePool<T> {
     pub fn insert(&mut self, resource: T) -> ResourceId<T> {
         let key = self.resources.insert(resource);
+
+        #[cfg(feature = "leak-tracking")]
+        self.origins.insert(key, std::backtrace::Backtrace::force_capture());
+
         ResourceId {
             key,
             phantom: PhantomData,
@@ -81,4 +128,67 @@ impl<T> ResourcePool<T> {
     pub fn get_mut(&mut self, id: ResourceId<T>) -> Option<&mut T> {
         self.resources.get_mut(id.key)
     }
-}
\ No newline at end of file
+
+    pub fn contains(&self, id: ResourceId<T>) -> bool {
+        self.resources.contains_key(id.key)
+    }
+
+    pub fn remove(&mut self, id: ResourceId<T>) -> Option<T> {
+        #[cfg(feature = "leak-tracking")]
+        self.origins.remove(id.key);
+
+        self.resources.remove(id.key)
+    }
+
+    /// Returns the creation backtraces of the resources still alive.
+    #[cfg(feature = "leak-tracking")]
+    pub fn leaks(&self) -> impl Iterator<Item = &std::backtrace::Backtrace> {
+        self.resources
+            .keys()
+            .filter_map(|key| self.origins.get(key))
+    }
+
+    /// Logs the resources still alive, with the backtrace of their creation.
+    #[cfg(feature = "leak-tracking")]
+    pub fn report_leaks(&self) {
+        for backtrace in self.leaks() {
+            log::warn!(
+                "{} leaked, created at:\n{}",
+                std::any::type_name::<T>(),
+                backtrace
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_erased_id_downcasts_to_its_type_only() {
+        let mut pool = ResourcePool::<u32>::default();
+        let id = pool.insert(1);
+
+        let erased = ErasedResourceId::from(id);
+        assert_eq!(erased.downcast::<u32>(), Some(id));
+        assert_eq!(erased.downcast::<u64>(), None);
+        assert_ne!(
+            erased,
+            ErasedResourceId::from(ResourceId::<u64>::dangling())
+        );
+    }
+
+    #[cfg(feature = "leak-tracking")]
+    #[test]
+    fn test_live_resources_are_reported_as_leaks() {
+        let mut pool = ResourcePool::<u32>::default();
+        let leaked = pool.insert(1);
+        let destroyed = pool.insert(2);
+        pool.remove(destroyed);
+
+        assert_eq!(pool.leaks().count(), 1);
+        pool.remove(leaked);
+        assert_eq!(pool.leaks().count(), 0);
+    }
+}
@@ -1,25 +1,43 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use crate::graph::{ExecuteContext, FrameNode, PrepareContext};
+use crate::{
+    graph::{ExecuteContext, FrameNode, PrepareContext},
+    resource::upload::{PendingCopy, UploadDestination, UploadQueue},
+};
 use ash::vk;
 use vislum_render_rhi::{
     buffer::Buffer,
     command::{
-        AccessFlags2, BufferImageCopy, BufferMemoryBarrier2, ImageAspectFlags, ImageLayout, ImageMemoryBarrier2, ImageSubresourceLayers, MemoryBarrier2, PipelineStageFlags2
+        AccessFlags2, BufferImageCopy, CommandEncoder, ImageAspectFlags, ImageBlit, ImageLayout,
+        ImageMemoryBarrier2, ImageSubresourceLayers, ImageSubresourceRange, MemoryBarrier2,
+        PipelineStageFlags2,
     },
     image::{
-        Extent3D, Image, ImageCreateInfo, ImageFormat, ImageType, ImageUsage, ImageView,
-        ImageViewCreateInfo, ImageViewType,
+        Extent3D, FormatFeatures, Image, ImageCreateInfo, ImageFormat, ImageType, ImageUsage,
+        ImageView, ImageViewCreateInfo, ImageViewType,
     },
     memory::MemoryAllocator,
 };
 
+#[derive(Debug, thiserror::Error)]
+pub enum TextureError {
+    #[error("texture format {0:?} does not support storage images")]
+    UnsupportedStorageFormat(TextureFormat),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TextureFormat {
     Rgba8Unorm,
     Rgba8Srgb,
     Rgb8Unorm,
     Rgb8Srgb,
+    Depth32Float,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -42,79 +60,286 @@ pub struct TextureCreateInfo {
     pub format: TextureFormat,
     pub dimensions: TextureDimensions,
     pub extent: Extent3D,
+    /// Whether to generate the full mip chain from the uploaded data.
+    ///
+    /// Ignored by textures created without initial data.
+    pub generate_mipmaps: bool,
 }
 
-pub struct Texture {
-    image: Arc<Image>,
-    view: Arc<ImageView>,
+/// Returns the number of levels in the full mip chain of an extent, down to
+/// a single texel.
+pub fn mip_level_count(extent: Extent3D) -> u32 {
+    let largest = extent.width.max(extent.height).max(extent.depth).max(1);
+    32 - largest.leading_zeros()
 }
 
-impl Texture {
-    /// Creates a texture with data and returns both the texture and an upload task.
-    pub fn new_with_data(
-        device: Arc<vislum_render_rhi::device::Device>,
-        allocator: Arc<MemoryAllocator>,
-        info: TextureCreateInfo,
-        data: &[u8],
-    ) -> (Self, TextureUploadTask) {
-        let rhi_format = match info.format {
+impl TextureFormat {
+    fn to_image_format(self) -> ImageFormat {
+        match self {
             TextureFormat::Rgba8Unorm => ImageFormat::Rgba8Unorm,
             TextureFormat::Rgba8Srgb => ImageFormat::Rgba8Srgb,
             TextureFormat::Rgb8Unorm => ImageFormat::Rgb8Unorm,
             TextureFormat::Rgb8Srgb => ImageFormat::Rgb8Srgb,
-        };
+            TextureFormat::Depth32Float => ImageFormat::D32Sfloat,
+        }
+    }
+}
 
-        let rhi_dimensions = match info.dimensions {
+impl TextureDimensions {
+    fn to_image_type(self) -> ImageType {
+        match self {
             TextureDimensions::D2 => ImageType::D2,
             TextureDimensions::D3 => ImageType::D3,
-        };
+        }
+    }
+}
+
+/// Tracks the completion of the upload of a texture's initial contents.
+///
+/// The upload is complete once the submit copying the staging buffer into the
+/// texture has finished executing on the GPU. Sampling a texture before then
+/// reads uninitialized memory.
+#[derive(Debug, Clone)]
+pub struct TextureUpload {
+    ready: Arc<AtomicBool>,
+}
+
+impl TextureUpload {
+    fn pending() -> Self {
+        Self {
+            ready: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn completed() -> Self {
+        Self {
+            ready: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Returns true if the upload has completed.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn complete(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+}
+
+pub struct Texture {
+    image: Arc<Image>,
+    view: Arc<ImageView>,
+    storage: bool,
+    upload: TextureUpload,
+}
+
+impl std::fmt::Debug for Texture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Texture")
+            .field("image", &self.image)
+            .field("storage", &self.storage)
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}
 
+impl Texture {
+    /// Creates the image and the default view for a texture.
+    fn create_image_and_view(
+        device: Arc<vislum_render_rhi::device::Device>,
+        allocator: Arc<MemoryAllocator>,
+        rhi_format: ImageFormat,
+        rhi_dimensions: ImageType,
+        extent: Extent3D,
+        mip_levels: u32,
+        usage: ImageUsage,
+    ) -> (Arc<Image>, Arc<ImageView>) {
         let image = Image::new(
             device.clone(),
-            allocator.clone(),
-            ImageCreateInfo {
-                dimensions: rhi_dimensions,
-                format: rhi_format,
-                extent: info.extent,
-                mip_levels: 1,
-                array_layers: 1,
-                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
-            },
+            allocator,
+            ImageCreateInfo::new(rhi_format, extent)
+                .dimensions(rhi_dimensions)
+                .mips(mip_levels)
+                .usage(usage),
             vislum_render_rhi::memory::MemoryLocation::GpuOnly,
         );
 
         // Create default image view
         let view_type = ImageViewType::from(rhi_dimensions);
         let view = ImageView::new(
-            device.clone(),
+            device,
             ImageViewCreateInfo {
                 image: image.clone(),
                 view_type,
                 format: rhi_format,
                 components: vk::ComponentMapping::default(),
                 subresource_range: vk::ImageSubresourceRange::default()
-                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .aspect_mask(rhi_format.aspect_mask())
                     .base_mip_level(0)
-                    .level_count(1)
+                    .level_count(mip_levels)
                     .base_array_layer(0)
                     .layer_count(1),
             },
         );
 
+        (image, view)
+    }
+
+    /// Creates a texture with data, enqueuing the upload of its contents.
+    ///
+    /// The texture isn't ready to be sampled until the upload queue is
+    /// flushed.
+    pub fn new_with_data(
+        device: Arc<vislum_render_rhi::device::Device>,
+        allocator: Arc<MemoryAllocator>,
+        uploads: &mut UploadQueue,
+        info: TextureCreateInfo,
+        data: &[u8],
+    ) -> Self {
+        let rhi_format = info.format.to_image_format();
+
+        let mut mip_levels = 1;
+        if info.generate_mipmaps {
+            // Mips are generated by blitting each level into the next one
+            // with a linear filter.
+            let required = FormatFeatures::BLIT_SRC
+                | FormatFeatures::BLIT_DST
+                | FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR;
+
+            if device
+                .physical_device()
+                .format_features(rhi_format)
+                .contains(required)
+            {
+                mip_levels = mip_level_count(info.extent);
+            } else {
+                log::warn!(
+                    "texture format {:?} does not support linear blits, skipping mipmap generation",
+                    info.format
+                );
+            }
+        }
+
+        let mut usage = ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED;
+        if mip_levels > 1 {
+            usage |= ImageUsage::TRANSFER_SRC;
+        }
+
+        let (image, view) = Self::create_image_and_view(
+            device.clone(),
+            allocator.clone(),
+            rhi_format,
+            info.dimensions.to_image_type(),
+            info.extent,
+            mip_levels,
+            usage,
+        );
+
         // Create staging buffer with host-visible memory
         let staging = Buffer::new_staging_with_data(device.clone(), allocator, data);
+        let upload = TextureUpload::pending();
 
-        let upload_task = TextureUploadTask {
-            image: image.clone(),
-            staging_buffer: staging,
-            extent: vk::Extent3D {
-                width: info.extent.width,
-                height: info.extent.height,
-                depth: info.extent.depth,
+        uploads.push(PendingCopy {
+            staging,
+            destination: UploadDestination::Texture {
+                image: image.clone(),
+                region: BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: ImageSubresourceLayers {
+                        aspect_mask: ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: [0, 0, 0],
+                    image_extent: info.extent,
+                },
+                mip_levels,
+                upload: upload.clone(),
             },
+        });
+
+        Texture {
+            image,
+            view,
+            storage: false,
+            upload,
+        }
+    }
+
+    /// Creates a texture that can be written from compute shaders and sampled
+    /// from graphics passes.
+    ///
+    /// The returned task transitions the image to [`ImageLayout::General`],
+    /// which is the layout storage textures are kept in for their whole lifetime.
+    pub fn new_storage(
+        device: Arc<vislum_render_rhi::device::Device>,
+        allocator: Arc<MemoryAllocator>,
+        info: TextureCreateInfo,
+    ) -> Result<(Self, StorageTextureInitTask), TextureError> {
+        let features = device
+            .physical_device()
+            .format_features(info.format.to_image_format());
+
+        if !features.contains(FormatFeatures::STORAGE_IMAGE) {
+            return Err(TextureError::UnsupportedStorageFormat(info.format));
+        }
+
+        let (image, view) = Self::create_image_and_view(
+            device,
+            allocator,
+            info.format.to_image_format(),
+            info.dimensions.to_image_type(),
+            info.extent,
+            1,
+            ImageUsage::STORAGE | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+        );
+
+        let init_task = StorageTextureInitTask {
+            image: image.clone(),
         };
 
-        (Texture { image, view }, upload_task)
+        Ok((
+            Texture {
+                image,
+                view,
+                storage: true,
+                upload: TextureUpload::completed(),
+            },
+            init_task,
+        ))
+    }
+
+    /// Creates a 2D depth texture which can be rendered to and sampled,
+    /// e.g. as a shadow map with a comparison sampler.
+    ///
+    /// The image starts in [`ImageLayout::Undefined`]; the pass rendering to it
+    /// is responsible for transitioning it.
+    pub fn new_depth(
+        device: Arc<vislum_render_rhi::device::Device>,
+        allocator: Arc<MemoryAllocator>,
+        extent: Extent3D,
+    ) -> Self {
+        let (image, view) = Self::create_image_and_view(
+            device,
+            allocator,
+            TextureFormat::Depth32Float.to_image_format(),
+            ImageType::D2,
+            extent,
+            1,
+            ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+        );
+
+        Texture {
+            image,
+            view,
+            storage: false,
+            upload: TextureUpload::completed(),
+        }
     }
 
     #[inline]
@@ -126,87 +351,557 @@ impl Texture {
     pub fn view(&self) -> &Arc<ImageView> {
         &self.view
     }
+
+    /// Returns true if the texture can be bound as a storage image.
+    #[inline]
+    pub fn is_storage(&self) -> bool {
+        self.storage
+    }
+
+    /// Returns true if the initial contents of the texture finished uploading,
+    /// so it can be sampled.
+    ///
+    /// Textures without initial contents are always ready.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.upload.is_ready()
+    }
+
+    /// Returns the handle tracking the upload of the texture's initial contents.
+    #[inline]
+    pub fn upload(&self) -> &TextureUpload {
+        &self.upload
+    }
+}
+
+/// Returns a barrier making compute shader writes to a storage texture visible
+/// to fragment shader reads.
+pub fn storage_write_to_sampled_barrier(image: Arc<Image>) -> ImageMemoryBarrier2 {
+    ImageMemoryBarrier2 {
+        image,
+        src_stage_mask: PipelineStageFlags2::COMPUTE_SHADER,
+        src_access_mask: AccessFlags2::SHADER_WRITE,
+        dst_stage_mask: PipelineStageFlags2::FRAGMENT_SHADER,
+        dst_access_mask: AccessFlags2::SHADER_READ,
+        old_layout: ImageLayout::General,
+        new_layout: ImageLayout::General,
+        ownership_transfer: None,
+        subresource_range: None,
+    }
+}
+
+/// Returns a barrier ensuring fragment shader reads of a storage texture have
+/// finished before a compute shader writes to it again.
+pub fn sampled_to_storage_write_barrier(image: Arc<Image>) -> ImageMemoryBarrier2 {
+    ImageMemoryBarrier2 {
+        image,
+        src_stage_mask: PipelineStageFlags2::FRAGMENT_SHADER,
+        src_access_mask: AccessFlags2::SHADER_READ,
+        dst_stage_mask: PipelineStageFlags2::COMPUTE_SHADER,
+        dst_access_mask: AccessFlags2::SHADER_WRITE,
+        old_layout: ImageLayout::General,
+        new_layout: ImageLayout::General,
+        ownership_transfer: None,
+        subresource_range: None,
+    }
 }
 
-pub struct TextureUploadTask {
+/// Transitions a freshly created storage texture to [`ImageLayout::General`].
+pub struct StorageTextureInitTask {
     image: Arc<Image>,
-    staging_buffer: Arc<Buffer>,
-    extent: vk::Extent3D,
 }
 
-impl FrameNode for TextureUploadTask {
+impl FrameNode for StorageTextureInitTask {
     fn name(&self) -> Cow<'static, str> {
-        "upload_texture".into()
+        "init_storage_texture".into()
     }
 
     fn prepare(
         &self,
         _context: &mut PrepareContext,
     ) -> Box<dyn FnMut(&mut ExecuteContext) + 'static> {
-        let destination = self.image.clone();
-        let staging_buffer = self.staging_buffer.clone();
-        let extent = Extent3D::from_vk(self.extent);
+        let image = self.image.clone();
 
         Box::new(move |execute_context| {
-            let cmd = &mut execute_context.command_buffer;
-
-            // Transition image from undefined to transfer destination layout
-            cmd.pipeline_barrier(
+            execute_context.command_buffer.pipeline_barrier(
+                std::iter::empty(),
                 std::iter::empty(),
-                std::iter::once(BufferMemoryBarrier2{
-                    buffer: staging_buffer.clone(),
-                    src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
-                    src_access_mask: AccessFlags2::NONE,
-                    dst_stage_mask: PipelineStageFlags2::TRANSFER,
-                    dst_access_mask: AccessFlags2::TRANSFER_WRITE,
-                    offset: 0,
-                    size: staging_buffer.size(),
-                }),
-                std::iter::once(ImageMemoryBarrier2 {
-                    image: destination.clone(),
-                    src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
-                    src_access_mask: AccessFlags2::NONE,
-                    dst_stage_mask: PipelineStageFlags2::TRANSFER,
-                    dst_access_mask: AccessFlags2::TRANSFER_WRITE,
-                    old_layout: ImageLayout::Undefined,
-                    new_layout: ImageLayout::TransferDstOptimal,
-                }),
+                std::iter::once(storage_init_barrier(image.clone())),
             );
+        })
+    }
+}
+
+/// Returns the barrier transitioning a freshly created storage texture to
+/// [`ImageLayout::General`], before its first compute shader write.
+fn storage_init_barrier(image: Arc<Image>) -> ImageMemoryBarrier2 {
+    ImageMemoryBarrier2 {
+        image,
+        src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
+        src_access_mask: AccessFlags2::NONE,
+        dst_stage_mask: PipelineStageFlags2::COMPUTE_SHADER,
+        dst_access_mask: AccessFlags2::SHADER_WRITE,
+        old_layout: ImageLayout::Undefined,
+        new_layout: ImageLayout::General,
+        ownership_transfer: None,
+        subresource_range: None,
+    }
+}
 
+/// Returns the subresource range of a single mip level of a color texture.
+pub(crate) fn color_mip_range(mip_level: u32) -> ImageSubresourceRange {
+    ImageSubresourceRange::new(ImageAspectFlags::COLOR, mip_level, 1, 0, 1)
+}
 
-            cmd.copy_buffer_to_image(
-                staging_buffer.clone(),
-                destination.clone(),
-                ImageLayout::TransferDstOptimal,
-                std::iter::once(BufferImageCopy {
-                    buffer_offset: 0,
-                    buffer_row_length: 0,
-                    buffer_image_height: 0,
-                    image_subresource: ImageSubresourceLayers {
-                        aspect_mask: ImageAspectFlags::COLOR,
-                        mip_level: 0,
-                        base_array_layer: 0,
-                        layer_count: 1,
+/// Returns the far corner of a mip level of an extent.
+fn mip_corner(extent: Extent3D, mip_level: u32) -> [i32; 3] {
+    [extent.width, extent.height, extent.depth].map(|size| (size >> mip_level).max(1) as i32)
+}
+
+/// Fills the mip levels after the first one by successively blitting each
+/// level into the next one.
+///
+/// All the levels must be in [`ImageLayout::TransferDstOptimal`], with the
+/// first one already written to. Every level but the last one is left in
+/// [`ImageLayout::ShaderReadOnlyOptimal`].
+pub(crate) fn generate_mipmaps(
+    cmd: &mut CommandEncoder,
+    image: &Arc<Image>,
+    extent: Extent3D,
+    mip_levels: u32,
+) {
+    let color_layers = |mip_level| ImageSubresourceLayers {
+        aspect_mask: ImageAspectFlags::COLOR,
+        mip_level,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    for level in 1..mip_levels {
+        let source = level - 1;
+
+        // Wait for the source level to be written before reading from it.
+        cmd.pipeline_barrier(
+            std::iter::empty(),
+            std::iter::empty(),
+            std::iter::once(ImageMemoryBarrier2 {
+                image: image.clone(),
+                src_stage_mask: PipelineStageFlags2::TRANSFER,
+                src_access_mask: AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                dst_access_mask: AccessFlags2::TRANSFER_READ,
+                old_layout: ImageLayout::TransferDstOptimal,
+                new_layout: ImageLayout::TransferSrcOptimal,
+                ownership_transfer: None,
+                subresource_range: Some(color_mip_range(source)),
+            }),
+        );
+
+        cmd.blit_image(
+            image.clone(),
+            ImageLayout::TransferSrcOptimal,
+            image.clone(),
+            ImageLayout::TransferDstOptimal,
+            std::iter::once(ImageBlit {
+                src_subresource: color_layers(source),
+                src_offsets: [[0, 0, 0], mip_corner(extent, source)],
+                dst_subresource: color_layers(level),
+                dst_offsets: [[0, 0, 0], mip_corner(extent, level)],
+            }),
+            vk::Filter::LINEAR,
+        );
+
+        cmd.pipeline_barrier(
+            std::iter::empty(),
+            std::iter::empty(),
+            std::iter::once(ImageMemoryBarrier2 {
+                image: image.clone(),
+                src_stage_mask: PipelineStageFlags2::TRANSFER,
+                src_access_mask: AccessFlags2::TRANSFER_READ,
+                dst_stage_mask: PipelineStageFlags2::FRAGMENT_SHADER,
+                dst_access_mask: AccessFlags2::SHADER_READ,
+                old_layout: ImageLayout::TransferSrcOptimal,
+                new_layout: ImageLayout::ShaderReadOnlyOptimal,
+                ownership_transfer: None,
+                subresource_range: Some(color_mip_range(source)),
+            }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        VkHandle,
+        command::PipelineBindPoint,
+        descriptor::{
+            CombinedImageSampler, DescriptorPool, DescriptorPoolCreateInfo, DescriptorPoolSizes,
+            DescriptorResourceInfo, DescriptorSetLayout, DescriptorSetLayoutBinding,
+            DescriptorSetLayoutCreateInfo, DescriptorType, DescriptorWrites,
+        },
+        image::Extent2D,
+        pipeline::{ComputePipeline, PipelineLayout, PipelineLayoutCreateInfo},
+        sampler::{CompareOp, Sampler, SamplerCreateInfo},
+        shader::ShaderStageFlags,
+        test_util::TestDevice,
+    };
+
+    use super::*;
+
+    /// Samples a depth texture cleared to 0.5 with a `Less` comparison, in
+    /// front of the stored depth in the left half and behind it in the right
+    /// half.
+    const SHADOW_SHADER: &str = r#"
+        [[vk::combinedImageSampler]] [[vk::binding(0)]]
+        Texture2D<float> shadow_map;
+        [[vk::combinedImageSampler]] [[vk::binding(0)]]
+        SamplerComparisonState shadow_sampler;
+
+        float4 main([[vk::location(0)]] float2 uv : TEXCOORD0) : SV_Target {
+            float reference = uv.x < 0.5 ? 0.25 : 0.75;
+            float lit = shadow_map.SampleCmpLevelZero(shadow_sampler, uv, reference);
+            return float4(lit, lit, lit, 1.0);
+        }
+    "#;
+
+    /// Writes a horizontal gradient of red into a storage texture.
+    const GRADIENT_SHADER: &str = r#"
+        [[vk::binding(0)]] RWTexture2D<float4> output;
+
+        [numthreads(4, 1, 1)]
+        void main(uint3 id : SV_DispatchThreadID) {
+            output[id.xy] = float4(id.x / 3.0, 0.0, 0.0, 1.0);
+        }
+    "#;
+
+    /// Copies the texels of a texture to the matching pixels.
+    const COPY_SHADER: &str = r#"
+        [[vk::binding(0)]] Texture2D<float4> source;
+
+        float4 main(float4 position : SV_Position) : SV_Target {
+            return source.Load(int3(position.xy, 0));
+        }
+    "#;
+
+    fn single_binding_layout(
+        test_device: &TestDevice,
+        descriptor_type: DescriptorType,
+    ) -> Arc<DescriptorSetLayout> {
+        DescriptorSetLayout::new(
+            test_device.device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: vec![DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type,
+                    count: 1,
+                    stages: ShaderStageFlags::FRAGMENT | ShaderStageFlags::COMPUTE,
+                }],
+            },
+        )
+    }
+
+    #[test]
+    fn test_upload_ready_after_completion() {
+        let upload = TextureUpload::pending();
+        let handle = upload.clone();
+        assert!(!handle.is_ready());
+
+        upload.complete();
+        assert!(handle.is_ready());
+        assert!(TextureUpload::completed().is_ready());
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_debug_output_describes_texture() {
+        let test_device = TestDevice::new();
+        let texture = Texture::new_depth(
+            test_device.device.clone(),
+            test_device.allocator.clone(),
+            Extent3D {
+                width: 4,
+                height: 2,
+                depth: 1,
+            },
+        );
+        texture.image().set_name("shadow map");
+
+        let debug = format!("{texture:?}");
+        assert!(debug.contains("D32Sfloat"), "{debug}");
+        assert!(debug.contains("width: 4, height: 2, depth: 1"), "{debug}");
+        assert!(debug.contains("\"shadow map\""), "{debug}");
+    }
+
+    #[test]
+    fn test_mip_level_count() {
+        let extent = |width, height, depth| Extent3D {
+            width,
+            height,
+            depth,
+        };
+
+        assert_eq!(mip_level_count(extent(1, 1, 1)), 1);
+        assert_eq!(mip_level_count(extent(256, 256, 1)), 9);
+        assert_eq!(mip_level_count(extent(256, 64, 1)), 9);
+        // Non power of two sizes round down at each level.
+        assert_eq!(mip_level_count(extent(300, 200, 1)), 9);
+        assert_eq!(mip_level_count(extent(5, 3, 1)), 3);
+        assert_eq!(mip_level_count(extent(4, 4, 32)), 6);
+    }
+
+    #[test]
+    fn test_mip_corner() {
+        let extent = Extent3D {
+            width: 300,
+            height: 7,
+            depth: 1,
+        };
+
+        assert_eq!(mip_corner(extent, 0), [300, 7, 1]);
+        assert_eq!(mip_corner(extent, 1), [150, 3, 1]);
+        assert_eq!(mip_corner(extent, 3), [37, 1, 1]);
+        assert_eq!(mip_corner(extent, 8), [1, 1, 1]);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver and the DXC library"]
+    fn test_depth_texture_is_sampled_with_comparison_sampler() {
+        let test_device = TestDevice::new();
+        let device = test_device.device.clone();
+
+        let extent = Extent2D::new(2, 1);
+        let depth = Texture::new_depth(
+            device.clone(),
+            test_device.allocator.clone(),
+            Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+        );
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: vk::Filter::NEAREST,
+                min_filter: vk::Filter::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                compare: Some(CompareOp::Less),
+            },
+        );
+
+        let set_layout = single_binding_layout(&test_device, DescriptorType::CombinedImageSampler);
+        let pool = DescriptorPool::new(
+            device.clone(),
+            DescriptorPoolCreateInfo {
+                sizes: DescriptorPoolSizes {
+                    combined_image_sampler: 1,
+                    ..Default::default()
+                },
+                max_sets: 1,
+            },
+        );
+        let set = pool.allocate(&set_layout).unwrap();
+        DescriptorWrites::new()
+            .write(
+                set.vk_handle(),
+                0,
+                &CombinedImageSampler {
+                    view: depth.view().clone(),
+                    sampler,
+                },
+            )
+            .update(&device);
+
+        let pipeline = test_device.create_full_screen_pipeline(
+            SHADOW_SHADER,
+            vec![set_layout],
+            ImageFormat::Rgba8Unorm,
+        );
+        let target = test_device.create_render_target(ImageFormat::Rgba8Unorm, extent);
+
+        test_device.submit_and_wait(|encoder| {
+            // Clear the depth texture as a shadow pass without draws would.
+            encoder.transition_image(
+                depth.image().clone(),
+                ImageLayout::DepthStencilAttachmentOptimal,
+                PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                    | PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+                AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            );
+            let depth_attachment = vk::RenderingAttachmentInfo::default()
+                .image_view(depth.view().vk_handle())
+                .image_layout(ImageLayout::DepthStencilAttachmentOptimal.to_vk())
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .clear_value(vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: 0.5,
+                        stencil: 0,
                     },
-                    image_offset: [0, 0, 0],
-                    image_extent: extent,
-                }),
+                });
+            encoder.begin_rendering(
+                &vk::RenderingInfo::default()
+                    .depth_attachment(&depth_attachment)
+                    .render_area(vk::Rect2D::default().extent(extent.to_vk()))
+                    .layer_count(1),
             );
+            encoder.end_rendering();
 
-            // Transition image to shader read layout
-            cmd.pipeline_barrier(
-                std::iter::empty(),
-                std::iter::empty(),
-                std::iter::once(ImageMemoryBarrier2 {
-                    image: destination.clone(),
-                    src_stage_mask: PipelineStageFlags2::TRANSFER,
-                    src_access_mask: AccessFlags2::TRANSFER_WRITE,
-                    dst_stage_mask: PipelineStageFlags2::FRAGMENT_SHADER,
-                    dst_access_mask: AccessFlags2::SHADER_READ,
-                    old_layout: ImageLayout::TransferDstOptimal,
-                    new_layout: ImageLayout::ShaderReadOnlyOptimal,
-                }),
+            encoder.transition_image(
+                depth.image().clone(),
+                ImageLayout::ShaderReadOnlyOptimal,
+                PipelineStageFlags2::FRAGMENT_SHADER,
+                AccessFlags2::SHADER_READ,
             );
-        })
+
+            target.begin_rendering(encoder);
+            encoder.bind_pipeline(PipelineBindPoint::Graphics, pipeline.vk_handle());
+            encoder.bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().vk_handle(),
+                0,
+                [set.vk_handle()],
+                [],
+            );
+            encoder.draw(3, 1, 0, 0);
+            encoder.end_rendering();
+        });
+
+        let pixels = test_device.read_image(&target.image, ImageLayout::ColorAttachmentOptimal);
+        assert_eq!(pixels, [[255, 255, 255, 255], [0, 0, 0, 255]]);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver and the DXC library"]
+    fn test_storage_texture_written_by_compute_is_sampled() {
+        let test_device = TestDevice::new();
+        let device = test_device.device.clone();
+
+        let extent = Extent2D::new(4, 1);
+        let (texture, _) = Texture::new_storage(
+            device.clone(),
+            test_device.allocator.clone(),
+            TextureCreateInfo {
+                format: TextureFormat::Rgba8Unorm,
+                dimensions: TextureDimensions::D2,
+                extent: Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+                generate_mipmaps: false,
+            },
+        )
+        .unwrap();
+        assert!(texture.is_storage());
+
+        // Storage textures are accessed in the general layout by both passes.
+        let general_view = |descriptor_type| {
+            (
+                descriptor_type,
+                DescriptorResourceInfo::Image(
+                    vk::DescriptorImageInfo::default()
+                        .image_layout(ImageLayout::General.to_vk())
+                        .image_view(texture.view().vk_handle()),
+                ),
+            )
+        };
+        let pool = DescriptorPool::new(
+            device.clone(),
+            DescriptorPoolCreateInfo {
+                sizes: DescriptorPoolSizes {
+                    storage_image: 1,
+                    sampled_image: 1,
+                    ..Default::default()
+                },
+                max_sets: 2,
+            },
+        );
+        let [
+            (compute_layout, compute_set),
+            (graphics_layout, graphics_set),
+        ] = [DescriptorType::StorageImage, DescriptorType::SampledImage].map(|descriptor_type| {
+            let layout = single_binding_layout(&test_device, descriptor_type);
+            let set = pool.allocate(&layout).unwrap();
+            let (descriptor_type, info) = general_view(descriptor_type);
+            DescriptorWrites::new()
+                .write_info(set.vk_handle(), 0, descriptor_type, info)
+                .update(&device);
+            (layout, set)
+        });
+
+        let compute_pipeline = ComputePipeline::new(
+            device.clone(),
+            PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutCreateInfo {
+                    set_layouts: vec![compute_layout],
+                },
+            ),
+            test_device.compile_shader(GRADIENT_SHADER, "cs_6_0"),
+        )
+        .unwrap();
+        let graphics_pipeline = test_device.create_full_screen_pipeline(
+            COPY_SHADER,
+            vec![graphics_layout],
+            ImageFormat::Rgba8Unorm,
+        );
+        let target = test_device.create_render_target(ImageFormat::Rgba8Unorm, extent);
+
+        test_device.submit_and_wait(|encoder| {
+            encoder.pipeline_barrier([], [], [storage_init_barrier(texture.image().clone())]);
+
+            // Two frames, the second one waiting for the reads of the first.
+            for frame in 0..2 {
+                if frame > 0 {
+                    encoder.pipeline_barrier(
+                        [],
+                        [],
+                        [sampled_to_storage_write_barrier(texture.image().clone())],
+                    );
+                }
+
+                encoder.bind_pipeline(PipelineBindPoint::Compute, compute_pipeline.vk_handle());
+                encoder.bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    compute_pipeline.layout().vk_handle(),
+                    0,
+                    [compute_set.vk_handle()],
+                    [],
+                );
+                encoder.dispatch(1, 1, 1);
+                encoder.pipeline_barrier(
+                    [],
+                    [],
+                    [storage_write_to_sampled_barrier(texture.image().clone())],
+                );
+
+                target.begin_rendering(encoder);
+                encoder.bind_pipeline(PipelineBindPoint::Graphics, graphics_pipeline.vk_handle());
+                encoder.bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    graphics_pipeline.layout().vk_handle(),
+                    0,
+                    [graphics_set.vk_handle()],
+                    [],
+                );
+                encoder.draw(3, 1, 0, 0);
+                encoder.end_rendering();
+            }
+        });
+
+        let pixels = test_device.read_image(&target.image, ImageLayout::ColorAttachmentOptimal);
+        assert_eq!(
+            pixels,
+            [
+                [0, 0, 0, 255],
+                [85, 0, 0, 255],
+                [170, 0, 0, 255],
+                [255, 0, 0, 255]
+            ]
+        );
     }
 }
@@ -0,0 +1,421 @@
+use std::sync::Arc;
+
+use vislum_render_rhi::{
+    buffer::Buffer,
+    command::{
+        AccessFlags2, BufferImageCopy, BufferMemoryBarrier2, CommandBufferLevel,
+        CommandBufferUsageFlags, CommandEncoder, CommandPool, ImageLayout, ImageMemoryBarrier2,
+        PipelineStageFlags2,
+    },
+    device::Device,
+    image::Image,
+    queue::Queue,
+    sync::Fence,
+};
+
+use crate::resource::texture::{self, TextureUpload};
+
+/// Where the contents of a staging buffer are copied to.
+pub enum UploadDestination {
+    /// Copied into the first mip level of a texture, generating the other
+    /// levels from it. The texture is left ready to be sampled by fragment
+    /// shaders.
+    Texture {
+        image: Arc<Image>,
+        region: BufferImageCopy,
+        mip_levels: u32,
+        upload: TextureUpload,
+    },
+    /// Copied into the start of a buffer, made visible to the given stages
+    /// and accesses.
+    Buffer {
+        buffer: Arc<Buffer>,
+        size: u64,
+        dst_stage_mask: PipelineStageFlags2,
+        dst_access_mask: AccessFlags2,
+    },
+}
+
+/// A copy from a staging buffer, waiting for the next flush.
+pub struct PendingCopy {
+    pub staging: Arc<Buffer>,
+    pub destination: UploadDestination,
+}
+
+/// Batches the uploads of resource contents, so they are all copied by a
+/// single queue submission.
+///
+/// The queue owns the staging buffers of the pending copies. They are kept
+/// alive until the fence of the submission copying them signals, and are
+/// released by the first poll noticing it.
+pub struct UploadQueue {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    command_pool: Arc<CommandPool>,
+    pending: Vec<PendingCopy>,
+    in_flight: Vec<InFlightBatch>,
+}
+
+/// The copies of a flush, executing on the GPU.
+struct InFlightBatch {
+    fence: Arc<Fence>,
+    copies: Vec<PendingCopy>,
+}
+
+impl InFlightBatch {
+    /// Marks the uploaded textures as ready. Dropping the batch afterwards
+    /// releases the staging buffers.
+    fn complete(self) -> usize {
+        for copy in &self.copies {
+            if let UploadDestination::Texture { upload, .. } = &copy.destination {
+                upload.complete();
+            }
+        }
+
+        self.copies.len()
+    }
+}
+
+impl UploadQueue {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>) -> Self {
+        // TODO: Get actual queue family index
+        let command_pool = CommandPool::new(device.clone(), 0);
+
+        Self {
+            device,
+            queue,
+            command_pool,
+            pending: Vec::new(),
+            in_flight: Vec::new(),
+        }
+    }
+
+    /// Schedules a copy for the next flush.
+    pub fn push(&mut self, copy: PendingCopy) {
+        self.pending.push(copy);
+    }
+
+    /// Returns the number of copies waiting for the next flush.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns true if there are no copies waiting for the next flush.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns the number of copies submitted but not known to have completed.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.iter().map(|batch| batch.copies.len()).sum()
+    }
+
+    /// Records all the pending copies into one command buffer and submits it,
+    /// without waiting for it to complete.
+    ///
+    /// The batches submitted by previous flushes are polled first. The
+    /// textures of the new batch become ready in a later poll, once its fence
+    /// has signaled. Returns the number of copies flushed.
+    pub fn flush(&mut self) -> usize {
+        self.poll();
+
+        if self.pending.is_empty() {
+            return 0;
+        }
+
+        let pending = std::mem::take(&mut self.pending);
+
+        let mut encoder =
+            CommandEncoder::new(self.command_pool.allocate(CommandBufferLevel::PRIMARY));
+        encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        record_copies(&mut encoder, &pending);
+        encoder.end();
+
+        let fence = Fence::unsignaled(self.device.clone());
+        self.queue
+            .submit(encoder, vec![], vec![], Some(fence.clone()));
+
+        let count = pending.len();
+        self.in_flight.push(InFlightBatch {
+            fence,
+            copies: pending,
+        });
+
+        count
+    }
+
+    /// Completes the batches whose fence has signaled, marking their textures
+    /// as ready and releasing their staging buffers.
+    ///
+    /// Returns the number of copies completed.
+    pub fn poll(&mut self) -> usize {
+        let (completed, in_flight) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition::<Vec<_>, _>(|batch| batch.fence.status());
+        self.in_flight = in_flight;
+
+        completed.into_iter().map(InFlightBatch::complete).sum()
+    }
+
+    /// Blocks until all the submitted batches complete.
+    ///
+    /// Returns the number of copies completed.
+    pub fn wait(&mut self) -> usize {
+        std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .map(|batch| {
+                batch.fence.wait(u64::MAX);
+                batch.complete()
+            })
+            .sum()
+    }
+}
+
+impl Drop for UploadQueue {
+    fn drop(&mut self) {
+        // The staging buffers must outlive the copies reading them.
+        self.wait();
+    }
+}
+
+/// Records the copies with a single barrier before and after them, besides
+/// the ones generating the mips.
+fn record_copies(encoder: &mut CommandEncoder, pending: &[PendingCopy]) {
+    // Prepare all the destinations to be written to.
+    encoder.pipeline_barrier(
+        [],
+        pending.iter().filter_map(|copy| match &copy.destination {
+            UploadDestination::Buffer { buffer, size, .. } => Some(BufferMemoryBarrier2 {
+                buffer: buffer.clone(),
+                src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
+                src_access_mask: AccessFlags2::NONE,
+                dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                dst_access_mask: AccessFlags2::TRANSFER_WRITE,
+                offset: 0,
+                size: *size,
+            }),
+            UploadDestination::Texture { .. } => None,
+        }),
+        pending.iter().filter_map(|copy| match &copy.destination {
+            UploadDestination::Texture { image, .. } => Some(ImageMemoryBarrier2 {
+                image: image.clone(),
+                src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
+                src_access_mask: AccessFlags2::NONE,
+                dst_stage_mask: PipelineStageFlags2::TRANSFER,
+                dst_access_mask: AccessFlags2::TRANSFER_WRITE,
+                old_layout: ImageLayout::Undefined,
+                new_layout: ImageLayout::TransferDstOptimal,
+                ownership_transfer: None,
+                subresource_range: None,
+            }),
+            UploadDestination::Buffer { .. } => None,
+        }),
+    );
+
+    for copy in pending {
+        match &copy.destination {
+            UploadDestination::Texture { image, region, .. } => {
+                encoder.copy_buffer_to_image(
+                    copy.staging.clone(),
+                    image.clone(),
+                    ImageLayout::TransferDstOptimal,
+                    [*region],
+                );
+            }
+            UploadDestination::Buffer { buffer, size, .. } => {
+                encoder.copy_buffer(copy.staging.clone(), buffer.clone(), 0, 0, *size);
+            }
+        }
+    }
+
+    for copy in pending {
+        if let UploadDestination::Texture {
+            image,
+            region,
+            mip_levels,
+            ..
+        } = &copy.destination
+        {
+            texture::generate_mipmaps(encoder, image, region.image_extent, *mip_levels);
+        }
+    }
+
+    // Make the contents visible to their readers. The mip levels before the
+    // last one were already transitioned while generating them.
+    encoder.pipeline_barrier(
+        [],
+        pending.iter().filter_map(|copy| match &copy.destination {
+            UploadDestination::Buffer {
+                buffer,
+                size,
+                dst_stage_mask,
+                dst_access_mask,
+            } => Some(BufferMemoryBarrier2 {
+                buffer: buffer.clone(),
+                src_stage_mask: PipelineStageFlags2::TRANSFER,
+                src_access_mask: AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: *dst_stage_mask,
+                dst_access_mask: *dst_access_mask,
+                offset: 0,
+                size: *size,
+            }),
+            UploadDestination::Texture { .. } => None,
+        }),
+        pending.iter().filter_map(|copy| match &copy.destination {
+            UploadDestination::Texture {
+                image, mip_levels, ..
+            } => Some(ImageMemoryBarrier2 {
+                image: image.clone(),
+                src_stage_mask: PipelineStageFlags2::TRANSFER,
+                src_access_mask: AccessFlags2::TRANSFER_WRITE,
+                dst_stage_mask: PipelineStageFlags2::FRAGMENT_SHADER,
+                dst_access_mask: AccessFlags2::SHADER_READ,
+                old_layout: ImageLayout::TransferDstOptimal,
+                new_layout: ImageLayout::ShaderReadOnlyOptimal,
+                ownership_transfer: None,
+                subresource_range: Some(texture::color_mip_range(mip_levels - 1)),
+            }),
+            UploadDestination::Buffer { .. } => None,
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        command::IndexType, image::Extent3D, memory::MemoryAllocator, test_util::TestDevice,
+    };
+
+    use super::*;
+    use crate::resource::{
+        mesh::{Mesh, Vertex},
+        texture::{Texture, TextureCreateInfo, TextureDimensions, TextureFormat},
+    };
+
+    fn create_upload_queue() -> (Arc<Device>, Arc<MemoryAllocator>, UploadQueue) {
+        let TestDevice {
+            device,
+            queue,
+            allocator,
+        } = TestDevice::new();
+
+        (device.clone(), allocator, UploadQueue::new(device, queue))
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_flush_uploads_several_textures_at_once() {
+        let (device, allocator, mut uploads) = create_upload_queue();
+
+        let textures = [1, 16, 300]
+            .into_iter()
+            .map(|size| {
+                let data = vec![255u8; (size * size * 4) as usize];
+                Texture::new_with_data(
+                    device.clone(),
+                    allocator.clone(),
+                    &mut uploads,
+                    TextureCreateInfo {
+                        format: TextureFormat::Rgba8Unorm,
+                        dimensions: TextureDimensions::D2,
+                        extent: Extent3D {
+                            width: size,
+                            height: size,
+                            depth: 1,
+                        },
+                        generate_mipmaps: true,
+                    },
+                    &data,
+                )
+            })
+            .collect::<Vec<_>>();
+        let mesh = Mesh::new(
+            device,
+            allocator,
+            &mut uploads,
+            [Vertex {
+                position: [0.0; 3],
+                normal: [0.0, 0.0, 1.0],
+                uv: [0.0; 2],
+            }; 3],
+            [0u16, 1, 2],
+        );
+
+        // One copy per texture, plus the vertex and index buffers.
+        assert_eq!(uploads.len(), 5);
+        assert!(textures.iter().all(|texture| !texture.is_ready()));
+
+        assert_eq!(uploads.flush(), 5);
+        assert!(uploads.is_empty());
+        assert_eq!(uploads.wait(), 5);
+        assert!(textures.iter().all(Texture::is_ready));
+        assert_eq!(mesh.index_count(), 3);
+
+        // Flushing again has nothing left to submit.
+        assert_eq!(uploads.flush(), 0);
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_textures_are_ready_once_their_fence_signals() {
+        let (device, allocator, mut uploads) = create_upload_queue();
+
+        let texture = Texture::new_with_data(
+            device.clone(),
+            allocator,
+            &mut uploads,
+            TextureCreateInfo {
+                format: TextureFormat::Rgba8Unorm,
+                dimensions: TextureDimensions::D2,
+                extent: Extent3D {
+                    width: 4,
+                    height: 4,
+                    depth: 1,
+                },
+                generate_mipmaps: false,
+            },
+            &[255u8; 4 * 4 * 4],
+        );
+
+        // The flush submits the copy without waiting for it.
+        assert_eq!(uploads.flush(), 1);
+        assert_eq!(uploads.in_flight(), 1);
+        assert!(!texture.is_ready());
+
+        // Once the fence has signaled, the next poll completes the upload.
+        device.wait_idle();
+        assert!(!texture.is_ready());
+        assert_eq!(uploads.poll(), 1);
+        assert_eq!(uploads.in_flight(), 0);
+        assert!(texture.is_ready());
+    }
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_meshes_store_their_index_type() {
+        let (device, allocator, mut uploads) = create_upload_queue();
+
+        let vertices = [Vertex {
+            position: [0.0; 3],
+            normal: [0.0, 0.0, 1.0],
+            uv: [0.0; 2],
+        }; 3];
+        let small = Mesh::new(
+            device.clone(),
+            allocator.clone(),
+            &mut uploads,
+            vertices,
+            [0u16, 1, 2],
+        );
+        let large = Mesh::new(device, allocator, &mut uploads, vertices, [0u32, 1, 2]);
+
+        assert_eq!(small.index_type(), IndexType::Uint16);
+        assert_eq!(small.index_buffer().size(), 6);
+        assert_eq!(large.index_type(), IndexType::Uint32);
+        assert_eq!(large.index_buffer().size(), 12);
+        assert_eq!(uploads.flush(), 4);
+    }
+}
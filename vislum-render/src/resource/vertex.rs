@@ -0,0 +1,263 @@
+use ash::vk;
+use vislum_render_rhi::pipeline::{self, VertexBufferLayout, VertexInputRate};
+
+/// The format of a vertex attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    Float,
+    Float2,
+    Float3,
+    Float4,
+    Uint4,
+    Unorm8x4,
+}
+
+impl VertexFormat {
+    /// Returns the size of the attribute in bytes.
+    pub const fn size(self) -> u32 {
+        match self {
+            VertexFormat::Float => 4,
+            VertexFormat::Float2 => 8,
+            VertexFormat::Float3 => 12,
+            VertexFormat::Float4 => 16,
+            VertexFormat::Uint4 => 16,
+            VertexFormat::Unorm8x4 => 4,
+        }
+    }
+
+    pub const fn to_vk(self) -> vk::Format {
+        match self {
+            VertexFormat::Float => vk::Format::R32_SFLOAT,
+            VertexFormat::Float2 => vk::Format::R32G32_SFLOAT,
+            VertexFormat::Float3 => vk::Format::R32G32B32_SFLOAT,
+            VertexFormat::Float4 => vk::Format::R32G32B32A32_SFLOAT,
+            VertexFormat::Uint4 => vk::Format::R32G32B32A32_UINT,
+            VertexFormat::Unorm8x4 => vk::Format::R8G8B8A8_UNORM,
+        }
+    }
+
+    pub const fn to_rhi(self) -> pipeline::VertexFormat {
+        match self {
+            VertexFormat::Float => pipeline::VertexFormat::Float,
+            VertexFormat::Float2 => pipeline::VertexFormat::Float2,
+            VertexFormat::Float3 => pipeline::VertexFormat::Float3,
+            VertexFormat::Float4 => pipeline::VertexFormat::Float4,
+            VertexFormat::Uint4 => pipeline::VertexFormat::Uint4,
+            VertexFormat::Unorm8x4 => pipeline::VertexFormat::Unorm8x4,
+        }
+    }
+}
+
+/// What a vertex attribute holds.
+///
+/// Each semantic is read from a fixed shader input location, so shaders can
+/// declare their inputs without knowing the layout of the vertex buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexSemantic {
+    Position,
+    Normal,
+    Uv,
+    Color,
+    Tangent,
+}
+
+impl VertexSemantic {
+    /// Returns the shader input location of the semantic.
+    pub const fn location(self) -> u32 {
+        match self {
+            VertexSemantic::Position => 0,
+            VertexSemantic::Normal => 1,
+            VertexSemantic::Uv => 2,
+            VertexSemantic::Color => 3,
+            VertexSemantic::Tangent => 4,
+        }
+    }
+}
+
+/// A single attribute within a vertex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    /// The shader input location of the attribute.
+    pub location: u32,
+    pub format: VertexFormat,
+    /// The offset of the attribute within the vertex, in bytes.
+    pub offset: u32,
+}
+
+/// Describes how the vertices of a mesh are laid out in its vertex buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    /// The size of a single vertex, in bytes.
+    pub stride: u32,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    /// Creates the layout of tightly packed attributes, in the given order,
+    /// each at the location of its semantic.
+    ///
+    /// Matches `#[repr(C)]` vertex structs whose fields are all `f32` arrays
+    /// or `u32`s, which have no padding.
+    pub fn from_semantics(
+        attributes: impl IntoIterator<Item = (VertexSemantic, VertexFormat)>,
+    ) -> Self {
+        let mut stride = 0;
+        let attributes = attributes
+            .into_iter()
+            .map(|(semantic, format)| {
+                let offset = stride;
+                stride += format.size();
+                VertexAttribute {
+                    location: semantic.location(),
+                    format,
+                    offset,
+                }
+            })
+            .collect();
+
+        Self { stride, attributes }
+    }
+
+    /// Returns the attribute holding the semantic, if any.
+    pub fn attribute(&self, semantic: VertexSemantic) -> Option<&VertexAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.location == semantic.location())
+    }
+
+    /// Returns the binding description of a vertex buffer with this layout.
+    pub fn binding_description(&self, binding: u32) -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::default()
+            .binding(binding)
+            .stride(self.stride)
+            .input_rate(vk::VertexInputRate::VERTEX)
+    }
+
+    /// Returns the attribute descriptions of a vertex buffer with this layout.
+    pub fn attribute_descriptions(&self, binding: u32) -> Vec<vk::VertexInputAttributeDescription> {
+        self.attributes
+            .iter()
+            .map(|attribute| {
+                vk::VertexInputAttributeDescription::default()
+                    .binding(binding)
+                    .location(attribute.location)
+                    .format(attribute.format.to_vk())
+                    .offset(attribute.offset)
+            })
+            .collect()
+    }
+
+    /// Returns the pipeline vertex buffer layout of a binding with this layout.
+    pub fn buffer_layout(&self, binding: u32) -> VertexBufferLayout {
+        VertexBufferLayout {
+            binding,
+            stride: self.stride,
+            input_rate: VertexInputRate::Vertex,
+            attributes: self
+                .attributes
+                .iter()
+                .map(|attribute| pipeline::VertexAttribute {
+                    location: attribute.location,
+                    format: attribute.format.to_rhi(),
+                    offset: attribute.offset,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A vertex type that can be stored in a [`Mesh`].
+///
+/// [`Mesh`]: crate::resource::mesh::Mesh
+pub trait MeshVertex: bytemuck::Pod {
+    /// Returns the layout of the vertex.
+    fn layout() -> VertexLayout;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+    struct ColoredVertex {
+        position: [f32; 3],
+        color: [f32; 4],
+    }
+
+    impl MeshVertex for ColoredVertex {
+        fn layout() -> VertexLayout {
+            VertexLayout {
+                stride: std::mem::size_of::<Self>() as u32,
+                attributes: vec![
+                    VertexAttribute {
+                        location: 0,
+                        format: VertexFormat::Float3,
+                        offset: std::mem::offset_of!(Self, position) as u32,
+                    },
+                    VertexAttribute {
+                        location: 1,
+                        format: VertexFormat::Float4,
+                        offset: std::mem::offset_of!(Self, color) as u32,
+                    },
+                ],
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_vertex_layout_descriptions() {
+        let layout = ColoredVertex::layout();
+
+        let binding = layout.binding_description(0);
+        assert_eq!(binding.stride, 28);
+
+        let attributes = layout.attribute_descriptions(0);
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[1].location, 1);
+        assert_eq!(attributes[1].format, vk::Format::R32G32B32A32_SFLOAT);
+        assert_eq!(attributes[1].offset, 12);
+
+        let buffer_layout = layout.buffer_layout(1);
+        assert_eq!(buffer_layout.binding, 1);
+        assert_eq!(buffer_layout.stride, 28);
+        assert_eq!(
+            buffer_layout.attributes[1].format,
+            pipeline::VertexFormat::Float4
+        );
+    }
+
+    #[test]
+    fn test_semantic_layouts_are_packed() {
+        let position_only =
+            VertexLayout::from_semantics([(VertexSemantic::Position, VertexFormat::Float3)]);
+        assert_eq!(position_only.binding_description(0).stride, 12);
+        assert_eq!(position_only.attribute(VertexSemantic::Normal), None);
+
+        let attributes = position_only.attribute_descriptions(0);
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0].location, 0);
+        assert_eq!(attributes[0].format, vk::Format::R32G32B32_SFLOAT);
+
+        let lit = VertexLayout::from_semantics([
+            (VertexSemantic::Position, VertexFormat::Float3),
+            (VertexSemantic::Normal, VertexFormat::Float3),
+            (VertexSemantic::Uv, VertexFormat::Float2),
+        ]);
+        assert_eq!(lit.binding_description(0).stride, 32);
+
+        let attributes = lit
+            .attribute_descriptions(0)
+            .iter()
+            .map(|attribute| (attribute.location, attribute.format, attribute.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            attributes,
+            [
+                (0, vk::Format::R32G32B32_SFLOAT, 0),
+                (1, vk::Format::R32G32B32_SFLOAT, 12),
+                (2, vk::Format::R32G32_SFLOAT, 24),
+            ]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use vislum_math::{Matrix4, Vector3};
+
+/// How a camera projects the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// A perspective projection, with a vertical field of view in radians.
+    Perspective { fov_y: f32 },
+    /// An orthographic projection, e.g. for 2D and UI, showing `height`
+    /// world units vertically.
+    Orthographic { height: f32 },
+}
+
+/// A point of view the scene is rendered from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: Vector3,
+    /// The direction the camera looks at. Doesn't need to be normalized.
+    pub forward: Vector3,
+    /// The up direction of the camera. Doesn't need to be normalized nor
+    /// orthogonal to the forward direction.
+    pub up: Vector3,
+    pub projection: Projection,
+    /// The width of the viewport divided by its height.
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    /// Creates a perspective camera at the origin, looking down the negative
+    /// z axis.
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self {
+            position: Vector3::default(),
+            forward: Vector3::new(0.0, 0.0, -1.0),
+            up: Vector3::new(0.0, 1.0, 0.0),
+            projection: Projection::Perspective { fov_y },
+            aspect,
+            near,
+            far,
+        }
+    }
+
+    /// Creates an orthographic camera at the origin, looking down the
+    /// negative z axis.
+    pub fn orthographic(height: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic { height },
+            ..Self::perspective(0.0, aspect, near, far)
+        }
+    }
+
+    /// Moves the camera to `position`, looking at `target`.
+    pub fn look_at(mut self, position: Vector3, target: Vector3) -> Self {
+        self.position = position;
+        self.forward = target - position;
+        self
+    }
+
+    /// Returns the matrix transforming world space to view space.
+    pub fn view_matrix(&self) -> Matrix4 {
+        Matrix4::look_at(self.position, self.position + self.forward, self.up)
+    }
+
+    /// Returns the matrix transforming view space to Vulkan clip space.
+    ///
+    /// Vulkan's clip space y axis points down, unlike the view space one, so
+    /// the projection flips it. Depth is mapped to `0.0..=1.0`.
+    pub fn projection_matrix(&self) -> Matrix4 {
+        let projection = match self.projection {
+            Projection::Perspective { fov_y } => {
+                Matrix4::perspective(fov_y, self.aspect, self.near, self.far)
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * self.aspect;
+                Matrix4::orthographic(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+        };
+
+        Matrix4::from_scale(Vector3::new(1.0, -1.0, 1.0)) * projection
+    }
+
+    /// Returns the matrix transforming world space to clip space.
+    pub fn view_projection_matrix(&self) -> Matrix4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    /// Returns the per-frame uniform data of the camera.
+    pub fn uniform(&self) -> CameraUniform {
+        CameraUniform {
+            view_projection: self.view_projection_matrix().to_cols_array(),
+            position: [self.position.x(), self.position.y(), self.position.z(), 1.0],
+        }
+    }
+}
+
+/// The camera data read by the vertex shaders, laid out as a std140
+/// `CameraData` block.
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+pub struct CameraUniform {
+    pub view_projection: [[f32; 4]; 4],
+    /// The world-space position of the camera, with w set to 1.
+    pub position: [f32; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_math::{Vector4, vec4};
+
+    use super::*;
+
+    fn to_ndc(clip: Vector4) -> [f32; 3] {
+        [
+            clip.x() / clip.w(),
+            clip.y() / clip.w(),
+            clip.z() / clip.w(),
+        ]
+    }
+
+    fn assert_near(actual: [f32; 3], expected: [f32; 3]) {
+        for (actual, expected) in actual.into_iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-5,
+                "{actual} isn't close to {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_perspective_point_lands_in_clip_space() {
+        let camera = Camera::perspective(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 10.0)
+            .look_at(Vector3::new(0.0, 0.0, 5.0), Vector3::default());
+
+        // Points above the camera's center land in the upper half of the
+        // viewport, which has negative y in Vulkan.
+        let clip = camera.view_projection_matrix() * vec4(0.0, 1.0, 0.0, 1.0);
+        assert_near(to_ndc(clip), [0.0, -0.2, 8.0 / 9.0]);
+    }
+
+    #[test]
+    fn test_orthographic_point_lands_in_clip_space() {
+        let camera = Camera::orthographic(4.0, 2.0, 0.0, 10.0);
+
+        let clip = camera.view_projection_matrix() * vec4(2.0, 1.0, -5.0, 1.0);
+        assert_near(to_ndc(clip), [0.5, -0.5, 0.5]);
+    }
+}
@@ -1,7 +1,181 @@
+use vislum_math::{Matrix4, Vector3};
+
+use crate::resource::{mesh::Mesh, pool::ResourceId};
+
+pub mod camera;
+
+pub use camera::{Camera, CameraUniform, Projection};
+
+/// Identifies an object pushed to a [`SceneCollector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SceneObjectId(usize);
+
+/// An object to be drawn.
+#[derive(Debug, Clone, Copy)]
 pub struct SceneObject {
+    pub mesh: ResourceId<Mesh>,
+    /// The transform of the object, relative to its parent, or to the world
+    /// if it has none.
+    pub transform: Matrix4,
+    /// The object the transform is relative to.
+    pub parent: Option<SceneObjectId>,
+    /// Whether the object is blended with what's behind it.
+    pub transparent: bool,
+}
+
+/// An object to be drawn, with its transform resolved.
+#[derive(Debug, Clone, Copy)]
+pub struct SceneDraw<'a> {
+    pub object: &'a SceneObject,
+    /// The model matrix of the object, transforming it to world space.
+    pub world: Matrix4,
+}
 
+impl SceneDraw<'_> {
+    /// Returns the world-space position of the object's origin.
+    pub fn position(&self) -> Vector3 {
+        self.world.transform_point(Vector3::default())
+    }
 }
 
 /// Collects objects from the scene. 
+#[derive(Debug, Default)]
 pub struct SceneCollector {
-}
\ No newline at end of file
+    objects: Vec<SceneObject>,
+}
+
+impl SceneCollector {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an object to be drawn.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parent of the object wasn't pushed yet. Parents come
+    /// before their children, so world matrices resolve in a single pass.
+    pub fn push(&mut self, object: SceneObject) -> SceneObjectId {
+        if let Some(SceneObjectId(parent)) = object.parent {
+            assert!(
+                parent < self.objects.len(),
+                "the parent of an object must be pushed before it"
+            );
+        }
+
+        self.objects.push(object);
+        SceneObjectId(self.objects.len() - 1)
+    }
+
+    /// Returns the world matrices of the collected objects, in push order.
+    ///
+    /// A child's world matrix is its parent's world matrix times its own
+    /// transform.
+    pub fn world_matrices(&self) -> Vec<Matrix4> {
+        let mut matrices: Vec<Matrix4> = Vec::with_capacity(self.objects.len());
+        for object in &self.objects {
+            let world = match object.parent {
+                Some(SceneObjectId(parent)) => matrices[parent] * object.transform,
+                None => object.transform,
+            };
+            matrices.push(world);
+        }
+
+        matrices
+    }
+
+    /// Returns the collected objects in the order they must be drawn.
+    ///
+    /// Opaque objects come first, front-to-back, so the depth test rejects
+    /// occluded fragments early. Transparent objects follow, back-to-front,
+    /// so each blends over the ones behind it.
+    pub fn sorted(&self, camera_position: Vector3) -> Vec<SceneDraw<'_>> {
+        let distance = |draw: &SceneDraw| (draw.position() - camera_position).length_squared();
+
+        let draws = self
+            .objects
+            .iter()
+            .zip(self.world_matrices())
+            .map(|(object, world)| SceneDraw { object, world });
+        let (mut opaque, mut transparent): (Vec<SceneDraw>, Vec<SceneDraw>) =
+            draws.partition(|draw| !draw.object.transparent);
+
+        opaque.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        transparent.sort_by(|a, b| distance(b).total_cmp(&distance(a)));
+
+        opaque.extend(transparent);
+        opaque
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(z: f32, transparent: bool) -> SceneObject {
+        SceneObject {
+            mesh: ResourceId::dangling(),
+            transform: Matrix4::from_translation(Vector3::new(0.0, 0.0, z)),
+            parent: None,
+            transparent,
+        }
+    }
+
+    #[test]
+    fn test_transparent_objects_are_drawn_back_to_front() {
+        let mut collector = SceneCollector::new();
+        collector.push(object(-1.0, true));
+        collector.push(object(-5.0, true));
+
+        let sorted = collector.sorted(Vector3::default());
+        let depths = sorted.iter().map(|d| d.position().z()).collect::<Vec<_>>();
+        assert_eq!(depths, [-5.0, -1.0]);
+    }
+
+    #[test]
+    fn test_opaque_objects_are_drawn_front_to_back_first() {
+        let mut collector = SceneCollector::new();
+        collector.push(object(-2.0, true));
+        collector.push(object(-8.0, false));
+        collector.push(object(-3.0, false));
+
+        let sorted = collector.sorted(Vector3::default());
+        let order = sorted
+            .iter()
+            .map(|d| (d.position().z(), d.object.transparent))
+            .collect::<Vec<_>>();
+        assert_eq!(order, [(-3.0, false), (-8.0, false), (-2.0, true)]);
+    }
+
+    #[test]
+    fn test_world_matrices_follow_parents() {
+        let mut collector = SceneCollector::new();
+        let parent = collector.push(SceneObject {
+            transform: Matrix4::from_translation(Vector3::new(1.0, 0.0, 0.0)),
+            ..object(0.0, false)
+        });
+        collector.push(SceneObject {
+            transform: Matrix4::from_translation(Vector3::new(0.0, 2.0, 0.0)),
+            ..object(0.0, false)
+        });
+        collector.push(SceneObject {
+            transform: Matrix4::from_translation(Vector3::new(0.0, 0.0, 3.0)),
+            parent: Some(parent),
+            ..object(0.0, false)
+        });
+
+        let positions = collector
+            .world_matrices()
+            .into_iter()
+            .map(|world| world.transform_point(Vector3::default()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            [
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 2.0, 0.0),
+                Vector3::new(1.0, 0.0, 3.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+mod offscreen;
+
+pub use offscreen::{OffscreenScreen, OffscreenScreenCreateInfo};
+
+use std::sync::Arc;
+
+use vislum_render_rhi::{
+    Error,
+    image::{Extent2D, Image, ImageFormat, ImageView},
+    sync::Semaphore,
+};
+
+/// The index of the frame slot an image was acquired from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameIndex(pub u32);
+
+/// An image acquired from a [`Screen`] to render a frame to.
+pub struct AcquiredImage {
+    pub index: FrameIndex,
+    pub image: Arc<Image>,
+    pub image_view: Arc<ImageView>,
+    /// Signaled once the image may be rendered to, which the frame's
+    /// submission must wait on.
+    pub acquire_semaphore: Arc<Semaphore>,
+    /// Must be signaled by the frame's submission once rendering finishes.
+    pub render_semaphore: Arc<Semaphore>,
+}
+
+/// Abstracts swapchain-based rendering (windows) and offscreen rendering
+/// (textures) behind a common frame loop.
+pub trait Screen {
+    /// Returns the extent of the images.
+    fn extent(&self) -> Extent2D;
+
+    /// Returns the format of the images.
+    fn format(&self) -> ImageFormat;
+
+    /// Acquires the next image to render to.
+    fn acquire_image(&mut self) -> Result<AcquiredImage, Error>;
+
+    /// Presents an image once its render semaphore is signaled.
+    fn present(&mut self, image: AcquiredImage) -> Result<(), Error>;
+}
@@ -0,0 +1,320 @@
+use std::sync::Arc;
+
+use ash::vk;
+use vislum_render_rhi::{
+    Error,
+    device::Device,
+    image::{
+        Extent2D, Extent3D, Image, ImageCreateInfo, ImageFormat, ImageUsage, ImageView,
+        ImageViewCreateInfo, ImageViewType,
+    },
+    memory::{MemoryAllocator, MemoryLocation},
+    queue::Queue,
+    sync::{Fence, Semaphore},
+};
+
+use crate::screen::{AcquiredImage, FrameIndex, Screen};
+
+pub struct OffscreenScreenCreateInfo {
+    pub extent: Extent2D,
+    pub format: ImageFormat,
+    /// The number of images rendered to in turns, allowing to render a frame
+    /// while the previous ones are still being read.
+    pub image_count: u32,
+    /// Renders to the images through sRGB views, so the frames are sRGB
+    /// encoded on write while the images keep their UNORM `format`, e.g. for
+    /// encoders expecting UNORM frames.
+    ///
+    /// Requires a UNORM format with an sRGB counterpart.
+    pub srgb_view: bool,
+}
+
+struct OffscreenFrame {
+    image: Arc<Image>,
+    image_view: Arc<ImageView>,
+    /// The view passes render to, the sRGB view if requested.
+    render_view: Arc<ImageView>,
+    acquire_semaphore: Arc<Semaphore>,
+    render_semaphore: Arc<Semaphore>,
+    /// Signaled once the last frame rendered to the image finishes.
+    ready: Arc<Fence>,
+}
+
+/// A [`Screen`] rendering to a ring of textures instead of a window, e.g. for
+/// the editor viewport or video export.
+///
+/// Presenting an image only marks it as finished. To read back a frame, wait
+/// on [`OffscreenScreen::ready_fence`] of its index and then sample the image
+/// or copy it into a buffer, as the images are created with the
+/// [`ImageUsage::SAMPLED`] and [`ImageUsage::TRANSFER_SRC`] usages. The image
+/// stays in the layout the frame left it in.
+///
+/// With [`OffscreenScreenCreateInfo::srgb_view`], the acquired images are
+/// rendered to through an sRGB view, and [`Screen::format`] returns the sRGB
+/// format the pipelines must target.
+///
+/// Acquiring an image blocks until the frame previously rendered to it
+/// finishes, so the image must have been read before acquiring it again.
+pub struct OffscreenScreen {
+    queue: Arc<Queue>,
+    extent: Extent2D,
+    /// The format of the views rendered to.
+    render_format: ImageFormat,
+    frames: Vec<OffscreenFrame>,
+    next_frame: usize,
+    last_presented: Option<FrameIndex>,
+}
+
+impl OffscreenScreen {
+    pub fn new(
+        device: Arc<Device>,
+        allocator: Arc<MemoryAllocator>,
+        queue: Arc<Queue>,
+        create_info: OffscreenScreenCreateInfo,
+    ) -> Self {
+        assert!(
+            !create_info.extent.is_empty(),
+            "the offscreen screen extent must not be empty"
+        );
+        assert!(
+            create_info.image_count > 0,
+            "the offscreen screen needs at least one image"
+        );
+
+        let render_format = if create_info.srgb_view {
+            create_info.format.to_srgb().unwrap_or_else(|| {
+                panic!("{:?} images can't have an sRGB view", create_info.format)
+            })
+        } else {
+            create_info.format
+        };
+
+        let extent = Extent3D {
+            width: create_info.extent.width,
+            height: create_info.extent.height,
+            depth: 1,
+        };
+
+        let frames = (0..create_info.image_count)
+            .map(|_| {
+                let mut image_create_info = ImageCreateInfo::new(create_info.format, extent).usage(
+                    ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+                );
+                if create_info.srgb_view {
+                    image_create_info = image_create_info.view_formats([render_format]);
+                }
+                let image = Image::new(
+                    device.clone(),
+                    allocator.clone(),
+                    image_create_info,
+                    MemoryLocation::GpuOnly,
+                );
+
+                let create_view = |format: ImageFormat| {
+                    ImageView::new(
+                        device.clone(),
+                        ImageViewCreateInfo {
+                            image: image.clone(),
+                            view_type: ImageViewType::D2,
+                            format,
+                            components: vk::ComponentMapping::default(),
+                            subresource_range: vk::ImageSubresourceRange::default()
+                                .aspect_mask(format.aspect_mask())
+                                .base_mip_level(0)
+                                .level_count(1)
+                                .base_array_layer(0)
+                                .layer_count(1),
+                        },
+                    )
+                };
+                let image_view = create_view(create_info.format);
+                let render_view = if create_info.srgb_view {
+                    create_view(render_format)
+                } else {
+                    image_view.clone()
+                };
+
+                OffscreenFrame {
+                    image,
+                    image_view,
+                    render_view,
+                    acquire_semaphore: Semaphore::new(device.clone()),
+                    render_semaphore: Semaphore::new(device.clone()),
+                    ready: Fence::signaled(device.clone()),
+                }
+            })
+            .collect();
+
+        Self {
+            queue,
+            extent: create_info.extent,
+            render_format,
+            frames,
+            next_frame: 0,
+            last_presented: None,
+        }
+    }
+
+    /// Returns the number of images in the ring.
+    #[inline]
+    pub fn image_count(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    /// Returns the image of a frame slot.
+    pub fn image(&self, index: FrameIndex) -> &Arc<Image> {
+        &self.frames[index.0 as usize].image
+    }
+
+    /// Returns the view of the image of a frame slot, with the format of the
+    /// image.
+    pub fn image_view(&self, index: FrameIndex) -> &Arc<ImageView> {
+        &self.frames[index.0 as usize].image_view
+    }
+
+    /// Returns the fence signaled once the frame last presented from the
+    /// slot finishes rendering.
+    pub fn ready_fence(&self, index: FrameIndex) -> &Arc<Fence> {
+        &self.frames[index.0 as usize].ready
+    }
+
+    /// Returns the index of the last presented frame, if any.
+    #[inline]
+    pub fn last_presented(&self) -> Option<FrameIndex> {
+        self.last_presented
+    }
+}
+
+impl Screen for OffscreenScreen {
+    fn extent(&self) -> Extent2D {
+        self.extent
+    }
+
+    fn format(&self) -> ImageFormat {
+        self.render_format
+    }
+
+    fn acquire_image(&mut self) -> Result<AcquiredImage, Error> {
+        let index = self.next_frame;
+        self.next_frame = (self.next_frame + 1) % self.frames.len();
+
+        let frame = &self.frames[index];
+        frame.ready.wait(u64::MAX);
+        frame.ready.reset();
+
+        // There's no presentation engine handing out the images, so the
+        // acquire semaphore is signaled right away.
+        self.queue
+            .signal(vec![], vec![frame.acquire_semaphore.clone()], None);
+
+        Ok(AcquiredImage {
+            index: FrameIndex(index as u32),
+            image: frame.image.clone(),
+            image_view: frame.render_view.clone(),
+            acquire_semaphore: frame.acquire_semaphore.clone(),
+            render_semaphore: frame.render_semaphore.clone(),
+        })
+    }
+
+    fn present(&mut self, image: AcquiredImage) -> Result<(), Error> {
+        let frame = &self.frames[image.index.0 as usize];
+
+        // Consume the render semaphore, so the next frame rendered to the
+        // image can signal it again.
+        self.queue.signal(
+            vec![frame.render_semaphore.clone()],
+            vec![],
+            Some(frame.ready.clone()),
+        );
+        self.last_presented = Some(image.index);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_render_rhi::{
+        VkHandle,
+        command::{
+            AccessFlags2, CommandBufferLevel, CommandBufferUsageFlags, CommandEncoder, CommandPool,
+            ImageLayout, PipelineStageFlags2,
+        },
+        test_util::TestDevice,
+    };
+
+    use super::*;
+
+    #[test]
+    #[ignore = "requires a Vulkan driver"]
+    fn test_srgb_view_encodes_unorm_images() {
+        let test_device = TestDevice::new();
+        let mut screen = OffscreenScreen::new(
+            test_device.device.clone(),
+            test_device.allocator.clone(),
+            test_device.queue.clone(),
+            OffscreenScreenCreateInfo {
+                extent: Extent2D::new(1, 1),
+                format: ImageFormat::Rgba8Unorm,
+                image_count: 1,
+                srgb_view: true,
+            },
+        );
+        assert_eq!(screen.format(), ImageFormat::Rgba8Srgb);
+
+        let acquired = screen.acquire_image().unwrap();
+        assert_eq!(acquired.image.format(), ImageFormat::Rgba8Unorm);
+
+        let pool = CommandPool::new(test_device.device.clone(), 0);
+        let mut encoder = CommandEncoder::new(pool.allocate(CommandBufferLevel::PRIMARY));
+        encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        encoder.transition_image(
+            acquired.image.clone(),
+            ImageLayout::ColorAttachmentOptimal,
+            PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+            AccessFlags2::COLOR_ATTACHMENT_WRITE,
+        );
+        let color_attachments = [vk::RenderingAttachmentInfo::default()
+            .image_view(acquired.image_view.vk_handle())
+            .image_layout(ImageLayout::ColorAttachmentOptimal.to_vk())
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .clear_value(vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.5, 0.5, 0.5, 1.0],
+                },
+            })];
+        encoder.begin_rendering(
+            &vk::RenderingInfo::default()
+                .color_attachments(&color_attachments)
+                .render_area(vk::Rect2D::default().extent(screen.extent().to_vk()))
+                .layer_count(1),
+        );
+        encoder.end_rendering();
+        encoder.end();
+
+        test_device.queue.submit(
+            encoder,
+            vec![acquired.acquire_semaphore.clone()],
+            vec![acquired.render_semaphore.clone()],
+            None,
+        );
+        let index = acquired.index;
+        let image = acquired.image.clone();
+        screen.present(acquired).unwrap();
+        assert!(screen.ready_fence(index).wait(u64::MAX));
+
+        // Linear 0.5 is encoded as 0.735 in sRGB.
+        let [[r, g, b, a]] = test_device
+            .read_image(&image, ImageLayout::ColorAttachmentOptimal)
+            .try_into()
+            .unwrap();
+        for channel in [r, g, b] {
+            assert!(
+                (187..=189).contains(&channel),
+                "{channel} isn't sRGB encoded"
+            );
+        }
+        assert_eq!(a, 255);
+    }
+}
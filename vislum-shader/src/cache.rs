@@ -0,0 +1,535 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use thiserror::Error;
+use vislum_fs::{FileEvent, FileEventType, FsError, VirtualFs, VirtualPath};
+
+use crate::{
+    compiler::{CompilerError, ShaderCompiler, ShaderType},
+    composer::{ComposeError, ShaderComposer},
+    directive::collect_includes,
+    material::{MATERIAL_INCLUDE_PATH, MaterialLayout, MaterialLayoutError},
+    persistent::PersistentShaderCache,
+};
+
+#[derive(Debug, Error)]
+pub enum ShaderCacheError {
+    #[error("failed to read shader source {0:?}: {1}")]
+    Read(VirtualPath, #[source] FsError),
+    #[error("shader source {0:?} is not valid UTF-8")]
+    InvalidUtf8(VirtualPath),
+    #[error("failed to compose shader {0:?}: {1}")]
+    Compose(VirtualPath, #[source] ComposeError),
+    #[error("failed to compile shader {0:?}: {1}")]
+    Compilation(VirtualPath, #[source] CompilerError),
+    #[error("invalid material layout: {0}")]
+    MaterialLayout(#[from] MaterialLayoutError),
+}
+
+/// Compiles shader sources into SPIR-V.
+///
+/// Implemented by [`ShaderCompiler`]. The cache is generic over it so the
+/// reload logic can be exercised without a DXC installation.
+pub trait SourceCompiler {
+    fn compile(
+        &self,
+        shader_source: &str,
+        entry_point: &str,
+        shader_type: ShaderType,
+    ) -> Result<Vec<u8>, CompilerError>;
+}
+
+impl SourceCompiler for ShaderCompiler {
+    fn compile(
+        &self,
+        shader_source: &str,
+        entry_point: &str,
+        shader_type: ShaderType,
+    ) -> Result<Vec<u8>, CompilerError> {
+        ShaderCompiler::compile(self, shader_source, entry_point, shader_type)
+    }
+}
+
+/// A compiled shader module.
+#[derive(Debug, Clone)]
+pub struct CachedShader {
+    pub spirv: Arc<[u8]>,
+    /// The generation of the cache when the module was compiled.
+    ///
+    /// Pipelines created from an older generation of the module must be
+    /// rebuilt.
+    pub generation: u64,
+    /// The files included by the shader, directly or not.
+    pub dependencies: Vec<VirtualPath>,
+}
+
+struct ShaderEntry {
+    entry_point: String,
+    shader_type: ShaderType,
+    shader: CachedShader,
+    /// Whether the shader includes the generated material block.
+    includes_material: bool,
+}
+
+impl ShaderEntry {
+    /// Returns true if the shader must be recompiled when `path` changes.
+    fn depends_on(&self, path: &VirtualPath, own_path: &VirtualPath) -> bool {
+        own_path == path || self.shader.dependencies.contains(path)
+    }
+}
+
+/// Caches the SPIR-V of the shaders compiled from a virtual filesystem, and
+/// recompiles them when their source changes.
+///
+/// Shaders are keyed by their source path, so each source file is compiled
+/// with a single entry point. Sources are composed before compiling them, with
+/// the includes which are virtual paths read from the filesystem.
+pub struct ShaderCache<C = ShaderCompiler> {
+    compiler: C,
+    composer: ShaderComposer,
+    persistent: Option<PersistentShaderCache>,
+    entries: HashMap<VirtualPath, ShaderEntry>,
+    generation: u64,
+}
+
+impl<C: SourceCompiler> ShaderCache<C> {
+    pub fn new(compiler: C) -> Self {
+        Self {
+            compiler,
+            composer: ShaderComposer::default(),
+            persistent: None,
+            entries: HashMap::new(),
+            generation: 0,
+        }
+    }
+
+    /// Stores the compiled modules in the `shader-cache://` namespace, so
+    /// they are reused across runs instead of being compiled again.
+    pub fn with_persistent_cache(mut self, persistent: PersistentShaderCache) -> Self {
+        self.persistent = Some(persistent);
+        self
+    }
+
+    /// Returns the composer used to compose the sources, to set its defines
+    /// and generated includes.
+    ///
+    /// Shaders already cached are not recompiled.
+    #[inline]
+    pub fn composer_mut(&mut self) -> &mut ShaderComposer {
+        &mut self.composer
+    }
+
+    /// Returns the current generation of the cache.
+    ///
+    /// It is bumped every time a shader is compiled, so comparing it against
+    /// the one of a previous frame tells whether any module changed.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the cached module of the shader at `path`, if it was loaded.
+    pub fn get(&self, path: &VirtualPath) -> Option<&CachedShader> {
+        self.entries.get(path).map(|entry| &entry.shader)
+    }
+
+    /// Returns the module of the shader at `path`, compiling it on the first
+    /// request.
+    pub fn load(
+        &mut self,
+        fs: &VirtualFs,
+        path: VirtualPath,
+        entry_point: &str,
+        shader_type: ShaderType,
+    ) -> Result<&CachedShader, ShaderCacheError> {
+        if !self.entries.contains_key(&path) {
+            let (shader, includes_material) = self.compile(fs, &path, entry_point, shader_type)?;
+
+            self.entries.insert(
+                path.clone(),
+                ShaderEntry {
+                    entry_point: entry_point.to_string(),
+                    shader_type,
+                    shader,
+                    includes_material,
+                },
+            );
+        }
+
+        Ok(&self.entries[&path].shader)
+    }
+
+    /// Recompiles the cached shaders whose sources, or any of the files they
+    /// include, were created or modified.
+    ///
+    /// Events for paths no cached shader depends on are ignored. If a shader
+    /// fails to compile, its previous module is kept, so a typo in a watched
+    /// source doesn't take down the render loop, and the error is returned for
+    /// the caller to report.
+    ///
+    /// Meant to be called once per frame with the events drained from
+    /// [`VirtualFs::events`].
+    pub fn handle_file_events(
+        &mut self,
+        fs: &VirtualFs,
+        events: impl Iterator<Item = FileEvent>,
+    ) -> Vec<ShaderCacheError> {
+        let mut errors = Vec::new();
+
+        for event in events {
+            if !matches!(
+                event.event_type,
+                FileEventType::Created | FileEventType::Modified
+            ) {
+                continue;
+            }
+
+            let stale = self
+                .entries
+                .iter()
+                .filter(|(path, entry)| entry.depends_on(&event.path, path))
+                .map(|(path, entry)| (path.clone(), entry.entry_point.clone(), entry.shader_type))
+                .collect::<Vec<_>>();
+
+            errors.extend(self.recompile(fs, stale));
+        }
+
+        errors
+    }
+
+    /// Sets the material layout of the composer, recompiling the cached
+    /// shaders which include the material block if it changed.
+    ///
+    /// Like [`ShaderCache::handle_file_events`], shaders failing to compile
+    /// keep their previous module and the errors are returned. An invalid
+    /// layout is returned as the only error, keeping the previous one.
+    pub fn set_material_layout(
+        &mut self,
+        fs: &VirtualFs,
+        layout: &MaterialLayout,
+    ) -> Vec<ShaderCacheError> {
+        match self.composer.set_material_layout(layout) {
+            Ok(true) => {}
+            Ok(false) => return Vec::new(),
+            Err(error) => return vec![error.into()],
+        }
+
+        let stale = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.includes_material)
+            .map(|(path, entry)| (path.clone(), entry.entry_point.clone(), entry.shader_type))
+            .collect::<Vec<_>>();
+
+        self.recompile(fs, stale)
+    }
+
+    /// Recompiles the given cached shaders, keeping the previous module of
+    /// the ones which fail to compile.
+    fn recompile(
+        &mut self,
+        fs: &VirtualFs,
+        stale: Vec<(VirtualPath, String, ShaderType)>,
+    ) -> Vec<ShaderCacheError> {
+        let mut errors = Vec::new();
+
+        for (path, entry_point, shader_type) in stale {
+            match self.compile(fs, &path, &entry_point, shader_type) {
+                Ok((shader, includes_material)) => {
+                    let entry = self.entries.get_mut(&path).unwrap();
+                    entry.shader = shader;
+                    entry.includes_material = includes_material;
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        errors
+    }
+
+    /// Composes and compiles the shader at `path`, bumping the generation on
+    /// success.
+    ///
+    /// Also returns whether the shader includes the material block.
+    fn compile(
+        &mut self,
+        fs: &VirtualFs,
+        path: &VirtualPath,
+        entry_point: &str,
+        shader_type: ShaderType,
+    ) -> Result<(CachedShader, bool), ShaderCacheError> {
+        let source = read_source(fs, path)?;
+        let includes = self.load_includes(fs, &source);
+
+        let (source, dependencies) = self
+            .composer
+            .compose_with_dependencies(&path.to_string(), &source)
+            .map_err(|error| ShaderCacheError::Compose(path.clone(), error))?;
+
+        let spirv = match &self.persistent {
+            Some(persistent) => persistent.compile(
+                fs,
+                &self.compiler,
+                &source,
+                entry_point,
+                shader_type,
+                &dependencies,
+            ),
+            None => self.compiler.compile(&source, entry_point, shader_type),
+        }
+        .map_err(|error| ShaderCacheError::Compilation(path.clone(), error))?;
+
+        self.generation += 1;
+
+        let shader = CachedShader {
+            spirv: spirv.into(),
+            generation: self.generation,
+            dependencies,
+        };
+
+        Ok((shader, includes.contains(MATERIAL_INCLUDE_PATH)))
+    }
+
+    /// Reads the files transitively included by `source` into the composer,
+    /// replacing their previous contents.
+    ///
+    /// Includes which aren't virtual paths are left to the composer. The ones
+    /// which can't be read are skipped, as they may be in inactive `#ifdef`
+    /// branches; the composer reports the ones actually missing.
+    ///
+    /// Returns every include found, including the ones left to the composer.
+    fn load_includes(&mut self, fs: &VirtualFs, source: &str) -> HashSet<String> {
+        let mut pending = collect_includes(source)
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let mut visited = HashSet::new();
+
+        while let Some(include) = pending.pop() {
+            if !visited.insert(include.clone()) {
+                continue;
+            }
+
+            let Ok(path) = VirtualPath::parse(&include) else {
+                continue;
+            };
+            let Ok(include_source) = read_source(fs, &path) else {
+                continue;
+            };
+
+            pending.extend(collect_includes(&include_source).map(str::to_string));
+            self.composer.add_import_source(include, include_source);
+        }
+
+        visited
+    }
+}
+
+fn read_source(fs: &VirtualFs, path: &VirtualPath) -> Result<String, ShaderCacheError> {
+    let source = fs
+        .read(path.clone())
+        .map_err(|error| ShaderCacheError::Read(path.clone(), error))?;
+
+    String::from_utf8(source.to_vec()).map_err(|_| ShaderCacheError::InvalidUtf8(path.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use vislum_fs::{Fs, MemoryFs, VirtualNamespace};
+
+    use super::*;
+
+    /// A compiler which "compiles" the source to its own bytes, failing on
+    /// sources containing `error`.
+    struct EchoCompiler;
+
+    impl SourceCompiler for EchoCompiler {
+        fn compile(
+            &self,
+            shader_source: &str,
+            _entry_point: &str,
+            _shader_type: ShaderType,
+        ) -> Result<Vec<u8>, CompilerError> {
+            if shader_source.contains("error") {
+                return Err(CompilerError::CompilationFailed(
+                    1,
+                    shader_source.to_string(),
+                ));
+            }
+
+            Ok(shader_source.as_bytes().to_vec())
+        }
+    }
+
+    fn modified(path: &VirtualPath) -> FileEvent {
+        FileEvent {
+            path: path.clone(),
+            event_type: FileEventType::Modified,
+        }
+    }
+
+    #[test]
+    fn test_modified_event_recompiles_shader() {
+        let memory = MemoryFs::new();
+        memory.insert("shaders/main.hlsl", b"v1".as_slice());
+        let vfs = VirtualFs::new([(VirtualNamespace::Project, Box::new(memory) as Box<dyn Fs>)]);
+
+        let path = VirtualPath::parse("project://shaders/main.hlsl").unwrap();
+        let mut cache = ShaderCache::new(EchoCompiler);
+        let first = cache
+            .load(&vfs, path.clone(), "main", ShaderType::Fragment)
+            .unwrap()
+            .clone();
+        assert_eq!(&*first.spirv, b"v1\n");
+
+        // Loading again hits the cache.
+        cache
+            .load(&vfs, path.clone(), "main", ShaderType::Fragment)
+            .unwrap();
+        assert_eq!(cache.generation(), first.generation);
+
+        vfs.write(path.clone(), b"v2").unwrap();
+        let errors = cache.handle_file_events(&vfs, [modified(&path)].into_iter());
+        assert!(errors.is_empty());
+
+        let second = cache.get(&path).unwrap();
+        assert_eq!(&*second.spirv, b"v2\n");
+        assert!(second.generation > first.generation);
+        assert_eq!(cache.generation(), second.generation);
+
+        // Events for shaders which were never loaded are ignored.
+        let other = VirtualPath::parse("project://shaders/other.hlsl").unwrap();
+        assert!(
+            cache
+                .handle_file_events(&vfs, [modified(&other)].into_iter())
+                .is_empty()
+        );
+        assert!(cache.get(&other).is_none());
+    }
+
+    #[test]
+    fn test_modified_header_recompiles_shaders_including_it() {
+        let memory = MemoryFs::new();
+        memory.insert("common.hlsl", b"common v1".as_slice());
+        memory.insert(
+            "a.hlsl",
+            b"#include \"project://common.hlsl\"\na".as_slice(),
+        );
+        memory.insert(
+            "b.hlsl",
+            b"#include \"project://common.hlsl\"\nb".as_slice(),
+        );
+        memory.insert("c.hlsl", b"c".as_slice());
+        let vfs = VirtualFs::new([(VirtualNamespace::Project, Box::new(memory) as Box<dyn Fs>)]);
+
+        let header = VirtualPath::parse("project://common.hlsl").unwrap();
+        let [a, b, c] = ["a", "b", "c"]
+            .map(|name| VirtualPath::parse(&format!("project://{name}.hlsl")).unwrap());
+
+        let mut cache = ShaderCache::new(EchoCompiler);
+        for path in [&a, &b, &c] {
+            cache
+                .load(&vfs, path.clone(), "main", ShaderType::Fragment)
+                .unwrap();
+        }
+        assert_eq!(&*cache.get(&a).unwrap().spirv, b"common v1\na\n");
+        assert_eq!(
+            cache.get(&b).unwrap().dependencies,
+            std::slice::from_ref(&header)
+        );
+        assert!(cache.get(&c).unwrap().dependencies.is_empty());
+        let c_generation = cache.get(&c).unwrap().generation;
+
+        vfs.write(header.clone(), b"common v2").unwrap();
+        let errors = cache.handle_file_events(&vfs, [modified(&header)].into_iter());
+        assert!(errors.is_empty());
+
+        assert_eq!(&*cache.get(&a).unwrap().spirv, b"common v2\na\n");
+        assert_eq!(&*cache.get(&b).unwrap().spirv, b"common v2\nb\n");
+        assert_eq!(cache.get(&c).unwrap().generation, c_generation);
+    }
+
+    #[test]
+    fn test_failed_recompilation_keeps_previous_module() {
+        let vfs = VirtualFs::new([(
+            VirtualNamespace::Project,
+            Box::new(MemoryFs::new()) as Box<dyn Fs>,
+        )]);
+        let path = VirtualPath::parse("project://main.hlsl").unwrap();
+        vfs.write(path.clone(), b"valid").unwrap();
+
+        let mut cache = ShaderCache::new(EchoCompiler);
+        cache
+            .load(&vfs, path.clone(), "main", ShaderType::Vertex)
+            .unwrap();
+        let generation = cache.generation();
+
+        vfs.write(path.clone(), b"syntax error").unwrap();
+        let errors = cache.handle_file_events(&vfs, [modified(&path)].into_iter());
+        assert!(matches!(
+            errors.as_slice(),
+            [ShaderCacheError::Compilation(error_path, _)] if *error_path == path
+        ));
+
+        let shader = cache.get(&path).unwrap();
+        assert_eq!(&*shader.spirv, b"valid\n");
+        assert_eq!(cache.generation(), generation);
+    }
+
+    #[test]
+    fn test_material_layout_recompiles_shaders_including_it() {
+        use crate::material::{MaterialProperty, MaterialPropertyType};
+
+        let memory = MemoryFs::new();
+        memory.insert(
+            "lit.hlsl",
+            b"#include \"vislum/material.hlsl\"\nlit".as_slice(),
+        );
+        memory.insert("plain.hlsl", b"plain".as_slice());
+        let vfs = VirtualFs::new([(VirtualNamespace::Project, Box::new(memory) as Box<dyn Fs>)]);
+        let [lit, plain] = ["lit", "plain"]
+            .map(|name| VirtualPath::parse(&format!("project://{name}.hlsl")).unwrap());
+
+        let mut layout = MaterialLayout {
+            name: "Material".to_string(),
+            set: 1,
+            binding: 0,
+            properties: vec![MaterialProperty {
+                name: "roughness".to_string(),
+                ty: MaterialPropertyType::Float,
+                offset: 0,
+            }],
+        };
+
+        let mut cache = ShaderCache::new(EchoCompiler);
+        assert!(cache.set_material_layout(&vfs, &layout).is_empty());
+        for path in [&lit, &plain] {
+            cache
+                .load(&vfs, path.clone(), "main", ShaderType::Fragment)
+                .unwrap();
+        }
+        let lit_generation = cache.get(&lit).unwrap().generation;
+        let plain_generation = cache.get(&plain).unwrap().generation;
+
+        // Setting the same layout again doesn't recompile anything.
+        assert!(cache.set_material_layout(&vfs, &layout).is_empty());
+        assert_eq!(cache.get(&lit).unwrap().generation, lit_generation);
+
+        layout.properties.push(MaterialProperty {
+            name: "metallic".to_string(),
+            ty: MaterialPropertyType::Float,
+            offset: 4,
+        });
+        assert!(cache.set_material_layout(&vfs, &layout).is_empty());
+
+        let shader = cache.get(&lit).unwrap();
+        assert!(shader.generation > lit_generation);
+        assert!(
+            std::str::from_utf8(&shader.spirv)
+                .unwrap()
+                .contains("metallic")
+        );
+        assert_eq!(cache.get(&plain).unwrap().generation, plain_generation);
+    }
+}
@@ -1,7 +1,15 @@
-use spirv_cross2::{Compiler, Module, targets::None as SpirvCrossNone};
+use spirv_cross2::{Compiler, Module, reflect::ResourceType, targets::None as SpirvCrossNone};
 use std::fs;
-use std::process::Command;
+use std::process::{Command, Output};
 use thiserror::Error;
+use vislum_dxc::{DxcCompilationError, DxcCompileOptions, DxcCompiler, DxcIncludeHandler};
+use vislum_render_rhi::{
+    descriptor::{
+        DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+        DescriptorType as RhiDescriptorType,
+    },
+    shader::ShaderStageFlags,
+};
 
 #[derive(Error, Debug)]
 pub enum CompilerError {
@@ -17,6 +25,40 @@ pub enum CompilerError {
     CompilationFailed(i32, String),
     #[error("Failed to create SPIRV reflection: {0}")]
     ReflectionFailed(String),
+    #[error("In-process DXC compilation failed: {0}")]
+    DxcCompilationFailed(#[from] DxcCompilationError),
+    #[error("SPIR-V bytecode length {0} is not a multiple of four")]
+    InvalidSpirvLength(usize),
+    #[error("SPIRV-Tools executable not found: {0}")]
+    SpirvToolNotFound(String),
+    #[error("SPIR-V disassembly failed: {0}")]
+    DisassemblyFailed(String),
+    #[error("SPIR-V validation failed with {} error(s)", .0.len())]
+    ValidationFailed(Vec<ValidationMessage>),
+}
+
+/// An error reported by the SPIR-V validator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationMessage {
+    pub message: String,
+    /// The disassembled instruction the error was reported at, if any.
+    pub instruction: Option<String>,
+}
+
+/// Converts SPIR-V bytecode into its words.
+///
+/// SPIR-V is made of little-endian 32-bit words, so bytecode with a trailing
+/// partial word is truncated or corrupt and is rejected rather than silently
+/// cut short.
+pub fn spirv_words(spirv_bytes: &[u8]) -> Result<Vec<u32>, CompilerError> {
+    if !spirv_bytes.len().is_multiple_of(4) {
+        return Err(CompilerError::InvalidSpirvLength(spirv_bytes.len()));
+    }
+
+    Ok(spirv_bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
 }
 
 #[derive(Error, Debug)]
@@ -33,6 +75,8 @@ pub enum ReflectorError {
     ResourceEnumerationFailed(String),
     #[error("Failed to get decoration: {0}")]
     DecorationFailed(String),
+    #[error("Descriptor type is not supported by the RHI: {0}")]
+    UnsupportedDescriptorType(String),
 }
 
 impl From<ReflectorError> for CompilerError {
@@ -81,10 +125,8 @@ impl ShaderReflector {
         }
 
         // Convert bytes to words for spirv-cross2
-        let words: Vec<u32> = spirv_bytes
-            .chunks_exact(4)
-            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-            .collect();
+        let words =
+            spirv_words(spirv_bytes).map_err(|e| ReflectorError::InvalidSpirv(e.to_string()))?;
 
         // Create SPIRV module
         let module = Module::from_words(&words);
@@ -112,131 +154,26 @@ impl ShaderReflector {
         let mut sets: std::collections::HashMap<u32, Vec<DescriptorBinding>> =
             std::collections::HashMap::new();
 
-        // Process sampled images
-        if let Ok(sampled_images) =
-            resources.resources_for_type(spirv_cross2::reflect::ResourceType::SampledImage)
-        {
-            for resource in sampled_images {
-                let set = compiler
-                    .decoration(resource.id, spirv_cross2::spirv::Decoration::DescriptorSet)
-                    .map_err(|e| ReflectorError::DecorationFailed(e.to_string()))?
-                    .and_then(|d| match d {
-                        spirv_cross2::reflect::DecorationValue::Literal(s) => Some(s),
-                        _ => None,
-                    })
-                    .ok_or_else(|| {
-                        ReflectorError::DecorationFailed(
-                            "Descriptor set decoration not found".to_string(),
-                        )
-                    })?;
-
-                let binding = compiler
-                    .decoration(resource.id, spirv_cross2::spirv::Decoration::Binding)
-                    .map_err(|e| ReflectorError::DecorationFailed(e.to_string()))?
-                    .and_then(|d| match d {
-                        spirv_cross2::reflect::DecorationValue::Literal(b) => Some(b),
-                        _ => None,
-                    })
-                    .ok_or_else(|| {
-                        ReflectorError::DecorationFailed("Binding decoration not found".to_string())
-                    })?;
-
-                let desc_binding = DescriptorBinding {
-                    binding,
-                    name: resource.name.to_string(),
-                    descriptor_type: DescriptorType::CombinedImageSampler,
-                    count: 1,
-                    stage_flags: 0,
-                };
-                sets.entry(set).or_insert_with(Vec::new).push(desc_binding);
-            }
-        }
-
-        // Process storage images
-        if let Ok(storage_images) =
-            resources.resources_for_type(spirv_cross2::reflect::ResourceType::StorageImage)
-        {
-            for resource in storage_images {
-                let set = compiler
-                    .decoration(resource.id, spirv_cross2::spirv::Decoration::DescriptorSet)
-                    .map_err(|e| ReflectorError::DecorationFailed(e.to_string()))?
-                    .and_then(|d| match d {
-                        spirv_cross2::reflect::DecorationValue::Literal(s) => Some(s),
-                        _ => None,
-                    })
-                    .ok_or_else(|| {
-                        ReflectorError::DecorationFailed(
-                            "Descriptor set decoration not found".to_string(),
-                        )
-                    })?;
-
-                let binding = compiler
-                    .decoration(resource.id, spirv_cross2::spirv::Decoration::Binding)
-                    .map_err(|e| ReflectorError::DecorationFailed(e.to_string()))?
-                    .and_then(|d| match d {
-                        spirv_cross2::reflect::DecorationValue::Literal(b) => Some(b),
-                        _ => None,
-                    })
-                    .ok_or_else(|| {
-                        ReflectorError::DecorationFailed("Binding decoration not found".to_string())
-                    })?;
-
-                let desc_binding = DescriptorBinding {
-                    binding,
-                    name: resource.name.to_string(),
-                    descriptor_type: DescriptorType::StorageImage,
-                    count: 1,
-                    stage_flags: 0,
-                };
-                sets.entry(set).or_insert_with(Vec::new).push(desc_binding);
-            }
-        }
-
-        // Process uniform buffers
-        if let Ok(uniform_buffers) =
-            resources.resources_for_type(spirv_cross2::reflect::ResourceType::UniformBuffer)
-        {
-            for resource in uniform_buffers {
-                let set = compiler
-                    .decoration(resource.id, spirv_cross2::spirv::Decoration::DescriptorSet)
-                    .map_err(|e| ReflectorError::DecorationFailed(e.to_string()))?
-                    .and_then(|d| match d {
-                        spirv_cross2::reflect::DecorationValue::Literal(s) => Some(s),
-                        _ => None,
-                    })
-                    .ok_or_else(|| {
-                        ReflectorError::DecorationFailed(
-                            "Descriptor set decoration not found".to_string(),
-                        )
-                    })?;
-
-                let binding = compiler
-                    .decoration(resource.id, spirv_cross2::spirv::Decoration::Binding)
-                    .map_err(|e| ReflectorError::DecorationFailed(e.to_string()))?
-                    .and_then(|d| match d {
-                        spirv_cross2::reflect::DecorationValue::Literal(b) => Some(b),
-                        _ => None,
-                    })
-                    .ok_or_else(|| {
-                        ReflectorError::DecorationFailed("Binding decoration not found".to_string())
-                    })?;
+        // HLSL textures and samplers are reflected as separate images and
+        // samplers, while combined image samplers come from GLSL-style sources.
+        let descriptor_resource_types = [
+            (
+                ResourceType::SampledImage,
+                DescriptorType::CombinedImageSampler,
+            ),
+            (ResourceType::SeparateImage, DescriptorType::SampledImage),
+            (ResourceType::SeparateSamplers, DescriptorType::Sampler),
+            (ResourceType::StorageImage, DescriptorType::StorageImage),
+            (ResourceType::UniformBuffer, DescriptorType::UniformBuffer),
+            (ResourceType::StorageBuffer, DescriptorType::StorageBuffer),
+        ];
 
-                let desc_binding = DescriptorBinding {
-                    binding,
-                    name: resource.name.to_string(),
-                    descriptor_type: DescriptorType::UniformBuffer,
-                    count: 1,
-                    stage_flags: 0,
-                };
-                sets.entry(set).or_insert_with(Vec::new).push(desc_binding);
-            }
-        }
+        for (resource_type, descriptor_type) in descriptor_resource_types {
+            let Ok(typed_resources) = resources.resources_for_type(resource_type) else {
+                continue;
+            };
 
-        // Process storage buffers
-        if let Ok(storage_buffers) =
-            resources.resources_for_type(spirv_cross2::reflect::ResourceType::StorageBuffer)
-        {
-            for resource in storage_buffers {
+            for resource in typed_resources {
                 let set = compiler
                     .decoration(resource.id, spirv_cross2::spirv::Decoration::DescriptorSet)
                     .map_err(|e| ReflectorError::DecorationFailed(e.to_string()))?
@@ -264,18 +201,20 @@ impl ShaderReflector {
                 let desc_binding = DescriptorBinding {
                     binding,
                     name: resource.name.to_string(),
-                    descriptor_type: DescriptorType::StorageBuffer,
+                    descriptor_type,
                     count: 1,
-                    stage_flags: 0,
+                    stage_flags: shader_stage.stage_flags(),
                 };
                 sets.entry(set).or_insert_with(Vec::new).push(desc_binding);
             }
         }
 
         // Convert sets to descriptor sets
-        for (set, bindings) in sets {
+        for (set, mut bindings) in sets {
+            bindings.sort_by_key(|binding| binding.binding);
             descriptor_sets.push(DescriptorSet { set, bindings });
         }
+        descriptor_sets.sort_by_key(|set| set.set);
 
         // Extract push constants
         let mut push_constants = Vec::new();
@@ -283,11 +222,25 @@ impl ShaderReflector {
             resources.resources_for_type(spirv_cross2::reflect::ResourceType::PushConstant)
         {
             for resource in push_constant_buffers {
+                // The range covers the members the shader actually reads, so
+                // stages reading disjoint members get disjoint ranges.
+                let ranges = compiler
+                    .active_buffer_ranges(resource.id)
+                    .map_err(|e| ReflectorError::ResourceEnumerationFailed(e.to_string()))?;
+                let Some(start) = ranges.iter().map(|range| range.offset).min() else {
+                    continue;
+                };
+                let end = ranges
+                    .iter()
+                    .map(|range| range.offset + range.range)
+                    .max()
+                    .unwrap_or(start);
+
                 push_constants.push(PushConstant {
                     name: resource.name.to_string(),
-                    offset: 0, // spirv-cross2 doesn't provide offset directly
-                    size: 0,   // spirv-cross2 doesn't provide size directly
-                    stage_flags: 0,
+                    offset: start as u32,
+                    size: (end - start) as u32,
+                    stage_flags: shader_stage.stage_flags(),
                 });
             }
         }
@@ -299,6 +252,27 @@ impl ShaderReflector {
             shader_stage,
         })
     }
+
+    /// Compiles a shader with the in-process DXC compiler, then reflects the
+    /// bytecode.
+    ///
+    /// DXC does not emit a reflection blob for SPIR-V targets, so this is the
+    /// same as calling [`DxcCompiler::compile`] and then
+    /// [`ShaderReflector::reflect_spirv`]; the bytecode is still parsed a
+    /// second time.
+    pub fn compile_with_reflection(
+        &self,
+        compiler: &DxcCompiler,
+        shader_source: &str,
+        entry_point: &str,
+        shader_type: ShaderType,
+        include_handler: &dyn DxcIncludeHandler,
+    ) -> Result<(Vec<u8>, ShaderReflection), CompilerError> {
+        let options = DxcCompileOptions::new(shader_type.target_profile(), entry_point);
+        let spirv_bytes = compiler.compile(shader_source, &options, include_handler)?;
+        let reflection = self.reflect_spirv(&spirv_bytes, shader_type)?;
+        Ok((spirv_bytes, reflection))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -309,6 +283,91 @@ pub struct ShaderReflection {
     pub shader_stage: ShaderStage,
 }
 
+impl ShaderReflection {
+    /// Merges the reflection of another stage of the same pipeline.
+    ///
+    /// Bindings and push constant blocks declared by both stages are merged
+    /// into one, visible to the stages of both.
+    pub fn merge(&mut self, other: &ShaderReflection) {
+        self.entry_points.extend(other.entry_points.iter().cloned());
+
+        for other_set in &other.descriptor_sets {
+            let Some(set) = self
+                .descriptor_sets
+                .iter_mut()
+                .find(|set| set.set == other_set.set)
+            else {
+                self.descriptor_sets.push(other_set.clone());
+                continue;
+            };
+
+            for other_binding in &other_set.bindings {
+                match set
+                    .bindings
+                    .iter_mut()
+                    .find(|binding| binding.binding == other_binding.binding)
+                {
+                    Some(binding) => {
+                        binding.stage_flags = binding.stage_flags.union(other_binding.stage_flags)
+                    }
+                    None => set.bindings.push(other_binding.clone()),
+                }
+            }
+        }
+
+        for other_push_constant in &other.push_constants {
+            match self
+                .push_constants
+                .iter_mut()
+                .find(|push_constant| push_constant.name == other_push_constant.name)
+            {
+                Some(push_constant) => {
+                    let end = (push_constant.offset + push_constant.size)
+                        .max(other_push_constant.offset + other_push_constant.size);
+                    push_constant.offset = push_constant.offset.min(other_push_constant.offset);
+                    push_constant.size = end - push_constant.offset;
+                    push_constant.stage_flags = push_constant
+                        .stage_flags
+                        .union(other_push_constant.stage_flags);
+                }
+                None => self.push_constants.push(other_push_constant.clone()),
+            }
+        }
+    }
+
+    /// Returns the descriptor set layouts declared by the shader, indexed by
+    /// set number.
+    ///
+    /// Sets the shader doesn't use, but which come before the last one it
+    /// does, are returned empty, so the layouts can be handed to a pipeline
+    /// layout as is.
+    pub fn descriptor_set_layouts(
+        &self,
+    ) -> Result<Vec<DescriptorSetLayoutCreateInfo>, ReflectorError> {
+        let set_count = self
+            .descriptor_sets
+            .iter()
+            .map(|set| set.set + 1)
+            .max()
+            .unwrap_or(0);
+
+        (0..set_count)
+            .map(|index| {
+                let mut bindings = self
+                    .descriptor_sets
+                    .iter()
+                    .filter(|set| set.set == index)
+                    .flat_map(|set| &set.bindings)
+                    .map(DescriptorBinding::to_layout_binding)
+                    .collect::<Result<Vec<_>, _>>()?;
+                bindings.sort_by_key(|binding| binding.binding);
+
+                Ok(DescriptorSetLayoutCreateInfo { bindings })
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EntryPoint {
     pub name: String,
@@ -327,7 +386,26 @@ pub struct DescriptorBinding {
     pub name: String,
     pub descriptor_type: DescriptorType,
     pub count: u32,
-    pub stage_flags: u32,
+    pub stage_flags: ShaderStageFlags,
+}
+
+impl DescriptorBinding {
+    /// Returns the binding as declared in a descriptor set layout.
+    pub fn to_layout_binding(&self) -> Result<DescriptorSetLayoutBinding, ReflectorError> {
+        let descriptor_type = self.descriptor_type.to_rhi().ok_or_else(|| {
+            ReflectorError::UnsupportedDescriptorType(format!(
+                "{:?} ({})",
+                self.descriptor_type, self.name
+            ))
+        })?;
+
+        Ok(DescriptorSetLayoutBinding {
+            binding: self.binding,
+            descriptor_type,
+            count: self.count,
+            stages: self.stage_flags,
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -335,7 +413,7 @@ pub struct PushConstant {
     pub name: String,
     pub offset: u32,
     pub size: u32,
-    pub stage_flags: u32,
+    pub stage_flags: ShaderStageFlags,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -345,6 +423,17 @@ pub enum ShaderStage {
     Compute,
 }
 
+impl ShaderStage {
+    /// Returns the flags of the pipeline stage running the shader.
+    pub fn stage_flags(self) -> ShaderStageFlags {
+        match self {
+            ShaderStage::Vertex => ShaderStageFlags::VERTEX,
+            ShaderStage::Fragment => ShaderStageFlags::FRAGMENT,
+            ShaderStage::Compute => ShaderStageFlags::COMPUTE,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DescriptorType {
     Sampler,
@@ -362,6 +451,27 @@ pub enum DescriptorType {
     Unknown,
 }
 
+impl DescriptorType {
+    /// Returns the matching RHI descriptor type, or `None` if the RHI doesn't
+    /// support it.
+    pub fn to_rhi(self) -> Option<RhiDescriptorType> {
+        Some(match self {
+            DescriptorType::Sampler => RhiDescriptorType::Sampler,
+            DescriptorType::CombinedImageSampler => RhiDescriptorType::CombinedImageSampler,
+            DescriptorType::SampledImage => RhiDescriptorType::SampledImage,
+            DescriptorType::StorageImage => RhiDescriptorType::StorageImage,
+            DescriptorType::UniformTexelBuffer => RhiDescriptorType::UniformTexelBuffer,
+            DescriptorType::StorageTexelBuffer => RhiDescriptorType::StorageTexelBuffer,
+            DescriptorType::UniformBuffer => RhiDescriptorType::UniformBuffer,
+            DescriptorType::StorageBuffer => RhiDescriptorType::StorageBuffer,
+            DescriptorType::UniformBufferDynamic => RhiDescriptorType::UniformBufferDynamic,
+            DescriptorType::StorageBufferDynamic => RhiDescriptorType::StorageBufferDynamic,
+            DescriptorType::InputAttachment => RhiDescriptorType::InputAttachment,
+            DescriptorType::AccelerationStructure | DescriptorType::Unknown => return None,
+        })
+    }
+}
+
 impl ShaderCompiler {
     pub fn new() -> Result<Self, CompilerError> {
         // Try to find DXC in common locations
@@ -437,6 +547,56 @@ impl ShaderCompiler {
         Ok(spirv_bytes)
     }
 
+    /// Disassembles SPIR-V bytecode into its human-readable form, with the
+    /// `spirv-dis` tool from SPIRV-Tools.
+    pub fn disassemble(spirv_bytes: &[u8]) -> Result<String, CompilerError> {
+        let output = run_spirv_tool(
+            &["spirv-dis", "spirv-dis.exe"],
+            &["--no-color"],
+            spirv_bytes,
+        )?;
+
+        if !output.status.success() {
+            return Err(CompilerError::DisassemblyFailed(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Validates SPIR-V bytecode for Vulkan 1.3, with the `spirv-val` tool
+    /// from SPIRV-Tools.
+    ///
+    /// The errors are returned in [`CompilerError::ValidationFailed`], along
+    /// with the instruction each one was reported at.
+    pub fn validate(spirv_bytes: &[u8]) -> Result<(), CompilerError> {
+        let output = run_spirv_tool(
+            &["spirv-val", "spirv-val.exe"],
+            &["--target-env", "vulkan1.3"],
+            spirv_bytes,
+        )?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let report = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let mut messages = parse_validation_output(&report);
+        if messages.is_empty() {
+            messages.push(ValidationMessage {
+                message: report.trim().to_string(),
+                instruction: None,
+            });
+        }
+
+        Err(CompilerError::ValidationFailed(messages))
+    }
+
     pub fn compile_vertex(
         &self,
         shader_source: &str,
@@ -461,11 +621,17 @@ impl ShaderCompiler {
         self.compile(shader_source, entry_point, ShaderType::Compute)
     }
 
-    pub fn compile_with_reflection(
+    /// Compiles a shader and reflects its entry points, descriptor bindings
+    /// and push constants.
+    ///
+    /// Renderers derive their descriptor set layouts from the reflection with
+    /// [`ShaderReflection::descriptor_set_layouts`], rather than declaring the
+    /// bindings a second time.
+    pub fn compile_and_reflect(
         &self,
         shader_source: &str,
-        entry_point: &str,
         shader_type: ShaderType,
+        entry_point: &str,
     ) -> Result<(Vec<u8>, ShaderReflection), CompilerError> {
         let spirv_bytes = self.compile(shader_source, entry_point, shader_type)?;
         let reflector = ShaderReflector::new();
@@ -474,6 +640,58 @@ impl ShaderCompiler {
     }
 }
 
+/// Runs the first SPIRV-Tools executable found among `names` on the bytecode.
+fn run_spirv_tool(
+    names: &[&str],
+    args: &[&str],
+    spirv_bytes: &[u8],
+) -> Result<Output, CompilerError> {
+    // Catch truncated bytecode before handing it to the tool.
+    spirv_words(spirv_bytes)?;
+
+    let temp_file_path = std::env::temp_dir().join(format!("shader_{}.spv", uuid::Uuid::new_v4()));
+    fs::write(&temp_file_path, spirv_bytes).map_err(CompilerError::TempFileWriteFailed)?;
+
+    let output = names
+        .iter()
+        .find_map(|name| {
+            Command::new(name)
+                .arg(&temp_file_path)
+                .args(args)
+                .output()
+                .ok()
+        })
+        .ok_or_else(|| CompilerError::SpirvToolNotFound(names[0].to_string()));
+
+    let _ = fs::remove_file(&temp_file_path);
+
+    output
+}
+
+/// Parses the report of `spirv-val` into one message per error.
+///
+/// Each error is printed on a line starting with `error:`, optionally
+/// followed by an indented line with the offending instruction.
+fn parse_validation_output(report: &str) -> Vec<ValidationMessage> {
+    let mut messages = Vec::<ValidationMessage>::new();
+
+    for line in report.lines() {
+        if let Some(message) = line.strip_prefix("error:") {
+            messages.push(ValidationMessage {
+                message: message.trim().to_string(),
+                instruction: None,
+            });
+        } else if let Some(last) = messages.last_mut()
+            && last.instruction.is_none()
+            && !line.trim().is_empty()
+        {
+            last.instruction = Some(line.trim().to_string());
+        }
+    }
+
+    messages
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ShaderType {
     Vertex,
@@ -482,7 +700,7 @@ pub enum ShaderType {
 }
 
 impl ShaderType {
-    fn target_profile(self) -> &'static str {
+    pub(crate) fn target_profile(self) -> &'static str {
         match self {
             ShaderType::Vertex => "vs_6_0",
             ShaderType::Fragment => "ps_6_0",
@@ -500,3 +718,279 @@ impl Into<ShaderStage> for ShaderType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use vislum_dxc::DxcLoader;
+
+    use super::*;
+
+    struct NoIncludes;
+
+    impl DxcIncludeHandler for NoIncludes {
+        fn load_source(&self, _filename: &str) -> Option<String> {
+            None
+        }
+    }
+
+    fn binding(
+        binding: u32,
+        descriptor_type: DescriptorType,
+        stage_flags: ShaderStageFlags,
+    ) -> DescriptorBinding {
+        DescriptorBinding {
+            binding,
+            name: format!("binding_{binding}"),
+            descriptor_type,
+            count: 1,
+            stage_flags,
+        }
+    }
+
+    #[test]
+    fn test_merged_reflection_set_layouts() {
+        let mut reflection = ShaderReflection {
+            entry_points: vec![],
+            descriptor_sets: vec![DescriptorSet {
+                set: 2,
+                bindings: vec![binding(
+                    0,
+                    DescriptorType::UniformBuffer,
+                    ShaderStageFlags::VERTEX,
+                )],
+            }],
+            push_constants: vec![PushConstant {
+                name: "constants".to_string(),
+                offset: 0,
+                size: 16,
+                stage_flags: ShaderStageFlags::VERTEX,
+            }],
+            shader_stage: ShaderStage::Vertex,
+        };
+        reflection.merge(&ShaderReflection {
+            entry_points: vec![],
+            descriptor_sets: vec![
+                DescriptorSet {
+                    set: 2,
+                    bindings: vec![binding(
+                        0,
+                        DescriptorType::UniformBuffer,
+                        ShaderStageFlags::FRAGMENT,
+                    )],
+                },
+                DescriptorSet {
+                    set: 0,
+                    bindings: vec![
+                        binding(1, DescriptorType::Sampler, ShaderStageFlags::FRAGMENT),
+                        binding(0, DescriptorType::SampledImage, ShaderStageFlags::FRAGMENT),
+                    ],
+                },
+            ],
+            push_constants: vec![PushConstant {
+                name: "constants".to_string(),
+                offset: 16,
+                size: 16,
+                stage_flags: ShaderStageFlags::FRAGMENT,
+            }],
+            shader_stage: ShaderStage::Fragment,
+        });
+
+        let layouts = reflection.descriptor_set_layouts().unwrap();
+        assert_eq!(layouts.len(), 3);
+        assert_eq!(
+            layouts[0].bindings,
+            [
+                DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: RhiDescriptorType::SampledImage,
+                    count: 1,
+                    stages: ShaderStageFlags::FRAGMENT,
+                },
+                DescriptorSetLayoutBinding {
+                    binding: 1,
+                    descriptor_type: RhiDescriptorType::Sampler,
+                    count: 1,
+                    stages: ShaderStageFlags::FRAGMENT,
+                },
+            ]
+        );
+        assert!(layouts[1].bindings.is_empty());
+        assert_eq!(
+            layouts[2].bindings[0].stages,
+            ShaderStageFlags::VERTEX.union(ShaderStageFlags::FRAGMENT)
+        );
+
+        // The push constant ranges of both stages are merged into one.
+        let [push_constant] = reflection.push_constants.as_slice() else {
+            panic!("expected a single push constant block");
+        };
+        assert_eq!((push_constant.offset, push_constant.size), (0, 32));
+    }
+
+    #[test]
+    fn test_unsupported_descriptor_types_have_no_layout() {
+        let reflection = ShaderReflection {
+            entry_points: vec![],
+            descriptor_sets: vec![DescriptorSet {
+                set: 0,
+                bindings: vec![binding(
+                    0,
+                    DescriptorType::AccelerationStructure,
+                    ShaderStageFlags::COMPUTE,
+                )],
+            }],
+            push_constants: vec![],
+            shader_stage: ShaderStage::Compute,
+        };
+
+        assert!(matches!(
+            reflection.descriptor_set_layouts(),
+            Err(ReflectorError::UnsupportedDescriptorType(_))
+        ));
+    }
+
+    #[test]
+    #[ignore = "requires the DXC executable"]
+    fn test_compile_and_reflect_texture_and_sampler() {
+        let compiler = ShaderCompiler::new().unwrap();
+
+        let source = r#"
+            [[vk::binding(0, 0)]] Texture2D albedo;
+            [[vk::binding(1, 0)]] SamplerState albedo_sampler;
+
+            float4 main(float2 uv : TEXCOORD0) : SV_Target {
+                return albedo.Sample(albedo_sampler, uv);
+            }
+        "#;
+
+        let (spirv, reflection) = compiler
+            .compile_and_reflect(source, ShaderType::Fragment, "main")
+            .unwrap();
+        assert!(!spirv.is_empty());
+        assert_eq!(reflection.shader_stage, ShaderStage::Fragment);
+
+        let layouts = reflection.descriptor_set_layouts().unwrap();
+        assert_eq!(layouts.len(), 1);
+        assert_eq!(
+            layouts[0].bindings,
+            [
+                DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: RhiDescriptorType::SampledImage,
+                    count: 1,
+                    stages: ShaderStageFlags::FRAGMENT,
+                },
+                DescriptorSetLayoutBinding {
+                    binding: 1,
+                    descriptor_type: RhiDescriptorType::Sampler,
+                    count: 1,
+                    stages: ShaderStageFlags::FRAGMENT,
+                },
+            ]
+        );
+    }
+
+    /// A module with no entry point, which disassembles fine but is only valid
+    /// with the `Linkage` capability.
+    fn library_module() -> Vec<u8> {
+        let words: [u32; 10] = [
+            0x07230203,     // Magic number
+            0x00010000,     // Version 1.0
+            0,              // Generator
+            1,              // Bound
+            0,              // Schema
+            (2 << 16) | 17, // OpCapability
+            1,              // Shader
+            (3 << 16) | 14, // OpMemoryModel
+            0,              // Logical
+            1,              // GLSL450
+        ];
+
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_spirv_words_rejects_partial_words() {
+        let module = library_module();
+        assert_eq!(spirv_words(&module).unwrap().len(), 10);
+
+        assert!(matches!(
+            spirv_words(&module[..module.len() - 1]),
+            Err(CompilerError::InvalidSpirvLength(39))
+        ));
+        assert!(matches!(
+            ShaderReflector::new().reflect_spirv(&module[..6], ShaderType::Vertex),
+            Err(ReflectorError::InvalidSpirv(_))
+        ));
+    }
+
+    #[test]
+    fn test_disassemble_known_good_module() {
+        let disassembly = match ShaderCompiler::disassemble(&library_module()) {
+            Ok(disassembly) => disassembly,
+            // SPIRV-Tools may not be installed.
+            Err(CompilerError::SpirvToolNotFound(_)) => return,
+            Err(error) => panic!("{error}"),
+        };
+
+        assert!(disassembly.contains("OpCapability Shader"));
+        assert!(disassembly.contains("OpMemoryModel Logical GLSL450"));
+
+        assert!(matches!(
+            ShaderCompiler::validate(&library_module()),
+            Err(CompilerError::ValidationFailed(messages)) if !messages.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_parse_validation_output() {
+        let report = "\
+error: line 3: Operand 2 of TypeInt requires one of these capabilities: Int64
+  %long = OpTypeInt 64 1
+
+error: No OpEntryPoint instruction was found.
+";
+
+        assert_eq!(
+            parse_validation_output(report),
+            [
+                ValidationMessage {
+                    message: "line 3: Operand 2 of TypeInt requires one of these capabilities: \
+                              Int64"
+                        .to_string(),
+                    instruction: Some("%long = OpTypeInt 64 1".to_string()),
+                },
+                ValidationMessage {
+                    message: "No OpEntryPoint instruction was found.".to_string(),
+                    instruction: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires the DXC library"]
+    fn test_combined_reflection_matches_separate_pass() {
+        let loader = DxcLoader::new().expect("the test requires the DXC library");
+        let compiler = DxcCompiler::new(loader).unwrap();
+
+        let source = r#"
+            [[vk::binding(0, 0)]] Texture2D albedo;
+            [[vk::binding(1, 0)]] SamplerState albedo_sampler;
+
+            float4 main(float3 position : POSITION) : SV_Position {
+                return albedo.SampleLevel(albedo_sampler, position.xy, 0) + float4(position, 1.0);
+            }
+        "#;
+
+        let reflector = ShaderReflector::new();
+        let (spirv_bytes, reflection) = reflector
+            .compile_with_reflection(&compiler, source, "main", ShaderType::Vertex, &NoIncludes)
+            .unwrap();
+        let separate = reflector
+            .reflect_spirv(&spirv_bytes, ShaderType::Vertex)
+            .unwrap();
+
+        assert_eq!(format!("{:?}", reflection), format!("{:?}", separate));
+    }
+}
@@ -4,8 +4,12 @@ use std::{
     str::Lines,
 };
 use thiserror::Error;
+use vislum_fs::VirtualPath;
 
-use crate::directive::Directive;
+use crate::{
+    directive::Directive,
+    material::{MATERIAL_INCLUDE_PATH, MaterialLayout, MaterialLayoutError},
+};
 
 #[derive(Debug, Error)]
 pub enum ComposeErrorType {
@@ -44,9 +48,52 @@ impl ShaderComposer {
         self.include_sources.insert(path, source);
     }
 
+    /// Sets the material layout, generating its struct and binding block.
+    ///
+    /// Shaders pull it in with `#include "vislum/material.hlsl"`. Setting a
+    /// new layout replaces the previous block, so shaders composed afterwards
+    /// always agree with the latest material definition.
+    ///
+    /// A composer holds a single material block, so shaders of different
+    /// materials must be composed with different composers, or after setting
+    /// the layout of their material.
+    ///
+    /// Returns true if the block changed, or an error if the layout can't be
+    /// declared in HLSL.
+    pub fn set_material_layout(
+        &mut self,
+        layout: &MaterialLayout,
+    ) -> Result<bool, MaterialLayoutError> {
+        let hlsl = layout.to_hlsl()?;
+        if self.include_sources.get(MATERIAL_INCLUDE_PATH) == Some(&hlsl) {
+            return Ok(false);
+        }
+
+        self.include_sources
+            .insert(MATERIAL_INCLUDE_PATH.to_string(), hlsl);
+        Ok(true)
+    }
+
     /// Composes the shader source into a single string.
     pub fn compose(&self, path: &str, source: &str) -> Result<String, ComposeError> {
+        self.compose_with_dependencies(path, source)
+            .map(|(output, _)| output)
+    }
+
+    /// Composes the shader source into a single string, returning the files
+    /// it depends on along with it.
+    ///
+    /// The dependencies are the transitive set of includes which are virtual
+    /// paths, such as `project://shaders/common.hlsl`, in the order they were
+    /// first included. Includes in inactive `#ifdef` branches and generated
+    /// includes, like the material block, are not files and aren't reported.
+    pub fn compose_with_dependencies(
+        &self,
+        path: &str,
+        source: &str,
+    ) -> Result<(String, Vec<VirtualPath>), ComposeError> {
         let mut output = String::with_capacity(source.len());
+        let mut dependencies = Vec::<VirtualPath>::new();
 
         let mut directive_frame_stack = DirectiveFrameStack::default();
         let mut include_stack = Vec::<&str>::with_capacity(self.include_sources.len());
@@ -100,6 +147,12 @@ impl ShaderComposer {
                             });
                         }
 
+                        if let Ok(dependency) = VirtualPath::parse(include_path)
+                            && !dependencies.contains(&dependency)
+                        {
+                            dependencies.push(dependency);
+                        }
+
                         include_stack.push(include_path);
                         source_stack.push(SourceStackEntry::new(include_path, include_source));
 
@@ -127,7 +180,7 @@ impl ShaderComposer {
             });
         }
 
-        Ok(output)
+        Ok((output, dependencies))
     }
 }
 
@@ -432,4 +485,138 @@ struct B { value: f32; }
         dbg!(&result);
         assert!(result.is_err()); // Should fail due to circular include
     }
+
+    #[test]
+    fn test_dependencies_of_shared_header() {
+        let mut composer = ShaderComposer::default();
+        composer.add_import_source(
+            "project://common.hlsl".to_string(),
+            "#include \"project://math.hlsl\"\nstruct Common {};\n".to_string(),
+        );
+        composer.add_import_source(
+            "project://math.hlsl".to_string(),
+            "float square(float x) { return x * x; }\n".to_string(),
+        );
+        composer.add_import_source("unused.hlsl".to_string(), "".to_string());
+
+        let vertex = r#"
+#include "project://common.hlsl"
+#include "unused.hlsl"
+float4 main() : SV_Position { return 0; }
+"#;
+        let fragment = r#"
+#include "project://common.hlsl"
+#include "project://math.hlsl"
+float4 main() : SV_Target { return 1; }
+"#;
+
+        let expected = [
+            VirtualPath::parse("project://common.hlsl").unwrap(),
+            VirtualPath::parse("project://math.hlsl").unwrap(),
+        ];
+
+        let (composed, dependencies) = composer
+            .compose_with_dependencies("project://vertex.hlsl", vertex)
+            .unwrap();
+        assert!(composed.contains("struct Common {};"));
+        assert!(composed.contains("float square(float x)"));
+        assert_eq!(dependencies, expected);
+
+        // Includes reached twice are only reported once.
+        let (_, dependencies) = composer
+            .compose_with_dependencies("project://fragment.hlsl", fragment)
+            .unwrap();
+        assert_eq!(dependencies, expected);
+    }
+
+    #[test]
+    fn test_cyclic_dependencies_are_reported() {
+        let mut composer = ShaderComposer::default();
+        composer.add_import_source(
+            "project://a.hlsl".to_string(),
+            "#include \"project://b.hlsl\"\n".to_string(),
+        );
+        composer.add_import_source(
+            "project://b.hlsl".to_string(),
+            "#include \"project://a.hlsl\"\n".to_string(),
+        );
+
+        let error = composer
+            .compose_with_dependencies("project://main.hlsl", "#include \"project://a.hlsl\"\n")
+            .unwrap_err();
+        assert!(matches!(error.ty, ComposeErrorType::CyclicReference(_)));
+    }
+
+    #[test]
+    fn test_material_layout_injection() {
+        use crate::material::{MaterialProperty, MaterialPropertyType};
+
+        let mut composer = ShaderComposer::default();
+        composer
+            .set_material_layout(&MaterialLayout {
+                name: "Material".to_string(),
+                set: 1,
+                binding: 0,
+                properties: vec![
+                    MaterialProperty {
+                        name: "base_color".to_string(),
+                        ty: MaterialPropertyType::Float4,
+                        offset: 0,
+                    },
+                    MaterialProperty {
+                        name: "roughness".to_string(),
+                        ty: MaterialPropertyType::Float,
+                        offset: 16,
+                    },
+                ],
+            })
+            .unwrap();
+
+        let source = r#"
+#include "vislum/material.hlsl"
+float4 main() : SV_Target {
+    return material.base_color * material.roughness;
+}
+"#;
+
+        let composed = composer.compose("material.hlsl", source).unwrap();
+
+        let base_color = composed.find("float4 base_color;").unwrap();
+        let roughness = composed.find("float roughness;").unwrap();
+        assert!(base_color < roughness);
+        assert!(composed.contains("struct Material {"));
+        assert!(composed.contains("[[vk::binding(0, 1)]]"));
+        assert!(composed.contains("ConstantBuffer<Material> material;"));
+    }
+
+    #[test]
+    fn test_material_layout_replaced() {
+        use crate::material::{MaterialProperty, MaterialPropertyType};
+
+        let mut composer = ShaderComposer::default();
+        let mut layout = MaterialLayout {
+            name: "Material".to_string(),
+            set: 1,
+            binding: 0,
+            properties: vec![MaterialProperty {
+                name: "roughness".to_string(),
+                ty: MaterialPropertyType::Float,
+                offset: 0,
+            }],
+        };
+        assert!(composer.set_material_layout(&layout).unwrap());
+        assert!(!composer.set_material_layout(&layout).unwrap());
+
+        layout.properties.push(MaterialProperty {
+            name: "metallic".to_string(),
+            ty: MaterialPropertyType::Float,
+            offset: 4,
+        });
+        assert!(composer.set_material_layout(&layout).unwrap());
+
+        let composed = composer
+            .compose("material.hlsl", "#include \"vislum/material.hlsl\"\n")
+            .unwrap();
+        assert!(composed.contains("float metallic;"));
+    }
 }
@@ -1,12 +1,22 @@
+pub mod cache;
 pub mod compiler;
 pub mod composer;
 pub mod directive;
+pub mod material;
+pub mod persistent;
 
 pub mod prelude {
+    pub use crate::cache::{CachedShader, ShaderCache, ShaderCacheError, SourceCompiler};
     pub use crate::compiler::{
         CompilerError, DescriptorBinding, DescriptorSet, DescriptorType, EntryPoint, PushConstant,
         ReflectorError, ShaderCompiler, ShaderReflection, ShaderReflector, ShaderStage, ShaderType,
+        ValidationMessage, spirv_words,
     };
     pub use crate::composer::{ComposeError, ComposeErrorType, ShaderComposer};
     pub use crate::directive::collect_includes;
+    pub use crate::material::{
+        MATERIAL_INCLUDE_PATH, MaterialLayout, MaterialLayoutError, MaterialProperty,
+        MaterialPropertyType,
+    };
+    pub use crate::persistent::PersistentShaderCache;
 }
@@ -0,0 +1,321 @@
+use std::fmt::Write;
+
+use thiserror::Error;
+
+/// The include path under which the material block is registered.
+pub const MATERIAL_INCLUDE_PATH: &str = "vislum/material.hlsl";
+
+/// The prefix of the members padding the generated material struct.
+const PADDING_PREFIX: &str = "_padding";
+
+/// HLSL keywords and type names, besides the scalar, vector and matrix types.
+const RESERVED_WORDS: &[&str] = &[
+    "break",
+    "Buffer",
+    "case",
+    "cbuffer",
+    "centroid",
+    "class",
+    "column_major",
+    "compile",
+    "const",
+    "ConstantBuffer",
+    "continue",
+    "default",
+    "discard",
+    "do",
+    "else",
+    "export",
+    "extern",
+    "false",
+    "for",
+    "groupshared",
+    "if",
+    "in",
+    "inline",
+    "inout",
+    "interface",
+    "linear",
+    "matrix",
+    "namespace",
+    "nointerpolation",
+    "noperspective",
+    "out",
+    "packoffset",
+    "precise",
+    "register",
+    "return",
+    "row_major",
+    "sample",
+    "sampler",
+    "SamplerState",
+    "shared",
+    "snorm",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "tbuffer",
+    "Texture2D",
+    "true",
+    "typedef",
+    "uniform",
+    "unorm",
+    "unsigned",
+    "vector",
+    "void",
+    "volatile",
+    "while",
+];
+
+/// HLSL scalar types, which also name vector and matrix types when followed
+/// by their dimensions, e.g. `float3` or `float4x4`.
+const SCALAR_TYPES: &[&str] = &[
+    "bool",
+    "double",
+    "dword",
+    "float",
+    "half",
+    "int",
+    "min16float",
+    "min16int",
+    "min16uint",
+    "uint",
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MaterialLayoutError {
+    #[error("{0:?} is not a valid HLSL identifier")]
+    InvalidName(String),
+    #[error("{0:?} is a reserved HLSL word")]
+    ReservedName(String),
+    #[error("material property {0:?} uses the prefix of the generated padding members")]
+    PaddingName(String),
+    #[error("material property {0:?} is misaligned or overlaps the previous one")]
+    InvalidOffset(String),
+}
+
+/// The type of a material property, as seen by shaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaterialPropertyType {
+    Float,
+    Float2,
+    Float3,
+    Float4,
+    Int,
+    Uint,
+    Float4x4,
+}
+
+impl MaterialPropertyType {
+    /// Returns the HLSL type name.
+    pub fn hlsl_type(self) -> &'static str {
+        match self {
+            MaterialPropertyType::Float => "float",
+            MaterialPropertyType::Float2 => "float2",
+            MaterialPropertyType::Float3 => "float3",
+            MaterialPropertyType::Float4 => "float4",
+            MaterialPropertyType::Int => "int",
+            MaterialPropertyType::Uint => "uint",
+            MaterialPropertyType::Float4x4 => "float4x4",
+        }
+    }
+
+    /// Returns the size of the type, in bytes.
+    pub fn size(self) -> u32 {
+        match self {
+            MaterialPropertyType::Float
+            | MaterialPropertyType::Int
+            | MaterialPropertyType::Uint => 4,
+            MaterialPropertyType::Float2 => 8,
+            MaterialPropertyType::Float3 => 12,
+            MaterialPropertyType::Float4 => 16,
+            MaterialPropertyType::Float4x4 => 64,
+        }
+    }
+}
+
+/// A property declared by a material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialProperty {
+    pub name: String,
+    pub ty: MaterialPropertyType,
+    /// The offset of the property in the uniform buffer, in bytes.
+    pub offset: u32,
+}
+
+/// The uniform layout of a material.
+///
+/// The generated struct places every property at its offset with explicit
+/// padding members, so it matches the layout the material writes its uniform
+/// buffer with regardless of the packing rules of the compiler. E.g. DXC
+/// packs a `float3` following a `float` at offset 4, where a std140 layout
+/// expects it at offset 16.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialLayout {
+    /// The name of the generated struct.
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    pub properties: Vec<MaterialProperty>,
+}
+
+impl MaterialLayout {
+    /// Generates the HLSL struct and the constant buffer binding for the material.
+    ///
+    /// The constant buffer is always named `material`. Fails if the name of
+    /// the struct or of a property isn't a valid HLSL identifier, if a property
+    /// name starts with `_padding`, or if a property isn't 4 byte aligned or
+    /// overlaps another one.
+    pub fn to_hlsl(&self) -> Result<String, MaterialLayoutError> {
+        check_identifier(&self.name)?;
+        for property in &self.properties {
+            check_identifier(&property.name)?;
+            if property.name.starts_with(PADDING_PREFIX) {
+                return Err(MaterialLayoutError::PaddingName(property.name.clone()));
+            }
+        }
+
+        let mut output = String::new();
+
+        let mut properties = self.properties.iter().collect::<Vec<_>>();
+        properties.sort_by_key(|property| property.offset);
+
+        writeln!(output, "struct {} {{", self.name).unwrap();
+        let mut end = 0;
+        let mut padding = 0;
+        for property in properties {
+            if !property.offset.is_multiple_of(4) || property.offset < end {
+                return Err(MaterialLayoutError::InvalidOffset(property.name.clone()));
+            }
+
+            // Scalars are 4 byte aligned, so they fill the gap exactly.
+            for _ in (end..property.offset).step_by(4) {
+                writeln!(output, "    uint {PADDING_PREFIX}{padding};").unwrap();
+                padding += 1;
+            }
+
+            writeln!(output, "    {} {};", property.ty.hlsl_type(), property.name).unwrap();
+            end = property.offset + property.ty.size();
+        }
+        writeln!(output, "}};").unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "[[vk::binding({}, {})]]", self.binding, self.set).unwrap();
+        writeln!(output, "ConstantBuffer<{}> material;", self.name).unwrap();
+
+        Ok(output)
+    }
+}
+
+/// Checks that `name` can be declared in HLSL.
+fn check_identifier(name: &str) -> Result<(), MaterialLayoutError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_');
+    if !valid {
+        return Err(MaterialLayoutError::InvalidName(name.to_string()));
+    }
+
+    if RESERVED_WORDS.contains(&name) || is_numeric_type(name) {
+        return Err(MaterialLayoutError::ReservedName(name.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Returns true if `name` is a scalar, vector or matrix type, e.g. `float`,
+/// `float3` or `float4x4`.
+fn is_numeric_type(name: &str) -> bool {
+    SCALAR_TYPES.iter().any(|scalar| {
+        matches!(
+            name.strip_prefix(scalar).map(str::as_bytes),
+            Some([] | [b'1'..=b'4'] | [b'1'..=b'4', b'x', b'1'..=b'4'])
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_properties_are_padded_to_their_offsets() {
+        let property = |name: &str, ty, offset| MaterialProperty {
+            name: name.to_string(),
+            ty,
+            offset,
+        };
+        let layout = MaterialLayout {
+            name: "Material".to_string(),
+            set: 1,
+            binding: 0,
+            properties: vec![
+                property("tint", MaterialPropertyType::Float3, 16),
+                property("opacity", MaterialPropertyType::Float, 0),
+                property("unlit", MaterialPropertyType::Uint, 28),
+            ],
+        };
+
+        let hlsl = layout.to_hlsl().unwrap();
+        let members = hlsl
+            .lines()
+            .skip(1)
+            .take_while(|line| *line != "};")
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            members,
+            [
+                "float opacity;",
+                "uint _padding0;",
+                "uint _padding1;",
+                "uint _padding2;",
+                "float3 tint;",
+                "uint unlit;",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_property_names_must_be_identifiers() {
+        let layout = |name: &str| MaterialLayout {
+            name: "Material".to_string(),
+            set: 1,
+            binding: 0,
+            properties: vec![MaterialProperty {
+                name: name.to_string(),
+                ty: MaterialPropertyType::Float,
+                offset: 0,
+            }],
+        };
+
+        assert!(layout("base_color").to_hlsl().is_ok());
+        assert!(layout("float_value").to_hlsl().is_ok());
+        assert!(layout("float5").to_hlsl().is_ok());
+        for name in ["base color", "2nd", "", "tint;"] {
+            assert_eq!(
+                layout(name).to_hlsl(),
+                Err(MaterialLayoutError::InvalidName(name.to_string()))
+            );
+        }
+        for name in ["float", "in", "float3", "uint4x4", "struct"] {
+            assert_eq!(
+                layout(name).to_hlsl(),
+                Err(MaterialLayoutError::ReservedName(name.to_string()))
+            );
+        }
+        assert_eq!(
+            layout("_padding0").to_hlsl(),
+            Err(MaterialLayoutError::PaddingName("_padding0".to_string()))
+        );
+
+        let mut misaligned = layout("roughness");
+        misaligned.properties[0].offset = 2;
+        assert_eq!(
+            misaligned.to_hlsl(),
+            Err(MaterialLayoutError::InvalidOffset("roughness".to_string()))
+        );
+    }
+}
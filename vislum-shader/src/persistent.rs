@@ -0,0 +1,255 @@
+use std::path::Path;
+
+use vislum_fs::{VirtualFs, VirtualNamespace, VirtualPath};
+
+use crate::{
+    cache::SourceCompiler,
+    compiler::{CompilerError, ShaderType, spirv_words},
+};
+
+/// Bumped whenever the layout of the key changes, so modules cached by a
+/// previous version are never mistaken for current ones.
+const KEY_VERSION: u64 = 1;
+
+/// Stores compiled shader modules in the `shader-cache://` namespace, so
+/// identical shaders aren't compiled again across runs.
+///
+/// Modules are keyed by a hash of the composed source, the compile options and
+/// the contents of the files the shader includes. Editing any of them results
+/// in a new key, so stale modules are never returned; they are simply left
+/// behind in the namespace.
+///
+/// The cache is best-effort: modules which can't be read back are compiled
+/// again, and modules which can't be written are not cached.
+#[derive(Debug, Default)]
+pub struct PersistentShaderCache;
+
+impl PersistentShaderCache {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Returns the path the module compiled with the given source and options
+    /// is stored at.
+    ///
+    /// Dependencies which can't be read are hashed as empty files.
+    pub fn key(
+        &self,
+        fs: &VirtualFs,
+        source: &str,
+        entry_point: &str,
+        shader_type: ShaderType,
+        dependencies: &[VirtualPath],
+    ) -> VirtualPath {
+        let mut hasher = StableHasher::new();
+        hasher.write_u64(KEY_VERSION);
+        hasher.write_str(source);
+        hasher.write_str(entry_point);
+        hasher.write_str(shader_type.target_profile());
+
+        for dependency in dependencies {
+            hasher.write_str(&dependency.to_string());
+            match fs.read(dependency.clone()) {
+                Ok(contents) => hasher.write_bytes(&contents),
+                Err(_) => hasher.write_bytes(&[]),
+            }
+        }
+
+        VirtualPath::new(
+            VirtualNamespace::ShaderCache,
+            Path::new(&format!("{:016x}.spv", hasher.finish())),
+        )
+    }
+
+    /// Returns the cached module compiled from the source, compiling and
+    /// caching it on a miss.
+    pub fn compile(
+        &self,
+        fs: &VirtualFs,
+        compiler: &impl SourceCompiler,
+        source: &str,
+        entry_point: &str,
+        shader_type: ShaderType,
+        dependencies: &[VirtualPath],
+    ) -> Result<Vec<u8>, CompilerError> {
+        let key = self.key(fs, source, entry_point, shader_type, dependencies);
+
+        // Truncated modules, from an interrupted write for example, are
+        // compiled again.
+        if let Ok(spirv) = fs.read(key.clone())
+            && !spirv.is_empty()
+            && spirv_words(&spirv).is_ok()
+        {
+            return Ok(spirv.to_vec());
+        }
+
+        let spirv = compiler.compile(source, entry_point, shader_type)?;
+        let _ = fs.write(key, &spirv);
+
+        Ok(spirv)
+    }
+}
+
+/// A 64-bit FNV-1a hasher.
+///
+/// The keys are persisted, so unlike the standard library hashers, the hash
+/// must not change between runs or compiler versions.
+struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        // Prefix the length, so consecutive fields can't be shifted into each
+        // other.
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_bytes(value.as_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use vislum_fs::{Fs, MemoryFs};
+
+    use super::*;
+
+    /// A compiler which counts its invocations, "compiling" sources to their
+    /// bytes padded to whole words.
+    #[derive(Default)]
+    struct CountingCompiler {
+        invocations: Cell<usize>,
+    }
+
+    impl SourceCompiler for CountingCompiler {
+        fn compile(
+            &self,
+            shader_source: &str,
+            _entry_point: &str,
+            _shader_type: ShaderType,
+        ) -> Result<Vec<u8>, CompilerError> {
+            self.invocations.set(self.invocations.get() + 1);
+
+            let mut spirv = shader_source.as_bytes().to_vec();
+            spirv.resize(spirv.len().next_multiple_of(4), 0);
+            Ok(spirv)
+        }
+    }
+
+    fn create_fs() -> VirtualFs {
+        VirtualFs::new([
+            (
+                VirtualNamespace::Project,
+                Box::new(MemoryFs::new()) as Box<dyn Fs>,
+            ),
+            (
+                VirtualNamespace::ShaderCache,
+                Box::new(MemoryFs::new()) as Box<dyn Fs>,
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_second_compile_reads_from_the_cache() {
+        let fs = create_fs();
+        let compiler = CountingCompiler::default();
+        let cache = PersistentShaderCache::new();
+
+        let compile = || {
+            cache
+                .compile(&fs, &compiler, "source", "main", ShaderType::Vertex, &[])
+                .unwrap()
+        };
+
+        let first = compile();
+        assert_eq!(compiler.invocations.get(), 1);
+
+        let key = cache.key(&fs, "source", "main", ShaderType::Vertex, &[]);
+        assert_eq!(*key.namespace(), VirtualNamespace::ShaderCache);
+        assert_eq!(&*fs.read(key).unwrap(), first.as_slice());
+
+        assert_eq!(compile(), first);
+        assert_eq!(compiler.invocations.get(), 1);
+
+        // Other options result in another module.
+        cache
+            .compile(&fs, &compiler, "source", "main", ShaderType::Fragment, &[])
+            .unwrap();
+        assert_eq!(compiler.invocations.get(), 2);
+    }
+
+    #[test]
+    fn test_modified_dependency_misses_the_cache() {
+        let fs = create_fs();
+        let compiler = CountingCompiler::default();
+        let cache = PersistentShaderCache::new();
+
+        let header = VirtualPath::parse("project://common.hlsl").unwrap();
+        fs.write(header.clone(), b"v1").unwrap();
+
+        let compile = || {
+            cache
+                .compile(
+                    &fs,
+                    &compiler,
+                    "source",
+                    "main",
+                    ShaderType::Vertex,
+                    std::slice::from_ref(&header),
+                )
+                .unwrap()
+        };
+
+        compile();
+        compile();
+        assert_eq!(compiler.invocations.get(), 1);
+
+        fs.write(header.clone(), b"v2").unwrap();
+        compile();
+        assert_eq!(compiler.invocations.get(), 2);
+    }
+
+    #[test]
+    fn test_truncated_module_is_compiled_again() {
+        let fs = create_fs();
+        let compiler = CountingCompiler::default();
+        let cache = PersistentShaderCache::new();
+
+        let key = cache.key(&fs, "source", "main", ShaderType::Vertex, &[]);
+        fs.write(key, b"trunc").unwrap();
+
+        let spirv = cache
+            .compile(&fs, &compiler, "source", "main", ShaderType::Vertex, &[])
+            .unwrap();
+        assert_eq!(compiler.invocations.get(), 1);
+        assert_eq!(&spirv[..6], b"source");
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        // The keys are persisted, so the hash must never change.
+        let mut hasher = StableHasher::new();
+        hasher.write_str("vislum");
+        assert_eq!(hasher.finish(), 0x708f47a76639fcff);
+    }
+}
@@ -1,18 +1,73 @@
 extern crate self as vislum_system;
 
 use std::any::{Any, TypeId};
-use std::cell::{Ref, RefCell, RefMut, UnsafeCell};
-use std::collections::HashMap;
+use std::cell::{Cell, Ref, RefCell, RefMut, UnsafeCell};
+use std::collections::{BTreeMap, HashMap};
+
+mod reload;
 
 // pub mod event;
 // pub mod system;
 
+use reload::ReloadableEntry;
+pub use reload::{ReloadSnapshot, Reloadable};
+
 // Re-export the System macro.
 pub use vislum_system_macros::Resource;
 
 /// A marker trait for identifying resources.
 pub trait Resource: 'static + Any {}
 
+/// A set of resource types, either a single type or a tuple of types.
+pub trait ResourceSet {
+    /// The mutable references to the resources in the set.
+    type Mut<'a>;
+
+    /// Returns the type ids of the resources in the set.
+    fn type_ids() -> Vec<TypeId>;
+
+    /// Mutably borrows the resources in the set, in order.
+    fn get_mut(resources: &Resources) -> Self::Mut<'_>;
+}
+
+impl<T> ResourceSet for T
+where
+    T: Resource,
+{
+    type Mut<'a> = ResMut<'a, T>;
+
+    fn type_ids() -> Vec<TypeId> {
+        vec![TypeId::of::<T>()]
+    }
+
+    fn get_mut(resources: &Resources) -> Self::Mut<'_> {
+        resources.get_mut::<T>()
+    }
+}
+
+macro_rules! impl_resource_set_tuple {
+    ($($ty:ident),*) => {
+        impl<$($ty),*> ResourceSet for ($($ty,)*)
+        where
+            $($ty: Resource),*
+        {
+            type Mut<'a> = ($(ResMut<'a, $ty>,)*);
+
+            fn type_ids() -> Vec<TypeId> {
+                vec![$(TypeId::of::<$ty>()),*]
+            }
+
+            fn get_mut(resources: &Resources) -> Self::Mut<'_> {
+                ($(resources.get_mut::<$ty>(),)*)
+            }
+        }
+    };
+}
+
+impl_resource_set_tuple!(A, B);
+impl_resource_set_tuple!(A, B, C);
+impl_resource_set_tuple!(A, B, C, D);
+
 /// A reference to a resource.
 pub struct Res<'a, T>(Ref<'a, T>);
 
@@ -41,7 +96,10 @@ impl<'a, T> std::ops::DerefMut for ResMut<'a, T> {
     }
 }
 
-struct ErasedResourceCell(Box<RefCell<dyn Any>>);
+struct ErasedResourceCell {
+    cell: Box<dyn Any>,
+    type_name: &'static str,
+}
 
 impl ErasedResourceCell {
     /// Creates a new shared system cell.
@@ -50,64 +108,85 @@ impl ErasedResourceCell {
     where
         T: 'static,
     {
-        Self(Box::new(RefCell::new(system)))
+        Self {
+            cell: Box::new(RefCell::new(system)),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    /// Returns the type name of the system.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
     }
 
     /// Get a reference to a system.
     ///
-    /// # Safety
-    ///
-    /// Assumes that the caller has checked that the system is of the correct type.
+    /// Panics if the system is not of type `T` or if it is mutably borrowed.
     #[inline]
     pub fn get_downcasted_ref<T>(&self) -> Res<'_, T>
     where
         T: 'static,
     {
-        let system = match self.0.try_borrow() {
-            Ok(system) => system,
+        match self.downcast_cell::<T>().try_borrow() {
+            Ok(system) => Res(system),
             Err(_) => resource_already_borrowed(std::any::type_name::<T>()),
-        };
-
-        let system = Ref::map(system, |s| match s.downcast_ref::<T>() {
-            Some(system) => system,
-            None => incompatible_resource_downcast(std::any::type_name::<T>()),
-        });
-
-        Res(system)
+        }
     }
 
     /// Gets a mutable reference to a system.
     ///
-    /// # Safety
-    ///
-    /// Assumes that the caller has checked that the system is of the correct type.
+    /// Panics if the system is not of type `T` or if it is already borrowed.
     #[inline]
     pub fn get_downcasted_mut<T>(&self) -> ResMut<'_, T>
     where
         T: 'static,
     {
-        let system = match self.0.try_borrow_mut() {
-            Ok(system) => system,
+        match self.downcast_cell::<T>().try_borrow_mut() {
+            Ok(system) => ResMut(system),
             Err(_) => resource_already_borrowed(std::any::type_name::<T>()),
-        };
+        }
+    }
 
-        let system = RefMut::map(system, |s| match s.downcast_mut::<T>() {
-            Some(system) => system,
-            None => incompatible_resource_downcast(std::any::type_name::<T>()),
-        });
+    /// Consumes the cell, returning the system.
+    ///
+    /// Panics if the system is not of type `T`.
+    #[inline]
+    pub fn into_downcasted<T>(self) -> T
+    where
+        T: 'static,
+    {
+        match self.cell.downcast::<RefCell<T>>() {
+            Ok(cell) => cell.into_inner(),
+            Err(_) => incompatible_resource_downcast(std::any::type_name::<T>()),
+        }
+    }
 
-        ResMut(system)
+    #[inline]
+    fn downcast_cell<T>(&self) -> &RefCell<T>
+    where
+        T: 'static,
+    {
+        match self.cell.downcast_ref::<RefCell<T>>() {
+            Some(cell) => cell,
+            None => incompatible_resource_downcast(std::any::type_name::<T>()),
+        }
     }
 }
 
 #[derive(Default)]
 pub struct Resources {
     resources: UnsafeCell<HashMap<TypeId, ErasedResourceCell>>,
+    reloadables: BTreeMap<&'static str, ReloadableEntry>,
+    /// The resource being built by [`Resources::init_with`], if any.
+    initializing: Cell<Option<TypeId>>,
 }
 
 impl std::fmt::Debug for Resources {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("Resources")
+        f.debug_struct("Resources")
+            .field("resources", &self.debug_list())
+            .finish()
     }
 }
 
@@ -123,6 +202,8 @@ impl Resources {
     where
         T: Resource,
     {
+        self.check_not_initializing::<T>();
+
         // SAFETY: We're not mutating the inner HashMap.
         let inner = unsafe { self.inner() };
 
@@ -137,6 +218,8 @@ impl Resources {
     where
         T: Resource,
     {
+        self.check_not_initializing::<T>();
+
         // SAFETY: We're not mutating the inner HashMap.
         let inner = unsafe { self.inner() };
 
@@ -146,12 +229,85 @@ impl Resources {
         }
     }
 
+    /// Mutably borrows several resources at once, panicking if any of them is
+    /// not found or if the same type is requested twice.
+    ///
+    /// The guards are returned in the order of the tuple, so they are dropped
+    /// in reverse order.
+    pub fn get_many_mut<T>(&self) -> T::Mut<'_>
+    where
+        T: ResourceSet,
+    {
+        let type_ids = T::type_ids();
+        for (index, type_id) in type_ids.iter().enumerate() {
+            if type_ids[..index].contains(type_id) {
+                resource_borrowed_twice(std::any::type_name::<T>());
+            }
+        }
+
+        T::get_mut(self)
+    }
+
+    /// Gets a resource by type, returning `None` if the resource is not found.
+    ///
+    /// Useful for optional subsystems, which may or may not be installed.
+    pub fn try_get<T>(&self) -> Option<Res<'_, T>>
+    where
+        T: Resource,
+    {
+        self.check_not_initializing::<T>();
+
+        // SAFETY: We're not mutating the inner HashMap.
+        let inner = unsafe { self.inner() };
+
+        inner
+            .get(&std::any::TypeId::of::<T>())
+            .map(|res| res.get_downcasted_ref::<T>())
+    }
+
+    /// Returns true if a resource of type `T` is present.
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Resource,
+    {
+        // SAFETY: We're not mutating the inner HashMap.
+        let inner = unsafe { self.inner() };
+
+        inner.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns the type ids of all the resources.
+    pub fn registered_type_ids(&self) -> Vec<TypeId> {
+        // SAFETY: We're not mutating the inner HashMap.
+        let inner = unsafe { self.inner() };
+
+        inner.keys().copied().collect()
+    }
+
+    /// Returns the type names of all the resources, sorted.
+    ///
+    /// Meant for debugging, as type names are not guaranteed to be unique
+    /// nor stable.
+    pub fn debug_list(&self) -> Vec<&'static str> {
+        // SAFETY: We're not mutating the inner HashMap.
+        let inner = unsafe { self.inner() };
+
+        let mut names = inner
+            .values()
+            .map(ErasedResourceCell::type_name)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
     /// Gets a resource by type, inserting a default resource if it is not found.
     pub fn get_or_insert_with<T, F>(&self, f: F) -> Res<'_, T>
     where
         T: Resource,
         F: FnOnce() -> T,
     {
+        self.check_not_initializing::<T>();
+
         // SAFETY: We only insert a new resource if it is not found, so no borrows to the
         // previous resource can be invalidated (as the resource does not exist). Moreover, the
         // HashMap stores a pointer to the resource, so we are guaranteed that changes within the
@@ -170,6 +326,8 @@ impl Resources {
         T: Resource,
         F: FnOnce() -> T,
     {
+        self.check_not_initializing::<T>();
+
         // SAFETY: We only insert a new resource if it is not found, so no borrows to the
         // previous resource can be invalidated (as the resource does not exist). Moreover, the
         // HashMap stores a pointer to the resource, so we are guaranteed that changes within the
@@ -201,6 +359,131 @@ impl Resources {
         self.insert(T::default());
     }
 
+    /// Removes a resource, returning it if it was present.
+    ///
+    /// Taking `&mut self` guarantees that no [`Res`] or [`ResMut`] to the
+    /// resource is alive, so it can always be moved out of its cell.
+    pub fn remove<T>(&mut self) -> Option<T>
+    where
+        T: Resource,
+    {
+        // SAFETY: We have exclusive access to the resources, so no borrows are possible.
+        let inner = unsafe { self.inner() };
+
+        inner
+            .remove(&TypeId::of::<T>())
+            .map(ErasedResourceCell::into_downcasted::<T>)
+    }
+
+    /// Removes all the resources, dropping them.
+    ///
+    /// Reloadable registrations are kept.
+    pub fn clear(&mut self) {
+        // SAFETY: We have exclusive access to the resources, so no borrows are possible.
+        let inner = unsafe { self.inner() };
+
+        inner.clear();
+    }
+
+    /// Removes all the resources except the ones in `T`, which may be a single
+    /// resource type or a tuple of them.
+    ///
+    /// Useful to reset state while keeping long-lived resources, such as the
+    /// render device and queue.
+    pub fn clear_except<T>(&mut self)
+    where
+        T: ResourceSet,
+    {
+        let keep = T::type_ids();
+
+        // SAFETY: We have exclusive access to the resources, so no borrows are possible.
+        let inner = unsafe { self.inner() };
+
+        inner.retain(|type_id, _| keep.contains(type_id));
+    }
+
+    /// Builds a resource from the already inserted ones and inserts it.
+    ///
+    /// The initializer can read the resources its construction depends on,
+    /// so startup wiring doesn't need to be ordered by hand beyond declaring
+    /// dependencies first. Borrowing `T` itself from the initializer panics.
+    pub fn init_with<T, F>(&mut self, f: F)
+    where
+        T: Resource,
+        F: FnOnce(&Resources) -> T,
+    {
+        /// Clears the resource being built, even if the initializer panics.
+        struct InitializingGuard<'a>(&'a Cell<Option<TypeId>>);
+
+        impl Drop for InitializingGuard<'_> {
+            fn drop(&mut self) {
+                self.0.set(None);
+            }
+        }
+
+        self.initializing.set(Some(TypeId::of::<T>()));
+        let resource = {
+            let _guard = InitializingGuard(&self.initializing);
+            f(self)
+        };
+
+        self.insert(resource);
+    }
+
+    /// Registers a resource type to be included in reload snapshots.
+    pub fn register_reloadable<T>(&mut self)
+    where
+        T: Reloadable,
+    {
+        self.reloadables.insert(T::KEY, ReloadableEntry::new::<T>());
+    }
+
+    /// Saves all the registered [`Reloadable`] resources into a snapshot.
+    ///
+    /// Registered resources which are not currently present are skipped.
+    pub fn serialize_reloadable(&self) -> ReloadSnapshot {
+        // SAFETY: We're not mutating the inner HashMap.
+        let inner = unsafe { self.inner() };
+
+        let mut snapshot = ReloadSnapshot::default();
+        for (key, entry) in &self.reloadables {
+            if let Some(cell) = inner.get(&entry.type_id) {
+                snapshot.insert(*key, (entry.save)(cell));
+            }
+        }
+
+        snapshot
+    }
+
+    /// Restores the registered [`Reloadable`] resources from a snapshot,
+    /// replacing the current ones.
+    ///
+    /// Keys in the snapshot which are not registered, or whose bytes cannot
+    /// be restored, are skipped.
+    pub fn deserialize_into(&mut self, snapshot: &ReloadSnapshot) {
+        // SAFETY: We have exclusive access to the resources, so no borrows are possible.
+        let inner = unsafe { self.inner() };
+
+        for (key, bytes) in snapshot.iter() {
+            let Some(entry) = self.reloadables.get(key) else {
+                continue;
+            };
+
+            if let Some(cell) = (entry.restore)(bytes) {
+                inner.insert(entry.type_id, cell);
+            }
+        }
+    }
+
+    fn check_not_initializing<T>(&self)
+    where
+        T: Resource,
+    {
+        if self.initializing.get() == Some(TypeId::of::<T>()) {
+            resource_initializing(std::any::type_name::<T>());
+        }
+    }
+
     unsafe fn inner(&self) -> &mut HashMap<TypeId, ErasedResourceCell> {
         unsafe { &mut *self.resources.get() }
     }
@@ -216,7 +499,221 @@ fn incompatible_resource_downcast(type_name: &str) -> ! {
     panic!("Resource is not of type {}", type_name)
 }
 
+#[cold]
+fn resource_borrowed_twice(type_name: &str) -> ! {
+    panic!("Resource requested more than once in {}", type_name)
+}
+
+#[cold]
+fn resource_initializing(type_name: &str) -> ! {
+    panic!(
+        "Resource borrowed during its own initialization: {}",
+        type_name
+    )
+}
+
 #[cold]
 fn resource_not_found(type_name: &str) -> ! {
     panic!("Resource not found: {}", type_name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u32);
+
+    impl Resource for Counter {}
+
+    impl Reloadable for Counter {
+        const KEY: &'static str = "test.counter";
+
+        fn save(&self) -> Vec<u8> {
+            self.0.to_le_bytes().to_vec()
+        }
+
+        fn restore(bytes: &[u8]) -> Option<Self> {
+            Some(Self(u32::from_le_bytes(bytes.try_into().ok()?)))
+        }
+    }
+
+    struct GpuHandle;
+
+    impl Resource for GpuHandle {}
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut resources = Resources::new();
+        resources.register_reloadable::<Counter>();
+        resources.insert(Counter(42));
+        resources.insert(GpuHandle);
+
+        let snapshot = resources.serialize_reloadable();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot.get("test.counter"), Some(&42u32.to_le_bytes()[..]));
+
+        *resources.get_mut::<Counter>() = Counter(0);
+        resources.deserialize_into(&snapshot);
+
+        assert_eq!(*resources.get::<Counter>(), Counter(42));
+    }
+
+    #[test]
+    fn test_restore_into_fresh_resources() {
+        let mut resources = Resources::new();
+        resources.register_reloadable::<Counter>();
+        resources.insert(Counter(7));
+        let snapshot = resources.serialize_reloadable();
+
+        let mut reloaded = Resources::new();
+        reloaded.register_reloadable::<Counter>();
+        reloaded.deserialize_into(&snapshot);
+
+        assert_eq!(*reloaded.get::<Counter>(), Counter(7));
+    }
+
+    #[test]
+    fn test_missing_resources_are_skipped() {
+        let mut resources = Resources::new();
+        resources.register_reloadable::<Counter>();
+
+        assert!(resources.serialize_reloadable().is_empty());
+    }
+
+    struct Device {
+        id: u32,
+    }
+
+    impl Resource for Device {}
+
+    struct LayoutCache {
+        device_id: u32,
+    }
+
+    impl Resource for LayoutCache {}
+
+    #[test]
+    fn test_init_with_reads_dependencies() {
+        let mut resources = Resources::new();
+        resources.insert(Device { id: 7 });
+        resources.init_with(|resources| LayoutCache {
+            device_id: resources.get::<Device>().id,
+        });
+
+        assert_eq!(resources.get::<LayoutCache>().device_id, 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "Resource borrowed during its own initialization")]
+    fn test_init_with_cannot_borrow_itself() {
+        let mut resources = Resources::new();
+        resources.insert(Device { id: 7 });
+        resources.init_with(|resources| Device {
+            id: resources.get::<Device>().id + 1,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Resource borrowed during its own initialization")]
+    fn test_init_with_cannot_insert_itself() {
+        let mut resources = Resources::new();
+        resources.init_with(|resources| Device {
+            id: resources.get_or_insert_with(|| Device { id: 7 }).id + 1,
+        });
+    }
+
+    #[test]
+    fn test_init_with_recovers_from_panicking_initializer() {
+        let mut resources = Resources::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            resources.init_with::<Device, _>(|_| panic!("initialization failed"));
+        }));
+        assert!(result.is_err());
+
+        assert!(!resources.contains::<Device>());
+        assert_eq!(resources.get_or_insert_with(|| Device { id: 7 }).id, 7);
+    }
+
+    #[test]
+    fn test_clear_except_keeps_listed_resources() {
+        let mut resources = Resources::new();
+        resources.insert(Device { id: 1 });
+        resources.insert(Counter(3));
+        resources.insert(GpuHandle);
+
+        resources.clear_except::<Device>();
+        assert!(resources.contains::<Device>());
+        assert!(!resources.contains::<Counter>());
+        assert!(!resources.contains::<GpuHandle>());
+
+        resources.insert(Counter(3));
+        resources.insert(GpuHandle);
+        resources.clear_except::<(Device, Counter)>();
+        assert!(resources.contains::<Counter>());
+        assert!(!resources.contains::<GpuHandle>());
+
+        resources.clear();
+        assert!(!resources.contains::<Device>());
+    }
+
+    #[test]
+    fn test_remove_returns_resource() {
+        let mut resources = Resources::new();
+        resources.insert(Counter(5));
+
+        assert_eq!(resources.remove::<Counter>(), Some(Counter(5)));
+        assert_eq!(resources.remove::<Counter>(), None);
+        assert!(!resources.contains::<Counter>());
+    }
+
+    #[test]
+    fn test_try_get_missing_resource() {
+        let mut resources = Resources::new();
+        assert!(resources.try_get::<Counter>().is_none());
+
+        resources.insert(Counter(1));
+        assert_eq!(*resources.try_get::<Counter>().unwrap(), Counter(1));
+    }
+
+    #[test]
+    fn test_get_many_mut() {
+        let mut resources = Resources::new();
+        resources.insert(Counter(1));
+        resources.insert(Device { id: 2 });
+
+        let (mut counter, mut device) = resources.get_many_mut::<(Counter, Device)>();
+        (*counter).0 += device.id;
+        device.id = 0;
+        drop((counter, device));
+
+        assert_eq!(*resources.get::<Counter>(), Counter(3));
+        assert_eq!(resources.get::<Device>().id, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Resource requested more than once")]
+    fn test_get_many_mut_rejects_duplicates() {
+        let mut resources = Resources::new();
+        resources.insert(Counter(1));
+
+        let _ = resources.get_many_mut::<(Counter, Counter)>();
+    }
+
+    #[test]
+    fn test_debug_lists_resources() {
+        let mut resources = Resources::new();
+        resources.insert(Counter(1));
+        resources.insert(GpuHandle);
+
+        assert_eq!(resources.registered_type_ids().len(), 2);
+        assert_eq!(
+            resources.debug_list(),
+            [
+                std::any::type_name::<Counter>(),
+                std::any::type_name::<GpuHandle>(),
+            ]
+        );
+        assert!(format!("{resources:?}").contains("GpuHandle"));
+    }
+}
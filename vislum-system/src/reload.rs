@@ -0,0 +1,84 @@
+use std::any::TypeId;
+use std::collections::BTreeMap;
+
+use crate::{ErasedResourceCell, Resource};
+
+/// A resource whose state survives a hot-reload of the logic library.
+///
+/// Resources which hold GPU handles or other process-bound state should not
+/// implement this trait, as they are recreated after a reload instead.
+pub trait Reloadable: Resource + Sized {
+    /// A stable key identifying the resource across reloads.
+    ///
+    /// Type ids are not stable between builds, so this is used instead.
+    const KEY: &'static str;
+
+    /// Saves the state of the resource to bytes.
+    fn save(&self) -> Vec<u8>;
+
+    /// Restores the resource from bytes previously produced by [`Reloadable::save`].
+    ///
+    /// Returns `None` if the bytes cannot be restored, in which case the
+    /// resource is left untouched.
+    fn restore(bytes: &[u8]) -> Option<Self>;
+}
+
+/// The type-erased operations of a registered [`Reloadable`] resource.
+#[derive(Clone, Copy)]
+pub(crate) struct ReloadableEntry {
+    pub type_id: TypeId,
+    pub save: fn(&ErasedResourceCell) -> Vec<u8>,
+    pub restore: fn(&[u8]) -> Option<ErasedResourceCell>,
+}
+
+impl ReloadableEntry {
+    pub fn new<T>() -> Self
+    where
+        T: Reloadable,
+    {
+        Self {
+            type_id: TypeId::of::<T>(),
+            save: |cell| cell.get_downcasted_ref::<T>().save(),
+            restore: |bytes| T::restore(bytes).map(ErasedResourceCell::new),
+        }
+    }
+}
+
+/// A snapshot of all the [`Reloadable`] resources, keyed by their stable key.
+///
+/// Entries are ordered by key, so the snapshot is deterministic.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReloadSnapshot {
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl ReloadSnapshot {
+    /// Returns the saved bytes of a resource by its key.
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.entries.get(key).map(Vec::as_slice)
+    }
+
+    /// Inserts the saved bytes of a resource.
+    pub fn insert(&mut self, key: impl Into<String>, bytes: Vec<u8>) {
+        self.entries.insert(key.into(), bytes);
+    }
+
+    /// Returns an iterator over the keys and saved bytes, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.entries
+            .iter()
+            .map(|(key, bytes)| (key.as_str(), bytes.as_slice()))
+    }
+
+    /// Returns the number of saved resources.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no resources were saved.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -4,12 +4,14 @@ Texture2D textureSampler;
 [[vk::binding(1, 0)]]
 SamplerState textureSamplerState;
 
+#include "vislum/material.hlsl"
+
 struct FragmentInput {
     float4 position : SV_POSITION;
     float2 uv : TEXCOORD0;
 };
 
 float4 main(FragmentInput input) : SV_Target {
-    return textureSampler.Sample(textureSamplerState, input.uv);
+    return textureSampler.Sample(textureSamplerState, input.uv) * material.tint;
 }
 
@@ -12,16 +12,124 @@ use winit::{
 };
 
 use vislum_render::context::RenderContext;
-use vislum_render::graph::pass::FrameGraphSubmitInfo;
+use vislum_render::frame::FramesInFlight;
 use vislum_render::resource::{
+    material::{
+        MATERIAL_UNIFORM_BINDING, MaterialDefinition, MaterialPropertyDefinition, MaterialValue,
+    },
     mesh::Vertex,
+    vertex::MeshVertex,
     pool::ResourceId,
     texture::{Texture, TextureCreateInfo, TextureDimensions, TextureFormat},
 };
 use vislum_render_rhi::{
-    VkHandle, command::{AccessFlags2, CommandPool, ImageLayout, ImageMemoryBarrier2, IndexType, PipelineBindPoint, PipelineStageFlags2, Rect2D, Viewport}, device::{Device, DeviceCreateInfo, DeviceExtensions, DeviceFeatures}, image::Extent2D, instance::{Instance, InstanceExtensions, Library}, memory::MemoryAllocator, queue::Queue, surface::Surface, swapchain::{Swapchain, SwapchainCreateInfo}, sync::{Fence, Semaphore}
+    descriptor::{
+        DescriptorPool, DescriptorPoolCreateInfo, DescriptorPoolSizes, DescriptorResourceInfo,
+        DescriptorSet, DescriptorSetLayout, DescriptorSetLayoutBinding,
+        DescriptorSetLayoutCreateInfo, DescriptorType, DescriptorWrites,
+    },
+    pipeline::{
+        BlendState, CullMode, DepthAttachmentState, FrontFace, GraphicsPipeline, PipelineLayout,
+        PipelineLayoutCreateInfo,
+    },
+    shader::{ShaderModule, ShaderStage, ShaderStageFlags},
+    VkHandle, command::{AccessFlags2, CommandBufferLevel, CommandBufferUsageFlags, CommandEncoder, CommandPool, ImageLayout, ImageMemoryBarrier2, PipelineBindPoint, PipelineStageFlags2, Rect2D, Viewport}, device::{Device, DeviceCreateInfo, DeviceExtensions, DeviceFeatures}, image::{Extent2D, Image}, instance::{Instance, InstanceExtensions, Library}, memory::MemoryAllocator, queue::Queue, surface::Surface, swapchain::{PresentOwnershipTransfer, Swapchain, SwapchainCreateInfo}, sync::{Fence, Semaphore}
 };
-use vislum_shader::compiler::ShaderCompiler;
+use vislum_shader::{compiler::ShaderCompiler, composer::ShaderComposer};
+
+/// The queue family the renderer records and submits the frames on.
+const GRAPHICS_QUEUE_FAMILY_INDEX: u32 = 0;
+
+/// The descriptor set the material of the quad is bound to.
+const MATERIAL_SET: u32 = 1;
+
+/// Presents swapchain images from a queue family other than the graphics one.
+///
+/// The frames release the ownership of their swapchain image, which the
+/// present queue acquires before presenting it.
+struct PresentTransfer {
+    queue: Arc<Queue>,
+    queue_family_index: u32,
+    /// The objects of the acquisitions, one per frame in flight.
+    frames: Vec<PresentTransferFrame>,
+}
+
+struct PresentTransferFrame {
+    command_pool: Arc<CommandPool>,
+    /// Signaled once the present queue acquired the image, and waited by the
+    /// presentation.
+    acquire_semaphore: Arc<Semaphore>,
+    /// Signaled once the acquisition finishes executing.
+    fence: Arc<Fence>,
+}
+
+impl PresentTransfer {
+    fn new(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        queue_family_index: u32,
+        frame_count: usize,
+    ) -> Self {
+        let frames = (0..frame_count)
+            .map(|_| PresentTransferFrame {
+                command_pool: CommandPool::new(device.clone(), queue_family_index),
+                acquire_semaphore: Semaphore::new(device.clone()),
+                fence: Fence::signaled(device.clone()),
+            })
+            .collect();
+
+        Self {
+            queue,
+            queue_family_index,
+            frames,
+        }
+    }
+
+    /// Acquires the ownership of a swapchain image on the present queue once
+    /// the frame is rendered, returning the semaphore to present it with.
+    fn acquire(
+        &self,
+        frame_index: usize,
+        image: Arc<Image>,
+        render_semaphore: Arc<Semaphore>,
+    ) -> Arc<Semaphore> {
+        let frame = &self.frames[frame_index];
+        frame.fence.wait(u64::MAX);
+        frame.fence.reset();
+        frame.command_pool.reset();
+
+        let transfer = PresentOwnershipTransfer::new(
+            image,
+            GRAPHICS_QUEUE_FAMILY_INDEX,
+            self.queue_family_index,
+        )
+        .expect("the present queue family is the graphics one");
+
+        let mut encoder =
+            CommandEncoder::new(frame.command_pool.allocate(CommandBufferLevel::PRIMARY));
+        encoder.begin(CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        encoder.pipeline_barrier([], [], [transfer.acquire]);
+        encoder.end();
+
+        self.queue.submit(
+            encoder,
+            vec![render_semaphore],
+            vec![frame.acquire_semaphore.clone()],
+            Some(frame.fence.clone()),
+        );
+
+        frame.acquire_semaphore.clone()
+    }
+}
+
+/// Returns the extent of the depth buffer used with a swapchain.
+fn depth_extent(swapchain_extent: Extent2D) -> vislum_render_rhi::image::Extent3D {
+    vislum_render_rhi::image::Extent3D {
+        width: swapchain_extent.width,
+        height: swapchain_extent.height,
+        depth: 1,
+    }
+}
 
 #[derive(Default)]
 enum AppState {
@@ -34,27 +142,29 @@ enum AppState {
         swapchain: Arc<Swapchain>,
         swapchain_images: Vec<Arc<vislum_render_rhi::image::Image>>,
         texture_id: ResourceId<Texture>,
+        // Depth buffer matching the swapchain extent
+        depth_texture_id: ResourceId<Texture>,
         // Direct ash handles for things not yet in RHI
         device: Arc<vislum_render_rhi::device::Device>,
         queue: Arc<Queue>,
-        // Pipeline and descriptor set (ash directly)
-        pipeline_layout: vk::PipelineLayout,
-        pipeline: vk::Pipeline,
-        descriptor_set_layout: vk::DescriptorSetLayout,
-        descriptor_set: vk::DescriptorSet,
-        descriptor_pool: vk::DescriptorPool,
+        // Set when presenting from another queue family than the graphics one
+        present_transfer: Option<PresentTransfer>,
+        pipeline: Arc<GraphicsPipeline>,
+        // The texture and sampler of the quad, one set per frame in flight
+        descriptor_sets: Vec<Arc<DescriptorSet>>,
+        descriptor_pool: Arc<DescriptorPool>,
+        // The uniform buffer of the materials and the set binding it
+        material_buffer: Arc<vislum_render_rhi::buffer::Buffer>,
+        material_descriptor_set: Arc<DescriptorSet>,
+        material_offset: u32,
         sampler: Arc<vislum_render_rhi::sampler::Sampler>,
-        image_view: Arc<vislum_render_rhi::image::ImageView>,
         // Mesh (using vislum-render abstraction)
         mesh_id: ResourceId<vislum_render::resource::mesh::Mesh>,
         // Command pool for frame rendering
-        command_pool: Arc<CommandPool>,
-        // Per-swapchain-image sync objects (one set per swapchain image)
-        frame_sync_objects: Vec<(Arc<Semaphore>, Arc<Semaphore>, Arc<Fence>)>, // (acquire, render, fence)
-        // Track which swapchain images have been used (to know initial layout)
-        swapchain_images_used: std::sync::Arc<std::sync::Mutex<std::collections::HashSet<u32>>>,
-        current_frame: usize,
+        frames: FramesInFlight,
         image_index: Option<u32>,
+        // Set on resize or when the swapchain reports it is out of date
+        swapchain_outdated: bool,
     },
 }
 
@@ -107,6 +217,8 @@ impl ApplicationHandler for App {
                 _ => unimplemented!(),
             }
             instance_extensions.khr_surface = true;
+            // Name objects for validation messages and frame captures, if available.
+            instance_extensions.ext_debug_utils = library.supported_extensions().ext_debug_utils;
 
             log::info!("Creating Vulkan instance...");
             let instance = Instance::new(library, instance_extensions);
@@ -120,7 +232,7 @@ impl ApplicationHandler for App {
             log::info!("Enumerating physical devices...");
             let physical_devices: Vec<_> = instance.enumerate_physical_devices().collect();
             log::info!("Found {} physical devices", physical_devices.len());
-            let (physical_device, queue_family_index) = physical_devices
+            let (physical_device, present_queue_family_index) = physical_devices
                 .iter()
                 .filter_map(|p| {
                     // Check if swapchain extension is supported
@@ -133,20 +245,28 @@ impl ApplicationHandler for App {
                         return None;
                     }
 
-                    // Check if any queue family supports graphics and presentation
-                    let queue_family_index =
-                        p.capabilities().enumerate().find_map(|(idx, q)| {
-                            if q.queue_flags
-                                .contains(vislum_render_rhi::device::QueueFlags::GRAPHICS)
-                                && surface.get_physical_device_surface_support(p, idx as u32)
-                            {
-                                Some(idx)
-                            } else {
-                                None
-                            }
-                        })?;
-
-                    Some((p.clone(), queue_family_index as u32))
+                    // The renderer records on the graphics queue family
+                    let graphics_family =
+                        p.capabilities().nth(GRAPHICS_QUEUE_FAMILY_INDEX as usize)?;
+                    if !graphics_family
+                        .queue_flags
+                        .contains(vislum_render_rhi::device::QueueFlags::GRAPHICS)
+                    {
+                        return None;
+                    }
+
+                    // Prefer presenting from the graphics queue family, which
+                    // spares the ownership transfers of the swapchain images
+                    let present_families = surface
+                        .supported_present_queue_families(p)
+                        .collect::<Vec<_>>();
+                    let present_queue_family_index = present_families
+                        .iter()
+                        .copied()
+                        .find(|&idx| idx == GRAPHICS_QUEUE_FAMILY_INDEX)
+                        .or_else(|| present_families.first().copied())?;
+
+                    Some((p.clone(), present_queue_family_index))
                 })
                 .min_by_key(|(p, _)| match p.properties().device_type {
                     vislum_render_rhi::device::PhysicalDeviceType::DISCRETE_GPU => 0,
@@ -175,16 +295,20 @@ impl ApplicationHandler for App {
                     physical_device,
                     extensions: device_extensions,
                     features: device_features,
+                    additional_queue_families: vec![present_queue_family_index],
                 },
             );
             log::info!("Device created");
 
-            // Get queue (device creates queue family 0, so get queue 0)
+            // Get the first queue of the graphics and present queue families
             log::info!("Getting queue...");
             use vislum_render_rhi::AshHandle;
-            let queue_handle =
-                unsafe { device.ash_handle().get_device_queue(queue_family_index, 0) };
-            let queue = Arc::new(Queue::new(device.clone(), queue_handle));
+            let get_queue = |queue_family_index| {
+                let queue_handle =
+                    unsafe { device.ash_handle().get_device_queue(queue_family_index, 0) };
+                Arc::new(Queue::new(device.clone(), queue_handle))
+            };
+            let queue = get_queue(GRAPHICS_QUEUE_FAMILY_INDEX);
             log::info!("Queue obtained");
 
             // Create memory allocator
@@ -201,16 +325,30 @@ impl ApplicationHandler for App {
                     min_image_count: None,
                     present_mode: None,
                     image_usage: None,
+                    image_extent: Some(Extent2D::new(
+                        window.inner_size().width,
+                        window.inner_size().height,
+                    )),
                     old_swapchain: None,
                 },
             );
             log::info!("Swapchain created with {} images", swapchain_images.len());
+            for (index, image) in swapchain_images.iter().enumerate() {
+                image.set_name(&format!("swapchain image {index}"));
+            }
 
             // Create RenderContext
             log::info!("Creating render context...");
             let mut render_context = RenderContext::new(device.clone(), queue.clone());
             log::info!("Render context created");
 
+            let depth_texture_id =
+                render_context.create_depth_texture(depth_extent(swapchain.image_extent()));
+            let depth_format = render_context
+                .get_texture_image(depth_texture_id)
+                .unwrap()
+                .format();
+
             // Load PNG image
             log::info!("Loading texture image...");
             // Try paths relative to workspace root and package directory
@@ -236,6 +374,7 @@ impl ApplicationHandler for App {
                         height,
                         depth: 1,
                     },
+                    generate_mipmaps: true,
                 },
                 image_data,
             );
@@ -270,10 +409,27 @@ impl ApplicationHandler for App {
             let mesh_id = render_context.create_mesh(vertices, indices);
             log::info!("Quad mesh created with id: {:?}", mesh_id);
 
-            // Get texture view for descriptor set
-            log::info!("Getting texture view...");
-            let image_view = render_context.get_texture_view(texture_id).unwrap();
-            log::info!("Texture view obtained");
+            // Create the material of the quad, tinting its texture
+            log::info!("Creating quad material...");
+            let material_definition = MaterialDefinition::new(
+                "QuadMaterial",
+                [MaterialPropertyDefinition::Color("tint".into())],
+            );
+            let material_layout = material_definition.shader_layout(MATERIAL_SET);
+            let material_uniform_size = material_definition.uniform_size() as u64;
+            let materials = render_context.materials_mut();
+            let material_definition = materials.create_definition(material_definition);
+            let material = materials
+                .instantiate(material_definition)
+                .expect("the material definition was just created");
+            materials
+                .set_instance_property(material, "tint", MaterialValue::Color([1.0, 0.8, 0.8, 1.0]))
+                .expect("Failed to set the material tint");
+            let material_offset = materials.dynamic_offset(material).unwrap();
+            let material_buffer = render_context
+                .materials()
+                .create_uniform_buffer(device.clone(), render_context.allocator().clone());
+            log::info!("Quad material created");
 
             // Create sampler
             let sampler = vislum_render_rhi::sampler::Sampler::new(
@@ -284,6 +440,8 @@ impl ApplicationHandler for App {
                     address_mode_u: vk::SamplerAddressMode::REPEAT,
                     address_mode_v: vk::SamplerAddressMode::REPEAT,
                     address_mode_w: vk::SamplerAddressMode::REPEAT,
+                    mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                    compare: None,
                 },
             );
             log::info!("Image view and sampler created");
@@ -303,298 +461,144 @@ impl ApplicationHandler for App {
             let vert_spirv = compiler
                 .compile_vertex(&vert_source, "main")
                 .expect("Failed to compile vertex shader");
+            let mut composer = ShaderComposer::default();
+            composer
+                .set_material_layout(&material_layout)
+                .expect("Failed to generate the material block");
+            let frag_source = composer
+                .compose("quad.frag.hlsl", &frag_source)
+                .expect("Failed to compose fragment shader");
             let frag_spirv = compiler
                 .compile_fragment(&frag_source, "main")
                 .expect("Failed to compile fragment shader");
             log::info!("Shaders compiled");
 
-            // Create shader modules using ash directly
             log::info!("Creating shader modules...");
-            // Convert byte slice to u32 slice for shader code
-            let vert_spirv_u32: Vec<u32> = vert_spirv
-                .chunks_exact(4)
-                .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                .collect();
-            let frag_spirv_u32: Vec<u32> = frag_spirv
-                .chunks_exact(4)
-                .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                .collect();
-
-            let vert_shader_module = {
-                let create_info = vk::ShaderModuleCreateInfo::default().code(&vert_spirv_u32);
-                unsafe {
-                    device
-                        .ash_handle()
-                        .create_shader_module(&create_info, None)
-                        .unwrap()
-                }
-            };
-
-            let frag_shader_module = {
-                let create_info = vk::ShaderModuleCreateInfo::default().code(&frag_spirv_u32);
-                unsafe {
-                    device
-                        .ash_handle()
-                        .create_shader_module(&create_info, None)
-                        .unwrap()
-                }
-            };
+            let vert_shader_module = ShaderModule::new(device.clone(), &vert_spirv);
+            let frag_shader_module = ShaderModule::new(device.clone(), &frag_spirv);
             log::info!("Shader modules created");
 
-            // Create descriptor set layout using ash
             log::info!("Creating descriptor set layout...");
-            let descriptor_set_layout = {
-                let bindings = [
-                    vk::DescriptorSetLayoutBinding::default()
-                        .binding(0)
-                        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
-                        .descriptor_count(1)
-                        .stage_flags(vk::ShaderStageFlags::FRAGMENT),
-                    vk::DescriptorSetLayoutBinding::default()
-                        .binding(1)
-                        .descriptor_type(vk::DescriptorType::SAMPLER)
-                        .descriptor_count(1)
-                        .stage_flags(vk::ShaderStageFlags::FRAGMENT),
-                ];
-
-                let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
-
-                unsafe {
-                    device
-                        .ash_handle()
-                        .create_descriptor_set_layout(&create_info, None)
-                        .unwrap()
-                }
-            };
-
-            // Create descriptor pool using ash
-            let descriptor_pool = {
-                let pool_sizes = [
-                    vk::DescriptorPoolSize::default()
-                        .ty(vk::DescriptorType::SAMPLED_IMAGE)
-                        .descriptor_count(1),
-                    vk::DescriptorPoolSize::default()
-                        .ty(vk::DescriptorType::SAMPLER)
-                        .descriptor_count(1),
-                ];
-
-                let create_info = vk::DescriptorPoolCreateInfo::default()
-                    .pool_sizes(&pool_sizes)
-                    .max_sets(1);
-
-                unsafe {
-                    device
-                        .ash_handle()
-                        .create_descriptor_pool(&create_info, None)
-                        .unwrap()
-                }
-            };
+            let descriptor_set_layout = DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutCreateInfo {
+                    bindings: vec![
+                        DescriptorSetLayoutBinding {
+                            binding: 0,
+                            descriptor_type: DescriptorType::SampledImage,
+                            count: 1,
+                            stages: ShaderStageFlags::FRAGMENT,
+                        },
+                        DescriptorSetLayoutBinding {
+                            binding: 1,
+                            descriptor_type: DescriptorType::Sampler,
+                            count: 1,
+                            stages: ShaderStageFlags::FRAGMENT,
+                        },
+                    ],
+                },
+            );
 
-            // Allocate descriptor set using ash
-            let descriptor_set = {
-                let layouts = [descriptor_set_layout];
-                let allocate_info = vk::DescriptorSetAllocateInfo::default()
-                    .descriptor_pool(descriptor_pool)
-                    .set_layouts(&layouts);
+            let material_descriptor_set_layout = DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutCreateInfo {
+                    bindings: vec![DescriptorSetLayoutBinding {
+                        binding: MATERIAL_UNIFORM_BINDING,
+                        descriptor_type: DescriptorType::UniformBufferDynamic,
+                        count: 1,
+                        stages: ShaderStageFlags::FRAGMENT,
+                    }],
+                },
+            );
 
-                let sets = unsafe {
+            let default_pool_info = DescriptorPoolCreateInfo::default();
+            let descriptor_pool = DescriptorPool::new(
+                device.clone(),
+                DescriptorPoolCreateInfo {
+                    sizes: DescriptorPoolSizes {
+                        uniform_buffer_dynamic: 16,
+                        ..default_pool_info.sizes
+                    },
+                    ..default_pool_info
+                },
+            );
+            // The texture is written every frame, so each frame in flight
+            // has its own set
+            let descriptor_sets = (0..swapchain_images.len())
+                .map(|index| {
+                    let descriptor_set = descriptor_pool
+                        .allocate(&descriptor_set_layout)
+                        .expect("Failed to allocate descriptor set");
                     device
-                        .ash_handle()
-                        .allocate_descriptor_sets(&allocate_info)
-                        .unwrap()
-                };
-                sets[0]
-            };
-
-            // Write descriptor set using ash
-            {
-                // Binding 0: Sampled image
-                use vislum_render_rhi::{VkHandle, command::types::ImageLayout};
-                let image_info = vk::DescriptorImageInfo::default()
-                    .image_layout(ImageLayout::ShaderReadOnlyOptimal.to_vk())
-                    .image_view(image_view.vk_handle());
-                let image_infos = [image_info];
-
-                // Binding 1: Sampler
-                let sampler_info = vk::DescriptorImageInfo::default().sampler(sampler.vk_handle());
-                let sampler_infos = [sampler_info];
-
-                let writes = [
-                    vk::WriteDescriptorSet::default()
-                        .dst_set(descriptor_set)
-                        .dst_binding(0)
-                        .dst_array_element(0)
-                        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
-                        .image_info(&image_infos),
-                    vk::WriteDescriptorSet::default()
-                        .dst_set(descriptor_set)
-                        .dst_binding(1)
-                        .dst_array_element(0)
-                        .descriptor_type(vk::DescriptorType::SAMPLER)
-                        .image_info(&sampler_infos),
-                ];
-
-                unsafe {
-                    device.ash_handle().update_descriptor_sets(&writes, &[]);
-                }
+                        .set_object_name(&*descriptor_set, &format!("quad descriptor set {index}"));
+                    descriptor_set
+                })
+                .collect::<Vec<_>>();
+            let material_descriptor_set = descriptor_pool
+                .allocate(&material_descriptor_set_layout)
+                .expect("Failed to allocate material descriptor set");
+            device.set_object_name(&*material_descriptor_set, "quad material descriptor set");
+
+            let mut descriptor_writes = DescriptorWrites::new();
+            for descriptor_set in &descriptor_sets {
+                descriptor_writes.write(descriptor_set.vk_handle(), 1, &*sampler);
             }
 
-            // Create pipeline layout using ash
-            let pipeline_layout = {
-                let layouts = [descriptor_set_layout];
-                let create_info = vk::PipelineLayoutCreateInfo::default().set_layouts(&layouts);
-
-                unsafe {
-                    device
-                        .ash_handle()
-                        .create_pipeline_layout(&create_info, None)
-                        .unwrap()
-                }
-            };
-
-            // Create graphics pipeline using ash
-            let pipeline = {
-                // Vertex shader stage
-                let vert_stage = vk::PipelineShaderStageCreateInfo::default()
-                    .stage(vk::ShaderStageFlags::VERTEX)
-                    .module(vert_shader_module)
-                    .name(c"main");
-
-                // Fragment shader stage
-                let frag_stage = vk::PipelineShaderStageCreateInfo::default()
-                    .stage(vk::ShaderStageFlags::FRAGMENT)
-                    .module(frag_shader_module)
-                    .name(c"main");
-
-                let stages = [vert_stage, frag_stage];
-
-                // Vertex input
-                let binding_description = vk::VertexInputBindingDescription::default()
-                    .binding(0)
-                    .stride(std::mem::size_of::<Vertex>() as u32)
-                    .input_rate(vk::VertexInputRate::VERTEX);
-
-                let attribute_descriptions = [
-                    vk::VertexInputAttributeDescription::default()
-                        .binding(0)
-                        .location(0)
-                        .format(vk::Format::R32G32B32_SFLOAT)
-                        .offset(0),
-                    vk::VertexInputAttributeDescription::default()
-                        .binding(0)
-                        .location(1)
-                        .format(vk::Format::R32G32B32_SFLOAT)
-                        .offset(12),
-                    vk::VertexInputAttributeDescription::default()
-                        .binding(0)
-                        .location(2)
-                        .format(vk::Format::R32G32_SFLOAT)
-                        .offset(24),
-                ];
-
-                let binding_descriptions = [binding_description];
-                let vertex_input = vk::PipelineVertexInputStateCreateInfo::default()
-                    .vertex_binding_descriptions(&binding_descriptions)
-                    .vertex_attribute_descriptions(&attribute_descriptions);
-
-                // Input assembly
-                let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
-                    .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
-
-                // Viewport
-                let viewport = vk::Viewport::default()
-                    .width(800.0)
-                    .height(600.0)
-                    .max_depth(1.0);
-
-                use vislum_render_rhi::image::Extent2D;
-                let scissor = vk::Rect2D::default().extent(Extent2D::new(800, 600).to_vk());
-
-                let viewports = [viewport];
-                let scissors = [scissor];
-                let viewport_state = vk::PipelineViewportStateCreateInfo::default()
-                    .viewports(&viewports)
-                    .scissors(&scissors);
-
-                // Rasterization
-                let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
-                    .polygon_mode(vk::PolygonMode::FILL)
-                    .cull_mode(vk::CullModeFlags::BACK)
-                    .front_face(vk::FrontFace::CLOCKWISE)
-                    .line_width(1.0);
-
-                // Multisample
-                let multisample = vk::PipelineMultisampleStateCreateInfo::default()
-                    .rasterization_samples(vk::SampleCountFlags::TYPE_1);
-
-                // Color blend (alpha blending enabled)
-                let color_blend_attachment = vk::PipelineColorBlendAttachmentState::default()
-                    .color_write_mask(
-                        vk::ColorComponentFlags::R
-                            | vk::ColorComponentFlags::G
-                            | vk::ColorComponentFlags::B
-                            | vk::ColorComponentFlags::A,
-                    )
-                    .blend_enable(true)
-                    .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
-                    .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-                    .color_blend_op(vk::BlendOp::ADD)
-                    .src_alpha_blend_factor(vk::BlendFactor::ONE)
-                    .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-                    .alpha_blend_op(vk::BlendOp::ADD);
-
-                let color_blend_attachments = [color_blend_attachment];
-                let color_blend = vk::PipelineColorBlendStateCreateInfo::default()
-                    .logic_op_enable(false)
-                    .attachments(&color_blend_attachments);
-
-                // Dynamic rendering (KHR extension)
-                let image_format_vk = swapchain.image_format().to_vk();
-                let color_attachment_formats = [image_format_vk];
-                let mut dynamic_rendering = vk::PipelineRenderingCreateInfo::default()
-                    .color_attachment_formats(&color_attachment_formats);
-
-                let create_info = vk::GraphicsPipelineCreateInfo::default()
-                    .stages(&stages)
-                    .vertex_input_state(&vertex_input)
-                    .input_assembly_state(&input_assembly)
-                    .viewport_state(&viewport_state)
-                    .rasterization_state(&rasterization)
-                    .multisample_state(&multisample)
-                    .color_blend_state(&color_blend)
-                    .layout(pipeline_layout)
-                    .push_next(&mut dynamic_rendering);
-
-                let pipelines = unsafe {
-                    device
-                        .ash_handle()
-                        .create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
-                        .unwrap()
-                };
-                pipelines[0]
-            };
-
-            // Create command pool
-            log::info!("Creating command pool...");
-            let command_pool = CommandPool::new(device.clone(), queue_family_index);
-            log::info!("Command pool created");
+            // The dynamic offset selects the slot of the instance, so the
+            // descriptor covers a single slot
+            descriptor_writes
+                .write_info(
+                    material_descriptor_set.vk_handle(),
+                    MATERIAL_UNIFORM_BINDING,
+                    DescriptorType::UniformBufferDynamic,
+                    DescriptorResourceInfo::Buffer(
+                        vk::DescriptorBufferInfo::default()
+                            .buffer(material_buffer.vk_handle())
+                            .offset(0)
+                            .range(material_uniform_size),
+                    ),
+                )
+                .update(&device);
+
+            let pipeline_layout = PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutCreateInfo {
+                    set_layouts: vec![descriptor_set_layout, material_descriptor_set_layout],
+                },
+            );
 
-            // Create per-frame sync objects (one set per swapchain image)
-            log::info!("Creating per-frame sync objects...");
+            let pipeline = GraphicsPipeline::builder(
+                pipeline_layout,
+                ShaderStage::new(vert_shader_module, "main"),
+            )
+            .fragment_shader(ShaderStage::new(frag_shader_module, "main"))
+            .vertex_buffer(Vertex::layout().buffer_layout(0))
+            .cull_mode(CullMode::Back)
+            .front_face(FrontFace::Clockwise)
+            .color_attachment(swapchain.image_format(), Some(BlendState::ALPHA_BLENDING))
+            .depth_attachment(DepthAttachmentState::less(depth_format))
+            .build(device.clone())
+            .expect("Failed to create graphics pipeline");
+            device.set_object_name(&*pipeline, "quad pipeline");
+
+            // Create the frames in flight, one per swapchain image
             let num_frames = swapchain_images.len();
-            log::info!(
-                "Creating {} sync object sets for {} swapchain images",
-                num_frames,
-                num_frames
-            );
-            let mut frame_sync_objects = Vec::with_capacity(num_frames);
-            for _ in 0..num_frames {
-                frame_sync_objects.push((
-                    Semaphore::new(device.clone()),  // acquire semaphore
-                    Semaphore::new(device.clone()),  // render semaphore
-                    Fence::signaled(device.clone()), // fence (starts signaled)
-                ));
-            }
+            log::info!("Creating {} frames in flight...", num_frames);
+            let frames =
+                FramesInFlight::new(device.clone(), GRAPHICS_QUEUE_FAMILY_INDEX, num_frames);
+
+            let present_transfer = (present_queue_family_index != GRAPHICS_QUEUE_FAMILY_INDEX)
+                .then(|| {
+                    log::info!(
+                        "Presenting from queue family {present_queue_family_index}, transferring the swapchain images"
+                    );
+                    PresentTransfer::new(
+                        device.clone(),
+                        get_queue(present_queue_family_index),
+                        present_queue_family_index,
+                        num_frames,
+                    )
+                });
 
             self.state = AppState::Ready {
                 window,
@@ -603,21 +607,21 @@ impl ApplicationHandler for App {
                 swapchain,
                 swapchain_images,
                 texture_id,
+                depth_texture_id,
                 device,
                 queue,
-                pipeline_layout,
+                present_transfer,
                 pipeline,
-                descriptor_set_layout,
-                descriptor_set,
+                descriptor_sets,
                 descriptor_pool,
+                material_buffer,
+                material_descriptor_set,
+                material_offset,
                 sampler,
-                image_view,
                 mesh_id,
-                command_pool,
-                frame_sync_objects,
-                swapchain_images_used: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashSet::new())),
-                current_frame: 0,
+                frames,
                 image_index: None,
+                swapchain_outdated: false,
             };
             log::info!("Application state initialized successfully");
         }
@@ -639,6 +643,17 @@ impl ApplicationHandler for App {
         event: WindowEvent,
     ) {
         match event {
+            WindowEvent::Resized(_) => {
+                if let AppState::Ready {
+                    window,
+                    swapchain_outdated,
+                    ..
+                } = &mut self.state
+                {
+                    *swapchain_outdated = true;
+                    window.request_redraw();
+                }
+            }
             WindowEvent::CloseRequested => {
                 log::info!("Window close requested");
                 event_loop.exit();
@@ -651,54 +666,88 @@ impl ApplicationHandler for App {
                     swapchain_images,
                     device,
                     queue,
+                    present_transfer,
                     pipeline,
-                    pipeline_layout,
-                    descriptor_set,
+                    descriptor_sets,
+                    material_descriptor_set,
+                    material_offset,
                     mesh_id,
                     window,
-                    command_pool: _command_pool,
-                    frame_sync_objects,
-                    swapchain_images_used,
-                    current_frame,
+                    frames,
                     image_index,
                     texture_id,
+                    depth_texture_id,
+                    swapchain_outdated,
                     ..
                 } = &mut self.state
                 {
-                    log::debug!("Processing frame {}", *current_frame);
-                    
-                    // Get sync objects for current frame
-                    let (acquire_semaphore, render_semaphore, render_fence) =
-                        &frame_sync_objects[*current_frame];
+                    log::debug!("Processing frame {}", frames.current_index());
+
+                    // Skip rendering while minimized, restoring the window
+                    // triggers a resize
+                    let window_size = window.inner_size();
+                    let window_extent = Extent2D::new(window_size.width, window_size.height);
+                    if window_extent.is_empty() {
+                        return;
+                    }
+
+                    if *swapchain_outdated {
+                        log::info!(
+                            "Recreating swapchain with extent {}x{}",
+                            window_extent.width,
+                            window_extent.height
+                        );
+                        device.wait_idle();
+                        (*swapchain, *swapchain_images) = swapchain.recreate(window_extent);
+                        render_context.destroy_texture(*depth_texture_id);
+                        *depth_texture_id = render_context
+                            .create_depth_texture(depth_extent(swapchain.image_extent()));
+                        *swapchain_outdated = false;
+                    }
 
-                    // Wait for fence from previous use of this frame
-                    log::debug!("Waiting for fence (status: {})...", render_fence.status());
-                    render_fence.wait(u64::MAX);
-                    render_fence.reset();
-                    log::debug!("Fence reset complete");
+                    // Wait for the previous use of this frame
+                    log::debug!("Waiting for frame {}...", frames.current_index());
+                    let frame = frames.begin_frame();
+
+                    // Bind the quad's texture, or the missing texture while it's
+                    // uploading. The previous use of the frame is over, so its set
+                    // can be written
+                    let descriptor_set = descriptor_sets[frame.index()].clone();
+                    let texture_view = render_context
+                        .get_texture_view(*texture_id)
+                        .expect("the render context substitutes missing textures");
+                    DescriptorWrites::new()
+                        .write(descriptor_set.vk_handle(), 0, &*texture_view)
+                        .update(device);
 
                     // Acquire next swapchain image
                     log::debug!("Acquiring swapchain image...");
-                    let (img_idx, suboptimal) =
-                        swapchain.acquire_next_image(u64::MAX, Some(&acquire_semaphore), None);
+                    let (img_idx, suboptimal) = match swapchain.acquire_next_image(
+                        u64::MAX,
+                        Some(&frame.acquire_semaphore),
+                        None,
+                    ) {
+                        Ok(acquired) => acquired,
+                        Err(vislum_render_rhi::Error::SwapchainOutOfDate) => {
+                            log::info!("Swapchain is out of date");
+                            *swapchain_outdated = true;
+                            window.request_redraw();
+                            return;
+                        }
+                        Err(e) => panic!("Failed to acquire swapchain image: {e}"),
+                    };
 
                     *image_index = Some(img_idx);
                     log::debug!("Acquired swapchain image {}", img_idx);
 
                     if suboptimal {
-                        log::warn!("Swapchain is suboptimal");
-                        // Could recreate swapchain here if needed
+                        log::info!("Swapchain is suboptimal, recreating it after this frame");
+                        *swapchain_outdated = true;
                     }
 
                     // Get swapchain image
                     let swapchain_image = swapchain_images[img_idx as usize].clone();
                     
-                    // Check if this image has been used before (to determine initial layout)
-                    let is_first_use = {
-                        let mut used = swapchain_images_used.lock().unwrap();
-                        used.insert(img_idx)
-                    };
-
                     // Create image view for swapchain image using RHI
                     log::debug!("Creating swapchain image view...");
                     use vislum_render_rhi::image::{ImageView, ImageViewCreateInfo, ImageViewType};
@@ -719,9 +768,10 @@ impl ApplicationHandler for App {
                     );
                     log::debug!("Swapchain image view created");
 
-                    let window_size = window.inner_size();
-                    let window_width = window_size.width;
-                    let window_height = window_size.height;
+                    // Render at the swapchain extent, which may lag behind the window size
+                    let swapchain_extent = swapchain.image_extent();
+                    let window_width = swapchain_extent.width;
+                    let window_height = swapchain_extent.height;
 
                     // Set up render pass for this frame using frame graph
                     // Note: We add the pass fresh each frame because frame graph drains nodes
@@ -733,14 +783,17 @@ impl ApplicationHandler for App {
                         swapchain_image_view: Arc<vislum_render_rhi::image::ImageView>,
                         window_width: u32,
                         window_height: u32,
-                        pipeline: vk::Pipeline,
-                        pipeline_layout: vk::PipelineLayout,
-                        descriptor_set: vk::DescriptorSet,
+                        pipeline: Arc<GraphicsPipeline>,
+                        descriptor_set: Arc<DescriptorSet>,
+                        material_descriptor_set: Arc<DescriptorSet>,
+                        material_offset: u32,
                         mesh_id: vislum_render::resource::pool::ResourceId<
                             vislum_render::resource::mesh::Mesh,
                         >,
                         texture_id: vislum_render::resource::pool::ResourceId<vislum_render::resource::texture::Texture>,
-                        is_first_use: bool,
+                        depth_texture_id: ResourceId<Texture>,
+                        depth_image_view: Arc<vislum_render_rhi::image::ImageView>,
+                        present_queue_family_index: u32,
                     }
 
                     impl vislum_render::graph::FrameNode for RenderQuadNode {
@@ -757,34 +810,50 @@ impl ApplicationHandler for App {
                             let swapchain_image_view = self.swapchain_image_view.clone();
                             let window_width = self.window_width;
                             let window_height = self.window_height;
-                            let pipeline = self.pipeline;
-                            let pipeline_layout = self.pipeline_layout;
-                            let descriptor_set = self.descriptor_set;
-
-                            // Read mesh from ResourceManager
-                            let mesh = context.read_mesh(self.mesh_id).unwrap();
-                            let vertex_buffer = mesh.vertex_buffer();
-                            let index_buffer = mesh.index_buffer();
+                            let pipeline = self.pipeline.clone();
+                            let descriptor_set = self.descriptor_set.clone();
+                            let material_descriptor_set = self.material_descriptor_set.clone();
+                            let material_offset = self.material_offset;
+
+                            // Read mesh from ResourceManager, sharing its buffers with the closure
+                            let mesh = context.read_mesh(self.mesh_id).unwrap().clone();
                             
                             // Read texture to ensure it's ready - clone the Arc for the closure
                             let texture_image = context.read_texture(self.texture_id).map(|img| img.clone());
-                            let is_first_use = self.is_first_use;
+
+                            let depth_image = context.write_texture(self.depth_texture_id).unwrap();
+                            let depth_image_view = self.depth_image_view.clone();
+                            let present_queue_family_index = self.present_queue_family_index;
 
                             Box::new(move |execute_context| {
                                 let cmd = &mut execute_context.command_buffer;
 
                                 // Transition swapchain image to the color attachment layout
+                                cmd.transition_image(
+                                    swapchain_image.clone(),
+                                    ImageLayout::ColorAttachmentOptimal,
+                                    PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                                    AccessFlags2::COLOR_ATTACHMENT_WRITE,
+                                );
+
+                                // Transition the depth buffer once the previous frame is
+                                // done testing against it, discarding its contents
+                                let depth_tests = PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                                    | PipelineStageFlags2::LATE_FRAGMENT_TESTS;
                                 cmd.pipeline_barrier(
                                     std::iter::empty(),
                                     std::iter::empty(),
                                     std::iter::once(ImageMemoryBarrier2 {
-                                        image: swapchain_image.clone(),
-                                        src_stage_mask: PipelineStageFlags2::TOP_OF_PIPE,
-                                        src_access_mask: AccessFlags2::NONE,
-                                        dst_stage_mask: PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
-                                        dst_access_mask: AccessFlags2::COLOR_ATTACHMENT_WRITE,
+                                        image: depth_image.clone(),
+                                        src_stage_mask: depth_tests,
+                                        src_access_mask: AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                                        dst_stage_mask: depth_tests,
+                                        dst_access_mask: AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                                            | AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
                                         old_layout: ImageLayout::Undefined,
-                                        new_layout: ImageLayout::ColorAttachmentOptimal,
+                                        new_layout: ImageLayout::DepthStencilAttachmentOptimal,
+                                        ownership_transfer: None,
+                                        subresource_range: None,
                                     }),
                                 );
 
@@ -801,12 +870,24 @@ impl ApplicationHandler for App {
                                     .store_op(vk::AttachmentStoreOp::STORE)
                                     .clear_value(clear_value);
 
+                                let depth_attachment = vk::RenderingAttachmentInfo::default()
+                                    .image_view(depth_image_view.vk_handle())
+                                    .image_layout(ImageLayout::DepthStencilAttachmentOptimal.to_vk())
+                                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                                    .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                                    .clear_value(vk::ClearValue {
+                                        depth_stencil: vk::ClearDepthStencilValue {
+                                            depth: 1.0,
+                                            stencil: 0,
+                                        },
+                                    });
+
                                 let render_area = vk::Rect2D::default()
                                     .extent(Extent2D::new(window_width, window_height).to_vk());
                                 let color_attachments = [color_attachment];
                                 let rendering_info = vk::RenderingInfo::default()
                                     .color_attachments(&color_attachments)
-                                    // Don't call depth_attachment() or stencil_attachment() to leave them as None
+                                    .depth_attachment(&depth_attachment)
                                     .render_area(render_area)
                                     .layer_count(1);
 
@@ -827,92 +908,100 @@ impl ApplicationHandler for App {
                                 cmd.set_scissor(0, [scissor]);
 
                                 // Bind pipeline
-                                cmd.bind_pipeline(PipelineBindPoint::Graphics, pipeline);
+                                cmd.bind_pipeline(PipelineBindPoint::Graphics, pipeline.vk_handle());
 
-                                // Bind descriptor set
-                                let descriptor_sets = [descriptor_set];
+                                // Bind the texture and material descriptor sets, selecting
+                                // the material instance's slot with its dynamic offset
                                 cmd.bind_descriptor_sets(
                                     PipelineBindPoint::Graphics,
-                                    pipeline_layout,
+                                    pipeline.layout().vk_handle(),
                                     0,
-                                    descriptor_sets,
-                                    [],
+                                    [
+                                        descriptor_set.vk_handle(),
+                                        material_descriptor_set.vk_handle(),
+                                    ],
+                                    [material_offset],
                                 );
 
-                                // Bind vertex buffer
-                                let vertex_offsets = [0u64];
-                                cmd.bind_vertex_buffers(
-                                    0,
-                                    [vertex_buffer.clone()],
-                                    vertex_offsets,
-                                );
-
-                                // Bind index buffer
-                                cmd.bind_index_buffer(
-                                    index_buffer.clone(),
-                                    0,
-                                    IndexType::Uint16,
-                                );
-
-                                // Draw
-                                cmd.draw_indexed(6, 1, 0, 0, 0);
+                                // Bind the vertex and index buffers, with the mesh's
+                                // index type, and draw
+                                mesh.draw(cmd, 1);
 
                                 // End rendering
                                 cmd.end_rendering();
 
-                                // Transition swapchain image to present layout
-                                cmd.pipeline_barrier(
-                                    std::iter::empty(),
-                                    std::iter::empty(),
-                                    std::iter::once(ImageMemoryBarrier2 {
-                                        image: swapchain_image.clone(),
-                                        src_stage_mask: PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
-                                        src_access_mask: AccessFlags2::COLOR_ATTACHMENT_WRITE,
-                                        dst_stage_mask: PipelineStageFlags2::BOTTOM_OF_PIPE,
-                                        dst_access_mask: AccessFlags2::NONE,
-                                        old_layout: ImageLayout::ColorAttachmentOptimal,
-                                        new_layout: ImageLayout::PresentSrcKhr,
-                                    }),
-                                );
+                                // Transition swapchain image to present layout, releasing it
+                                // to the present queue family if it's another one
+                                match PresentOwnershipTransfer::new(
+                                    swapchain_image.clone(),
+                                    GRAPHICS_QUEUE_FAMILY_INDEX,
+                                    present_queue_family_index,
+                                ) {
+                                    Some(transfer) => {
+                                        cmd.pipeline_barrier([], [], [transfer.release])
+                                    }
+                                    None => cmd.transition_image(
+                                        swapchain_image.clone(),
+                                        ImageLayout::PresentSrcKhr,
+                                        PipelineStageFlags2::BOTTOM_OF_PIPE,
+                                        AccessFlags2::NONE,
+                                    ),
+                                }
                             })
                         }
                     }
 
-                    let pipeline_copy = *pipeline;
-                    let pipeline_layout_copy = *pipeline_layout;
-                    let descriptor_set_copy = *descriptor_set;
-
+                    let depth_image_view =
+                        render_context.get_texture_view(*depth_texture_id).unwrap();
                     render_context.add_pass(RenderQuadNode {
                         swapchain_image: swapchain_image.clone(),
                         swapchain_image_view,
                         window_width,
                         window_height,
-                        pipeline: pipeline_copy,
-                        pipeline_layout: pipeline_layout_copy,
-                        descriptor_set: descriptor_set_copy,
+                        pipeline: pipeline.clone(),
+                        descriptor_set,
+                        material_descriptor_set: material_descriptor_set.clone(),
+                        material_offset: *material_offset,
                         mesh_id: *mesh_id,
                         texture_id: *texture_id,
-                        is_first_use,
+                        depth_texture_id: *depth_texture_id,
+                        depth_image_view,
+                        present_queue_family_index: present_transfer
+                            .as_ref()
+                            .map_or(GRAPHICS_QUEUE_FAMILY_INDEX, |transfer| {
+                                transfer.queue_family_index
+                            }),
                     });
 
                     // Execute render pass
                     log::debug!("Executing and submitting frame graph...");
-                    render_context.execute_and_submit(FrameGraphSubmitInfo {
-                        wait_semaphores: vec![acquire_semaphore.clone()],
-                        signal_semaphores: vec![render_semaphore.clone()],
-                        signal_fence: Some(render_fence.clone()),
-                    });
+                    render_context.execute_and_submit(frames.end_frame());
                     log::debug!("Frame graph executed and submitted");
 
                     // Present
                     log::debug!("Presenting swapchain image...");
-                    swapchain.present(queue, img_idx, &[&render_semaphore]);
+                    let (present_queue, present_semaphore) = match present_transfer {
+                        Some(transfer) => (
+                            &transfer.queue,
+                            transfer.acquire(
+                                frame.index(),
+                                swapchain_image.clone(),
+                                frame.render_semaphore.clone(),
+                            ),
+                        ),
+                        None => (&*queue, frame.render_semaphore.clone()),
+                    };
+                    match swapchain.present(present_queue, img_idx, &[&present_semaphore]) {
+                        Ok(false) => {}
+                        Ok(true) | Err(vislum_render_rhi::Error::SwapchainOutOfDate) => {
+                            *swapchain_outdated = true;
+                        }
+                        Err(e) => panic!("Failed to present swapchain image: {e}"),
+                    }
 
                     // Swapchain image view is automatically cleaned up when dropped (RHI manages it)
 
-                    // Advance to next frame index (for tracking purposes, but we use image index for semaphores)
-                    *current_frame = (*current_frame + 1) % frame_sync_objects.len();
-                    log::debug!("Frame complete, advanced to frame {}", *current_frame);
+                    log::debug!("Frame complete, advanced to frame {}", frames.current_index());
 
                     // Request redraw
                     window.request_redraw();
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::window::RunnerRenderContext;
+use crate::window::{RunnerContext, RunnerRenderContext};
 use anyhow::Result;
 use vislum_render_rhi::{device::Device, queue::Queue};
 
@@ -16,11 +16,19 @@ pub trait Application {
     where
         Self: Sized;
 
+    /// Called each frame before rendering, including while the window is
+    /// minimized and nothing is rendered.
+    ///
+    /// The `context` allows controlling the runner, e.g. requesting an exit
+    /// or capping the frame rate.
+    fn update(&mut self, _context: &mut RunnerContext) {}
+
     /// Called each frame to render the scene.
     /// 
     /// The application is responsible for managing its own render context and
     /// frame graph. The `render_ctx` provides the current frame's swapchain
-    /// image, image view, and sync objects (all as Arcs).
+    /// image, image view, and sync objects (all as Arcs). The frame latency
+    /// of the swapchain only accounts for the submits to the runner's queue.
     fn render(&mut self, render_ctx: &RunnerRenderContext) -> Result<()>;
 }
 
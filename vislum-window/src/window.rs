@@ -1,17 +1,21 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use ash::vk;
 use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
-    event_loop::{ActiveEventLoop, EventLoop},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     window::{Window, WindowId},
 };
 
 use vislum_render_rhi::{
+    Error,
     device::Device,
-    image::{Image, ImageView, ImageViewCreateInfo, ImageViewType},
+    image::{Extent2D, Image, ImageView, ImageViewCreateInfo, ImageViewType},
     instance::Instance,
     queue::Queue,
     surface::Surface,
@@ -45,6 +49,183 @@ pub struct RunnerContext {
     pub surface: Arc<Surface>,
     pub swapchain: Arc<Swapchain>,
     pub frame_infos: Vec<SwapchainFrameInfo>,
+    exit_requested: bool,
+    frame_interval: Option<Duration>,
+    next_frame_at: Instant,
+    /// Set when the swapchain no longer matches the window and must be
+    /// recreated before the next frame.
+    swapchain_outdated: bool,
+}
+
+impl RunnerContext {
+    /// Requests the runner to stop the event loop after the current frame.
+    pub fn request_exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    /// Returns true if an exit was requested.
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    /// Sets the target frame rate. `None` means uncapped.
+    pub fn set_target_fps(&mut self, fps: Option<u32>) {
+        self.frame_interval = fps
+            .filter(|fps| *fps > 0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps as f64));
+    }
+
+    /// Returns the minimum interval between frames, if the frame rate is capped.
+    pub fn target_frame_interval(&self) -> Option<Duration> {
+        self.frame_interval
+    }
+
+    /// Recreates the swapchain to match the current window size, along with
+    /// the per-frame info depending on its images.
+    fn recreate_swapchain(&mut self, extent: Extent2D) {
+        log::info!(
+            "Recreating swapchain with extent {}x{}",
+            extent.width,
+            extent.height
+        );
+
+        // The old images and semaphores may still be in use by the device.
+        self.device.wait_idle();
+
+        let (swapchain, swapchain_images) = self.swapchain.recreate(extent);
+        self.frame_infos = create_frame_infos(&self.device, &swapchain, &swapchain_images);
+        self.swapchain = swapchain;
+        self.swapchain_outdated = false;
+    }
+
+    /// Acquires the next swapchain image, lets the application render into it
+    /// and presents it, recreating the swapchain first if it's outdated.
+    fn render_frame<A: Application>(
+        &mut self,
+        app: &mut A,
+        frame_index: &mut usize,
+        extent: Extent2D,
+    ) {
+        if self.swapchain_outdated {
+            self.recreate_swapchain(extent);
+            *frame_index = 0;
+        }
+
+        // Get sync objects for current frame slot
+        let frame_info = &self.frame_infos[*frame_index];
+
+        // Acquire next swapchain image
+        log::debug!("Acquiring swapchain image...");
+        let (img_idx, suboptimal) = match self.swapchain.acquire_next_image(
+            u64::MAX,
+            Some(&frame_info.acquire_semaphore),
+            None,
+        ) {
+            Ok(acquired) => acquired,
+            Err(Error::SwapchainOutOfDate) => {
+                log::debug!("Swapchain is out of date");
+                self.swapchain_outdated = true;
+                self.window.request_redraw();
+                return;
+            }
+            Err(e) => {
+                log::error!("Failed to acquire swapchain image: {:?}", e);
+                return;
+            }
+        };
+        log::debug!("Acquired swapchain image {}", img_idx);
+
+        // Suboptimal swapchains can still be presented to, so only recreate
+        // them after this frame.
+        if suboptimal {
+            log::debug!("Swapchain is suboptimal");
+            self.swapchain_outdated = true;
+        }
+
+        // Get frame info for the acquired swapchain image
+        let swapchain_frame_info = &self.frame_infos[img_idx as usize];
+
+        // Create render context with current frame info
+        let render_ctx = RunnerRenderContext {
+            image: swapchain_frame_info.image.clone(),
+            image_view: swapchain_frame_info.image_view.clone(),
+            acquire_semaphore: frame_info.acquire_semaphore.clone(),
+            render_semaphore: frame_info.render_semaphore.clone(),
+        };
+
+        // Call user's render function
+        // The application is responsible for managing its own render context and frame graph
+        if let Err(e) = app.render(&render_ctx) {
+            log::error!("Render error: {:?}", e);
+        }
+
+        // Track the frame's completion for the frame latency. The empty
+        // submit signals once the frame's submits are done
+        let frame_fence = Fence::unsignaled(self.device.clone());
+        self.queue.signal(vec![], vec![], Some(frame_fence.clone()));
+        self.swapchain.track_frame(frame_fence);
+
+        // Present
+        log::debug!("Presenting swapchain image...");
+        match self
+            .swapchain
+            .present(&self.queue, img_idx, &[&render_ctx.render_semaphore])
+        {
+            Ok(false) => {}
+            Ok(true) | Err(Error::SwapchainOutOfDate) => {
+                self.swapchain_outdated = true;
+            }
+            Err(e) => log::error!("Failed to present swapchain image: {:?}", e),
+        }
+
+        // Advance to next frame slot
+        *frame_index = (*frame_index + 1) % self.frame_infos.len();
+    }
+}
+
+/// Creates the per-frame info for the images of a swapchain.
+fn create_frame_infos(
+    device: &Arc<Device>,
+    swapchain: &Swapchain,
+    swapchain_images: &[Arc<Image>],
+) -> Vec<SwapchainFrameInfo> {
+    swapchain_images
+        .iter()
+        .map(|image| {
+            // Create image view for this swapchain image
+            let image_view = ImageView::new(
+                device.clone(),
+                ImageViewCreateInfo {
+                    image: image.clone(),
+                    view_type: ImageViewType::D2,
+                    format: swapchain.image_format(),
+                    components: vk::ComponentMapping::default(),
+                    subresource_range: vk::ImageSubresourceRange::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .base_mip_level(0)
+                        .level_count(1)
+                        .base_array_layer(0)
+                        .layer_count(1),
+                },
+            );
+
+            SwapchainFrameInfo {
+                image: image.clone(),
+                image_view,
+                acquire_semaphore: Semaphore::new(device.clone()),
+                render_semaphore: Semaphore::new(device.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Computes when the next frame should start, given the start of the
+/// previous frame.
+///
+/// If the previous frame took longer than the interval, the next frame starts
+/// right away instead of trying to catch up on the missed frames.
+fn next_frame_deadline(previous_frame_at: Instant, interval: Duration, now: Instant) -> Instant {
+    (previous_frame_at + interval).max(now)
 }
 
 /// Window runner containing all initialized objects for rendering.
@@ -71,6 +252,7 @@ impl<A: Application> ApplicationHandler for Runner<A> {
             log::info!("Surface created");
 
             log::info!("Creating swapchain...");
+            let window_size = window.inner_size();
             let (swapchain, swapchain_images) = Swapchain::new(
                 self.device.clone(),
                 surface.clone(),
@@ -78,6 +260,7 @@ impl<A: Application> ApplicationHandler for Runner<A> {
                     min_image_count: None,
                     present_mode: None,
                     image_usage: None,
+                    image_extent: Some(Extent2D::new(window_size.width, window_size.height)),
                     old_swapchain: None,
                 },
             );
@@ -85,34 +268,7 @@ impl<A: Application> ApplicationHandler for Runner<A> {
 
             // Create per-frame info (one set per swapchain image)
             log::info!("Creating per-frame info...");
-            let frame_infos: Vec<SwapchainFrameInfo> = swapchain_images
-                .iter()
-                .map(|image| {
-                    // Create image view for this swapchain image
-                    let image_view = ImageView::new(
-                        self.device.clone(),
-                        ImageViewCreateInfo {
-                            image: image.clone(),
-                            view_type: ImageViewType::D2,
-                            format: swapchain.image_format(),
-                            components: vk::ComponentMapping::default(),
-                            subresource_range: vk::ImageSubresourceRange::default()
-                                .aspect_mask(vk::ImageAspectFlags::COLOR)
-                                .base_mip_level(0)
-                                .level_count(1)
-                                .base_array_layer(0)
-                                .layer_count(1),
-                        },
-                    );
-
-                    SwapchainFrameInfo {
-                        image: image.clone(),
-                        image_view,
-                        acquire_semaphore: Semaphore::new(self.device.clone()),
-                        render_semaphore: Semaphore::new(self.device.clone()),
-                    }
-                })
-                .collect();
+            let frame_infos = create_frame_infos(&self.device, &swapchain, &swapchain_images);
             log::info!("Created {} frame info sets", frame_infos.len());
 
             // Create the application
@@ -136,6 +292,10 @@ impl<A: Application> ApplicationHandler for Runner<A> {
                 surface,
                 swapchain,
                 frame_infos,
+                exit_requested: false,
+                frame_interval: None,
+                next_frame_at: Instant::now(),
+                swapchain_outdated: false,
             });
         }
 
@@ -159,58 +319,62 @@ impl<A: Application> ApplicationHandler for Runner<A> {
                 log::info!("Window close requested");
                 event_loop.exit();
             }
+            WindowEvent::Resized(size) => {
+                log::debug!("Window resized to {}x{}", size.width, size.height);
+                context.swapchain_outdated = true;
+                context.window.request_redraw();
+            }
             WindowEvent::RedrawRequested => {
                 log::debug!("RedrawRequested event received");
+                let frame_start = Instant::now();
 
-                // Get sync objects for current frame slot
-                let frame_info = &context.frame_infos[self.frame_index];
-
-                // Acquire next swapchain image
-                log::debug!("Acquiring swapchain image...");
-                let (img_idx, suboptimal) = context.swapchain.acquire_next_image(
-                    u64::MAX,
-                    Some(&frame_info.acquire_semaphore),
-                    None,
-                );
-                log::debug!("Acquired swapchain image {}", img_idx);
-
-                if suboptimal {
-                    log::warn!("Swapchain is suboptimal");
-                    // Could recreate swapchain here if needed
-                }
+                // Bound the frames queued for presentation before sampling
+                // the input of this one
+                context.swapchain.wait_for_present_ready();
 
-                // Get frame info for the acquired swapchain image
-                let swapchain_frame_info = &context.frame_infos[img_idx as usize];
-
-                // Create render context with current frame info
-                let render_ctx = RunnerRenderContext {
-                    image: swapchain_frame_info.image.clone(),
-                    image_view: swapchain_frame_info.image_view.clone(),
-                    acquire_semaphore: frame_info.acquire_semaphore.clone(),
-                    render_semaphore: frame_info.render_semaphore.clone(),
-                };
-
-                // Call user's render function
-                // The application is responsible for managing its own render context and frame graph
-                if let Err(e) = app.render(&render_ctx) {
-                    log::error!("Render error: {:?}", e);
-                }
+                // Let the application update the runner controls, even while
+                // the window is minimized
+                app.update(context);
 
-                // Present
-                log::debug!("Presenting swapchain image...");
-                context
-                    .swapchain
-                    .present(&context.queue, img_idx, &[&render_ctx.render_semaphore]);
+                // Nothing can be presented while the window is minimized
+                let window_size = context.window.inner_size();
+                let extent = Extent2D::new(window_size.width, window_size.height);
+                if !extent.is_empty() {
+                    context.render_frame(app, &mut self.frame_index, extent);
+                }
 
-                // Advance to next frame slot
-                self.frame_index = (self.frame_index + 1) % context.frame_infos.len();
+                if context.exit_requested {
+                    log::info!("Application requested exit");
+                    event_loop.exit();
+                    return;
+                }
 
-                // Request redraw
-                context.window.request_redraw();
+                // Schedule the next frame
+                match context.frame_interval {
+                    Some(interval) => {
+                        context.next_frame_at =
+                            next_frame_deadline(frame_start, interval, Instant::now());
+                        event_loop.set_control_flow(ControlFlow::WaitUntil(context.next_frame_at));
+                    }
+                    None => {
+                        event_loop.set_control_flow(ControlFlow::Poll);
+                        context.window.request_redraw();
+                    }
+                }
             }
             _ => {}
         }
     }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        let Some(ref context) = self.context else { return; };
+
+        // Only paced frames are requested here, uncapped frames are requested
+        // right after presenting.
+        if context.frame_interval.is_some() && Instant::now() >= context.next_frame_at {
+            context.window.request_redraw();
+        }
+    }
 }
 
 impl<A: Application> Runner<A> {
@@ -220,6 +384,7 @@ impl<A: Application> Runner<A> {
     /// - Window creation in `resumed()` callback
     /// - Application creation in `resumed()` callback
     /// - Window events (close, resize)
+    /// - Swapchain recreation on resize and out of date swapchains
     /// - RedrawRequested events → calls `app.render()`
     /// - Swapchain image acquisition, synchronization, presentation
     /// 
@@ -231,7 +396,7 @@ impl<A: Application> Runner<A> {
         queue: Arc<Queue>,
         window_attributes: winit::window::WindowAttributes,
     ) -> Result<()> {
-        let mut runner = Runner {
+        let mut runner = Self {
             context: None,
             app: None,
             frame_index: 0,
@@ -246,3 +411,32 @@ impl<A: Application> Runner<A> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_frame_deadline_respects_interval() {
+        let interval = Duration::from_millis(16);
+        let start = Instant::now();
+
+        let mut frame_at = start;
+        for _ in 0..10 {
+            let deadline = next_frame_deadline(frame_at, interval, frame_at);
+            assert_eq!(deadline - frame_at, interval);
+            frame_at = deadline;
+        }
+
+        assert_eq!(frame_at - start, interval * 10);
+    }
+
+    #[test]
+    fn test_next_frame_deadline_does_not_catch_up() {
+        let interval = Duration::from_millis(16);
+        let start = Instant::now();
+        let late = start + Duration::from_millis(40);
+
+        assert_eq!(next_frame_deadline(start, interval, late), late);
+    }
+}